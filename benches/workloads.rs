@@ -0,0 +1,143 @@
+//! 典型的な操作列の実行時間を測る（cargo bench で実行する）
+//!
+//! 外部のクレートには依存せず、各操作列を数回実行して最も速かった時間を表示する。
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use link_cut_tree::action::AssignAdd;
+use link_cut_tree::monoid::Sum;
+use link_cut_tree::subtree::SubtreeSum;
+use link_cut_tree::{Forest, Node};
+
+const N: usize = 100_000;
+const OPS: usize = 200_000;
+const RUNS: usize = 5;
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 7;
+        self.0 ^= self.0 >> 9;
+        (self.0 % n as u64) as usize
+    }
+}
+
+/// f を RUNS 回実行して最も速かった時間を表示する
+fn bench(backend: &str, workload: &str, ops: usize, mut f: impl FnMut()) {
+    let best = (0 .. RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+    println!("{:<8} {:<16} {:>10.1?} {:>8.1} ns/op", backend, workload, best, best.as_nanos() as f64 / ops as f64);
+}
+
+/// 頂点 i の親を i 未満から選んだ木
+fn random_parents(rng: &mut Rng) -> Vec<Option<usize>> {
+    (0 .. N).map(|i| (i > 0).then(|| rng.next(i))).collect()
+}
+
+fn forest_from(parents: &[Option<usize>]) -> Forest<Sum<i64>> {
+    Forest::from_parents(parents.iter().enumerate().map(|(i, &p)| (i as i64, p))).unwrap()
+}
+
+fn forest_workloads() {
+    let mut rng = Rng(88172645463325252);
+    let parents = random_parents(&mut rng);
+
+    bench("forest", "bulk build", N, || {
+        black_box(forest_from(&parents));
+    });
+
+    let mut forest = forest_from(&parents);
+    bench("forest", "link/cut mix", OPS, || {
+        for _ in 0 .. OPS {
+            let (u, v) = (rng.next(N), rng.next(N));
+            if forest.connected(u, v) {
+                if forest.root(u) != u {
+                    forest.cut(u);
+                }
+            } else {
+                forest.evert(u);
+                forest.link(u, v);
+            }
+        }
+    });
+
+    let mut forest = forest_from(&parents);
+    bench("forest", "path queries", OPS, || {
+        for _ in 0 .. OPS {
+            black_box(forest.path_query(rng.next(N), rng.next(N)));
+        }
+    });
+
+    // 中心 0 に葉をつないだ星で、葉を根にして中心の部分木（ほぼ全体）に足す
+    let star: Vec<_> = (0 .. N).map(|i| (i > 0).then_some(0)).collect();
+    let mut forest = Forest::<Sum<i64>, (), AssignAdd, SubtreeSum<i64>>::from_parents(star.iter().enumerate().map(|(i, &p)| (i as i64, p))).unwrap();
+    bench("forest", "star subtree add", OPS, || {
+        for i in 0 .. OPS {
+            forest.evert(1 + rng.next(N - 1));
+            if i % 2 == 0 {
+                forest.subtree_add(0, 1);
+            } else {
+                black_box(forest.subtree_sum(0));
+            }
+        }
+    });
+
+    let chain: Vec<_> = (0 .. N).map(|i| (i > 0).then(|| i - 1)).collect();
+    let mut forest = forest_from(&chain);
+    bench("forest", "deep path", OPS, || {
+        for i in 0 .. OPS {
+            let v = if i % 2 == 0 { N - 1 - rng.next(16) } else { rng.next(16) };
+            black_box(forest.path_query(0, v));
+        }
+    });
+}
+
+fn node_workloads() {
+    let mut rng = Rng(88172645463325252);
+    let parents = random_parents(&mut rng);
+    let build = |parents: &[Option<usize>]| {
+        let nodes: Vec<Node> = (0 .. N).map(|_| Node::new()).collect();
+        for (i, p) in parents.iter().enumerate() {
+            if let Some(p) = *p {
+                nodes[i].link(&nodes[p]);
+            }
+        }
+        nodes
+    };
+
+    bench("node", "bulk build", N, || {
+        black_box(build(&parents));
+    });
+
+    let nodes = build(&parents);
+    bench("node", "expose", OPS, || {
+        for _ in 0 .. OPS {
+            let v = rng.next(N);
+            nodes[v].expose();
+            black_box(nodes[v].len());
+        }
+    });
+
+    let chain: Vec<_> = (0 .. N).map(|i| (i > 0).then(|| i - 1)).collect();
+    let nodes = build(&chain);
+    bench("node", "deep path", OPS, || {
+        for i in 0 .. OPS {
+            let v = if i % 2 == 0 { N - 1 - rng.next(16) } else { rng.next(16) };
+            nodes[v].expose();
+            black_box(nodes[v].len());
+        }
+    });
+}
+
+fn main() {
+    forest_workloads();
+    node_workloads();
+}
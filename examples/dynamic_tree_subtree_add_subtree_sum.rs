@@ -0,0 +1,48 @@
+//! Library Checker: Dynamic Tree Subtree Add Subtree Sum
+//! https://judge.yosupo.jp/problem/dynamic_tree_subtree_add_subtree_sum
+//!
+//! subtree_add は部分木に遅延をかけるだけなので、星の中心の部分木に足し続けても一回 O(log n) で済む。
+
+use std::io::{self, BufWriter, Read, Write};
+
+use link_cut_tree::action::AssignAdd;
+use link_cut_tree::monoid::Sum;
+use link_cut_tree::subtree::SubtreeSum;
+use link_cut_tree::Forest;
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).unwrap();
+    let mut words = input.split_ascii_whitespace().map(|word| word.parse::<i64>().unwrap());
+    let mut next = || words.next().unwrap();
+    let (n, q) = (next() as usize, next() as usize);
+    let mut forest = Forest::<Sum<i64>, (), AssignAdd, SubtreeSum<i64>>::new();
+    for _ in 0 .. n {
+        forest.add_node(next());
+    }
+    for _ in 1 .. n {
+        let (u, v) = (next() as usize, next() as usize);
+        forest.connect(u, v).unwrap();
+    }
+    let mut out = BufWriter::new(io::stdout().lock());
+    for _ in 0 .. q {
+        match next() {
+            0 => {
+                let (u, v, w, x) = (next() as usize, next() as usize, next() as usize, next() as usize);
+                forest.toggle_edge(u, v).unwrap();
+                forest.connect(w, x).unwrap();
+            }
+            1 => {
+                // p を根にすると、v の部分木が p の反対側になる
+                let (v, p, x) = (next() as usize, next() as usize, next());
+                forest.evert(p);
+                forest.subtree_add(v, x);
+            }
+            _ => {
+                let (v, p) = (next() as usize, next() as usize);
+                forest.evert(p);
+                writeln!(out, "{}", forest.subtree_sum(v)).unwrap();
+            }
+        }
+    }
+}
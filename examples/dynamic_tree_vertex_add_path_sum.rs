@@ -0,0 +1,42 @@
+//! Library Checker: Dynamic Tree Vertex Add Path Sum
+//! https://judge.yosupo.jp/problem/dynamic_tree_vertex_add_path_sum
+
+use std::io::{self, BufWriter, Read, Write};
+
+use link_cut_tree::monoid::Sum;
+use link_cut_tree::Forest;
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).unwrap();
+    let mut words = input.split_ascii_whitespace().map(|word| word.parse::<i64>().unwrap());
+    let mut next = || words.next().unwrap();
+    let (n, q) = (next() as usize, next() as usize);
+    let mut forest = Forest::<Sum<i64>>::new();
+    for _ in 0 .. n {
+        forest.add_node(next());
+    }
+    for _ in 1 .. n {
+        let (u, v) = (next() as usize, next() as usize);
+        forest.connect(u, v).unwrap();
+    }
+    let mut out = BufWriter::new(io::stdout().lock());
+    for _ in 0 .. q {
+        match next() {
+            0 => {
+                let (u, v, w, x) = (next() as usize, next() as usize, next() as usize, next() as usize);
+                forest.toggle_edge(u, v).unwrap();
+                forest.connect(w, x).unwrap();
+            }
+            1 => {
+                let (p, x) = (next() as usize, next());
+                let value = forest.value(p) + x;
+                forest.set_value(p, value);
+            }
+            _ => {
+                let (u, v) = (next() as usize, next() as usize);
+                writeln!(out, "{}", forest.path_query(u, v).unwrap()).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! 標準入力から一行に一つのコマンドを読み、森を操作して結果を書く
+//! 報告された操作の列をそのまま流して再現したり、構造を見せながら説明したりするのに使う
+//!
+//! ```text
+//! new 5          値 0 の頂点 5 個からなる森で始め直す
+//! add 3          値 3 の頂点を追加する
+//! link 1 0       木の根 1 の親を 0 にする
+//! cut 1          1 を親から切り離す
+//! evert 2        2 を根にする
+//! set 2 5        2 の値を 5 にする
+//! query 0 2      0 から 2 へのパスの値の和
+//! print 0        0 を含む木を罫線で描く
+//! dot            内部の splay 木を DOT で書く
+//! ```
+
+use std::io::{self, BufRead, Write};
+
+use link_cut_tree::monoid::Sum;
+use link_cut_tree::{Fallible, PolicyForest};
+
+type Repl = PolicyForest<Fallible, Sum<i64>>;
+
+/// 一行を実行して書き出す内容を返す
+fn run(forest: &mut Repl, line: &str) -> Result<String, String> {
+    // # から行末までは注釈
+    let line = line.split('#').next().unwrap();
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else { return Ok(String::new()) };
+    let args: Vec<i64> = words.map(|word| word.parse().map_err(|_| format!("expected an integer: {}", word))).collect::<Result<_, _>>()?;
+    let vertex = |x: i64| usize::try_from(x).ok().filter(|&v| v < forest.len()).ok_or(format!("no vertex {}", x));
+    let done = || Ok("ok\n".to_string());
+    match (name, args.as_slice()) {
+        ("new", &[n]) => {
+            let n = usize::try_from(n).map_err(|_| "expected a count".to_string())?;
+            *forest = Repl::default();
+            for _ in 0 .. n {
+                forest.add_node(0);
+            }
+            done()
+        }
+        ("add", &[x]) => Ok(format!("{}\n", forest.add_node(x))),
+        ("link", &[c, p]) => {
+            let (c, p) = (vertex(c)?, vertex(p)?);
+            forest.link(c, p).map_err(|error| error.to_string())?;
+            done()
+        }
+        ("cut", &[v]) => {
+            let v = vertex(v)?;
+            forest.cut(v).map_err(|error| error.to_string())?;
+            done()
+        }
+        ("evert", &[v]) => {
+            let v = vertex(v)?;
+            forest.evert(v).map_err(|error| error.to_string())?;
+            done()
+        }
+        ("set", &[v, x]) => {
+            let v = vertex(v)?;
+            forest.set_value(v, x).map_err(|error| error.to_string())?;
+            done()
+        }
+        ("query", &[u, v]) => {
+            let (u, v) = (vertex(u)?, vertex(v)?);
+            match forest.path_query(u, v) {
+                Some(sum) => Ok(format!("{}\n", sum)),
+                None => Ok("disconnected\n".to_string()),
+            }
+        }
+        ("print", &[v]) => {
+            let v = vertex(v)?;
+            Ok(forest.to_ascii(v, |value| value.to_string()))
+        }
+        ("dot", &[]) => Ok(forest.splay_dot()),
+        ("new" | "add" | "link" | "cut" | "evert" | "set" | "query" | "print" | "dot", _) => Err("wrong number of arguments".to_string()),
+        _ => Err(format!("unknown command: {}", name)),
+    }
+}
+
+fn main() {
+    let mut forest = Repl::default();
+    let mut out = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap();
+        match run(&mut forest, &line) {
+            Ok(text) => write!(out, "{}", text).unwrap(),
+            Err(message) => writeln!(out, "error: {}", message).unwrap(),
+        }
+        out.flush().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session() {
+        let mut forest = Repl::default();
+        let mut run = |line: &str| run(&mut forest, line);
+        // 空の森と空の行・注釈だけの行
+        assert_eq!(run(""), Ok(String::new()));
+        assert_eq!(run("  # only a comment"), Ok(String::new()));
+        assert_eq!(run("new 0"), Ok("ok\n".to_string()));
+        assert_eq!(run("link 0 1"), Err("no vertex 0".to_string()));
+        assert_eq!(run("add 3"), Ok("0\n".to_string()));
+        assert_eq!(run("query 0 0"), Ok("3\n".to_string()));
+        assert_eq!(run("new 3 # three vertices"), Ok("ok\n".to_string()));
+        assert_eq!(run("set 1 4"), Ok("ok\n".to_string()));
+        assert_eq!(run("set 2 5"), Ok("ok\n".to_string()));
+        assert_eq!(run("query 0 2"), Ok("disconnected\n".to_string()));
+        assert_eq!(run("link 1 0"), Ok("ok\n".to_string()));
+        assert_eq!(run("link 2 1"), Ok("ok\n".to_string()));
+        assert_eq!(run("query 0 2"), Ok("9\n".to_string()));
+        assert_eq!(run("evert 2"), Ok("ok\n".to_string()));
+        assert_eq!(run("query 1 1"), Ok("4\n".to_string()));
+        // 森が断る操作と読めない行
+        assert!(run("link 0 2").is_err());
+        assert!(run("cut 2").is_err());
+        assert_eq!(run("cut -1"), Err("no vertex -1".to_string()));
+        assert_eq!(run("cut 3"), Err("no vertex 3".to_string()));
+        assert_eq!(run("cut x"), Err("expected an integer: x".to_string()));
+        assert_eq!(run("new -1"), Err("expected a count".to_string()));
+        assert_eq!(run("query 0"), Err("wrong number of arguments".to_string()));
+        assert_eq!(run("dot 1"), Err("wrong number of arguments".to_string()));
+        assert_eq!(run("grow 1"), Err("unknown command: grow".to_string()));
+        assert_eq!(run("cut 1"), Ok("ok\n".to_string()));
+        assert_eq!(run("query 0 2"), Ok("disconnected\n".to_string()));
+        assert!(run("print 2").unwrap().contains('2'));
+        assert!(run("dot").unwrap().starts_with("digraph"));
+    }
+}
@@ -0,0 +1,45 @@
+//! Library Checker: Vertex Set Path Composite
+//! https://judge.yosupo.jp/problem/vertex_set_path_composite
+
+use std::io::{self, BufWriter, Read, Write};
+
+use link_cut_tree::monoid::{AffineComposite, ConstModulus};
+use link_cut_tree::Forest;
+
+const P: u64 = 998244353;
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).unwrap();
+    let mut words = input.split_ascii_whitespace().map(|word| word.parse::<u64>().unwrap());
+    let mut next = || words.next().unwrap();
+    let (n, q) = (next() as usize, next() as usize);
+    // 頂点 i は x ↦ a_i x + b_i、パスの値は u から v への順の合成
+    let mut forest = Forest::<AffineComposite<ConstModulus<P>>>::new();
+    for _ in 0 .. n {
+        forest.add_node((next(), next()));
+    }
+    for _ in 1 .. n {
+        let (u, v) = (next() as usize, next() as usize);
+        forest.connect(u, v).unwrap();
+    }
+    let mut out = BufWriter::new(io::stdout().lock());
+    for _ in 0 .. q {
+        match next() {
+            0 => {
+                let (u, v, w, x) = (next() as usize, next() as usize, next() as usize, next() as usize);
+                forest.toggle_edge(u, v).unwrap();
+                forest.connect(w, x).unwrap();
+            }
+            1 => {
+                let (p, c, d) = (next() as usize, next(), next());
+                forest.set_value(p, (c, d));
+            }
+            _ => {
+                let (u, v, x) = (next() as usize, next() as usize, next());
+                let (a, b) = forest.path_query(u, v).unwrap();
+                writeln!(out, "{}", ((a as u128 * x as u128 + b as u128) % P as u128) as u64).unwrap();
+            }
+        }
+    }
+}
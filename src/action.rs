@@ -0,0 +1,266 @@
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::monoid::{mul_mod, AffineComposite, Beats, ChminChmax, CompensatedSum, Max, Min, ModSum, Modulus, Monoid, Sum};
+
+/// パス上の頂点の値と辺の重みにまとめて遅延して作用させる写像
+/// 写像は列の各要素に独立に作用するものとする（列の向きによらない）
+pub trait Action<M: Monoid, E: Monoid = ()> {
+    type Map: Clone;
+
+    /// g を作用させた後に f を作用させる写像
+    fn compose(f: &Self::Map, g: &Self::Map) -> Self::Map;
+
+    /// len 個の頂点の値の集約 sum に f を作用させる
+    /// 集約だけから求められなければ None を返す（子に伝播してから集約し直す）
+    /// len が 1 のときは必ず Some を返すこと
+    fn act(f: &Self::Map, sum: &M::Value, len: usize) -> Option<M::Value>;
+
+    /// len 本の辺の重みの集約 sum に f を作用させる
+    fn act_weight(f: &Self::Map, sum: &E::Value, len: usize) -> E::Value {
+        let _ = (f, len);
+        sum.clone()
+    }
+}
+
+/// 作用させない
+impl<M: Monoid, E: Monoid> Action<M, E> for () {
+    type Map = ();
+
+    fn compose(_: &(), _: &()) {}
+    fn act(_: &(), sum: &M::Value, _: usize) -> Option<M::Value> { Some(sum.clone()) }
+}
+
+/// 値を [lo, hi] に収める写像（lo <= hi）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clamp {
+    pub lo: i64,
+    pub hi: i64,
+}
+
+impl Clamp {
+    /// 値を x 以下にする（x > i64::MIN）
+    pub fn chmin(x: i64) -> Self {
+        Self { lo: i64::MIN, hi: x }
+    }
+
+    /// 値を x 以上にする（x < i64::MAX）
+    pub fn chmax(x: i64) -> Self {
+        Self { lo: x, hi: i64::MAX }
+    }
+}
+
+/// 最大値を x 以下にする（次に大きい値が x 未満でなければ None）
+fn beats_chmin(s: &Beats, x: i64) -> Option<Beats> {
+    if s.max <= x {
+        return Some(*s);
+    }
+    if s.max2 >= x {
+        return None;
+    }
+    let mut s = *s;
+    s.sum -= (s.max - x) * s.max_count as i64;
+    if s.min == s.max {
+        s.min = x;
+    }
+    if s.min2 == s.max {
+        s.min2 = x;
+    }
+    s.max = x;
+    Some(s)
+}
+
+/// 最小値を x 以上にする（次に小さい値が x より大きくなければ None）
+fn beats_chmax(s: &Beats, x: i64) -> Option<Beats> {
+    if s.min >= x {
+        return Some(*s);
+    }
+    if s.min2 <= x {
+        return None;
+    }
+    let mut s = *s;
+    s.sum += (x - s.min) * s.min_count as i64;
+    if s.max == s.min {
+        s.max = x;
+    }
+    if s.max2 == s.min {
+        s.max2 = x;
+    }
+    s.min = x;
+    Some(s)
+}
+
+impl<E: Monoid> Action<ChminChmax, E> for Clamp {
+    type Map = Clamp;
+
+    fn compose(f: &Clamp, g: &Clamp) -> Clamp {
+        Clamp { lo: g.lo.clamp(f.lo, f.hi), hi: g.hi.clamp(f.lo, f.hi) }
+    }
+
+    fn act(f: &Clamp, sum: &Beats, _: usize) -> Option<Beats> {
+        beats_chmax(&beats_chmin(sum, f.hi)?, f.lo)
+    }
+}
+
+/// 法 P で値を x ↦ ax + b に置き換える写像
+pub struct Affine<P>(PhantomData<P>);
+
+impl<P: Modulus, E: Monoid> Action<ModSum<P>, E> for Affine<P> {
+    /// (a, b)
+    type Map = (u64, u64);
+
+    fn compose(f: &(u64, u64), g: &(u64, u64)) -> (u64, u64) {
+        AffineComposite::<P>::op(g, f)
+    }
+
+    fn act(f: &(u64, u64), sum: &u64, len: usize) -> Option<u64> {
+        let len = len as u64 % P::modulus();
+        Some((mul_mod::<P>(f.0, *sum) + mul_mod::<P>(f.1, len)) % P::modulus())
+    }
+}
+
+/// CompensatedSum の値に x を足す写像
+/// 写像も (x, 丸め誤差) で持ち、合成と len 倍で出る丸め誤差も和の補正に回す
+pub struct CompensatedAdd;
+
+impl<E: Monoid> Action<CompensatedSum, E> for CompensatedAdd {
+    type Map = (f64, f64);
+
+    fn compose(f: &(f64, f64), g: &(f64, f64)) -> (f64, f64) {
+        CompensatedSum::op(f, g)
+    }
+
+    fn act(f: &(f64, f64), sum: &(f64, f64), len: usize) -> Option<(f64, f64)> {
+        let len = len as f64;
+        // f.0 * len の丸め誤差は fma で正確に求まる
+        let p = f.0 * len;
+        let p_err = f.0.mul_add(len, -p);
+        let (s, err) = CompensatedSum::two_sum(sum.0, p);
+        Some((s, sum.1 + err + p_err + f.1 * len))
+    }
+}
+
+/// 頂点の値を置き換えてから足す写像（subtree_assign と subtree_add に使う）
+pub struct AssignAdd;
+
+/// 置き換えた値に add を足す写像
+/// 置き換えには作った順の番号を振り、軽い子に遅延させた写像のうちどこから後を作用させるかを見分ける
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AssignAddMap {
+    /// (作った順の番号, 置き換える値)
+    assign: Option<(u64, i64)>,
+    add: i64,
+}
+
+static NEXT_ASSIGN: AtomicU64 = AtomicU64::new(0);
+
+impl AssignAddMap {
+    /// 値を x に置き換える
+    pub fn assign(x: i64) -> Self {
+        Self { assign: Some((NEXT_ASSIGN.fetch_add(1, Ordering::Relaxed), x)), add: 0 }
+    }
+
+    /// 値に x を足す
+    pub fn add(x: i64) -> Self {
+        Self { assign: None, add: x }
+    }
+
+    /// history がこの写像の後に続けて作用させた写像を合成したものであるとき、続けた写像
+    pub(crate) fn since(&self, history: &Self) -> Self {
+        match history.assign {
+            Some((stamp, _)) if self.assign.map(|(s, _)| s) != Some(stamp) => *history,
+            _ => Self { assign: None, add: history.add - self.add },
+        }
+    }
+
+    /// len 個の値の和 sum に作用させる
+    pub(crate) fn act_sum(&self, sum: i64, len: usize) -> i64 {
+        let len = len as i64;
+        self.assign.map_or(sum, |(_, x)| x * len) + self.add * len
+    }
+}
+
+impl<E: Monoid> Action<Sum<i64>, E> for AssignAdd {
+    type Map = AssignAddMap;
+
+    fn compose(f: &AssignAddMap, g: &AssignAddMap) -> AssignAddMap {
+        match f.assign {
+            Some(_) => *f,
+            None => AssignAddMap { assign: g.assign, add: g.add + f.add },
+        }
+    }
+
+    fn act(f: &AssignAddMap, sum: &i64, len: usize) -> Option<i64> {
+        Some(f.act_sum(*sum, len))
+    }
+}
+
+/// 辺の重みに足す写像（頂点の値はそのまま）
+pub struct EdgeAdd;
+
+impl<M: Monoid> Action<M, Min<i64>> for EdgeAdd {
+    type Map = i64;
+
+    fn compose(f: &i64, g: &i64) -> i64 {
+        f + g
+    }
+
+    fn act(_: &i64, sum: &M::Value, _: usize) -> Option<M::Value> {
+        Some(sum.clone())
+    }
+
+    fn act_weight(f: &i64, sum: &i64, _: usize) -> i64 {
+        sum + f
+    }
+}
+
+/// 辺の重みを一つの値に置き換える写像（頂点の値はそのまま）
+pub struct EdgeAssign;
+
+impl<M: Monoid> Action<M, Sum<i64>> for EdgeAssign {
+    type Map = i64;
+
+    fn compose(f: &i64, _: &i64) -> i64 {
+        *f
+    }
+
+    fn act(_: &i64, sum: &M::Value, _: usize) -> Option<M::Value> {
+        Some(sum.clone())
+    }
+
+    fn act_weight(f: &i64, _: &i64, len: usize) -> i64 {
+        f * len as i64
+    }
+}
+
+impl<M: Monoid> Action<M, Min<i64>> for EdgeAssign {
+    type Map = i64;
+
+    fn compose(f: &i64, _: &i64) -> i64 {
+        *f
+    }
+
+    fn act(_: &i64, sum: &M::Value, _: usize) -> Option<M::Value> {
+        Some(sum.clone())
+    }
+
+    fn act_weight(f: &i64, _: &i64, _: usize) -> i64 {
+        *f
+    }
+}
+
+impl<M: Monoid> Action<M, Max<i64>> for EdgeAssign {
+    type Map = i64;
+
+    fn compose(f: &i64, _: &i64) -> i64 {
+        *f
+    }
+
+    fn act(_: &i64, sum: &M::Value, _: usize) -> Option<M::Value> {
+        Some(sum.clone())
+    }
+
+    fn act_weight(f: &i64, _: &i64, _: usize) -> i64 {
+        *f
+    }
+}
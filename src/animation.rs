@@ -0,0 +1,116 @@
+//! 実行の手順を JSON で書き出す（animation 機能）
+//!
+//! 回転・優先する子の付け替え・link・cut を一行に一つの JSON オブジェクトとして並べ、
+//! 外部の可視化ツールでアニメーションにできるようにする。
+
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::trace::{clear_trace_hook, set_trace_hook, TraceEvent, TraceNode};
+
+/// このスレッドで起きた手順を記録する
+/// 記録している間はトレースのフックを占有する
+pub struct StepRecorder {
+    steps: Rc<RefCell<Vec<TraceEvent>>>,
+}
+
+impl StepRecorder {
+    /// 記録を始める
+    pub fn start() -> Self {
+        let steps = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&steps);
+        set_trace_hook(move |event| {
+            if matches!(event, TraceEvent::Rotate { .. } | TraceEvent::Switch { .. } | TraceEvent::Link { .. } | TraceEvent::Cut { .. }) {
+                sink.borrow_mut().push(event.clone());
+            }
+        });
+        Self { steps }
+    }
+
+    /// ここまでに記録した手順の数
+    pub fn len(&self) -> usize {
+        self.steps.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.borrow().is_empty()
+    }
+
+    /// 記録をやめ、手順を JSON Lines で返す
+    pub fn finish(self) -> String {
+        clear_trace_hook();
+        let mut json = String::new();
+        for (i, step) in self.steps.borrow().iter().enumerate() {
+            json.push_str(&step_json(i, step));
+            json.push('\n');
+        }
+        json
+    }
+}
+
+fn node_json(node: Option<TraceNode>) -> String {
+    match node {
+        Some(TraceNode::Vertex(v)) => format!("{{\"vertex\":{}}}", v),
+        Some(TraceNode::Edge(e)) => format!("{{\"edge\":{}}}", e),
+        None => "null".to_string(),
+    }
+}
+
+/// i 番目の手順を一つの JSON オブジェクトにする（時間は含めない）
+pub fn step_json(i: usize, step: &TraceEvent) -> String {
+    let mut json = format!("{{\"step\":{},", i);
+    match *step {
+        TraceEvent::Rotate { node, parent } => {
+            write!(json, "\"type\":\"rotate\",\"node\":{},\"parent\":{}", node_json(Some(node)), node_json(Some(parent))).unwrap()
+        }
+        TraceEvent::Switch { node, old, new } => write!(
+            json,
+            "\"type\":\"switch\",\"node\":{},\"old\":{},\"new\":{}",
+            node_json(Some(node)),
+            node_json(old),
+            node_json(new)
+        )
+        .unwrap(),
+        TraceEvent::Link { child, parent, .. } => write!(json, "\"type\":\"link\",\"child\":{},\"parent\":{}", child, parent).unwrap(),
+        TraceEvent::Cut { v, .. } => write!(json, "\"type\":\"cut\",\"vertex\":{}", v).unwrap(),
+        TraceEvent::Expose { vertex, splays } => write!(json, "\"type\":\"expose\",\"vertex\":{},\"splays\":{}", vertex, splays).unwrap(),
+        TraceEvent::Splay { node, rotations } => {
+            write!(json, "\"type\":\"splay\",\"node\":{},\"rotations\":{}", node_json(Some(node)), rotations).unwrap()
+        }
+    }
+    json.push('}');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Forest;
+
+    #[test]
+    fn records_steps() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 3 {
+            forest.add_node(());
+        }
+        let recorder = StepRecorder::start();
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.root(2);
+        forest.cut(2);
+        assert!(!recorder.is_empty());
+        let count = recorder.len();
+        let json = recorder.finish();
+        let lines: Vec<&str> = json.lines().collect();
+        assert_eq!(lines.len(), count);
+        assert!(lines.iter().enumerate().all(|(i, line)| line.starts_with(&format!("{{\"step\":{},\"type\":", i)) && line.ends_with('}')));
+        assert_eq!(lines.iter().filter(|line| line.contains("\"type\":\"link\"")).count(), 2);
+        assert!(lines.iter().any(|line| line.ends_with(r#""type":"link","child":1,"parent":0}"#)));
+        assert!(lines.iter().any(|line| line.contains("\"type\":\"rotate\"")));
+        assert!(lines.iter().any(|line| line.contains("\"type\":\"switch\"")));
+        assert!(lines.last().unwrap().ends_with(r#""type":"cut","vertex":2}"#));
+        let step = TraceEvent::Switch { node: TraceNode::Vertex(1), old: None, new: Some(TraceNode::Edge(0)) };
+        assert_eq!(step_json(3, &step), r#"{"step":3,"type":"switch","node":{"vertex":1},"old":null,"new":{"edge":0}}"#);
+    }
+}
@@ -0,0 +1,116 @@
+use std::any::Any;
+use std::ops::{Deref, DerefMut};
+
+use crate::Forest;
+
+/// 頂点ごとに型の異なる値を `Box<dyn Any>` で持つ森
+/// 値は森の外に並べて持ち、パスや部分木の集約はしない（構造を変える操作と問い合わせは Deref で Forest のものを使う）
+/// 頂点の番号を変える操作（clear、gc など）を Deref で呼ぶと値と食い違うので呼ばないこと
+#[derive(Default)]
+pub struct AnyForest {
+    forest: Forest,
+    payloads: Vec<Option<Box<dyn Any>>>,
+}
+
+impl Deref for AnyForest {
+    type Target = Forest;
+
+    fn deref(&self) -> &Forest {
+        &self.forest
+    }
+}
+
+impl DerefMut for AnyForest {
+    fn deref_mut(&mut self) -> &mut Forest {
+        &mut self.forest
+    }
+}
+
+impl AnyForest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 値 payload を持つ孤立点を追加し、その番号を返す
+    pub fn add_node<T: Any>(&mut self, payload: T) -> usize {
+        self.add_boxed(Some(Box::new(payload)))
+    }
+
+    /// payload を持つ孤立点を追加し、その番号を返す（None なら値を持たない）
+    pub fn add_boxed(&mut self, payload: Option<Box<dyn Any>>) -> usize {
+        self.payloads.push(payload);
+        self.forest.add_node(())
+    }
+
+    /// v の値が T ならその参照
+    pub fn get<T: Any>(&self, v: usize) -> Option<&T> {
+        self.payloads[v].as_ref()?.downcast_ref()
+    }
+
+    pub fn get_mut<T: Any>(&mut self, v: usize) -> Option<&mut T> {
+        self.payloads[v].as_mut()?.downcast_mut()
+    }
+
+    /// v の値が T か
+    pub fn is<T: Any>(&self, v: usize) -> bool {
+        self.payloads[v].as_ref().is_some_and(|payload| payload.is::<T>())
+    }
+
+    /// v の値を payload にし、元の値を返す
+    pub fn set<T: Any>(&mut self, v: usize, payload: T) -> Option<Box<dyn Any>> {
+        self.payloads[v].replace(Box::new(payload))
+    }
+
+    /// v の値が T なら取り出して返す（T でなければそのまま残す）
+    pub fn take<T: Any>(&mut self, v: usize) -> Option<T> {
+        if !self.is::<T>(v) {
+            return None;
+        }
+        self.payloads[v].take().map(|payload| *payload.downcast().unwrap())
+    }
+
+    /// v の値（型を問わない）
+    pub fn payload(&self, v: usize) -> Option<&dyn Any> {
+        self.payloads[v].as_deref()
+    }
+
+    /// u から v へのパス上の頂点のうち、値が T のものを u に近い順に並べる（異なる木なら None）
+    pub fn path_of<T: Any>(&mut self, u: usize, v: usize) -> Option<Vec<usize>> {
+        let path: Vec<usize> = self.forest.path_iter(u, v)?.collect();
+        Some(path.into_iter().filter(|&w| self.is::<T>(w)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Folder(&'static str);
+
+    #[test]
+    fn typed_payloads() {
+        let mut forest = AnyForest::new();
+        let root = forest.add_node(Folder("root"));
+        let file = forest.add_node(42u32);
+        let note = forest.add_node(String::from("note"));
+        let empty = forest.add_boxed(None);
+        forest.link(file, root);
+        forest.link(note, file);
+        forest.link(empty, root);
+        assert_eq!(forest.get::<Folder>(root), Some(&Folder("root")));
+        assert_eq!(forest.get::<u32>(root), None);
+        assert!(forest.is::<String>(note));
+        assert!(!forest.is::<String>(empty));
+        *forest.get_mut::<u32>(file).unwrap() += 1;
+        assert_eq!(forest.get::<u32>(file), Some(&43));
+        assert_eq!(forest.path_of::<u32>(note, empty), Some(vec![file]));
+        assert_eq!(forest.take::<u32>(note), None);
+        assert_eq!(forest.take::<String>(note).as_deref(), Some("note"));
+        assert!(forest.payload(note).is_none());
+        let old = forest.set(empty, 1.5f64).map(|_| ());
+        assert_eq!(old, None);
+        assert_eq!(forest.payload(empty).and_then(|p| p.downcast_ref::<f64>()), Some(&1.5));
+        assert_eq!(forest.root(note), root);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::monoid::Xor;
+use crate::Forest;
+
+/// 辺の追加・削除のもとで、グラフが二部グラフか（奇閉路がないか）を保つ
+/// 全域森の辺の重みを 1 として、パスの排他的論理和で距離の偶奇を求める
+/// 森の外の辺は、両端の距離が偶数なら奇閉路を作る
+pub struct DynamicBipartite {
+    forest: Forest<(), Xor<u8>>,
+    edges: Vec<Option<BipartiteEdge>>,
+    /// 奇閉路を作る森の外の辺の数
+    odd: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BipartiteEdge {
+    ends: [usize; 2],
+    in_forest: bool,
+    /// 森の外の辺で、奇閉路を作るか
+    odd: bool,
+}
+
+impl DynamicBipartite {
+    /// n 頂点で辺のない状態
+    pub fn new(n: usize) -> Self {
+        let mut forest = Forest::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        Self { forest, edges: vec![], odd: 0 }
+    }
+
+    pub fn is_bipartite(&self) -> bool {
+        self.odd == 0
+    }
+
+    /// 辺 (u, v) を加えると奇閉路ができるか（すでにある奇閉路は数えない）
+    pub fn closes_odd_cycle(&mut self, u: usize, v: usize) -> bool {
+        self.forest.path_query_edges(u, v) == Some(0)
+    }
+
+    /// 辺 (u, v) を加え、辺の番号と、その辺が奇閉路を作ったかを返す
+    pub fn add_edge(&mut self, u: usize, v: usize) -> (usize, bool) {
+        let odd = self.closes_odd_cycle(u, v);
+        let in_forest = !self.forest.connected(u, v);
+        if in_forest {
+            self.forest.connect_weighted(u, v, 1).unwrap();
+        }
+        self.odd += odd as usize;
+        self.edges.push(Some(BipartiteEdge { ends: [u, v], in_forest, odd }));
+        (self.edges.len() - 1, odd)
+    }
+
+    /// 辺 i を取り除く
+    /// 森の辺なら両側を結ぶ森の外の辺を探して置き換え、森の外の辺の偶奇を調べ直す（O(m log n)）
+    pub fn remove_edge(&mut self, i: usize) {
+        let edge = self.edges[i].take().expect("edge must not be removed");
+        if !edge.in_forest {
+            self.odd -= edge.odd as usize;
+            return;
+        }
+        let [u, v] = edge.ends;
+        self.forest.toggle_edge(u, v).unwrap();
+        let (ru, rv) = (self.forest.root(u), self.forest.root(v));
+        for j in 0 .. self.edges.len() {
+            let Some(other) = self.edges[j] else { continue };
+            let [a, b] = other.ends;
+            let (ra, rb) = (self.forest.root(a), self.forest.root(b));
+            if !other.in_forest && ((ra == ru && rb == rv) || (ra == rv && rb == ru)) {
+                self.forest.connect_weighted(a, b, 1).unwrap();
+                self.edges[j] = Some(BipartiteEdge { in_forest: true, odd: false, ..other });
+                break;
+            }
+        }
+        // 森の形が変わったので、森の外の辺の偶奇を数え直す
+        self.odd = 0;
+        for j in 0 .. self.edges.len() {
+            let Some(other) = self.edges[j] else { continue };
+            if !other.in_forest {
+                let [a, b] = other.ends;
+                let odd = self.closes_odd_cycle(a, b);
+                self.edges[j] = Some(BipartiteEdge { odd, ..other });
+                self.odd += odd as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    /// 辺の一覧が二部グラフか、色を塗って調べる
+    fn naive(n: usize, edges: &[(usize, usize)]) -> bool {
+        let mut color = vec![None; n];
+        for s in 0 .. n {
+            if color[s].is_some() {
+                continue;
+            }
+            color[s] = Some(false);
+            let mut stack = vec![s];
+            while let Some(x) = stack.pop() {
+                for &(a, b) in edges {
+                    for (p, q) in [(a, b), (b, a)] {
+                        if p != x {
+                            continue;
+                        }
+                        match color[q] {
+                            None => {
+                                color[q] = Some(!color[x].unwrap());
+                                stack.push(q);
+                            }
+                            Some(c) if c == color[x].unwrap() => return false,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn against_coloring() {
+        let mut rand = xorshift(0x6a09e667f3bcc909);
+        let n = 10;
+        let mut graph = DynamicBipartite::new(n);
+        let mut edges: Vec<Option<(usize, usize)>> = vec![];
+        for _ in 0 .. 1500 {
+            let alive: Vec<usize> = (0 .. edges.len()).filter(|&i| edges[i].is_some()).collect();
+            if alive.len() < 12 && rand(2) == 0 {
+                let (u, v) = (rand(n), rand(n));
+                let before: Vec<_> = edges.iter().flatten().copied().collect();
+                let closes = graph.closes_odd_cycle(u, v);
+                let (i, odd) = graph.add_edge(u, v);
+                assert_eq!(i, edges.len());
+                assert_eq!(odd, closes);
+                edges.push(Some((u, v)));
+                let mut after = before.clone();
+                after.push((u, v));
+                // 二部グラフに加えた辺が奇閉路を作るかは、加えた後の判定と一致する
+                if naive(n, &before) {
+                    assert_eq!(odd, !naive(n, &after));
+                }
+            } else if !alive.is_empty() {
+                let i = alive[rand(alive.len())];
+                graph.remove_edge(i);
+                edges[i] = None;
+            }
+            let current: Vec<_> = edges.iter().flatten().copied().collect();
+            assert_eq!(graph.is_bipartite(), naive(n, &current));
+        }
+    }
+
+    #[test]
+    fn triangle() {
+        let mut graph = DynamicBipartite::new(4);
+        assert_eq!(graph.add_edge(0, 1), (0, false));
+        assert_eq!(graph.add_edge(1, 2), (1, false));
+        assert!(graph.closes_odd_cycle(0, 2));
+        assert!(!graph.closes_odd_cycle(0, 3));
+        assert_eq!(graph.add_edge(2, 0), (2, true));
+        assert!(!graph.is_bipartite());
+        // 森の辺を取り除くと残りの辺で置き換わる
+        graph.remove_edge(0);
+        assert!(graph.is_bipartite());
+        assert_eq!(graph.add_edge(3, 3), (3, true));
+        graph.remove_edge(3);
+        assert!(graph.is_bipartite());
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::subtree::Subtree;
+use crate::Forest;
+
+/// パスの集約と最小共通祖先の答えを覚えておく森
+///
+/// 答えは操作ごとに、変わりうるものだけを捨てる。
+/// link は異なる木どうしの答え（None）を、cut は同じ木どうしの答えを、
+/// evert は最小共通祖先を、set_value は頂点の値の集約を捨てる。
+/// 覚えておく答えが capacity に達したら、その種類の答えをすべて捨てる。
+pub struct CachedForest<M: Monoid = (), E: Monoid = (), A: Action<M, E> = (), S: Subtree<M, E> = ()> {
+    forest: Forest<M, E, A, S>,
+    capacity: usize,
+    paths: HashMap<(usize, usize), Option<M::Value>>,
+    edge_paths: HashMap<(usize, usize), Option<E::Value>>,
+    lcas: HashMap<(usize, usize), Option<usize>>,
+    hits: usize,
+    misses: usize,
+}
+
+/// 答えを覚えておく（いっぱいなら先に空にする）
+fn remember<K: Eq + Hash, V: Clone>(cache: &mut HashMap<K, V>, capacity: usize, key: K, value: &V) {
+    if cache.len() >= capacity {
+        cache.clear();
+    }
+    cache.insert(key, value.clone());
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> CachedForest<M, E, A, S> {
+    /// 種類ごとに capacity 個まで答えを覚える
+    pub fn new(forest: Forest<M, E, A, S>, capacity: usize) -> Self {
+        Self { forest, capacity, paths: HashMap::new(), edge_paths: HashMap::new(), lcas: HashMap::new(), hits: 0, misses: 0 }
+    }
+
+    pub fn forest(&self) -> &Forest<M, E, A, S> {
+        &self.forest
+    }
+
+    /// 森を直接書き換える（覚えた答えはすべて捨てる）
+    pub fn forest_mut(&mut self) -> &mut Forest<M, E, A, S> {
+        self.clear_cache();
+        &mut self.forest
+    }
+
+    pub fn into_inner(self) -> Forest<M, E, A, S> {
+        self.forest
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.paths.clear();
+        self.edge_paths.clear();
+        self.lcas.clear();
+    }
+
+    /// (覚えた答えを返した回数, 森に問い合わせた回数)
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits, self.misses)
+    }
+
+    /// u から v へのパス上の頂点の値の集約
+    pub fn path_query(&mut self, u: usize, v: usize) -> Option<M::Value> {
+        if let Some(answer) = self.paths.get(&(u, v)) {
+            self.hits += 1;
+            return answer.clone();
+        }
+        self.misses += 1;
+        let answer = self.forest.path_query(u, v);
+        remember(&mut self.paths, self.capacity, (u, v), &answer);
+        answer
+    }
+
+    /// u から v へのパス上の辺の重みの集約
+    pub fn path_query_edges(&mut self, u: usize, v: usize) -> Option<E::Value> {
+        if let Some(answer) = self.edge_paths.get(&(u, v)) {
+            self.hits += 1;
+            return answer.clone();
+        }
+        self.misses += 1;
+        let answer = self.forest.path_query_edges(u, v);
+        remember(&mut self.edge_paths, self.capacity, (u, v), &answer);
+        answer
+    }
+
+    pub fn lca(&mut self, u: usize, v: usize) -> Option<usize> {
+        if let Some(&answer) = self.lcas.get(&(u, v)) {
+            self.hits += 1;
+            return answer;
+        }
+        self.misses += 1;
+        let answer = self.forest.lca(u, v);
+        remember(&mut self.lcas, self.capacity, (u, v), &answer);
+        answer
+    }
+
+    /// 木の根 child の親を parent にする
+    pub fn link(&mut self, child: usize, parent: usize) {
+        self.link_weighted(child, parent, E::identity());
+    }
+
+    /// 木の根 child の親を parent にし、その辺の重みを weight にする
+    pub fn link_weighted(&mut self, child: usize, parent: usize, weight: E::Value) {
+        self.forest.link_weighted(child, parent, weight);
+        // 同じ木どうしの答えは変わらない
+        self.paths.retain(|_, answer| answer.is_some());
+        self.edge_paths.retain(|_, answer| answer.is_some());
+        self.lcas.retain(|_, answer| answer.is_some());
+    }
+
+    /// v を親から切り離し、親を返す
+    pub fn cut(&mut self, v: usize) -> usize {
+        let parent = self.forest.parent(v).expect("v must not be a root");
+        self.forest.cut(v);
+        // 異なる木どうしの答えは変わらない
+        self.paths.retain(|_, answer| answer.is_none());
+        self.edge_paths.retain(|_, answer| answer.is_none());
+        self.lcas.retain(|_, answer| answer.is_none());
+        parent
+    }
+
+    /// v を木の根にする（パスの集約は向きを含めて変わらない）
+    pub fn evert(&mut self, v: usize) {
+        self.forest.evert(v);
+        self.lcas.clear();
+    }
+
+    pub fn set_value(&mut self, v: usize, value: M::Value) {
+        self.forest.set_value(v, value);
+        self.paths.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn matches_forest() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 12;
+        let mut forest = Forest::<Sum<i64>, Sum<i64>>::new();
+        let mut inner = Forest::<Sum<i64>, Sum<i64>>::new();
+        for v in 0 .. n {
+            forest.add_node(v as i64);
+            inner.add_node(v as i64);
+        }
+        let mut cached = CachedForest::new(inner, 16);
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(12) {
+                0 => {
+                    if forest.root(u) == u && forest.root(v) != u {
+                        let w = rand(10) as i64;
+                        forest.link_weighted(u, v, w);
+                        cached.link_weighted(u, v, w);
+                    }
+                }
+                1 => {
+                    if let Some(p) = forest.parent(u) {
+                        assert_eq!(cached.cut(u), p);
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    forest.evert(u);
+                    cached.evert(u);
+                }
+                3 => {
+                    let x = rand(100) as i64;
+                    forest.set_value(u, x);
+                    cached.set_value(u, x);
+                }
+                _ => {
+                    assert_eq!(cached.path_query(u, v), forest.path_query(u, v));
+                    assert_eq!(cached.path_query_edges(u, v), forest.path_query_edges(u, v));
+                    assert_eq!(cached.lca(u, v), forest.lca(u, v));
+                }
+            }
+        }
+        let (hits, misses) = cached.stats();
+        assert!(hits > 0 && misses > 0);
+        cached.forest_mut().set_value(0, 1000);
+        assert_eq!(cached.path_query(0, 0), Some(1000));
+    }
+}
@@ -0,0 +1,132 @@
+//! ほかの link-cut tree の実装でよく使われる名前で Forest を呼ぶ（compat 機能）
+//!
+//! 頂点を番号で指し、辺を向きのない組として扱う書き方に合わせてある。
+//! 呼び出し側を少しずつ Forest の名前に書き換えられるよう、ここにない操作は Deref で Forest のものを使う。
+
+use std::ops::{Deref, DerefMut};
+
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::Forest;
+
+/// 辺に重みを持たない森を、make_root・find_root・向きのない link と cut で操作する
+pub struct LinkCutTree<M: Monoid = (), A: Action<M, ()> = ()> {
+    forest: Forest<M, (), A>,
+}
+
+impl<M: Monoid, A: Action<M, ()>> Deref for LinkCutTree<M, A> {
+    type Target = Forest<M, (), A>;
+
+    fn deref(&self) -> &Forest<M, (), A> {
+        &self.forest
+    }
+}
+
+impl<M: Monoid, A: Action<M, ()>> DerefMut for LinkCutTree<M, A> {
+    fn deref_mut(&mut self) -> &mut Forest<M, (), A> {
+        &mut self.forest
+    }
+}
+
+impl<M: Monoid, A: Action<M, ()>> From<Vec<M::Value>> for LinkCutTree<M, A> {
+    /// `values[v]` を頂点 v の値とする、辺のない森
+    fn from(values: Vec<M::Value>) -> Self {
+        let mut forest = Forest::new();
+        for value in values {
+            forest.add_node(value);
+        }
+        Self { forest }
+    }
+}
+
+impl<M: Monoid, A: Action<M, ()>> LinkCutTree<M, A> {
+    /// n 頂点で辺がなく、値がすべて単位元の森
+    pub fn new(n: usize) -> Self {
+        Self::from(vec![M::identity(); n])
+    }
+
+    pub fn into_inner(self) -> Forest<M, (), A> {
+        self.forest
+    }
+
+    /// v を根にする（Forest::evert）
+    pub fn make_root(&mut self, v: usize) {
+        self.forest.evert(v);
+    }
+
+    /// v を含む木の根（Forest::root）
+    pub fn find_root(&mut self, v: usize) -> usize {
+        self.forest.root(v)
+    }
+
+    /// 異なる木に属する u と v を辺で結び、結べたかを返す（u を根にしてから v の子にする）
+    pub fn link(&mut self, u: usize, v: usize) -> bool {
+        if self.forest.connected(u, v) {
+            return false;
+        }
+        self.forest.evert(u);
+        self.forest.link(u, v);
+        true
+    }
+
+    /// u と v を結ぶ辺を切り、切れたかを返す
+    pub fn cut(&mut self, u: usize, v: usize) -> bool {
+        if self.forest.parent(u) == Some(v) {
+            self.forest.cut(u);
+        } else if self.forest.parent(v) == Some(u) {
+            self.forest.cut(v);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    pub fn get(&mut self, v: usize) -> M::Value {
+        self.forest.value(v).clone()
+    }
+
+    pub fn set(&mut self, v: usize, value: M::Value) {
+        self.forest.set_value(v, value);
+    }
+
+    /// u から v へのパス上の頂点の値を u の側から畳み込む（Forest::path_query）
+    pub fn fold(&mut self, u: usize, v: usize) -> Option<M::Value> {
+        self.forest.path_query(u, v)
+    }
+
+    /// u から v へのパス上の頂点の値に f を作用させ、同じ木に属していたかを返す（Forest::path_apply）
+    pub fn apply(&mut self, u: usize, v: usize, f: A::Map) -> bool {
+        self.forest.path_apply(u, v, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    #[test]
+    fn compat_names() {
+        let mut lct = LinkCutTree::<Sum<i64>>::from(vec![1, 2, 3, 4, 5]);
+        assert!(lct.link(0, 1));
+        assert!(lct.link(2, 1));
+        assert!(lct.link(1, 3));
+        assert!(!lct.link(0, 3));
+        assert_eq!(lct.fold(0, 2), Some(6));
+        assert_eq!(lct.fold(0, 4), None);
+        lct.make_root(2);
+        assert_eq!(lct.find_root(0), 2);
+        assert_eq!(lct.lca(0, 3), Some(1));
+        assert!(lct.cut(3, 1));
+        assert!(!lct.cut(3, 1));
+        assert!(!lct.connected(3, 0));
+        lct.set(1, 10);
+        assert_eq!(lct.get(1), 10);
+        assert_eq!(lct.fold(2, 0), Some(14));
+        let forest = lct.into_inner();
+        assert_eq!(forest.edge_count(), 2);
+        let mut lct = LinkCutTree::<Sum<i64>>::new(3);
+        assert!(lct.link(2, 0));
+        assert_eq!(lct.fold(0, 2), Some(0));
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+
+/// 森の操作が失敗した理由
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LctError {
+    /// 頂点が木の根で、親を持たない
+    IsRoot(usize),
+    /// 頂点が木の根でない
+    NotRoot(usize),
+    /// 頂点の番号が範囲外
+    OutOfRange(usize),
+    /// ancestor が descendant の祖先である
+    Ancestor { ancestor: usize, descendant: usize },
+    /// ancestor が descendant の祖先でない
+    NotAncestor { ancestor: usize, descendant: usize },
+    /// 2 頂点が辺で結ばれていない
+    NotAdjacent(usize, usize),
+    /// 2 頂点がすでに同じ木に属している
+    Connected(usize, usize),
+    /// 別の森の Handle が渡された（頂点の番号）
+    ForeignHandle(usize),
+    /// 頂点の次数が上限に達している
+    DegreeLimitExceeded(usize),
+}
+
+impl fmt::Display for LctError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LctError::IsRoot(v) => write!(f, "vertex {} is a root", v),
+            LctError::NotRoot(v) => write!(f, "vertex {} is not a root", v),
+            LctError::OutOfRange(v) => write!(f, "vertex {} is out of range", v),
+            LctError::Ancestor { ancestor, descendant } => {
+                write!(f, "vertex {} is an ancestor of vertex {}", ancestor, descendant)
+            }
+            LctError::NotAncestor { ancestor, descendant } => {
+                write!(f, "vertex {} is not an ancestor of vertex {}", ancestor, descendant)
+            }
+            LctError::NotAdjacent(u, v) => write!(f, "vertices {} and {} are not adjacent", u, v),
+            LctError::Connected(u, v) => write!(f, "vertices {} and {} are already connected", u, v),
+            LctError::ForeignHandle(v) => write!(f, "vertex {} belongs to another forest", v),
+            LctError::DegreeLimitExceeded(v) => write!(f, "vertex {} has reached its degree limit", v),
+        }
+    }
+}
+
+impl std::error::Error for LctError {}
+
+/// CSV の辺の列を読めなかった理由（line は 1 始まりの行番号）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CsvError {
+    /// 入力を読めなかった
+    Io { line: usize, message: String },
+    /// 行が `u,v` か `u,v,w` の形でない
+    Parse { line: usize, message: String },
+    /// 辺を加えられない（閉路を作るなど）
+    Edge { line: usize, error: LctError },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io { line, message } | CsvError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            CsvError::Edge { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
@@ -0,0 +1,273 @@
+use crate::monoid::Monoid;
+use crate::splay::SplayTree;
+use crate::Dir;
+
+/// 頂点数が N に固定された森（節点を配列に直接並べ、ヒープを使わない）
+/// 組み込み向けに core の機能だけで書いてある
+/// 辺の重み・遅延作用・部分木の集約は持たない
+pub struct FixedForest<const N: usize, M: Monoid = ()> {
+    nodes: [FixedNode<M::Value>; N],
+}
+
+struct FixedNode<T> {
+    parent: Option<usize>,
+    children: [Option<usize>; 2],
+    rev: bool,
+    value: T,
+    sum: T,
+    rev_sum: T,
+}
+
+impl<const N: usize, M: Monoid> Default for FixedForest<N, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, M: Monoid> FixedForest<N, M> {
+    /// N 頂点で辺がなく、値がすべて単位元の森
+    pub fn new() -> Self {
+        let nodes = core::array::from_fn(|_| FixedNode {
+            parent: None,
+            children: [None, None],
+            rev: false,
+            value: M::identity(),
+            sum: M::identity(),
+            rev_sum: M::identity(),
+        });
+        Self { nodes }
+    }
+
+    pub fn len(&self) -> usize {
+        N
+    }
+
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// 部分木を反転する（子への伝播は遅延させる）
+    fn toggle(&mut self, x: usize) {
+        let node = &mut self.nodes[x];
+        node.children.swap(0, 1);
+        core::mem::swap(&mut node.sum, &mut node.rev_sum);
+        node.rev ^= true;
+    }
+
+    /// 根から x までを優先パスにし、x を splay 木の根にする
+    /// 最後に優先パスに合流した頂点を返す
+    fn expose(&mut self, x: usize) -> usize {
+        let mut last = None;
+        let mut cur = Some(x);
+        let mut joined = x;
+        while let Some(y) = cur {
+            self.splay(y);
+            self.nodes[y].children[Dir::Right.index()] = last;
+            self.update(y);
+            joined = y;
+            last = Some(y);
+            cur = self.nodes[y].parent;
+        }
+        self.splay(x);
+        joined
+    }
+
+    pub fn value(&self, v: usize) -> &M::Value {
+        &self.nodes[v].value
+    }
+
+    pub fn set_value(&mut self, v: usize, value: M::Value) {
+        self.expose(v);
+        self.nodes[v].value = value;
+        self.update(v);
+    }
+
+    /// v を木の根にする
+    pub fn evert(&mut self, v: usize) {
+        self.expose(v);
+        self.toggle(v);
+        self.push(v);
+    }
+
+    /// v を含む木の根
+    pub fn root(&mut self, v: usize) -> usize {
+        self.expose(v);
+        let mut x = v;
+        loop {
+            self.push(x);
+            match self.child(x, Dir::Left) {
+                Some(left) => x = left,
+                None => break,
+            }
+        }
+        self.splay(x);
+        x
+    }
+
+    pub fn connected(&mut self, u: usize, v: usize) -> bool {
+        self.root(u) == self.root(v)
+    }
+
+    /// v の親
+    pub fn parent(&mut self, v: usize) -> Option<usize> {
+        self.expose(v);
+        let mut x = self.child(v, Dir::Left)?;
+        loop {
+            self.push(x);
+            match self.child(x, Dir::Right) {
+                Some(right) => x = right,
+                None => break,
+            }
+        }
+        self.splay(x);
+        Some(x)
+    }
+
+    /// 木の根 child の親を parent にする
+    pub fn link(&mut self, child: usize, parent: usize) {
+        assert_eq!(self.root(child), child, "child must be the root of its tree");
+        assert_ne!(self.root(parent), child, "link would create a cycle");
+        self.expose(child);
+        self.nodes[child].parent = Some(parent);
+    }
+
+    /// v を親から切り離し、親を返す
+    pub fn cut(&mut self, v: usize) -> usize {
+        let parent = self.parent(v).expect("v must not be a root");
+        self.expose(v);
+        let left = self.child(v, Dir::Left).unwrap();
+        self.nodes[left].parent = None;
+        self.nodes[v].children[Dir::Left.index()] = None;
+        self.update(v);
+        parent
+    }
+
+    /// u と v の最小共通祖先（異なる木に属するなら None）
+    pub fn lca(&mut self, u: usize, v: usize) -> Option<usize> {
+        if !self.connected(u, v) {
+            return None;
+        }
+        self.expose(u);
+        Some(self.expose(v))
+    }
+
+    /// u から v へのパス上の頂点の値の集約（異なる木に属するなら None）
+    pub fn path_query(&mut self, u: usize, v: usize) -> Option<M::Value> {
+        let root = self.root(u);
+        if self.root(v) != root {
+            return None;
+        }
+        self.evert(u);
+        self.expose(v);
+        let sum = self.nodes[v].sum.clone();
+        self.evert(root);
+        Some(sum)
+    }
+}
+
+/// 既定の push_path は Vec を使うので、ヒープを使わない再帰に置き換える
+impl<const N: usize, M: Monoid> SplayTree for FixedForest<N, M> {
+    fn parent_of(&self, x: usize) -> Option<usize> {
+        self.nodes[x].parent
+    }
+
+    fn set_parent_of(&mut self, x: usize, parent: Option<usize>) {
+        self.nodes[x].parent = parent;
+    }
+
+    fn child(&self, x: usize, dir: Dir) -> Option<usize> {
+        self.nodes[x].children[dir.index()]
+    }
+
+    fn set_child(&mut self, x: usize, dir: Dir, child: Option<usize>) {
+        self.nodes[x].children[dir.index()] = child;
+    }
+
+    fn push(&mut self, x: usize) {
+        if self.nodes[x].rev {
+            self.nodes[x].rev = false;
+            for child in self.nodes[x].children.into_iter().flatten() {
+                self.toggle(child);
+            }
+        }
+    }
+
+    fn update(&mut self, x: usize) {
+        let [left, right] = self.nodes[x].children;
+        let mut sum = self.nodes[x].value.clone();
+        let mut rev_sum = sum.clone();
+        if let Some(left) = left {
+            sum = M::op(&self.nodes[left].sum, &sum);
+            rev_sum = M::op(&rev_sum, &self.nodes[left].rev_sum);
+        }
+        if let Some(right) = right {
+            sum = M::op(&sum, &self.nodes[right].sum);
+            rev_sum = M::op(&self.nodes[right].rev_sum, &rev_sum);
+        }
+        self.nodes[x].sum = sum;
+        self.nodes[x].rev_sum = rev_sum;
+    }
+
+    fn push_path(&mut self, x: usize) -> usize {
+        let depth = match self.dir(x) {
+            Some(_) => self.push_path(self.nodes[x].parent.unwrap()) + 1,
+            None => 0,
+        };
+        self.push(x);
+        depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+    use crate::test_util::xorshift;
+    use crate::Forest;
+
+    #[test]
+    fn matches_forest() {
+        let mut rand = xorshift(88172645463325252);
+        const N: usize = 20;
+        let mut fixed = FixedForest::<N, Sum<i64>>::new();
+        let mut forest = Forest::<Sum<i64>>::new();
+        for v in 0 .. N {
+            fixed.set_value(v, v as i64);
+            forest.add_node(v as i64);
+        }
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(N), rand(N));
+            match rand(6) {
+                0 => {
+                    if forest.root(u) == u && forest.root(v) != u {
+                        fixed.link(u, v);
+                        forest.link(u, v);
+                    }
+                }
+                1 => {
+                    if let Some(p) = forest.parent(u) {
+                        assert_eq!(fixed.cut(u), p);
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    fixed.evert(u);
+                    forest.evert(u);
+                }
+                3 => {
+                    let x = rand(100) as i64;
+                    fixed.set_value(u, x);
+                    forest.set_value(u, x);
+                }
+                _ => {
+                    assert_eq!(fixed.root(u), forest.root(u));
+                    assert_eq!(fixed.parent(u), forest.parent(u));
+                    assert_eq!(fixed.lca(u, v), forest.lca(u, v));
+                    assert_eq!(fixed.path_query(u, v), forest.path_query(u, v));
+                }
+            }
+        }
+        assert_eq!(fixed.len(), N);
+        assert_eq!(*fixed.value(0), *forest.value(0));
+    }
+}
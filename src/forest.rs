@@ -0,0 +1,3341 @@
+mod arith;
+mod assign;
+mod batch;
+mod beats;
+mod builder;
+mod centroid;
+mod component;
+mod csv;
+mod cursor;
+mod dsu;
+mod export;
+mod exposed;
+mod flow;
+mod frozen;
+mod gc;
+mod handle;
+mod hook;
+mod inspect;
+mod marked;
+mod merge;
+mod parens;
+mod peek;
+mod subtree;
+mod super_root;
+mod transform;
+mod transaction;
+mod traverse;
+
+pub use builder::ForestBuilder;
+pub use cursor::Cursor;
+pub use export::GraphmlValue;
+pub use exposed::ExposedPath;
+pub use frozen::{edit_script, Edit, Frozen, FrozenAnswer, FrozenQuery, PathOrder};
+#[cfg(feature = "parallel")]
+pub use frozen::par_path_queries;
+#[cfg(feature = "snapshot")]
+pub use frozen::{FrozenView, SnapshotError, SnapshotValue};
+pub use handle::Handle;
+pub use hook::HookNode;
+pub use parens::Parentheses;
+use hook::UpdateHook;
+pub use inspect::SplayNode;
+pub use transaction::Transaction;
+pub use traverse::{AtDepth, Bfs, PathIter, Preorder};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::splay::SplayTree;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+#[cfg(feature = "tracing")]
+use crate::trace::{TraceEvent, TraceNode};
+use crate::{Dir, LctError, Observer};
+
+/// tracing 機能が有効なときだけ事象を記録する
+macro_rules! trace {
+    ($event:expr) => {
+        #[cfg(feature = "tracing")]
+        crate::trace::emit($event);
+    };
+}
+
+// 頂点 v は節点 2v、辺 e は節点 2e + 1 として同じ配列に並べる。
+// 森の辺の数は頂点数未満なので、辺の番号は常に頂点数未満に収まる。
+
+/// 頂点 v の節点
+fn vertex_node(v: usize) -> usize {
+    v << 1
+}
+
+/// 辺 e の節点
+fn edge_node(e: usize) -> usize {
+    e << 1 | 1
+}
+
+fn is_vertex(x: usize) -> bool {
+    x & 1 == 0
+}
+
+/// g（なければ恒等写像）を作用させた後に f を作用させる写像
+fn compose<M: Monoid, E: Monoid, A: Action<M, E>>(f: &A::Map, g: &Option<A::Map>) -> A::Map {
+    match g {
+        Some(g) => A::compose(f, g),
+        None => f.clone(),
+    }
+}
+
+/// 森 Forest<M, E, A, S, .., N> の節点の型（NodeStore を選ぶときに使う）
+pub type ForestNodeOf<M, E, A, S> = ForestNode<
+    <M as Monoid>::Value,
+    <E as Monoid>::Value,
+    <A as Action<M, E>>::Map,
+    <S as Subtree<M, E>>::Virtual,
+    <S as Subtree<M, E>>::Sum,
+>;
+
+/// 森の節点（頂点か辺の一つ。中身は森の外からは見えない）
+#[derive(Clone)]
+pub struct ForestNode<T, W, F, V, U> {
+    parent: Option<usize>,
+    children: [Option<usize>; 2],
+    /// 部分木に含まれる頂点の数
+    len: usize,
+    /// 部分木に含まれる辺の数
+    edge_len: usize,
+    rev: bool,
+    /// 子に伝播していない写像
+    lazy: Option<F>,
+    /// 子に垂れ下がる軽い子の部分木に伝播していない、部分木への写像
+    hanging: Option<F>,
+    /// 軽い子の部分木すべてにこれまで作用させた部分木への写像を合成したもの
+    light: Option<F>,
+    /// 軽い子をなす splay 木の根のとき、最後に受け取った時点での path-parent の light
+    base: Option<F>,
+    value: T,
+    sum: T,
+    rev_sum: T,
+    weight: W,
+    weight_sum: W,
+    weight_rev_sum: W,
+    /// 軽い子の部分木の集約
+    virt: V,
+    /// 軽い子の部分木の頂点の数
+    virt_len: usize,
+    /// splay 木の部分木と、そこに垂れ下がる部分木の集約
+    total: U,
+    rev_total: U,
+    /// splay 木の部分木と、そこに垂れ下がる部分木の頂点の数
+    total_len: usize,
+}
+
+impl<T: Clone, W: Clone, F, V, U: Clone> ForestNode<T, W, F, V, U> {
+    fn new(value: T, weight: W, len: usize, virt: V, total: U) -> Self {
+        Self {
+            parent: None,
+            children: [None, None],
+            len,
+            edge_len: (len == 0) as usize,
+            rev: false,
+            lazy: None,
+            hanging: None,
+            light: None,
+            base: None,
+            sum: value.clone(),
+            rev_sum: value.clone(),
+            value,
+            weight_sum: weight.clone(),
+            weight_rev_sum: weight.clone(),
+            weight,
+            virt,
+            virt_len: 0,
+            rev_total: total.clone(),
+            total,
+            total_len: len,
+        }
+    }
+}
+
+/// FileStore に置くための書き出し（None の添字は usize::MAX として書く）
+#[cfg(feature = "snapshot")]
+impl<T: SnapshotValue, W: SnapshotValue, F: SnapshotValue, V: SnapshotValue, U: SnapshotValue> SnapshotValue for ForestNode<T, W, F, V, U> {
+    const SIZE: usize = 8 * 7 + 1 + (1 + F::SIZE) * 4 + T::SIZE * 3 + W::SIZE * 3 + V::SIZE + U::SIZE * 2;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        for x in [self.parent, self.children[0], self.children[1]] {
+            x.unwrap_or(usize::MAX).write(out);
+        }
+        for x in [self.len, self.edge_len, self.virt_len, self.total_len] {
+            x.write(out);
+        }
+        out.push(self.rev as u8);
+        for f in [&self.lazy, &self.hanging, &self.light, &self.base] {
+            out.push(f.is_some() as u8);
+            match f {
+                Some(f) => f.write(out),
+                None => out.resize(out.len() + F::SIZE, 0),
+            }
+        }
+        for x in [&self.value, &self.sum, &self.rev_sum] {
+            x.write(out);
+        }
+        for x in [&self.weight, &self.weight_sum, &self.weight_rev_sum] {
+            x.write(out);
+        }
+        self.virt.write(out);
+        self.total.write(out);
+        self.rev_total.write(out);
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        let mut offset = 0;
+        let mut next = |size: usize| {
+            offset += size;
+            &bytes[offset - size .. offset]
+        };
+        let mut index = || Some(usize::read(next(8))).filter(|&x| x != usize::MAX);
+        let (parent, left, right) = (index(), index(), index());
+        let [len, edge_len, virt_len, total_len] = [(); 4].map(|_| usize::read(next(8)));
+        let rev = next(1)[0] != 0;
+        let [lazy, hanging, light, base] = [(); 4].map(|_| {
+            let some = next(1)[0] != 0;
+            let f = F::read(next(F::SIZE));
+            some.then_some(f)
+        });
+        Self {
+            parent,
+            children: [left, right],
+            len,
+            edge_len,
+            rev,
+            lazy,
+            hanging,
+            light,
+            base,
+            value: T::read(next(T::SIZE)),
+            sum: T::read(next(T::SIZE)),
+            rev_sum: T::read(next(T::SIZE)),
+            weight: W::read(next(W::SIZE)),
+            weight_sum: W::read(next(W::SIZE)),
+            weight_rev_sum: W::read(next(W::SIZE)),
+            virt: V::read(next(V::SIZE)),
+            virt_len,
+            total: U::read(next(U::SIZE)),
+            rev_total: U::read(next(U::SIZE)),
+            total_len,
+        }
+    }
+}
+
+/// つないだときに振られ、切るまで変わらない辺の番号（切った辺の番号は再利用しない）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EdgeId(pub usize);
+
+/// 木の番号（evert では変わらず、link・cut で新しい番号になる）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ComponentId(pub usize);
+
+/// 辺の両端と、両端の隣接リストでの位置
+#[derive(Clone, Default)]
+struct Edge {
+    ends: [usize; 2],
+    pos: [usize; 2],
+    id: EdgeId,
+    /// 同じ二頂点を結ぶ並列な辺の数（多重度）
+    copies: usize,
+}
+
+/// 部分木への写像の関数（subtree_apply で SubtreeAction から取り出す）
+/// 森の型は SubtreeAction を求めないので、作用させ始めてから関数として持つ
+struct SubtreeOps<F, V, U> {
+    act_virtual: fn(&F, &V, usize) -> V,
+    act_subtree: fn(&F, &U, usize) -> U,
+    since: fn(&F, &F) -> F,
+}
+
+impl<F, V, U> Clone for SubtreeOps<F, V, U> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F, V, U> Copy for SubtreeOps<F, V, U> {}
+
+/// 優先パスへの分解の様子
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DecompositionStats {
+    /// 優先パスの数
+    pub paths: usize,
+    /// 優先パスの頂点数ごとの、パスの数
+    pub lengths: BTreeMap<usize, usize>,
+    /// 両端が別の優先パスにある辺の数
+    pub light_edges: usize,
+}
+
+/// 統計を有効にした森（ForestBuilder::statistics）で数える操作の回数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpStats {
+    /// expose を呼んだ回数（すでに露出していて何もしなかったものも含む）
+    pub exposes: u64,
+    /// splay の回数
+    pub splays: u64,
+    /// splay で回した回数
+    pub rotations: u64,
+}
+
+/// toggle_edge で行われた操作
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Toggle {
+    Linked,
+    Cut,
+}
+
+/// try_link で結べなかった理由
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkError {
+    /// すでに同じ木に属している
+    Cycle(CyclePath),
+    /// 頂点の次数が set_degree_limit の上限に達している
+    DegreeLimitExceeded(usize),
+}
+
+/// try_link で結べなかったときの、u から v へのすでにあるパス
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CyclePath {
+    /// u から v への順の頂点
+    pub vertices: Vec<usize>,
+    /// u から v への順の辺
+    pub edges: Vec<EdgeId>,
+}
+
+/// 写像を持たない森では値がつねに確定している
+impl<M: Monoid, E: Monoid, N: NodeStore<ForestNodeOf<M, E, (), ()>>> Index<usize> for Forest<M, E, (), (), N> {
+    type Output = M::Value;
+
+    fn index(&self, v: usize) -> &M::Value {
+        &self.nodes[vertex_node(v)].value
+    }
+}
+
+/// 書き換えた頂点は次の操作の前に集約が直される
+impl<M: Monoid, E: Monoid, N: NodeStore<ForestNodeOf<M, E, (), ()>>> IndexMut<usize> for Forest<M, E, (), (), N> {
+    fn index_mut(&mut self, v: usize) -> &mut M::Value {
+        let x = vertex_node(v);
+        self.dirty.push(x);
+        &mut self.nodes[x].value
+    }
+}
+
+/// 辺 (u, v) を connect で追加する（閉路ができる場合は panic）
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Extend<(usize, usize)> for Forest<M, E, A, S, N> {
+    fn extend<I: IntoIterator<Item = (usize, usize)>>(&mut self, edges: I) {
+        if let Err(err) = self.try_extend(edges) {
+            panic!("{}", err);
+        }
+    }
+}
+
+/// 辺の列から森を作る（頂点の値は単位元）
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>> + Default> FromIterator<(usize, usize)> for Forest<M, E, A, S, N> {
+    fn from_iter<I: IntoIterator<Item = (usize, usize)>>(edges: I) -> Self {
+        let mut forest = Self::default();
+        forest.extend(edges);
+        forest
+    }
+}
+
+/// 根付き木を入れ子で表したもの
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeRepr<T> {
+    pub value: T,
+    pub children: Vec<TreeRepr<T>>,
+}
+
+/// 頂点を添字で管理する link-cut tree
+/// 頂点の値を M で、辺の重みを E で、それぞれ独立に集約する
+/// A はパス上の値と重みにまとめて作用させる写像、S は部分木全体の集約
+///
+/// 子をたどる操作（children、各種の iter、to_nested、to_ascii、to_svg など）は隣接リストの順に子を並べる
+/// 隣接リストの順は link と cut の列だけで決まり、問い合わせによる splay 木の形には左右されない
+/// new_ordered ならつないだ順、new_sorted なら番号の順で、どちらでもなければ切った辺の位置に末尾の辺を移す
+///
+/// 各操作の O(log n) は償却の評価で、一回の操作が O(n) かかることもある
+/// 補助の木は splay 木に固定する（treap などにしても、優先パスの付け替えの回数は償却でしか抑えられない）
+/// 節点は N に置く（既定は Vec。NodeStore を実装すれば自前の置き場所を使える）
+/// N を差し替えた森は new ではなく default か builder で作る
+/// 形を変えない間の問い合わせに最悪の時間の上限が要るなら、freeze で作る Frozen を使う
+pub struct Forest<M: Monoid = (), E: Monoid = (), A: Action<M, E> = (), S: Subtree<M, E> = (), N: NodeStore<ForestNodeOf<M, E, A, S>> = Vec<ForestNodeOf<M, E, A, S>>> {
+    nodes: N,
+    free_edges: Vec<usize>,
+    edges: Vec<Edge>,
+    /// 各頂点に接する辺（根の向きによらない）
+    adjacency: Vec<Vec<usize>>,
+    /// IndexMut で値を書き換えられ、集約を直す必要のある節点
+    dirty: Vec<usize>,
+    /// begin_batch から end_batch までの間か
+    batching: bool,
+    /// バッチ中に set_value で溜めた (節点, 値)
+    pending: Vec<(usize, M::Value)>,
+    /// 辺を切っても隣接リストをつないだ順に保つか
+    ordered: bool,
+    /// 隣接リストを隣の頂点の番号の順に保つか
+    sorted: bool,
+    /// 木をまとめてつなぐための番兵の頂点
+    super_root: Option<usize>,
+    /// 辺の番号から辺の添字
+    edge_ids: HashMap<EdgeId, usize>,
+    next_edge_id: usize,
+    /// 木の根の頂点に付けた値
+    component_data: HashMap<usize, u64>,
+    /// 木の根の頂点に振った木の番号
+    component_ids: HashMap<usize, ComponentId>,
+    next_component_id: usize,
+    /// 頂点ごとの次数の上限（上限のない頂点は持たない）
+    degree_limits: HashMap<usize, usize>,
+    /// 頂点ごとの pin の数（gc はこの頂点を含む木を残す）
+    pins: HashMap<usize, usize>,
+    /// 節点を直すたびに呼び、summaries を求めるもの
+    update_hook: Option<UpdateHook<M::Value, E::Value>>,
+    /// 節点ごとの、splay 木の部分木についての update_hook の要約
+    summaries: Vec<u64>,
+    /// 部分木への写像を作用させたことがあれば、軽い子への遅延を扱う関数
+    subtree_ops: Option<SubtreeOps<A::Map, S::Virtual, S::Sum>>,
+    /// 統計を有効にしていれば、これまでの操作の回数
+    stats: Option<OpStats>,
+    /// link・cut・evert で構造を変えるたびに増える番号
+    version: u64,
+    /// 形が変わるたびに呼ぶもの
+    observers: Vec<Box<dyn Observer + Send + Sync>>,
+    /// 森ごとに異なる番号（Handle の持ち主を確かめる）
+    id: usize,
+    marker: PhantomData<(A, S)>,
+}
+
+/// N を既定のものから差し替えた森はここから作る（new は既定の森だけにある）
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>> + Default> Default for Forest<M, E, A, S, N> {
+    fn default() -> Self {
+        Self::with_store(N::default())
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 節点を nodes に置く空の森（Default を持たない置き場所のとき使う）
+    pub fn with_store(mut nodes: N) -> Self {
+        nodes.clear();
+        Self {
+            nodes,
+            free_edges: Vec::new(),
+            edges: Vec::new(),
+            adjacency: Vec::new(),
+            dirty: Vec::new(),
+            batching: false,
+            pending: Vec::new(),
+            ordered: false,
+            sorted: false,
+            super_root: None,
+            edge_ids: HashMap::new(),
+            next_edge_id: 0,
+            component_data: HashMap::new(),
+            component_ids: HashMap::new(),
+            next_component_id: 0,
+            degree_limits: HashMap::new(),
+            pins: HashMap::new(),
+            update_hook: None,
+            summaries: vec![],
+            subtree_ops: None,
+            stats: None,
+            version: 0,
+            observers: vec![],
+            id: handle::new_forest_id(),
+            marker: PhantomData,
+        }
+    }
+
+    /// 節点の置き場所（FileStore の resident_pages を見るときなどに使う）
+    pub fn store(&self) -> &N {
+        &self.nodes
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> Forest<M, E, A, S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 各頂点の子をつないだ順に並べて保つ森
+    /// 辺を切るたびに隣接リストを詰めるので、cut は次数に比例する時間がかかる
+    pub fn new_ordered() -> Self {
+        Self { ordered: true, ..Self::default() }
+    }
+
+    /// 各頂点の子を番号の順に並べて保つ森（出力が link と cut の順にもよらない）
+    /// link と cut は次数に比例する時間がかかる
+    pub fn new_sorted() -> Self {
+        Self { sorted: true, ..Self::default() }
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 構造の版の番号（link・cut・evert のたびに増え、減ることはない）
+    /// 前に読んだ値と同じなら、その間に森の形は変わっていない（頂点の値や辺の重みの変更は数えない）
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// 頂点数
+    pub fn len(&self) -> usize {
+        self.nodes.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 辺の数
+    pub fn edge_count(&self) -> usize {
+        self.edges.len() - self.free_edges.len()
+    }
+
+    /// 木の数（森では頂点の数から辺の数を引いたもの）
+    pub fn component_count(&self) -> usize {
+        self.len() - self.edge_count()
+    }
+
+    /// 軽い子を持たない節点（len が 1 なら頂点、0 なら辺）
+    fn new_node(value: M::Value, weight: E::Value, len: usize) -> ForestNodeOf<M, E, A, S> {
+        let virt = S::empty();
+        let total = if len == 1 { S::vertex(&value, &virt) } else { S::edge(&weight, &virt) };
+        ForestNode::new(value, weight, len, virt, total)
+    }
+
+    /// 値 value を持つ孤立点を追加し、その番号を返す
+    pub fn add_node(&mut self, value: M::Value) -> usize {
+        let id = self.len();
+        self.nodes.alloc(Self::new_node(value, E::identity(), 1));
+        self.nodes.alloc(Self::new_node(M::identity(), E::identity(), 0));
+        self.adjacency.push(Vec::new());
+        if self.update_hook.is_some() {
+            self.run_update_hook(vertex_node(id));
+        }
+        id
+    }
+
+    /// 頂点数が n になるまで値が単位元の孤立点を追加する
+    pub fn grow(&mut self, n: usize) {
+        while self.len() < n {
+            self.add_node(M::identity());
+        }
+    }
+
+    /// 辺の列から森を作る（頂点の値は単位元）
+    pub fn try_from_edges(edges: impl IntoIterator<Item = (usize, usize)>) -> Result<Self, LctError>
+    where
+        N: Default,
+    {
+        let mut forest = Self::default();
+        forest.try_extend(edges)?;
+        Ok(forest)
+    }
+
+    /// 頂点 i の値と親が `nodes[i]` である森を作る（アリーナ型の木からの変換に使う）
+    /// 親をたどって閉路ができるなら、閉路を閉じる辺の子と親を返す
+    /// 親の番号が頂点の数以上なら OutOfRange
+    pub fn from_parents(nodes: impl IntoIterator<Item = (M::Value, Option<usize>)>) -> Result<Self, LctError>
+    where
+        N: Default,
+    {
+        let mut forest = Self::default();
+        let mut parents = vec![];
+        for (value, parent) in nodes {
+            forest.add_node(value);
+            parents.push(parent);
+        }
+        for (v, parent) in parents.into_iter().enumerate() {
+            if let Some(p) = parent {
+                if p >= forest.len() {
+                    return Err(LctError::OutOfRange(p));
+                }
+                // v はまだ親を持たないので木の根
+                if forest.root(p) == v {
+                    return Err(LctError::Ancestor { ancestor: v, descendant: p });
+                }
+                forest.link(v, p);
+            }
+        }
+        Ok(forest)
+    }
+
+    /// 各頂点の親を番号の順に並べる（from_parents の逆）
+    pub fn to_parents(&mut self) -> Vec<Option<usize>> {
+        (0 .. self.len()).map(|v| self.parent(v)).collect()
+    }
+
+    /// 辺 (u, v) を順に connect で追加し、足りない頂点は単位元の値で作る
+    /// 重複した辺や閉路を作る辺があればそこで止めて返す
+    pub fn try_extend(&mut self, edges: impl IntoIterator<Item = (usize, usize)>) -> Result<(), LctError> {
+        for (u, v) in edges {
+            self.grow(u.max(v) + 1);
+            self.connect(u, v)?;
+        }
+        Ok(())
+    }
+
+    /// すべての頂点を削除する（確保した領域は再利用する）
+    pub fn clear(&mut self) {
+        self.version += 1;
+        self.super_root = None;
+        self.edge_ids.clear();
+        self.component_data.clear();
+        self.component_ids.clear();
+        self.degree_limits.clear();
+        self.pins.clear();
+        self.summaries.clear();
+        self.dirty.clear();
+        self.pending.clear();
+        self.nodes.clear();
+        self.free_edges.clear();
+        self.edges.clear();
+        self.adjacency.clear();
+    }
+
+    /// 頂点と値を残したまますべての辺を削除する
+    pub fn clear_edges(&mut self) {
+        self.version += 1;
+        // 作用させていない写像を頂点まで伝播してから切り離す
+        for v in 0 .. self.len() {
+            self.reach(vertex_node(v));
+        }
+        for x in 0 .. self.nodes.len() {
+            if is_vertex(x) {
+                let node = &mut self.nodes[x];
+                node.parent = None;
+                node.children = [None, None];
+                node.rev = false;
+                node.lazy = None;
+                node.hanging = None;
+                node.light = None;
+                node.base = None;
+                node.virt = S::empty();
+                node.virt_len = 0;
+                self.update(x);
+            } else {
+                self.nodes[x] = Self::new_node(M::identity(), E::identity(), 0);
+            }
+        }
+        self.free_edges.clear();
+        self.edges.clear();
+        self.edge_ids.clear();
+        self.component_data.clear();
+        self.component_ids.clear();
+        for adjacency in &mut self.adjacency {
+            adjacency.clear();
+        }
+    }
+
+    /// v の値（作用させていない写像があれば先に作用させる）
+    pub fn value(&mut self, v: usize) -> &M::Value {
+        let x = vertex_node(v);
+        self.reach(x);
+        &self.nodes[x].value
+    }
+
+    /// 節点 x の辺の重み（作用させていない写像があれば先に作用させる）
+    fn weight(&mut self, x: usize) -> &E::Value {
+        self.reach(x);
+        &self.nodes[x].weight
+    }
+
+    pub fn set_value(&mut self, v: usize, value: M::Value) {
+        if self.batching {
+            self.pending.push((vertex_node(v), value));
+            return;
+        }
+        self.with_value_mut(v, |x| *x = value);
+    }
+
+    /// v の値を f で書き換え、集約を直す
+    pub fn with_value_mut<R>(&mut self, v: usize, f: impl FnOnce(&mut M::Value) -> R) -> R {
+        let x = vertex_node(v);
+        self.expose(x);
+        let result = f(&mut self.nodes[x].value);
+        self.update(x);
+        result
+    }
+
+    /// 添字 e の辺の重みを weight にし、集約を直す
+    fn set_weight(&mut self, e: usize, weight: E::Value) {
+        let x = edge_node(e);
+        // 下の端点を expose すると、辺は根からの列の splay 木に入る
+        let [a, b] = self.edges[e].ends;
+        self.expose(vertex_node(a));
+        if self.splay_root(x) != vertex_node(a) {
+            self.expose(vertex_node(b));
+        }
+        self.splay(x);
+        self.nodes[x].weight = weight;
+        self.update(x);
+    }
+
+    /// u と v を結ぶ辺の重み
+    pub fn edge_weight(&mut self, u: usize, v: usize) -> Option<&E::Value> {
+        let e = self.edge_ids[&self.edge(u, v)?];
+        Some(self.weight(edge_node(e)))
+    }
+
+    /// u と v を結ぶ辺の重みを weight にする
+    pub fn set_edge_weight(&mut self, u: usize, v: usize, weight: E::Value) -> Result<(), LctError> {
+        let id = self.edge(u, v).ok_or(LctError::NotAdjacent(u, v))?;
+        self.set_weight(self.edge_ids[&id], weight);
+        Ok(())
+    }
+
+    /// 番号 id の辺の重みを weight にし、辺があったかを返す
+    pub fn set_edge_weight_by_id(&mut self, id: EdgeId, weight: E::Value) -> bool {
+        let Some(&e) = self.edge_ids.get(&id) else { return false };
+        self.set_weight(e, weight);
+        true
+    }
+
+    /// a と b を結ぶ辺の節点を確保する
+    fn alloc_edge(&mut self, a: usize, b: usize, weight: E::Value) -> usize {
+        let e = self.free_edges.pop().unwrap_or_else(|| {
+            self.edges.push(Edge::default());
+            self.edges.len() - 1
+        });
+        let pos = [a, b].map(|v| {
+            let w = v ^ a ^ b;
+            if self.sorted { self.adjacency[v].partition_point(|&f| self.opposite(f, v) < w) } else { self.adjacency[v].len() }
+        });
+        self.adjacency[a].insert(pos[0], e);
+        self.adjacency[b].insert(pos[1], e);
+        let id = self.new_edge_id(e);
+        self.edges[e] = Edge { ends: [a, b], pos, id, copies: 1 };
+        if self.sorted {
+            for (v, start) in [(a, pos[0]), (b, pos[1])] {
+                self.renumber(v, start + 1 .. self.adjacency[v].len());
+            }
+        }
+        let x = edge_node(e);
+        self.nodes[x] = Self::new_node(M::identity(), weight, 0);
+        x
+    }
+
+    /// 添字 e の辺に新しい番号を振る
+    fn new_edge_id(&mut self, e: usize) -> EdgeId {
+        let id = EdgeId(self.next_edge_id);
+        self.next_edge_id += 1;
+        self.edge_ids.insert(id, e);
+        id
+    }
+
+    /// v の隣接リストの positions の位置にある辺に、その位置を覚え直させる
+    fn renumber(&mut self, v: usize, positions: std::ops::Range<usize>) {
+        for pos in positions {
+            let edge = &mut self.edges[self.adjacency[v][pos]];
+            let side = (edge.ends[1] == v) as usize;
+            edge.pos[side] = pos;
+        }
+    }
+
+    /// 辺の節点を解放し、その重みを返す
+    fn free_edge(&mut self, x: usize) -> E::Value {
+        let e = x >> 1;
+        self.edge_ids.remove(&self.edges[e].id);
+        for side in 0 .. 2 {
+            let (v, pos) = (self.edges[e].ends[side], self.edges[e].pos[side]);
+            // 順序を保つなら詰め、そうでなければ末尾の辺を移す
+            let moved = if self.ordered || self.sorted {
+                self.adjacency[v].remove(pos);
+                pos .. self.adjacency[v].len()
+            } else {
+                self.adjacency[v].swap_remove(pos);
+                pos .. self.adjacency[v].len().min(pos + 1)
+            };
+            self.renumber(v, moved);
+        }
+        let node = std::mem::replace(&mut self.nodes[x], Self::new_node(M::identity(), E::identity(), 0));
+        self.free_edges.push(e);
+        node.weight
+    }
+
+    /// e の v でない方の端点
+    fn opposite(&self, e: usize, v: usize) -> usize {
+        let [a, b] = self.edges[e].ends;
+        if a == v { b } else { a }
+    }
+
+    /// u と v を結ぶ辺の番号
+    pub fn edge(&self, u: usize, v: usize) -> Option<EdgeId> {
+        self.edge_slot(u, v).map(|e| self.edges[e].id)
+    }
+
+    /// u と v を結ぶ辺の edges での位置
+    fn edge_slot(&self, u: usize, v: usize) -> Option<usize> {
+        let (u, v) = if self.degree(u) <= self.degree(v) { (u, v) } else { (v, u) };
+        self.adjacency[u].iter().copied().find(|&e| self.opposite(e, u) == v)
+    }
+
+    /// 番号 id の辺をつないだときの（子, 親）（切った辺なら None）
+    pub fn endpoints(&self, id: EdgeId) -> Option<(usize, usize)> {
+        let [child, parent] = self.edges[*self.edge_ids.get(&id)?].ends;
+        Some((child, parent))
+    }
+
+    /// u から v へのパスで、つないだときの親から子への向きと逆にたどる辺を u に近い順に並べる（異なる木なら None）
+    /// 子から親へ進む辺が逆向きで、evert で根を変えたあとも向きはつないだときのまま
+    pub fn path_reversed_edges(&mut self, u: usize, v: usize) -> Option<Vec<EdgeId>> {
+        self.with_path(u, v, |forest, top| {
+            let path = forest.collect(top);
+            path.windows(2)
+                .filter(|w| !is_vertex(w[1]) && forest.edges[w[1] >> 1].ends[0] == w[0] >> 1)
+                .map(|w| forest.edges[w[1] >> 1].id)
+                .collect()
+        })
+    }
+
+    /// v に接する辺の数（親との辺も含む）
+    pub fn degree(&self, v: usize) -> usize {
+        self.adjacency[v].len()
+    }
+
+    /// v の次数の上限を limit にする（None なら上限なし）
+    /// すでに上限を超えていても辺は切らず、以後のつなぐ操作だけを断る
+    pub fn set_degree_limit(&mut self, v: usize, limit: Option<usize>) {
+        match limit {
+            Some(limit) => self.degree_limits.insert(v, limit),
+            None => self.degree_limits.remove(&v),
+        };
+    }
+
+    pub fn degree_limit(&self, v: usize) -> Option<usize> {
+        self.degree_limits.get(&v).copied()
+    }
+
+    /// u と v を結ぶ辺を新たに加えても、両端の次数が上限を超えないか
+    pub(crate) fn check_degree(&self, u: usize, v: usize) -> Result<(), LctError> {
+        for w in [u, v] {
+            if self.degree_limit(w).is_some_and(|limit| self.degree(w) >= limit) {
+                return Err(LctError::DegreeLimitExceeded(w));
+            }
+        }
+        Ok(())
+    }
+
+    /// v に隣接する頂点（親も含む）
+    pub fn neighbors(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.adjacency[v].iter().map(move |&e| self.opposite(e, v))
+    }
+
+    /// 現在の根に関する v の子（隣接リストの順）
+    pub fn children(&mut self, v: usize) -> Vec<usize> {
+        let parent = self.parent(v);
+        self.neighbors(v).filter(|&w| Some(w) != parent).collect()
+    }
+
+    /// 部分木を反転する（子への伝播は遅延させる）
+    fn toggle(&mut self, x: usize) {
+        let node = &mut self.nodes[x];
+        node.children.swap(0, 1);
+        std::mem::swap(&mut node.sum, &mut node.rev_sum);
+        std::mem::swap(&mut node.weight_sum, &mut node.weight_rev_sum);
+        std::mem::swap(&mut node.total, &mut node.rev_total);
+        node.rev ^= true;
+    }
+
+    /// 部分木に f を作用させる（子への伝播は遅延させる）
+    fn apply(&mut self, x: usize, f: &A::Map) {
+        if !self.act_path(x, f) {
+            // 集約だけでは求められないので、子に作用させてから集約し直す
+            self.push(x);
+            for child in self.nodes[x].children.into_iter().flatten() {
+                self.apply(child, f);
+            }
+            self.update(x);
+        }
+    }
+
+    /// splay 木の部分木の頂点の値と辺の重みに f を作用させ、子への写像に合成する
+    /// 集約だけでは求められなければ false を返す（節点の値と重みは作用させてある）
+    fn act_path(&mut self, x: usize, f: &A::Map) -> bool {
+        let node = &mut self.nodes[x];
+        if is_vertex(x) {
+            node.value = A::act(f, &node.value, 1).expect("an action on a single vertex must succeed");
+        } else {
+            node.weight = A::act_weight(f, &node.weight, 1);
+        }
+        if node.edge_len > 0 {
+            node.weight_sum = A::act_weight(f, &node.weight_sum, node.edge_len);
+            node.weight_rev_sum = A::act_weight(f, &node.weight_rev_sum, node.edge_len);
+        }
+        let sums = if node.len > 0 {
+            A::act(f, &node.sum, node.len).zip(A::act(f, &node.rev_sum, node.len))
+        } else {
+            Some((node.sum.clone(), node.rev_sum.clone()))
+        };
+        match sums {
+            Some((sum, rev_sum)) => {
+                node.sum = sum;
+                node.rev_sum = rev_sum;
+                node.lazy = Some(compose::<M, E, A>(f, &node.lazy));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// splay 木の部分木とそこに垂れ下がる部分木すべてに f を作用させる
+    fn apply_subtree(&mut self, x: usize, f: &A::Map) {
+        let acted = self.act_path(x, f);
+        assert!(acted, "SubtreeAction::act must return Some");
+        self.apply_hanging(x, f);
+    }
+
+    /// 節点 x とその splay 木の子に垂れ下がる軽い子の部分木に f を作用させる（列の頂点の値はそのまま）
+    fn apply_hanging(&mut self, x: usize, f: &A::Map) {
+        let ops = self.subtree_ops.expect("subtree actions must be enabled");
+        let node = &mut self.nodes[x];
+        node.virt = (ops.act_virtual)(f, &node.virt, node.virt_len);
+        node.light = Some(compose::<M, E, A>(f, &node.light));
+        node.hanging = Some(compose::<M, E, A>(f, &node.hanging));
+        // 列の頂点にも同じ写像が作用しているとき（S が () でなければ常に）の集約
+        node.total = (ops.act_subtree)(f, &node.total, node.total_len);
+        node.rev_total = (ops.act_subtree)(f, &node.rev_total, node.total_len);
+    }
+
+    /// 軽い子をなす splay 木の根 y に、path-parent p の light のうちまだ受け取っていない分を作用させる
+    fn settle_light(&mut self, y: usize, p: usize) {
+        let Some(light) = self.nodes[p].light.clone() else {
+            return;
+        };
+        let f = match &self.nodes[y].base {
+            Some(base) => (self.subtree_ops.unwrap().since)(&light, base),
+            None => light.clone(),
+        };
+        self.apply_subtree(y, &f);
+        self.nodes[y].base = Some(light);
+    }
+
+    /// y の親より上の遅延を伝えてあるとき、y の遅延を子に伝える（軽い子なら path-parent からも受け取る）
+    fn settle_node(&mut self, y: usize) {
+        if self.subtree_ops.is_some() && self.dir(y).is_none() {
+            if let Some(p) = self.nodes[y].parent {
+                self.settle_light(y, p);
+            }
+        }
+        self.push(y);
+    }
+
+    /// x から補助木の根までの節点の遅延を上から順に伝える
+    fn settle(&mut self, x: usize) {
+        let mut path = vec![x];
+        while let Some(p) = self.nodes[*path.last().unwrap()].parent {
+            path.push(p);
+        }
+        for y in path.into_iter().rev() {
+            self.settle_node(y);
+        }
+    }
+
+    /// x の値と重みを読めるように遅延を受け取る
+    /// 部分木への写像があれば上の splay 木すべてをたどるので、償却の評価が効くように expose する
+    fn reach(&mut self, x: usize) {
+        if self.subtree_ops.is_some() {
+            self.expose(x);
+        } else {
+            self.splay(x);
+        }
+    }
+
+    /// 書き換えられた節点の集約を直す
+    fn flush(&mut self) {
+        for x in std::mem::take(&mut self.dirty) {
+            self.expose(x);
+            self.update(x);
+        }
+    }
+
+    fn splay(&mut self, x: usize) {
+        if !self.pending.is_empty() {
+            self.flush_batch();
+        }
+        if !self.dirty.is_empty() {
+            self.flush();
+        }
+        if self.subtree_ops.is_some() {
+            self.settle(x);
+        }
+        self.splay_settled(x);
+    }
+
+    /// 上の遅延を受け取ってある x を splay する
+    fn splay_settled(&mut self, x: usize) {
+        let rotations = SplayTree::splay(self, x);
+        if let Some(stats) = &mut self.stats {
+            stats.splays += 1;
+            stats.rotations += rotations as u64;
+        }
+        trace!(TraceEvent::Splay { node: TraceNode::from_index(x), rotations });
+    }
+
+    /// x を木の根からのパスにつなげ、そのパスの根にする
+    /// 最後につなぎ替えた頂点を返す
+    fn expose(&mut self, x: usize) -> usize {
+        if let Some(stats) = &mut self.stats {
+            stats.exposes += 1;
+        }
+        // すでに x が補助木の根で重い子を持たなければ、根から x までが一つの splay 木をなしている
+        if self.nodes[x].parent.is_none() && self.nodes[x].children[Dir::Right.index()].is_none() && self.dirty.is_empty() && self.pending.is_empty() {
+            if is_vertex(x) {
+                trace!(TraceEvent::Expose { vertex: x >> 1, splays: 0 });
+            }
+            return x;
+        }
+        #[cfg(feature = "tracing")]
+        let mut splays = 1;
+        // 上の遅延はここで一度に受け取る
+        self.splay(x);
+        let mut last: Option<usize> = None;
+        let mut cur = Some(x);
+        while let Some(y) = cur {
+            #[cfg(feature = "tracing")]
+            {
+                splays += 1;
+            }
+            self.splay_settled(y);
+            #[cfg(feature = "tracing")]
+            if self.child(y, Dir::Right) != last {
+                let (old, new) = (self.child(y, Dir::Right).map(TraceNode::from_index), last.map(TraceNode::from_index));
+                trace!(TraceEvent::Switch { node: TraceNode::from_index(y), old, new });
+            }
+            // 右の子を軽い子にし、last を重い子にする
+            if let Some(right) = self.child(y, Dir::Right) {
+                let total = self.nodes[right].total.clone();
+                S::add(&mut self.nodes[y].virt, &total);
+                self.nodes[y].virt_len += self.nodes[right].total_len;
+                self.nodes[right].base = self.nodes[y].light.clone();
+            }
+            if let Some(last) = last {
+                let total = self.nodes[last].total.clone();
+                S::remove(&mut self.nodes[y].virt, &total);
+                self.nodes[y].virt_len -= self.nodes[last].total_len;
+            }
+            self.nodes[y].children[Dir::Right.index()] = last;
+            self.update(y);
+            last = Some(y);
+            cur = self.nodes[y].parent;
+        }
+        self.splay(x);
+        if is_vertex(x) {
+            trace!(TraceEvent::Expose { vertex: x >> 1, splays });
+        }
+        last.unwrap()
+    }
+
+    /// x を含む splay 木の列の端の節点を根にする
+    fn splay_end(&mut self, mut x: usize, dir: Dir) -> usize {
+        loop {
+            self.push(x);
+            match self.child(x, dir) {
+                Some(child) => x = child,
+                None => break,
+            }
+        }
+        self.splay(x);
+        x
+    }
+
+    /// x を根とする splay 木の列で k 番目（0 始まり）の頂点を根にする
+    fn splay_kth(&mut self, mut x: usize, mut k: usize) -> usize {
+        loop {
+            self.push(x);
+            let left = self.child(x, Dir::Left).map_or(0, |left| self.nodes[left].len);
+            if k < left {
+                x = self.child(x, Dir::Left).unwrap();
+                continue;
+            }
+            k -= left;
+            if is_vertex(x) {
+                if k == 0 {
+                    break;
+                }
+                k -= 1;
+            }
+            x = self.child(x, Dir::Right).unwrap();
+        }
+        self.splay(x);
+        x
+    }
+
+    fn evert_node(&mut self, x: usize) {
+        if !self.component_data.is_empty() || !self.component_ids.is_empty() {
+            // 木に付けた値と木の番号を新しい根に移す
+            let root = self.root_node(x) >> 1;
+            if let Some(data) = self.component_data.remove(&root) {
+                self.component_data.insert(x >> 1, data);
+            }
+            if let Some(id) = self.component_ids.remove(&root) {
+                self.component_ids.insert(x >> 1, id);
+            }
+        }
+        self.expose(x);
+        self.toggle(x);
+    }
+
+    fn root_node(&mut self, x: usize) -> usize {
+        self.expose(x);
+        self.splay_end(x, Dir::Left)
+    }
+
+    /// v を木の根にする
+    pub fn evert(&mut self, v: usize) {
+        self.version += 1;
+        if self.observers.is_empty() {
+            self.evert_node(vertex_node(v));
+            return;
+        }
+        let old_root = self.root(v);
+        self.evert_node(vertex_node(v));
+        self.notify(|observer| observer.on_evert(v, old_root));
+    }
+
+    /// roots の各頂点を、それぞれの木の根にする
+    /// 同じ木の頂点が二つ含まれていれば、何もせずに LctError::Connected を返す
+    pub fn reroot_all(&mut self, roots: &[usize]) -> Result<(), LctError> {
+        let mut seen = HashMap::new();
+        for &v in roots {
+            if let Some(u) = seen.insert(self.root(v), v) {
+                return Err(LctError::Connected(u, v));
+            }
+        }
+        for &v in roots {
+            self.evert(v);
+        }
+        Ok(())
+    }
+
+    /// 形が変わるたびに observer を呼ぶようにする
+    pub fn add_observer(&mut self, observer: impl Observer + Send + Sync + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// 登録したものをすべて外す
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    fn notify(&mut self, mut f: impl FnMut(&mut dyn Observer)) {
+        for observer in &mut self.observers {
+            f(observer.as_mut());
+        }
+    }
+
+    /// v を含む木の根
+    pub fn root(&mut self, v: usize) -> usize {
+        self.root_node(vertex_node(v)) >> 1
+    }
+
+    /// v を含む木の頂点を v から幅優先順に、たどってきた辺とともに並べる
+    fn bfs(&self, v: usize) -> Vec<(usize, Option<usize>)> {
+        // 木なので、たどってきた辺以外の先はすべて未訪問
+        let mut order = vec![(v, None)];
+        let mut i = 0;
+        while i < order.len() {
+            let (x, from) = order[i];
+            for &e in &self.adjacency[x] {
+                if Some(e) != from {
+                    order.push((self.opposite(e, x), Some(e)));
+                }
+            }
+            i += 1;
+        }
+        order
+    }
+
+    /// v を含む木の辺を (親, 子, 重み) として根から幅優先順に並べる
+    pub fn tree_edges(&mut self, v: usize) -> Vec<(usize, usize, E::Value)> {
+        let root = self.root(v);
+        let mut edges = vec![];
+        for (x, from) in self.bfs(root) {
+            if let Some(e) = from {
+                edges.push((self.opposite(e, x), x, self.weight(edge_node(e)).clone()));
+            }
+        }
+        edges
+    }
+
+    /// v を含む木を根から入れ子の構造として書き出す
+    pub fn to_nested(&mut self, v: usize) -> TreeRepr<M::Value> {
+        let root = self.root(v);
+        let order = self.bfs(root);
+        // 幅優先順では各頂点の子が連続して並ぶ
+        let mut parent = vec![0; order.len()];
+        let mut next = 1;
+        for (i, &(x, _)) in order.iter().enumerate() {
+            let count = self.degree(x) - (i > 0) as usize;
+            parent[next .. next + count].fill(i);
+            next += count;
+        }
+        let mut children: Vec<Vec<TreeRepr<M::Value>>> = vec![vec![]; order.len()];
+        for i in (0 .. order.len()).rev() {
+            let mut nested = std::mem::take(&mut children[i]);
+            nested.reverse();
+            let repr = TreeRepr { value: self.value(order[i].0).clone(), children: nested };
+            if i == 0 {
+                return repr;
+            }
+            children[parent[i]].push(repr);
+        }
+        unreachable!()
+    }
+
+    /// 各木の根（最小の番号の頂点が小さい木から順に）
+    pub fn roots(&mut self) -> Vec<usize> {
+        let mut visited = vec![false; self.len()];
+        let mut roots = vec![];
+        for v in 0 .. self.len() {
+            if !visited[v] {
+                for (w, _) in self.bfs(v) {
+                    visited[w] = true;
+                }
+                roots.push(self.root(v));
+            }
+        }
+        roots
+    }
+
+    pub fn connected(&mut self, u: usize, v: usize) -> bool {
+        u == v || self.root(u) == self.root(v)
+    }
+
+    /// 木の根 child の親を parent にする
+    pub fn link(&mut self, child: usize, parent: usize) {
+        self.link_weighted(child, parent, E::identity());
+    }
+
+    /// 木の根 child の親を parent にし、その辺の重みを weight にする
+    pub fn link_weighted(&mut self, child: usize, parent: usize, weight: E::Value) {
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
+        assert_eq!(self.root(child), child, "child must be the root of its tree");
+        assert_ne!(self.root(parent), child, "link would create a cycle");
+        if let Err(err) = self.check_degree(child, parent) {
+            panic!("{}", err);
+        }
+        self.version += 1;
+        if let Some(data) = self.component_data.remove(&child) {
+            // 親の側の木に値がなければ子の側の値を引き継ぐ
+            let root = self.root(parent);
+            self.component_data.entry(root).or_insert(data);
+        }
+        if !self.component_ids.is_empty() {
+            let root = self.root(parent);
+            self.component_ids.remove(&child);
+            self.component_ids.remove(&root);
+        }
+        let (x, y) = (vertex_node(child), vertex_node(parent));
+        self.expose(x);
+        self.expose(y);
+        let e = self.alloc_edge(child, parent, weight);
+        // x の木を e の軽い子に、e を y の軽い子にする
+        let total = self.nodes[x].total.clone();
+        S::add(&mut self.nodes[e].virt, &total);
+        self.nodes[e].virt_len = self.nodes[x].total_len;
+        self.nodes[x].base = self.nodes[e].light.clone();
+        self.update(e);
+        let total = self.nodes[e].total.clone();
+        S::add(&mut self.nodes[y].virt, &total);
+        self.nodes[y].virt_len += self.nodes[e].total_len;
+        self.nodes[e].base = self.nodes[y].light.clone();
+        self.update(y);
+        self.nodes[e].parent = Some(y);
+        self.nodes[x].parent = Some(e);
+        trace!(TraceEvent::Link { child, parent, elapsed: start.elapsed() });
+        if !self.observers.is_empty() {
+            let id = self.edges[e >> 1].id;
+            self.notify(|observer| observer.on_link(child, parent, id));
+        }
+    }
+
+    /// u と v が辺で結ばれているか
+    pub fn has_edge(&mut self, u: usize, v: usize) -> bool {
+        u != v && (self.parent(u) == Some(v) || self.parent(v) == Some(u))
+    }
+
+    /// u と v を結ぶ辺があれば切り、なければ connect でつなぐ
+    /// 多重度が 2 以上の辺なら、切らずに多重度を一つ減らす
+    pub fn toggle_edge(&mut self, u: usize, v: usize) -> Result<Toggle, LctError> {
+        if self.multiplicity(u, v) > 1 {
+            self.remove_multi_edge(u, v)?;
+            return Ok(Toggle::Cut);
+        }
+        if self.parent(u) == Some(v) {
+            self.cut(u);
+            return Ok(Toggle::Cut);
+        }
+        if self.parent(v) == Some(u) {
+            self.cut(v);
+            return Ok(Toggle::Cut);
+        }
+        self.connect(u, v)?;
+        Ok(Toggle::Linked)
+    }
+
+    /// u と v を結ぶ辺の多重度（辺がなければ 0）
+    pub fn multiplicity(&self, u: usize, v: usize) -> usize {
+        self.edge_slot(u, v).map_or(0, |e| self.edges[e].copies)
+    }
+
+    /// u と v を結ぶ辺を一本加え、多重度を返す
+    /// 辺がなければ connect と同じ向きでつなぎ、同じ木に属するが隣接しなければ LctError::Connected
+    pub fn add_multi_edge(&mut self, u: usize, v: usize) -> Result<usize, LctError> {
+        if let Some(e) = self.edge_slot(u, v) {
+            self.edges[e].copies += 1;
+            return Ok(self.edges[e].copies);
+        }
+        self.connect(u, v)?;
+        Ok(1)
+    }
+
+    /// u と v を結ぶ辺を一本取り除き、残りの多重度を返す（辺がなければ LctError::NotAdjacent）
+    /// 0 になったときだけ木の辺を切る（代わりの辺を探すのは呼び出し側に任せる）
+    /// cut と cut_edges は多重度によらず辺を切る
+    pub fn remove_multi_edge(&mut self, u: usize, v: usize) -> Result<usize, LctError> {
+        let e = self.edge_slot(u, v).ok_or(LctError::NotAdjacent(u, v))?;
+        self.edges[e].copies -= 1;
+        if self.edges[e].copies == 0 {
+            let child = if self.parent(v) == Some(u) { v } else { u };
+            self.cut(child);
+        }
+        Ok(self.edges[e].copies)
+    }
+
+    /// 別の木に属する u と v を辺で結ぶ
+    /// 根である方を子にし、どちらも根でなければ v を根にしてから u の子にする
+    pub fn connect(&mut self, u: usize, v: usize) -> Result<(), LctError> {
+        self.connect_weighted(u, v, E::identity())
+    }
+
+    /// connect と同じ向きで、重み weight の辺で u と v を結ぶ
+    pub fn connect_weighted(&mut self, u: usize, v: usize, weight: E::Value) -> Result<(), LctError> {
+        if self.connected(u, v) {
+            return Err(LctError::Connected(u, v));
+        }
+        self.check_degree(u, v)?;
+        if self.root(u) == u && self.root(v) != v {
+            self.link_weighted(u, v, weight);
+        } else {
+            self.evert(v);
+            self.link_weighted(v, u, weight);
+        }
+        Ok(())
+    }
+
+    /// connect と同じ向きで u と v を結ぶ
+    /// すでに同じ木に属していれば、辺を加えると閉路になるパスを返す（パスを求め直さずに済む）
+    /// どちらかの次数が上限に達していれば LinkError::DegreeLimitExceeded
+    pub fn try_link(&mut self, u: usize, v: usize) -> Result<(), LinkError> {
+        let path = self.with_path(u, v, |forest, top| {
+            let (vertices, edges): (Vec<usize>, Vec<usize>) = forest.collect(top).into_iter().partition(|&x| is_vertex(x));
+            CyclePath {
+                vertices: vertices.into_iter().map(|x| x >> 1).collect(),
+                edges: edges.into_iter().map(|x| forest.edges[x >> 1].id).collect(),
+            }
+        });
+        if let Some(path) = path {
+            return Err(LinkError::Cycle(path));
+        }
+        if let Err(LctError::DegreeLimitExceeded(w)) = self.check_degree(u, v) {
+            return Err(LinkError::DegreeLimitExceeded(w));
+        }
+        if self.root(u) == u && self.root(v) != v {
+            self.link(u, v);
+        } else {
+            self.evert(v);
+            self.link(v, u);
+        }
+        Ok(())
+    }
+
+    /// v を親から切り離し、切った辺の番号を返す
+    pub fn cut(&mut self, v: usize) -> EdgeId {
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
+        let x = vertex_node(v);
+        let e = self.parent_edge(x).expect("v must not be a root");
+        let id = self.edges[e].id;
+        self.detach(x);
+        trace!(TraceEvent::Cut { v, elapsed: start.elapsed() });
+        id
+    }
+
+    /// 切った辺 edge を child と parent の間につなぎ直し、番号・多重度・つないだときの向き・隣接リストでの位置を戻す
+    /// 隣接リストが辺を切った直後のままのときに呼ぶ（Transaction の取り消しに使う）
+    fn restore_edge(&mut self, child: usize, parent: usize, weight: E::Value, edge: Edge) {
+        self.link_weighted(child, parent, weight);
+        let e = self.parent_edge(vertex_node(child)).unwrap();
+        let from = edge.ends.map(|v| self.edges[e].pos[(self.edges[e].ends[1] == v) as usize]);
+        self.edge_ids.remove(&self.edges[e].id);
+        self.edge_ids.insert(edge.id, e);
+        for side in 0 .. 2 {
+            let (v, from, to) = (edge.ends[side], from[side], edge.pos[side]);
+            // free_edge で詰めたか末尾の辺を移したかに合わせて、末尾に加わった辺を元の位置へ戻す
+            if self.ordered || self.sorted {
+                let f = self.adjacency[v].remove(from);
+                self.adjacency[v].insert(to, f);
+            } else {
+                self.adjacency[v].swap(from, to);
+            }
+        }
+        self.edges[e] = edge;
+        for side in 0 .. 2 {
+            let (v, pos) = (self.edges[e].ends[side], self.edges[e].pos[side]);
+            self.renumber(v, pos .. self.adjacency[v].len());
+        }
+    }
+
+    /// 各 (u, v) を結ぶ辺を順に切り、それぞれの結果を返す
+    /// 辺がない（同じ辺が二度現れた場合も含む）なら LctError::NotAdjacent
+    pub fn cut_edges(&mut self, edges: &[(usize, usize)]) -> Vec<Result<(), LctError>> {
+        edges
+            .iter()
+            .map(|&(u, v)| {
+                self.edge(u, v).ok_or(LctError::NotAdjacent(u, v))?;
+                let child = if self.parent(v) == Some(u) { v } else { u };
+                self.cut(child);
+                Ok(())
+            })
+            .collect()
+    }
+
+    /// v から根へのパスで v から k 番目（0 始まり）の辺を切り、(下側の木の根, 上側の木の根) を返す
+    /// v の深さが k 以下なら何もせず None
+    pub fn split_at_kth(&mut self, v: usize, k: usize) -> Option<(usize, usize)> {
+        let x = vertex_node(v);
+        self.expose(x);
+        let len = self.nodes[x].len;
+        if k + 1 >= len {
+            return None;
+        }
+        let root = self.splay_end(x, Dir::Left);
+        let lower = self.splay_kth(root, len - 1 - k);
+        self.detach(lower);
+        Some((lower >> 1, root >> 1))
+    }
+
+    /// 頂点の節点 x と親を結ぶ辺の添字
+    fn parent_edge(&mut self, x: usize) -> Option<usize> {
+        self.expose(x);
+        let left = self.child(x, Dir::Left)?;
+        Some(self.splay_end(left, Dir::Right) >> 1)
+    }
+
+    /// 頂点の節点 x を親から切り離し、親との辺の重みを返す
+    fn detach(&mut self, x: usize) -> Option<E::Value> {
+        self.expose(x);
+        let left = self.child(x, Dir::Left)?;
+        self.version += 1;
+        self.nodes[left].parent = None;
+        self.nodes[x].children[Dir::Left.index()] = None;
+        self.update(x);
+        // 切り離した側の最後の節点が親との辺
+        let e = self.splay_end(left, Dir::Right);
+        let rest = self.child(e, Dir::Left).unwrap();
+        self.nodes[rest].parent = None;
+        if !self.component_data.is_empty() || !self.component_ids.is_empty() {
+            // 切り離した木にも元の木の値を付け、木の番号は振り直す
+            let root = self.splay_end(rest, Dir::Left) >> 1;
+            if let Some(&data) = self.component_data.get(&root) {
+                self.component_data.insert(x >> 1, data);
+            }
+            self.component_ids.remove(&root);
+        }
+        if !self.observers.is_empty() {
+            let (child, edge) = (x >> 1, &self.edges[e >> 1]);
+            let (parent, id) = (edge.ends[0] + edge.ends[1] - child, edge.id);
+            self.notify(|observer| observer.on_cut(child, parent, id));
+        }
+        Some(self.free_edge(e))
+    }
+
+    /// 頂点の節点 x の親の頂点の節点
+    fn parent_node(&mut self, x: usize) -> Option<usize> {
+        self.expose(x);
+        self.prev_vertex(x)
+    }
+
+    /// splay 木の根である頂点の節点 x の、列で一つ前の頂点の節点
+    fn prev_vertex(&mut self, x: usize) -> Option<usize> {
+        let e = self.splay_end(self.child(x, Dir::Left)?, Dir::Right);
+        Some(self.splay_end(self.child(e, Dir::Left).unwrap(), Dir::Right))
+    }
+
+    /// v の親
+    pub fn parent(&mut self, v: usize) -> Option<usize> {
+        self.parent_node(vertex_node(v)).map(|x| x >> 1)
+    }
+
+    /// 同じ木に属する節点 x, y の最小共通祖先
+    fn lca_node(&mut self, x: usize, y: usize) -> usize {
+        self.expose(x);
+        self.expose(y)
+    }
+
+    /// u と v の最小共通祖先（異なる木に属するなら None）
+    pub fn lca(&mut self, u: usize, v: usize) -> Option<usize> {
+        let (x, y) = (vertex_node(u), vertex_node(v));
+        if self.root_node(x) != self.root_node(y) {
+            return None;
+        }
+        Some(self.lca_node(x, y) >> 1)
+    }
+
+    /// nodes のすべての頂点の最小共通祖先（空か、異なる木の頂点を含むなら None）
+    pub fn lca_many(&mut self, nodes: impl IntoIterator<Item = usize>) -> Option<usize> {
+        let mut nodes = nodes.into_iter().map(vertex_node);
+        let mut lca = nodes.next()?;
+        let root = self.root_node(lca);
+        for x in nodes {
+            if self.root_node(x) != root {
+                return None;
+            }
+            lca = self.lca_node(lca, x);
+        }
+        Some(lca >> 1)
+    }
+
+    /// u が v の祖先か（u 自身も含む）
+    pub fn is_ancestor(&mut self, u: usize, v: usize) -> bool {
+        self.lca(u, v) == Some(u)
+    }
+
+    /// v を根とする部分木を new_parent の子に付け替える
+    /// 親との辺の重みは引き継ぐ（v が根だった場合は単位元）
+    pub fn move_subtree(&mut self, v: usize, new_parent: usize) -> Result<(), LctError> {
+        if self.is_ancestor(v, new_parent) {
+            return Err(LctError::Ancestor { ancestor: v, descendant: new_parent });
+        }
+        let weight = self.detach(vertex_node(v)).unwrap_or_else(E::identity);
+        self.link_weighted(v, new_parent, weight);
+        Ok(())
+    }
+
+    /// a から b へのパスの区間（a は b の祖先）を切り出し、new_parent の子に付け替える
+    /// 区間の途中の頂点から垂れ下がる部分木は区間とともに移り、b の子は a の元の親の子になる
+    /// （a が根だった場合は b の子がそれぞれ木の根になる）
+    /// 辺の重みはそれぞれ引き継ぐ（a が根だった場合、a と new_parent の辺の重みは単位元）
+    pub fn splice(&mut self, a: usize, b: usize, new_parent: usize) -> Result<(), LctError> {
+        if !self.is_ancestor(a, b) {
+            return Err(LctError::NotAncestor { ancestor: a, descendant: b });
+        }
+        // new_parent が b の真の子孫でなく、a の子孫なら区間とともに移ってしまう
+        if self.is_ancestor(a, new_parent) && (new_parent == b || !self.is_ancestor(b, new_parent)) {
+            return Err(LctError::Ancestor { ancestor: a, descendant: new_parent });
+        }
+        let parent = self.parent(a);
+        let weight = self.detach(vertex_node(a));
+        for c in self.children(b) {
+            let w = self.detach(vertex_node(c)).unwrap();
+            if let Some(p) = parent {
+                self.link_weighted(c, p, w);
+            }
+        }
+        self.link_weighted(a, new_parent, weight.unwrap_or_else(E::identity));
+        Ok(())
+    }
+
+    /// v に接する辺をすべて切り、v 以外にできた木の根を返す
+    /// 親のいた木の根（v が根でなかった場合）が先頭で、続いて v の子
+    pub fn isolate(&mut self, v: usize) -> Vec<usize> {
+        let x = vertex_node(v);
+        let parent = self.parent_node(x);
+        let mut roots = vec![];
+        if parent.is_some() {
+            roots.push(self.root(v));
+        }
+        roots.extend(self.neighbors(v).filter(|&w| Some(vertex_node(w)) != parent).collect::<Vec<_>>());
+        for &w in &roots[parent.is_some() as usize ..] {
+            self.detach(vertex_node(w));
+        }
+        self.detach(x);
+        roots
+    }
+
+    /// 隣接する u, v を縮約し、v の子を u につなぎ替える
+    /// u の値は combine(u の値, v の値) になり、v は孤立点として残る
+    /// u と v を結ぶ辺の重みは捨てられる
+    pub fn contract(&mut self, u: usize, v: usize, combine: impl FnOnce(&M::Value, &M::Value) -> M::Value) -> Result<(), LctError> {
+        if !self.neighbors(v).any(|w| w == u) {
+            return Err(LctError::NotAdjacent(u, v));
+        }
+        let (x, y) = (vertex_node(u), vertex_node(v));
+        let parent = self.parent_node(y);
+        let adopted: Vec<usize> = self.neighbors(v).filter(|&w| w != u && Some(vertex_node(w)) != parent).collect();
+        if parent == Some(x) {
+            self.detach(y);
+        } else {
+            // v が u の親なので、u が v の位置を引き継ぐ
+            self.detach(x);
+            if let Some(weight) = self.detach(y) {
+                self.link_weighted(u, parent.unwrap() >> 1, weight);
+            }
+        }
+        for w in adopted {
+            let weight = self.detach(vertex_node(w)).unwrap();
+            self.link_weighted(w, u, weight);
+        }
+        let value = combine(&self.value(u).clone(), self.value(v));
+        self.set_value(u, value);
+        Ok(())
+    }
+
+    /// a を根とする部分木と b を根とする部分木を入れ替える
+    /// 親との辺の重みは部分木とともに移る
+    pub fn swap_subtrees(&mut self, a: usize, b: usize) -> Result<(), LctError> {
+        if a == b {
+            return Ok(());
+        }
+        let (x, y) = (vertex_node(a), vertex_node(b));
+        let px = self.parent_node(x).ok_or(LctError::IsRoot(a))?;
+        let py = self.parent_node(y).ok_or(LctError::IsRoot(b))?;
+        match self.lca(a, b) {
+            Some(lca) if lca == a => return Err(LctError::Ancestor { ancestor: a, descendant: b }),
+            Some(lca) if lca == b => return Err(LctError::Ancestor { ancestor: b, descendant: a }),
+            _ => {}
+        }
+        let wx = self.detach(x).unwrap();
+        let wy = self.detach(y).unwrap();
+        self.link_weighted(a, py >> 1, wx);
+        self.link_weighted(b, px >> 1, wy);
+        Ok(())
+    }
+
+    /// u から v へのパスを一つの splay 木にまとめて f に渡し、根を元に戻す
+    fn with_path<R>(&mut self, u: usize, v: usize, f: impl FnOnce(&mut Self, usize) -> R) -> Option<R> {
+        let (x, y) = (vertex_node(u), vertex_node(v));
+        let root = self.root_node(x);
+        if self.root_node(y) != root {
+            return None;
+        }
+        self.evert_node(x);
+        self.expose(y);
+        let result = f(self, y);
+        self.evert_node(root);
+        Some(result)
+    }
+
+    /// x を根とする splay 木の頂点を列の順に並べる
+    fn collect(&mut self, x: usize) -> Vec<usize> {
+        let mut order = Vec::with_capacity(2 * self.nodes[x].len);
+        self.find_in_order(x, |_, y| {
+            order.push(y);
+            false
+        });
+        order
+    }
+
+    /// x を根とする splay 木の節点を列の順に見て、pred を満たす最初の節点
+    /// 見つかった時点で打ち切る
+    fn find_in_order(&mut self, x: usize, mut pred: impl FnMut(&Self, usize) -> bool) -> Option<usize> {
+        let mut stack = Vec::new();
+        let mut cur = Some(x);
+        loop {
+            while let Some(y) = cur {
+                self.push(y);
+                stack.push(y);
+                cur = self.child(y, Dir::Left);
+            }
+            let y = stack.pop()?;
+            if pred(self, y) {
+                return Some(y);
+            }
+            cur = self.child(y, Dir::Right);
+        }
+    }
+
+    /// x を含む splay 木の根
+    fn splay_root(&self, mut x: usize) -> usize {
+        while self.dir(x).is_some() {
+            x = self.nodes[x].parent.unwrap();
+        }
+        x
+    }
+
+    /// v の子のうち、v と同じ優先パスにないもの（木の構造は変えない）
+    pub fn light_children(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        let x = vertex_node(v);
+        let top = self.splay_root(x);
+        // 優先パスの末尾は常に頂点なので、v と別の splay 木にある辺は
+        // v に垂れ下がる軽い子への辺か、v が先頭であるパスの上の親との辺
+        self.adjacency[v].iter().filter_map(move |&e| {
+            let root = self.splay_root(edge_node(e));
+            (root != top && self.nodes[root].parent == Some(x)).then(|| self.opposite(e, v))
+        })
+    }
+
+    /// 現在の優先パスへの分解を数える（木の構造は変えない）
+    pub fn decomposition_stats(&self) -> DecompositionStats {
+        let mut stats = DecompositionStats::default();
+        // 優先パスはどれも頂点を含むので、頂点から splay 木の根をたどれば全部見つかる
+        let roots: BTreeSet<usize> = (0 .. self.len()).map(|v| self.splay_root(vertex_node(v))).collect();
+        for root in roots {
+            stats.paths += 1;
+            *stats.lengths.entry(self.nodes[root].len).or_insert(0) += 1;
+        }
+        // 木の数は頂点数から辺の数を引いたもので、パスどうしをつなぐ辺がそれ以外の分だけある
+        stats.light_edges = stats.paths - (self.len() - self.edge_ids.len());
+        stats
+    }
+
+    /// 統計を有効にした森の、これまでの操作の回数（有効でなければ None）
+    pub fn op_stats(&self) -> Option<OpStats> {
+        self.stats
+    }
+
+    /// 操作の回数を数え始めるか、数えるのをやめる（数え始めるときは 0 から）
+    pub fn set_statistics(&mut self, enabled: bool) {
+        self.stats = enabled.then(OpStats::default);
+    }
+
+    /// v を含む優先パスの頂点を根に近い順に並べる（木の構造は変えない）
+    pub fn preferred_path(&self, v: usize) -> Vec<usize> {
+        let top = self.splay_root(vertex_node(v));
+        // 祖先の反転フラグの偶奇を持ちながら通りがけ順にたどる
+        let mut order = vec![];
+        let mut stack = vec![];
+        let mut cur = Some((top, false));
+        loop {
+            while let Some((x, flip)) = cur {
+                stack.push((x, flip));
+                let dir = if flip { Dir::Right } else { Dir::Left };
+                cur = self.child(x, dir).map(|child| (child, flip ^ self.nodes[x].rev));
+            }
+            let Some((x, flip)) = stack.pop() else { break };
+            if is_vertex(x) {
+                order.push(x >> 1);
+            }
+            let dir = if flip { Dir::Left } else { Dir::Right };
+            cur = self.child(x, dir).map(|child| (child, flip ^ self.nodes[x].rev));
+        }
+        order
+    }
+
+    /// u から v へのパスの真ん中の頂点
+    /// 辺の数が奇数なら真ん中の辺の両端を u に近い順に、偶数なら同じ頂点を二つ返す
+    pub fn path_midpoint(&mut self, u: usize, v: usize) -> Option<(usize, usize)> {
+        self.with_path(u, v, |forest, top| {
+            let len = forest.nodes[top].len;
+            let near = forest.splay_kth(top, (len - 1) / 2);
+            let far = forest.splay_kth(near, len / 2);
+            (near >> 1, far >> 1)
+        })
+    }
+
+    /// u から v へのパス上の頂点の値のうち k 番目（0 始まり）に小さいもの
+    /// 異なる木に属するか、k がパスの頂点数以上なら None
+    /// パス上の値を集めて選ぶので、償却 O(log n) の他のパスの集約と違い O(パスの長さ) かかる
+    /// 形を変えずに何度も聞くなら freeze().path_order() の PathOrder を使うと一回 O(log n) で答えられる
+    pub fn path_kth_smallest(&mut self, u: usize, v: usize, k: usize) -> Option<M::Value>
+    where
+        M::Value: Ord,
+    {
+        self.with_path(u, v, |forest, top| {
+            let mut values: Vec<M::Value> =
+                forest.collect(top).into_iter().filter(|&x| is_vertex(x)).map(|x| forest.nodes[x].value.clone()).collect();
+            (k < values.len()).then(|| values.select_nth_unstable(k).1.clone())
+        })
+        .flatten()
+    }
+
+    /// u から v へのパス上の頂点の異なる値の個数（異なる木なら None）
+    /// パス上の値を集めて数えるので、償却 O(log n) の他のパスの集約と違い O(パスの長さ) かかる
+    /// 近似でよければ `Forest<HyperLogLog<B>>` の path_count_distinct_approx が一回 O(2^B log n) で推定する
+    pub fn path_count_distinct(&mut self, u: usize, v: usize) -> Option<usize>
+    where
+        M::Value: std::hash::Hash + Eq,
+    {
+        self.with_path(u, v, |forest, top| {
+            let vertices = forest.collect(top).into_iter().filter(|&x| is_vertex(x));
+            vertices.map(|x| &forest.nodes[x].value).collect::<HashSet<_>>().len()
+        })
+    }
+
+    /// u から v へのパス上の頂点を一様に一つ選ぶ（異なる木なら None）
+    /// rng は一様な 64 ビットの乱数を返すこと
+    pub fn random_path_vertex(&mut self, u: usize, v: usize, rng: &mut impl FnMut() -> u64) -> Option<usize> {
+        self.with_path(u, v, |forest, top| {
+            let len = forest.nodes[top].len;
+            let k = ((rng() as u128 * len as u128) >> 64) as usize;
+            forest.splay_kth(top, k) >> 1
+        })
+    }
+
+    /// 各 (u, v) について u から v へのパスの辺の数（異なる木なら None）
+    /// 同じ u の問い合わせをまとめて、u を根にし直す回数を減らす
+    pub fn bulk_distances(&mut self, pairs: &[(usize, usize)]) -> Vec<Option<usize>> {
+        let mut order: Vec<usize> = (0 .. pairs.len()).collect();
+        order.sort_by_key(|&i| pairs[i].0);
+        let mut distances = vec![None; pairs.len()];
+        let mut i = 0;
+        while i < order.len() {
+            let x = vertex_node(pairs[order[i]].0);
+            let root = self.root_node(x);
+            self.evert_node(x);
+            while i < order.len() && vertex_node(pairs[order[i]].0) == x {
+                let y = vertex_node(pairs[order[i]].1);
+                if self.root_node(y) == x {
+                    self.expose(y);
+                    distances[order[i]] = Some(self.nodes[y].len - 1);
+                }
+                i += 1;
+            }
+            self.evert_node(root);
+        }
+        distances
+    }
+
+    /// u から v へのパス上の値の集約
+    pub fn path_query(&mut self, u: usize, v: usize) -> Option<M::Value> {
+        self.with_path(u, v, |forest, top| forest.nodes[top].sum.clone())
+    }
+
+    /// 根から v へのパス上の値の集約（根を変えない）
+    pub fn root_path_query(&mut self, v: usize) -> M::Value {
+        let x = vertex_node(v);
+        self.expose(x);
+        self.nodes[x].sum.clone()
+    }
+
+    /// top を根とする splay 木を列の順に見て、先頭からの集約が pred を満たさなくなる最初の節点
+    /// 最後に訪れた節点を根にする
+    #[allow(clippy::type_complexity)]
+    fn search<T>(
+        &mut self,
+        top: usize,
+        op: fn(&T, &T) -> T,
+        mut acc: T,
+        subtree: fn(&ForestNodeOf<M, E, A, S>) -> &T,
+        single: fn(&ForestNodeOf<M, E, A, S>) -> &T,
+        mut pred: impl FnMut(&T) -> bool,
+    ) -> Option<usize> {
+        let mut x = top;
+        let found = loop {
+            self.push(x);
+            if let Some(left) = self.child(x, Dir::Left) {
+                let next = op(&acc, subtree(&self.nodes[left]));
+                if !pred(&next) {
+                    x = left;
+                    continue;
+                }
+                acc = next;
+            }
+            let next = op(&acc, single(&self.nodes[x]));
+            if !pred(&next) {
+                break Some(x);
+            }
+            acc = next;
+            match self.child(x, Dir::Right) {
+                Some(right) => x = right,
+                None => break None,
+            }
+        };
+        self.splay(x);
+        found
+    }
+
+    /// u から v へのパス上の辺の重みの集約
+    pub fn path_query_edges(&mut self, u: usize, v: usize) -> Option<E::Value> {
+        self.with_path(u, v, |forest, top| forest.nodes[top].weight_sum.clone())
+    }
+
+    /// u から v へのパス上の、両端を除いた頂点の値の集約
+    pub fn path_query_open(&mut self, u: usize, v: usize) -> Option<M::Value> {
+        self.path_query_excluding(u, v, true, true)
+    }
+
+    /// u から v へのパス上の頂点の値の集約
+    /// exclude_u, exclude_v が真ならそれぞれの端点を除く（可逆でない演算でもよい）
+    pub fn path_query_excluding(&mut self, u: usize, v: usize, exclude_u: bool, exclude_v: bool) -> Option<M::Value> {
+        // 端点を除いた後の両端の頂点を求めてから集約する
+        let (len, last) = self.with_path(u, v, |forest, top| {
+            let len = forest.nodes[top].len;
+            (len, if exclude_v { forest.prev_vertex(top) } else { Some(top) })
+        })?;
+        if len <= exclude_u as usize + exclude_v as usize {
+            return Some(M::identity());
+        }
+        let first = if exclude_u {
+            self.with_path(v, u, |forest, top| forest.prev_vertex(top)).flatten().unwrap() >> 1
+        } else {
+            u
+        };
+        self.path_query(first, last.unwrap() >> 1)
+    }
+
+    /// u から v へのパス上の頂点の値の集約と辺の重みの集約
+    pub fn path_aggregates(&mut self, u: usize, v: usize) -> Option<(M::Value, E::Value)> {
+        self.with_path(u, v, |forest, top| {
+            let node = &forest.nodes[top];
+            (node.sum.clone(), node.weight_sum.clone())
+        })
+    }
+
+    /// u から v へのパスを先頭から見て、先頭からの集約が pred を満たさなくなる最初の頂点
+    /// pred は単調であること（満たさない頂点がなければ None）
+    pub fn path_partition_point(&mut self, u: usize, v: usize, pred: impl FnMut(&M::Value) -> bool) -> Option<usize> {
+        self.with_path(u, v, |forest, top| {
+            forest.search(top, M::op, M::identity(), |node| &node.sum, |node| &node.value, pred)
+        })
+        .flatten()
+        .map(|x| x >> 1)
+    }
+
+    /// u から v へのパス上で、u からの辺の重みの集約が d 以下である最も遠い頂点
+    /// 重みは非負であること（u 自身も d を超えるなら None）
+    pub fn jump_weighted(&mut self, u: usize, v: usize, d: &E::Value) -> Option<usize>
+    where
+        E::Value: PartialOrd,
+    {
+        self.with_path(u, v, |forest, top| {
+            match forest.search(top, E::op, E::identity(), |node| &node.weight_sum, |node| &node.weight, |w| w <= d) {
+                None => Some(top),
+                Some(x) if is_vertex(x) => None,
+                Some(x) => {
+                    let left = forest.child(x, Dir::Left).unwrap();
+                    Some(forest.splay_end(left, Dir::Right))
+                }
+            }
+        })
+        .flatten()
+        .map(|x| x >> 1)
+    }
+
+    /// v の祖先（v 自身も含む）のうち、根からの辺の重みの集約が w 以下である最も深い頂点
+    /// 重みは非負であること（根も w を超えるなら None）
+    pub fn ancestor_at_weight(&mut self, v: usize, w: &E::Value) -> Option<usize>
+    where
+        E::Value: PartialOrd,
+    {
+        let x = vertex_node(v);
+        self.expose(x);
+        match self.search(x, E::op, E::identity(), |node| &node.weight_sum, |node| &node.weight, |d| d <= w) {
+            None => Some(v),
+            Some(y) if is_vertex(y) => None,
+            Some(y) => {
+                let left = self.child(y, Dir::Left).unwrap();
+                Some(self.splay_end(left, Dir::Right) >> 1)
+            }
+        }
+    }
+
+    /// u から v へのパス上の辺の番号を u に近い順に並べる
+    pub fn path_edges(&mut self, u: usize, v: usize) -> Option<Vec<EdgeId>> {
+        self.with_path(u, v, |forest, top| {
+            forest.collect(top).into_iter().filter(|&x| !is_vertex(x)).map(|x| forest.edges[x >> 1].id).collect()
+        })
+    }
+
+    /// u から v へのパス上の頂点に順に f を適用する
+    pub fn path_for_each(&mut self, u: usize, v: usize, mut f: impl FnMut(usize, &M::Value)) -> bool {
+        self.with_path(u, v, |forest, top| {
+            for x in forest.collect(top).into_iter().filter(|&x| is_vertex(x)) {
+                f(x >> 1, &forest.nodes[x].value);
+            }
+        })
+        .is_some()
+    }
+
+    /// u から v へのパス上の頂点の値を順に f の返り値で置き換える
+    pub fn path_map(&mut self, u: usize, v: usize, mut f: impl FnMut(usize, &M::Value) -> M::Value) -> bool {
+        self.with_path(u, v, |forest, top| {
+            for x in forest.collect(top).into_iter().filter(|&x| is_vertex(x)) {
+                forest.nodes[x].value = f(x >> 1, &forest.nodes[x].value);
+            }
+            // 子が親より後に来る順に並べ、逆順に集約し直す
+            let mut order = vec![top];
+            let mut i = 0;
+            while i < order.len() {
+                order.extend(forest.nodes[order[i]].children.into_iter().flatten());
+                i += 1;
+            }
+            for &x in order.iter().rev() {
+                forest.update(x);
+            }
+        })
+        .is_some()
+    }
+
+    /// u から v へのパス上に pred を満たす頂点があるか
+    /// u の側から順に調べ、見つかった時点で打ち切る
+    pub fn path_any(&mut self, u: usize, v: usize, mut pred: impl FnMut(&M::Value) -> bool) -> Option<bool> {
+        self.with_path(u, v, |forest, top| {
+            forest.find_in_order(top, |forest, x| is_vertex(x) && pred(&forest.nodes[x].value)).is_some()
+        })
+    }
+
+    /// u から v へのパス上のすべての頂点が pred を満たすか
+    /// u の側から順に調べ、満たさない頂点が見つかった時点で打ち切る
+    pub fn path_all(&mut self, u: usize, v: usize, mut pred: impl FnMut(&M::Value) -> bool) -> Option<bool> {
+        self.path_any(u, v, |x| !pred(x)).map(|found| !found)
+    }
+}
+
+/// 補助木の節点の組み替え（splay 木の根の parent は path-parent を指す）
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> SplayTree for Forest<M, E, A, S, N> {
+    fn parent_of(&self, x: usize) -> Option<usize> {
+        self.nodes[x].parent
+    }
+
+    fn set_parent_of(&mut self, x: usize, parent: Option<usize>) {
+        self.nodes[x].parent = parent;
+    }
+
+    fn child(&self, x: usize, dir: Dir) -> Option<usize> {
+        self.nodes[x].children[dir.index()]
+    }
+
+    fn set_child(&mut self, x: usize, dir: Dir, child: Option<usize>) {
+        self.nodes[x].children[dir.index()] = child;
+    }
+
+    fn push(&mut self, x: usize) {
+        if self.nodes[x].rev {
+            self.nodes[x].rev = false;
+            for child in self.nodes[x].children.into_iter().flatten() {
+                self.toggle(child);
+            }
+        }
+        if let Some(f) = self.nodes[x].lazy.take() {
+            for child in self.nodes[x].children.into_iter().flatten() {
+                self.apply(child, &f);
+            }
+        }
+        if let Some(f) = self.nodes[x].hanging.take() {
+            for child in self.nodes[x].children.into_iter().flatten() {
+                self.apply_hanging(child, &f);
+            }
+        }
+    }
+
+    fn update(&mut self, x: usize) {
+        let [left, right] = self.nodes[x].children;
+        let node = &self.nodes[x];
+        let mut len = is_vertex(x) as usize;
+        let mut edge_len = 1 - len;
+        let mut sum = node.value.clone();
+        let mut rev_sum = sum.clone();
+        let mut weight_sum = node.weight.clone();
+        let mut weight_rev_sum = weight_sum.clone();
+        let mut total = if is_vertex(x) { S::vertex(&node.value, &node.virt) } else { S::edge(&node.weight, &node.virt) };
+        let mut rev_total = total.clone();
+        let mut total_len = len + node.virt_len;
+        if let Some(left) = left {
+            let left = &self.nodes[left];
+            len += left.len;
+            edge_len += left.edge_len;
+            sum = M::op(&left.sum, &sum);
+            rev_sum = M::op(&rev_sum, &left.rev_sum);
+            weight_sum = E::op(&left.weight_sum, &weight_sum);
+            weight_rev_sum = E::op(&weight_rev_sum, &left.weight_rev_sum);
+            total = S::op(&left.total, &total);
+            rev_total = S::op(&rev_total, &left.rev_total);
+            total_len += left.total_len;
+        }
+        if let Some(right) = right {
+            let right = &self.nodes[right];
+            len += right.len;
+            edge_len += right.edge_len;
+            sum = M::op(&sum, &right.sum);
+            rev_sum = M::op(&right.rev_sum, &rev_sum);
+            weight_sum = E::op(&weight_sum, &right.weight_sum);
+            weight_rev_sum = E::op(&right.weight_rev_sum, &weight_rev_sum);
+            total = S::op(&total, &right.total);
+            rev_total = S::op(&right.rev_total, &rev_total);
+            total_len += right.total_len;
+        }
+        let node = &mut self.nodes[x];
+        node.len = len;
+        node.edge_len = edge_len;
+        node.sum = sum;
+        node.rev_sum = rev_sum;
+        node.weight_sum = weight_sum;
+        node.weight_rev_sum = weight_rev_sum;
+        node.total = total;
+        node.rev_total = rev_total;
+        node.total_len = total_len;
+        if self.update_hook.is_some() {
+            self.run_update_hook(x);
+        }
+    }
+
+    fn on_rotate(&mut self, x: usize, parent: usize) {
+        trace!(TraceEvent::Rotate { node: TraceNode::from_index(x), parent: TraceNode::from_index(parent) });
+        // x が splay 木の根になるので、path-parent から受け取った位置を引き継ぐ
+        if self.dir(parent).is_none() {
+            self.nodes[x].base = self.nodes[parent].base.take();
+        }
+    }
+}
+
+/// 部分木全体の集約は写像を作用させても直せないので、持たない場合に限る
+impl<M: Monoid, E: Monoid, A: Action<M, E>, N: NodeStore<ForestNodeOf<M, E, A, ()>>> Forest<M, E, A, (), N> {
+    /// u から v へのパス上の頂点の値と辺の重みに f を作用させる
+    pub fn path_apply(&mut self, u: usize, v: usize, f: A::Map) -> bool {
+        self.with_path(u, v, |forest, top| forest.apply(top, &f)).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+    use crate::store::Slab;
+    use crate::test_util::{xorshift, xorshift64};
+
+    #[test]
+    fn path_for_each_visits_in_order() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for i in 0 .. 6 {
+            forest.add_node(i);
+        }
+        // 0 - 1 - 2 - 3, 1 - 4 - 5
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 2);
+        forest.link(4, 1);
+        forest.link(5, 4);
+        let mut visited = vec![];
+        assert!(forest.path_for_each(3, 5, |v, &x| visited.push((v, x))));
+        assert_eq!(visited, vec![(3, 3), (2, 2), (1, 1), (4, 4), (5, 5)]);
+        assert_eq!(forest.root(5), 0);
+
+        let other = forest.add_node(100);
+        assert!(!forest.path_for_each(0, other, |_, _| unreachable!()));
+    }
+
+    #[test]
+    fn path_map_repairs_aggregates() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for i in 0 .. 5 {
+            forest.add_node(i);
+        }
+        for i in 1 .. 5 {
+            forest.link(i, i - 1);
+        }
+        assert!(forest.path_map(1, 3, |_, &x| x * 10));
+        assert_eq!(forest.path_query(0, 4), Some(64));
+        assert_eq!(forest.path_query(2, 4), Some(54));
+        assert_eq!(*forest.value(2), 20);
+    }
+
+    #[test]
+    fn path_partition_point() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for x in [3, 1, 4, 1, 5, 9] {
+            forest.add_node(x);
+        }
+        for i in 1 .. 6 {
+            forest.link(i, i - 1);
+        }
+        assert_eq!(forest.path_partition_point(0, 5, |&s| s <= 8), Some(3));
+        assert_eq!(forest.path_partition_point(5, 0, |&s| s < 15), Some(3));
+        assert_eq!(forest.path_partition_point(1, 4, |&s| s < 100), None);
+        assert_eq!(forest.path_partition_point(2, 2, |&s| s < 4), Some(2));
+        assert_eq!(forest.root(3), 0);
+    }
+
+    #[test]
+    fn jump_weighted() {
+        let mut forest = Forest::<(), Sum<u64>>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 -2- 1 -3- 2 -1- 3, 1 -5- 4
+        forest.link_weighted(1, 0, 2);
+        forest.link_weighted(2, 1, 3);
+        forest.link_weighted(3, 2, 1);
+        forest.link_weighted(4, 1, 5);
+        assert_eq!(forest.jump_weighted(0, 3, &0), Some(0));
+        assert_eq!(forest.jump_weighted(0, 3, &4), Some(1));
+        assert_eq!(forest.jump_weighted(0, 3, &5), Some(2));
+        assert_eq!(forest.jump_weighted(0, 3, &100), Some(3));
+        assert_eq!(forest.jump_weighted(3, 4, &4), Some(1));
+        assert_eq!(forest.jump_weighted(3, 4, &8), Some(1));
+        assert_eq!(forest.jump_weighted(3, 4, &9), Some(4));
+        forest.cut(2);
+        forest.link_weighted(2, 4, 10);
+        assert_eq!(forest.jump_weighted(3, 0, &15), Some(4));
+        assert_eq!(forest.jump_weighted(3, 0, &21), Some(0));
+        assert_eq!(forest.root(3), 0);
+    }
+
+    #[test]
+    fn set_edge_weight() {
+        let mut forest = Forest::<(), Sum<i64>>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 -1- 1 -2- 2 -3- 3, 1 -4- 4
+        forest.link_weighted(1, 0, 1);
+        forest.link_weighted(2, 1, 2);
+        forest.link_weighted(3, 2, 3);
+        forest.link_weighted(4, 1, 4);
+        forest.root(4);
+        assert_eq!(forest.set_edge_weight(2, 1, 20), Ok(()));
+        assert_eq!(forest.path_query_edges(0, 3), Some(24));
+        assert_eq!(forest.path_query_edges(4, 3), Some(27));
+        forest.evert(3);
+        assert_eq!(forest.set_edge_weight(0, 1, 10), Ok(()));
+        assert_eq!(forest.path_query_edges(0, 4), Some(14));
+        let id = forest.edge(3, 2).unwrap();
+        assert!(forest.set_edge_weight_by_id(id, 0));
+        assert_eq!(forest.path_query_edges(3, 0), Some(30));
+        assert_eq!(forest.set_edge_weight(0, 2, 1), Err(LctError::NotAdjacent(0, 2)));
+        forest.cut(2);
+        assert!(!forest.set_edge_weight_by_id(id, 1));
+    }
+
+    #[test]
+    fn ancestor_at_weight() {
+        let mut forest = Forest::<(), Sum<i64>>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 -3- 1 -0- 2 -4- 3, 1 -1- 4
+        forest.link_weighted(1, 0, 3);
+        forest.link_weighted(2, 1, 0);
+        forest.link_weighted(3, 2, 4);
+        forest.link_weighted(4, 1, 1);
+        assert_eq!(forest.ancestor_at_weight(3, &0), Some(0));
+        assert_eq!(forest.ancestor_at_weight(3, &3), Some(2));
+        assert_eq!(forest.ancestor_at_weight(3, &6), Some(2));
+        assert_eq!(forest.ancestor_at_weight(3, &7), Some(3));
+        assert_eq!(forest.ancestor_at_weight(4, &3), Some(1));
+        assert_eq!(forest.ancestor_at_weight(4, &100), Some(4));
+        assert_eq!(forest.ancestor_at_weight(4, &-1), None);
+        assert_eq!(forest.root(3), 0);
+    }
+
+    #[test]
+    fn ordered_children() {
+        let mut forest = Forest::<()>::new_ordered();
+        for _ in 0 .. 6 {
+            forest.add_node(());
+        }
+        for v in 1 .. 5 {
+            forest.link(v, 0);
+        }
+        forest.link(5, 2);
+        forest.cut(2);
+        assert_eq!(forest.children(0), vec![1, 3, 4]);
+        forest.link(2, 0);
+        assert_eq!(forest.children(0), vec![1, 3, 4, 2]);
+        forest.cut(1);
+        forest.cut(3);
+        assert_eq!(forest.children(0), vec![4, 2]);
+        assert_eq!(forest.preorder_iter(0).collect::<Vec<_>>(), vec![0, 4, 2, 5]);
+        forest.evert(5);
+        assert_eq!(forest.children(2), vec![0]);
+        assert_eq!(forest.children(0), vec![4]);
+    }
+
+    #[test]
+    fn version_counts_structural_changes() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for x in 0 .. 4 {
+            forest.add_node(x);
+        }
+        let mut last = forest.version();
+        let mut changed = |forest: &Forest<Sum<i64>>| {
+            let now = forest.version();
+            assert!(now >= last);
+            std::mem::replace(&mut last, now) != now
+        };
+        forest.link(1, 0);
+        assert!(changed(&forest));
+        forest.link(2, 1);
+        forest.path_query(2, 0);
+        forest.lca(0, 2);
+        forest.set_value(1, 10);
+        assert!(changed(&forest));
+        forest.path_query(0, 2);
+        forest.set_value(2, 5);
+        assert!(!changed(&forest));
+        forest.evert(2);
+        assert!(changed(&forest));
+        forest.cut(1);
+        assert!(changed(&forest));
+        assert!(forest.toggle_edge(0, 3).is_ok());
+        assert!(changed(&forest));
+        forest.path_query(0, 3);
+        forest.root(3);
+        assert!(!changed(&forest));
+        forest.clear();
+        assert!(changed(&forest));
+    }
+
+    #[test]
+    fn sorted_children() {
+        let mut forest = Forest::<()>::new_sorted();
+        for _ in 0 .. 6 {
+            forest.add_node(());
+        }
+        for v in [4, 1, 5, 3] {
+            forest.link(v, 0);
+        }
+        forest.link(2, 5);
+        assert_eq!(forest.children(0), vec![1, 3, 4, 5]);
+        forest.cut(3);
+        forest.link(3, 0);
+        assert_eq!(forest.children(0), vec![1, 3, 4, 5]);
+        forest.evert(2);
+        assert_eq!(forest.children(5), vec![0]);
+        assert_eq!(forest.children(0), vec![1, 3, 4]);
+        assert_eq!(forest.preorder_iter(0).collect::<Vec<_>>(), vec![2, 5, 0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn traversal_order_ignores_access_history() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 40;
+        let mut edges: Vec<(usize, usize)> = (1 .. n).map(|v| (v, rand(v))).collect();
+        let mut outputs = vec![];
+        for (round, sorted) in [(0, false), (1, false), (2, true), (3, true)] {
+            let mut forest = if sorted { Forest::<Sum<i64>>::new_sorted() } else { Forest::<Sum<i64>>::new() };
+            for v in 0 .. n {
+                forest.add_node(v as i64);
+            }
+            for &(child, parent) in &edges {
+                forest.link(child, parent);
+            }
+            // 問い合わせで splay 木の形を変える
+            for _ in 0 .. 200 * round {
+                let (u, v) = (rand(n), rand(n));
+                forest.path_query(u, v);
+                forest.lca(u, v);
+                forest.parent(u);
+            }
+            let ascii = forest.to_ascii(0, |x| x.to_string());
+            let preorder: Vec<_> = forest.preorder_iter(0).collect();
+            let bfs: Vec<_> = forest.bfs_iter(0).collect();
+            outputs.push((ascii, preorder, bfs, forest.to_nested(0), forest.tree_edges(0)));
+            if round == 1 {
+                // 番号の順に保つ森はつなぐ順にもよらない
+                edges.reverse();
+            }
+        }
+        assert!(outputs[0] == outputs[1]);
+        assert!(outputs[2] == outputs[3]);
+    }
+
+    #[test]
+    fn edge_ids() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 4 {
+            forest.add_node(());
+        }
+        forest.link(1, 0);
+        forest.link(2, 0);
+        forest.link(3, 2);
+        let a = forest.edge(0, 1).unwrap();
+        let b = forest.edge(3, 2).unwrap();
+        assert_eq!(forest.edge(2, 0), forest.edge(0, 2));
+        assert_eq!(forest.edge(1, 2), None);
+        assert_eq!(forest.endpoints(a), Some((1, 0)));
+        forest.evert(3);
+        assert_eq!(forest.edge(3, 2), Some(b));
+        let d = forest.edge(0, 2).unwrap();
+        assert_eq!(forest.cut(0), d);
+        assert_eq!(forest.cut(1), a);
+        assert_eq!(forest.endpoints(a), None);
+        forest.link(1, 3);
+        let c = forest.edge(1, 3).unwrap();
+        assert_ne!(c, a);
+        assert_eq!(forest.endpoints(c), Some((1, 3)));
+        assert_eq!(forest.endpoints(b), Some((3, 2)));
+        forest.link(0, 3);
+        let d = forest.edge(0, 3).unwrap();
+        assert_eq!(forest.path_edges(1, 2), Some(vec![c, b]));
+        assert_eq!(forest.path_edges(0, 2), Some(vec![d, b]));
+        assert_eq!(forest.path_edges(2, 1), Some(vec![b, c]));
+        assert_eq!(forest.path_edges(2, 2), Some(vec![]));
+    }
+
+    #[test]
+    fn cut_edges() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 6 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2 - 3, 1 - 4, 5
+        for (child, parent) in [(1, 0), (2, 1), (3, 2), (4, 1)] {
+            forest.link(child, parent);
+        }
+        let results = forest.cut_edges(&[(2, 1), (0, 1), (3, 5), (1, 2), (3, 2)]);
+        assert_eq!(results, vec![Ok(()), Ok(()), Err(LctError::NotAdjacent(3, 5)), Err(LctError::NotAdjacent(1, 2)), Ok(())]);
+        assert_eq!(forest.edge_count(), 1);
+        assert_eq!(forest.parent(4), Some(1));
+        assert!(forest.roots().into_iter().eq([0, 1, 2, 3, 5]));
+    }
+
+    #[test]
+    fn split_at_kth() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 30;
+        let mut forest = Forest::<()>::new();
+        let mut naive = Naive { parent: vec![None; n] };
+        for v in 0 .. n {
+            forest.add_node(());
+            if v > 0 {
+                let p = rand(v);
+                forest.link(v, p);
+                naive.parent[v] = Some(p);
+            }
+        }
+        for _ in 0 .. 500 {
+            let (v, k) = (rand(n), rand(6));
+            match rand(3) {
+                0 => {
+                    let mut lower = v;
+                    for _ in 0 .. k {
+                        lower = naive.parent[lower].unwrap_or(lower);
+                    }
+                    let expected = naive.parent[lower].map(|_| (lower, naive.root(v)));
+                    assert_eq!(forest.split_at_kth(v, k), expected);
+                    if expected.is_some() {
+                        naive.parent[lower] = None;
+                    }
+                }
+                1 => {
+                    let u = rand(n);
+                    if naive.root(u) != naive.root(v) {
+                        let r = naive.root(v);
+                        forest.link(r, u);
+                        naive.parent[r] = Some(u);
+                    }
+                }
+                _ => {
+                    forest.evert(v);
+                    naive.evert(v);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn swap_subtrees() {
+        let mut forest = Forest::<(), Sum<u64>>::new();
+        for _ in 0 .. 7 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2 - 3, 0 - 4 - 5, 6
+        forest.link_weighted(1, 0, 1);
+        forest.link_weighted(2, 1, 2);
+        forest.link_weighted(3, 2, 3);
+        forest.link_weighted(4, 0, 4);
+        forest.link_weighted(5, 4, 5);
+        assert_eq!(forest.swap_subtrees(1, 3), Err(LctError::Ancestor { ancestor: 1, descendant: 3 }));
+        assert_eq!(forest.swap_subtrees(5, 4), Err(LctError::Ancestor { ancestor: 4, descendant: 5 }));
+        assert_eq!(forest.swap_subtrees(2, 6), Err(LctError::IsRoot(6)));
+        assert_eq!(forest.swap_subtrees(2, 5), Ok(()));
+        assert_eq!(forest.parent(2), Some(4));
+        assert_eq!(forest.parent(5), Some(1));
+        assert_eq!(forest.parent(3), Some(2));
+        assert_eq!(forest.jump_weighted(3, 5, &9), Some(0));
+        assert_eq!(forest.jump_weighted(3, 5, &14), Some(1));
+        assert_eq!(forest.jump_weighted(3, 5, &15), Some(5));
+        assert_eq!(forest.root(3), 0);
+    }
+
+    #[test]
+    fn move_subtree() {
+        let mut forest = Forest::<(), Sum<u64>>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2 - 3, 4
+        forest.link_weighted(1, 0, 1);
+        forest.link_weighted(2, 1, 2);
+        forest.link_weighted(3, 2, 3);
+        assert_eq!(forest.move_subtree(1, 3), Err(LctError::Ancestor { ancestor: 1, descendant: 3 }));
+        assert_eq!(forest.move_subtree(2, 2), Err(LctError::Ancestor { ancestor: 2, descendant: 2 }));
+        assert_eq!(forest.parent(1), Some(0));
+        assert_eq!(forest.move_subtree(2, 4), Ok(()));
+        assert_eq!(forest.parent(2), Some(4));
+        assert_eq!(forest.jump_weighted(4, 3, &4), Some(2));
+        assert_eq!(forest.move_subtree(4, 0), Ok(()));
+        assert_eq!(forest.root(3), 0);
+        assert_eq!(forest.jump_weighted(3, 0, &4), Some(2));
+        assert_eq!(forest.jump_weighted(3, 0, &5), Some(0));
+    }
+
+    #[test]
+    fn splice() {
+        let mut forest = Forest::<(), Sum<u64>>::new();
+        for _ in 0 .. 8 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2 - 3 - 4, 2 - 5, 3 - 6, 7
+        forest.link_weighted(1, 0, 1);
+        forest.link_weighted(2, 1, 2);
+        forest.link_weighted(3, 2, 3);
+        forest.link_weighted(4, 3, 4);
+        forest.link_weighted(5, 2, 5);
+        forest.link_weighted(6, 3, 6);
+        assert_eq!(forest.splice(2, 1, 7), Err(LctError::NotAncestor { ancestor: 2, descendant: 1 }));
+        assert_eq!(forest.splice(1, 3, 5), Err(LctError::Ancestor { ancestor: 1, descendant: 5 }));
+        assert_eq!(forest.splice(1, 2, 2), Err(LctError::Ancestor { ancestor: 1, descendant: 2 }));
+        // 区間 1 - 2 を 3 の子に移すと、2 の子 3, 5 は 0 の子になる
+        assert_eq!(forest.splice(1, 2, 3), Ok(()));
+        assert_eq!(forest.parent(3), Some(0));
+        assert_eq!(forest.parent(1), Some(3));
+        assert_eq!(forest.parent(5), Some(0));
+        assert_eq!(forest.parent(4), Some(3));
+        assert_eq!(forest.path_query_edges(0, 3), Some(3));
+        assert_eq!(forest.path_query_edges(3, 5), Some(8));
+        // 根を含む区間 0 - 3 を切り出すと、3 の子はそれぞれ木の根になり、0 の子 5 は区間とともに移る
+        assert_eq!(forest.splice(0, 3, 7), Ok(()));
+        assert_eq!(forest.parent(0), Some(7));
+        assert_eq!(forest.parent(5), Some(0));
+        assert_eq!(forest.root(4), 4);
+        assert_eq!(forest.root(2), 1);
+        assert_eq!(forest.path_query_edges(7, 3), Some(3));
+        assert_eq!(forest.edge_count(), 4);
+    }
+
+    #[test]
+    fn contract() {
+        let mut forest = Forest::<Sum<i64>, Sum<u64>>::new();
+        for i in 0 .. 6 {
+            forest.add_node(i);
+        }
+        // 0 - 1 - 2, 1 - 3 - 4, 5
+        forest.link_weighted(1, 0, 1);
+        forest.link_weighted(2, 1, 2);
+        forest.link_weighted(3, 1, 3);
+        forest.link_weighted(4, 3, 4);
+        assert_eq!(forest.contract(0, 2, |a, b| a + b), Err(LctError::NotAdjacent(0, 2)));
+        assert_eq!(forest.contract(0, 5, |a, b| a + b), Err(LctError::NotAdjacent(0, 5)));
+
+        // 子を親に縮約する
+        assert_eq!(forest.contract(1, 3, |a, b| a + b), Ok(()));
+        assert_eq!(*forest.value(1), 4);
+        assert_eq!(forest.parent(4), Some(1));
+        assert_eq!(forest.root(3), 3);
+        assert_eq!(forest.path_query(4, 0), Some(4 + 4));
+        assert_eq!(forest.jump_weighted(4, 0, &4), Some(1));
+
+        // 親を子に縮約する
+        assert_eq!(forest.contract(2, 1, |a, b| a * 10 + b), Ok(()));
+        assert_eq!(*forest.value(2), 24);
+        assert_eq!(forest.parent(2), Some(0));
+        assert_eq!(forest.parent(4), Some(2));
+        assert_eq!(forest.root(1), 1);
+        assert_eq!(forest.path_query(4, 0), Some(28));
+        assert_eq!(forest.jump_weighted(4, 0, &4), Some(2));
+        assert_eq!(forest.jump_weighted(4, 0, &5), Some(0));
+    }
+
+    #[test]
+    fn isolate() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for i in 0 .. 6 {
+            forest.add_node(i);
+        }
+        // 0 - 1 - 2, 1 - 3 - 4, 5
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        forest.link(4, 3);
+        let mut roots = forest.isolate(1);
+        roots[1 ..].sort();
+        assert_eq!(roots, vec![0, 2, 3]);
+        assert_eq!(forest.root(4), 3);
+        assert_eq!(forest.parent(1), None);
+        assert!(!forest.connected(0, 1));
+        assert!(!forest.connected(1, 2));
+        assert_eq!(forest.isolate(3), vec![4]);
+        assert_eq!(forest.isolate(5), vec![]);
+    }
+
+    #[test]
+    fn path_reversed_edges() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 -> 1 -> 2, 1 -> 3
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        let id = |forest: &Forest<()>, u, v| forest.edge(u, v).unwrap();
+        assert_eq!(forest.path_reversed_edges(0, 2), Some(vec![]));
+        assert_eq!(forest.path_reversed_edges(2, 0), Some(vec![id(&forest, 2, 1), id(&forest, 1, 0)]));
+        assert_eq!(forest.path_reversed_edges(2, 3), Some(vec![id(&forest, 2, 1)]));
+        forest.evert(2);
+        assert_eq!(forest.path_reversed_edges(3, 0), Some(vec![id(&forest, 3, 1), id(&forest, 1, 0)]));
+        assert_eq!(forest.path_reversed_edges(0, 2), Some(vec![]));
+        assert_eq!(forest.path_reversed_edges(4, 4), Some(vec![]));
+        assert_eq!(forest.path_reversed_edges(0, 4), None);
+    }
+
+    #[test]
+    fn reroot_all() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 6 {
+            forest.add_node(());
+        }
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(4, 3);
+        assert_eq!(forest.reroot_all(&[2, 5, 0]), Err(LctError::Connected(2, 0)));
+        assert_eq!(forest.roots(), vec![0, 3, 5]);
+        assert_eq!(forest.reroot_all(&[4, 4]), Err(LctError::Connected(4, 4)));
+        assert_eq!(forest.reroot_all(&[2, 4]), Ok(()));
+        assert_eq!(forest.roots(), vec![2, 4, 5]);
+        assert_eq!(forest.parent(0), Some(1));
+        assert_eq!(forest.reroot_all(&[]), Ok(()));
+    }
+
+    #[test]
+    fn multi_edges() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 4 {
+            forest.add_node(());
+        }
+        assert_eq!(forest.add_multi_edge(0, 1), Ok(1));
+        assert_eq!(forest.add_multi_edge(1, 0), Ok(2));
+        assert_eq!(forest.add_multi_edge(1, 2), Ok(1));
+        assert_eq!(forest.add_multi_edge(0, 2), Err(LctError::Connected(0, 2)));
+        assert_eq!(forest.multiplicity(0, 1), 2);
+        assert_eq!(forest.multiplicity(0, 2), 0);
+        assert_eq!(forest.remove_multi_edge(0, 1), Ok(1));
+        assert!(forest.connected(0, 1));
+        assert_eq!(forest.toggle_edge(1, 2), Ok(Toggle::Cut));
+        assert!(!forest.connected(1, 2));
+        assert_eq!(forest.add_multi_edge(2, 1), Ok(1));
+        assert_eq!(forest.add_multi_edge(2, 1), Ok(2));
+        assert_eq!(forest.toggle_edge(1, 2), Ok(Toggle::Cut));
+        assert!(forest.connected(0, 2));
+        assert_eq!(forest.multiplicity(1, 2), 1);
+        assert_eq!(forest.remove_multi_edge(1, 0), Ok(0));
+        assert!(!forest.connected(0, 1));
+        assert_eq!(forest.remove_multi_edge(1, 0), Err(LctError::NotAdjacent(1, 0)));
+        assert_eq!(forest.remove_multi_edge(0, 3), Err(LctError::NotAdjacent(0, 3)));
+        // 切ったあとにつなぎ直した辺の多重度は 1 から数える
+        assert_eq!(forest.add_multi_edge(0, 1), Ok(1));
+    }
+
+    #[test]
+    fn toggle_edge() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 4 {
+            forest.add_node(());
+        }
+        assert_eq!(forest.toggle_edge(0, 1), Ok(Toggle::Linked));
+        assert_eq!(forest.toggle_edge(1, 2), Ok(Toggle::Linked));
+        assert!(forest.has_edge(2, 1));
+        assert!(!forest.has_edge(0, 2));
+        assert_eq!(forest.toggle_edge(0, 2), Err(LctError::Connected(0, 2)));
+        assert_eq!(forest.toggle_edge(3, 2), Ok(Toggle::Linked));
+        assert_eq!(forest.root(3), 0);
+        assert_eq!(forest.toggle_edge(1, 0), Ok(Toggle::Cut));
+        assert!(!forest.connected(0, 3));
+        assert_eq!(forest.toggle_edge(2, 3), Ok(Toggle::Cut));
+        assert!(!forest.has_edge(2, 3));
+    }
+
+    #[test]
+    fn is_ancestor() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 6 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2, 1 - 3 - 4, 5
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        forest.link(4, 3);
+        assert!(forest.is_ancestor(0, 4));
+        assert!(forest.is_ancestor(1, 2));
+        assert!(forest.is_ancestor(3, 3));
+        assert!(!forest.is_ancestor(4, 1));
+        assert!(!forest.is_ancestor(2, 4));
+        assert!(!forest.is_ancestor(0, 5));
+        assert_eq!(forest.lca(2, 4), Some(1));
+        assert_eq!(forest.lca(5, 4), None);
+        assert_eq!(forest.root(2), 0);
+    }
+
+    #[test]
+    fn roots() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 7 {
+            forest.add_node(());
+        }
+        forest.link(0, 3);
+        forest.link(1, 3);
+        forest.link(4, 5);
+        forest.link(5, 6);
+        assert_eq!(forest.roots(), vec![3, 2, 6]);
+        forest.evert(4);
+        forest.cut(0);
+        assert_eq!(forest.roots(), vec![0, 3, 2, 4]);
+    }
+
+    #[test]
+    fn clear() {
+        let mut forest = Forest::<Sum<i64>, Sum<u64>>::new();
+        for i in 0 .. 4 {
+            forest.add_node(i);
+        }
+        forest.link_weighted(1, 0, 5);
+        forest.link_weighted(2, 1, 5);
+        forest.evert(2);
+        forest.clear_edges();
+        assert_eq!(forest.len(), 4);
+        assert_eq!(forest.roots(), vec![0, 1, 2, 3]);
+        assert_eq!(forest.path_query(1, 1), Some(1));
+        assert_eq!(forest.degree(1), 0);
+        forest.link(3, 2);
+        assert_eq!(forest.path_query(3, 2), Some(5));
+        assert_eq!(forest.jump_weighted(3, 2, &0), Some(2));
+
+        forest.clear();
+        assert!(forest.is_empty());
+        assert_eq!(forest.add_node(7), 0);
+        assert_eq!(forest.add_node(8), 1);
+        forest.link(0, 1);
+        assert_eq!(forest.path_query(0, 1), Some(15));
+    }
+
+    #[test]
+    fn tree_edges() {
+        let mut forest = Forest::<(), Sum<u64>>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        forest.link_weighted(1, 0, 10);
+        forest.link_weighted(2, 1, 20);
+        forest.link_weighted(3, 1, 30);
+        assert_eq!(forest.tree_edges(3), vec![(0, 1, 10), (1, 2, 20), (1, 3, 30)]);
+        forest.evert(2);
+        assert_eq!(forest.tree_edges(0), vec![(2, 1, 20), (1, 0, 10), (1, 3, 30)]);
+        assert_eq!(forest.tree_edges(4), vec![]);
+    }
+
+    #[test]
+    fn to_nested() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for i in 0 .. 5 {
+            forest.add_node(i * 10);
+        }
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        let leaf = |value| TreeRepr { value, children: vec![] };
+        assert_eq!(
+            forest.to_nested(2),
+            TreeRepr { value: 0, children: vec![TreeRepr { value: 10, children: vec![leaf(20), leaf(30)] }] },
+        );
+        forest.evert(3);
+        assert_eq!(
+            forest.to_nested(0),
+            TreeRepr { value: 30, children: vec![TreeRepr { value: 10, children: vec![leaf(0), leaf(20)] }] },
+        );
+        assert_eq!(forest.to_nested(4), leaf(40));
+    }
+
+    #[test]
+    fn preferred_path() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 6 {
+            forest.add_node(());
+        }
+        for i in 1 .. 6 {
+            forest.link(i, i - 1);
+        }
+        forest.evert(5);
+        forest.root(3);
+        assert_eq!(forest.preferred_path(3), vec![5, 4, 3]);
+        assert_eq!(forest.preferred_path(1), vec![2, 1, 0]);
+        assert_eq!(forest.preferred_path(4), forest.preferred_path(5));
+        let snapshot = forest.preferred_path(0);
+        assert_eq!(forest.preferred_path(0), snapshot);
+    }
+
+    #[test]
+    fn light_children() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 6 {
+            forest.add_node(());
+        }
+        for i in 1 .. 6 {
+            forest.link(i, i - 1);
+        }
+        forest.evert(5);
+        forest.root(3);
+        assert_eq!(forest.light_children(3).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(forest.light_children(2).count(), 0);
+        assert_eq!(forest.light_children(4).count(), 0);
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        for v in 1 .. 4 {
+            forest.link(v, 0);
+        }
+        forest.link(4, 2);
+        forest.root(2);
+        assert_eq!(forest.light_children(0).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(forest.light_children(2).collect::<Vec<_>>(), vec![4]);
+        forest.root(4);
+        assert_eq!(forest.light_children(2).count(), 0);
+        assert_eq!(forest.preferred_path(4), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn decomposition_stats() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 7 {
+            forest.add_node(());
+        }
+        for v in 1 .. 4 {
+            forest.link(v, 0);
+        }
+        forest.link(4, 2);
+        forest.root(4);
+        // 0 - 2 - 4 と 1, 3, 5, 6
+        let stats = forest.decomposition_stats();
+        assert_eq!(stats.paths, 5);
+        assert_eq!(stats.lengths, BTreeMap::from([(1, 4), (3, 1)]));
+        assert_eq!(stats.light_edges, 2);
+        forest.link(6, 5);
+        forest.root(6);
+        let stats = forest.decomposition_stats();
+        assert_eq!(stats.paths, 4);
+        assert_eq!(stats.lengths, BTreeMap::from([(1, 2), (2, 1), (3, 1)]));
+        assert_eq!(stats.light_edges, 2);
+    }
+
+    #[test]
+    fn with_value_mut() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for i in 0 .. 4 {
+            forest.add_node(i);
+        }
+        for i in 1 .. 4 {
+            forest.link(i, i - 1);
+        }
+        assert_eq!(forest.path_query(0, 3), Some(6));
+        let old = forest.with_value_mut(2, |x| std::mem::replace(x, 10));
+        assert_eq!(old, 2);
+        assert_eq!(forest.path_query(0, 3), Some(14));
+        forest.with_value_mut(0, |x| *x -= 5);
+        assert_eq!(forest.path_query(3, 0), Some(9));
+    }
+
+    #[test]
+    fn index_mut_repairs_aggregates() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for i in 0 .. 5 {
+            forest.add_node(i);
+        }
+        for i in 1 .. 5 {
+            forest.link(i, i - 1);
+        }
+        assert_eq!(forest.path_query(0, 4), Some(10));
+        forest[1] = 100;
+        forest[3] += 1000;
+        forest[4] *= 2;
+        assert_eq!(forest[3], 1003);
+        assert_eq!(forest.path_query(0, 4), Some(1113));
+        assert_eq!(forest.path_query(2, 3), Some(1005));
+        forest[3] = 0;
+        assert_eq!(forest.path_partition_point(0, 4, |&s| s < 110), Some(4));
+    }
+
+    #[test]
+    fn from_edges() {
+        let mut forest: Forest = [(0, 1), (0, 2), (2, 3), (5, 4)].into_iter().collect();
+        assert_eq!(forest.len(), 6);
+        assert_eq!(forest.roots(), vec![0, 5]);
+        assert_eq!(forest.parent(3), Some(2));
+        forest.extend([(3, 4), (6, 7)]);
+        assert_eq!(forest.len(), 8);
+        assert_eq!(forest.root(5), 0);
+        assert_eq!(forest.parent(5), Some(4));
+        assert_eq!(forest.try_extend([(7, 8), (1, 5)]), Err(LctError::Connected(1, 5)));
+        assert_eq!(forest.len(), 9);
+        assert!(forest.connected(6, 8));
+        assert_eq!(Forest::<()>::try_from_edges([(0, 1), (1, 0)]).err(), Some(LctError::Connected(1, 0)));
+        assert_eq!(Forest::<()>::try_from_edges([(2, 2)]).err(), Some(LctError::Connected(2, 2)));
+    }
+
+    #[test]
+    fn from_parents() {
+        let parents = vec![Some(2), None, Some(1), Some(2), None];
+        let mut forest = Forest::<Sum<i64>>::from_parents(parents.iter().enumerate().map(|(v, &p)| (v as i64, p))).unwrap();
+        assert_eq!(forest.to_parents(), parents);
+        assert_eq!(forest.path_query(0, 3), Some(5));
+        assert_eq!(forest.roots(), vec![1, 4]);
+        let cycle = [((), Some(1)), ((), Some(2)), ((), Some(0))];
+        assert_eq!(Forest::<()>::from_parents(cycle).err(), Some(LctError::Ancestor { ancestor: 2, descendant: 0 }));
+        let missing = [((), None), ((), Some(2))];
+        assert_eq!(Forest::<()>::from_parents(missing).err(), Some(LctError::OutOfRange(2)));
+    }
+
+    #[test]
+    fn path_aggregates() {
+        use crate::monoid::Max;
+
+        let mut forest = Forest::<Sum<i64>, Max<u32>>::new();
+        for i in 0 .. 5 {
+            forest.add_node(i);
+        }
+        forest.link_weighted(1, 0, 4);
+        forest.link_weighted(2, 1, 9);
+        forest.link_weighted(3, 2, 1);
+        forest.link_weighted(4, 1, 2);
+        assert_eq!(forest.path_aggregates(3, 4), Some((10, 9)));
+        assert_eq!(forest.path_aggregates(0, 4), Some((5, 4)));
+        assert_eq!(forest.path_aggregates(2, 2), Some((2, 0)));
+        assert_eq!(forest.path_query(0, 3), Some(6));
+    }
+
+    #[test]
+    fn path_query_open_and_edges() {
+        let mut forest = Forest::<Sum<i64>, Sum<u64>>::new();
+        for i in 0 .. 5 {
+            forest.add_node(1 << i);
+        }
+        // 0 - 1 - 2 - 3, 1 - 4
+        forest.link_weighted(1, 0, 10);
+        forest.link_weighted(2, 1, 20);
+        forest.link_weighted(3, 2, 30);
+        forest.link_weighted(4, 1, 40);
+        assert_eq!(forest.path_query_edges(3, 4), Some(90));
+        assert_eq!(forest.path_query_edges(2, 2), Some(0));
+        assert_eq!(forest.path_query_open(3, 4), Some(2 + 4));
+        assert_eq!(forest.path_query_open(3, 2), Some(0));
+        assert_eq!(forest.path_query_open(3, 3), Some(0));
+        assert_eq!(forest.path_query_excluding(3, 4, true, false), Some(2 + 4 + 16));
+        assert_eq!(forest.path_query_excluding(3, 4, false, true), Some(8 + 4 + 2));
+        assert_eq!(forest.path_query_excluding(0, 0, false, true), Some(0));
+        assert_eq!(forest.path_query_excluding(0, 1, false, false), Some(3));
+        assert_eq!(forest.root(4), 0);
+    }
+
+    #[test]
+    fn path_any_all_short_circuit() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for i in 0 .. 6 {
+            forest.add_node(i);
+        }
+        for i in 1 .. 6 {
+            forest.link(i, i - 1);
+        }
+        let mut seen = vec![];
+        assert_eq!(forest.path_any(1, 5, |&x| { seen.push(x); x % 2 == 0 }), Some(true));
+        assert_eq!(seen, vec![1, 2]);
+        assert_eq!(forest.path_any(3, 3, |&x| x % 2 == 0), Some(false));
+        assert_eq!(forest.path_all(5, 0, |&x| x < 6), Some(true));
+        seen.clear();
+        assert_eq!(forest.path_all(5, 0, |&x| { seen.push(x); x > 3 }), Some(false));
+        assert_eq!(seen, vec![5, 4, 3]);
+        let other = forest.add_node(0);
+        assert_eq!(forest.path_any(0, other, |_| true), None);
+        assert_eq!(forest.root(4), 0);
+    }
+
+    #[test]
+    fn lca_many() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 8 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 3, 1 - 4 - 6, 0 - 2 - 5, 7
+        forest.link(1, 0);
+        forest.link(2, 0);
+        forest.link(3, 1);
+        forest.link(4, 1);
+        forest.link(5, 2);
+        forest.link(6, 4);
+        assert_eq!(forest.lca_many([3, 6]), Some(1));
+        assert_eq!(forest.lca_many([6, 3, 4]), Some(1));
+        assert_eq!(forest.lca_many([6, 3, 5]), Some(0));
+        assert_eq!(forest.lca_many([6]), Some(6));
+        assert_eq!(forest.lca_many([6, 7]), None);
+        assert_eq!(forest.lca_many([]), None);
+        forest.evert(6);
+        assert_eq!(forest.lca_many([3, 5]), Some(1));
+    }
+
+    #[test]
+    fn path_midpoint() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 7 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2 - 3 - 4, 2 - 5, 6
+        for (child, parent) in [(1, 0), (2, 1), (3, 2), (4, 3), (5, 2)] {
+            forest.link(child, parent);
+        }
+        assert_eq!(forest.path_midpoint(0, 4), Some((2, 2)));
+        assert_eq!(forest.path_midpoint(4, 0), Some((2, 2)));
+        assert_eq!(forest.path_midpoint(0, 3), Some((1, 2)));
+        assert_eq!(forest.path_midpoint(3, 0), Some((2, 1)));
+        assert_eq!(forest.path_midpoint(5, 4), Some((2, 3)));
+        assert_eq!(forest.path_midpoint(5, 5), Some((5, 5)));
+        assert_eq!(forest.path_midpoint(5, 6), None);
+        assert_eq!(forest.root(4), 0);
+    }
+
+    #[test]
+    fn random_path_vertex() {
+        let mut rng = xorshift64(88172645463325252);
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 7 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2 - 3 - 4, 2 - 5, 6
+        for (child, parent) in [(1, 0), (2, 1), (3, 2), (4, 3), (5, 2)] {
+            forest.link(child, parent);
+        }
+        let mut counts = [0; 7];
+        for _ in 0 .. 8000 {
+            counts[forest.random_path_vertex(5, 0, &mut rng).unwrap()] += 1;
+        }
+        assert_eq!((counts[3], counts[4], counts[6]), (0, 0, 0));
+        for v in [0, 1, 2, 5] {
+            assert!((counts[v] as i64 - 2000).abs() < 300, "{:?}", counts);
+        }
+        assert_eq!(forest.random_path_vertex(4, 4, &mut rng), Some(4));
+        assert_eq!(forest.random_path_vertex(5, 6, &mut rng), None);
+        assert_eq!(forest.root(4), 0);
+    }
+
+    #[test]
+    fn bulk_distances() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 7 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2 - 3, 1 - 4, 5 - 6
+        for (child, parent) in [(1, 0), (2, 1), (3, 2), (4, 1), (6, 5)] {
+            forest.link(child, parent);
+        }
+        let pairs = [(3, 4), (0, 3), (3, 0), (5, 6), (3, 6), (4, 4), (3, 3), (0, 4)];
+        let distances = forest.bulk_distances(&pairs);
+        assert_eq!(distances, vec![Some(3), Some(3), Some(3), Some(1), None, Some(0), Some(0), Some(2)]);
+        assert_eq!(forest.root(3), 0);
+        assert_eq!(forest.root(6), 5);
+    }
+
+    /// 親の配列で表した素朴な森
+    pub(super) struct Naive {
+        pub(super) parent: Vec<Option<usize>>,
+    }
+
+    impl Naive {
+        pub(super) fn root(&self, mut v: usize) -> usize {
+            while let Some(p) = self.parent[v] {
+                v = p;
+            }
+            v
+        }
+
+        pub(super) fn evert(&mut self, v: usize) {
+            let mut prev = None;
+            let mut cur = Some(v);
+            while let Some(x) = cur {
+                cur = std::mem::replace(&mut self.parent[x], prev);
+                prev = Some(x);
+            }
+        }
+
+        pub(super) fn path(&self, u: usize, v: usize) -> Option<Vec<usize>> {
+            let up = |mut x: usize| {
+                let mut path = vec![x];
+                while let Some(p) = self.parent[x] {
+                    path.push(p);
+                    x = p;
+                }
+                path
+            };
+            let (mut pu, mut pv) = (up(u), up(v));
+            if pu.last() != pv.last() {
+                return None;
+            }
+            while pu.len() >= 2 && pv.len() >= 2 && pu[pu.len() - 2] == pv[pv.len() - 2] {
+                pu.pop();
+                pv.pop();
+            }
+            pv.pop();
+            pu.extend(pv.into_iter().rev());
+            Some(pu)
+        }
+    }
+
+    #[test]
+    fn random_against_naive() {
+        against_naive(Forest::<Sum<i64>>::new());
+    }
+
+    #[test]
+    fn slab_store_against_naive() {
+        against_naive(Forest::<Sum<i64>, (), (), (), Slab<_>>::default());
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn file_store_against_naive() {
+        let path = std::env::temp_dir().join(format!("link-cut-tree-forest-{}", std::process::id()));
+        let file = std::fs::File::options().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+        let forest = Forest::<Sum<i64>, (), (), (), _>::with_store(crate::FileStore::new(file, 4, 3).unwrap());
+        against_naive(forest);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn file_store_keeps_few_pages_resident() {
+        use crate::SnapshotValue;
+        let path = std::env::temp_dir().join(format!("link-cut-tree-resident-{}", std::process::id()));
+        let file = std::fs::File::options().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+        let (page_len, max_pages) = (16, 8);
+        let mut forest = Forest::<Sum<i64>, (), (), (), _>::with_store(crate::FileStore::new(file, page_len, max_pages).unwrap());
+        let mut rand = xorshift(0x6a09e667f3bcc908);
+        let n = 4000;
+        for i in 0 .. n {
+            forest.add_node(i as i64);
+            if i > 0 {
+                forest.link(i, rand(i));
+            }
+            assert!(forest.store().resident_pages() <= max_pages);
+        }
+        for _ in 0 .. 2000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(3) {
+                0 => {
+                    forest.path_query(u, v);
+                }
+                1 => forest.evert(u),
+                _ => {
+                    if let Some(p) = forest.parent(u) {
+                        forest.cut(u);
+                        let w = if forest.root(v) == u { p } else { v };
+                        forest.link(u, w);
+                    }
+                }
+            }
+            assert!(forest.store().resident_pages() <= max_pages);
+        }
+        // メモリに置く節点は全体の 1/16 未満で、残りはファイルにある
+        let node_size = ForestNodeOf::<Sum<i64>, (), (), ()>::SIZE;
+        let resident = forest.store().resident_pages() * page_len * node_size;
+        assert!(resident * 16 < forest.store().len() * node_size);
+        drop(forest);
+        assert!(std::fs::metadata(&path).unwrap().len() >= (2 * n - 1) as u64 * node_size as u64);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn against_naive<N: NodeStore<ForestNodeOf<Sum<i64>, (), (), ()>>>(mut forest: Forest<Sum<i64>, (), (), (), N>) {
+        let mut rand = xorshift(88172645463325252);
+        let n = 30;
+        let mut values = vec![];
+        for i in 0 .. n {
+            values.push(i as i64 * 7 % 11);
+            forest.add_node(values[i]);
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        for _ in 0 .. 5000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(5) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        forest.link(u, v);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+                3 => {
+                    values[u] = rand(100) as i64;
+                    forest.set_value(u, values[u]);
+                }
+                _ => {
+                    let expected = naive.path(u, v);
+                    let mut visited = vec![];
+                    let found = forest.path_for_each(u, v, |x, _| visited.push(x));
+                    assert_eq!(expected.is_some(), found);
+                    if let Some(path) = expected {
+                        assert_eq!(visited, path);
+                        let sum = path.iter().map(|&x| values[x]).sum::<i64>();
+                        assert_eq!(forest.path_query(u, v), Some(sum));
+                    }
+                }
+            }
+            assert_eq!(forest.root(u), naive.root(u));
+            let mut neighbors: Vec<usize> = forest.neighbors(u).collect();
+            neighbors.sort();
+            let mut expected: Vec<usize> = (0 .. n).filter(|&w| naive.parent[w] == Some(u)).chain(naive.parent[u]).collect();
+            expected.sort();
+            assert_eq!(neighbors, expected);
+            assert_eq!(forest.degree(u), expected.len());
+        }
+    }
+
+    #[test]
+    fn neighbors_after_evert_and_expose() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 7 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2 - 3, 2 - 4 - 6, 2 - 5
+        for (child, parent) in [(1, 0), (2, 1), (3, 2), (4, 2), (5, 2), (6, 4)] {
+            forest.link(child, parent);
+        }
+        let sorted = |forest: &Forest<()>, v: usize| {
+            let mut neighbors: Vec<_> = forest.neighbors(v).collect();
+            neighbors.sort();
+            neighbors
+        };
+        let light = |forest: &Forest<()>, v: usize| {
+            let mut children: Vec<_> = forest.light_children(v).collect();
+            children.sort();
+            children
+        };
+        // 2 の親は 1、優先する子は 4、軽い子は 3 と 5
+        forest.evert(0);
+        forest.expose(vertex_node(6));
+        assert_eq!(light(&forest, 2), vec![3, 5]);
+        assert_eq!(sorted(&forest, 2), vec![1, 3, 4, 5]);
+        assert_eq!(forest.degree(2), 4);
+        // 根を 5 に移すと 1 も子になり、3 を優先する子にする
+        forest.evert(5);
+        forest.expose(vertex_node(3));
+        assert_eq!(light(&forest, 2), vec![1, 4]);
+        assert_eq!(sorted(&forest, 2), vec![1, 3, 4, 5]);
+        assert_eq!(forest.degree(2), 4);
+        assert_eq!(forest.parent(2), Some(5));
+        // 2 を expose すると子はすべて軽くなり、親との辺だけが優先パスに残る
+        forest.expose(vertex_node(2));
+        assert_eq!(light(&forest, 2), vec![1, 3, 4]);
+        assert_eq!(sorted(&forest, 2), vec![1, 3, 4, 5]);
+        assert_eq!(sorted(&forest, 4), vec![2, 6]);
+        assert_eq!((forest.degree(0), forest.degree(5), forest.degree(4)), (1, 1, 2));
+        assert_eq!(forest.children(2).len(), 3);
+    }
+
+    #[test]
+    fn dynamic_aggregates() {
+        use crate::monoid::{Aggregate, Aggregates, Dynamic};
+        use std::sync::Arc;
+
+        let mut set = Aggregates::new();
+        // 設定から読んだつもりの集約の組
+        for (name, kind) in [("sum", Aggregate::Sum), ("max", Aggregate::Max), ("hash", Aggregate::Hash(131)), ("last", Aggregate::Custom { identity: -1, op: |_, b| b })] {
+            set.register(name, kind);
+        }
+        let set = Arc::new(set);
+        let values = [5, -3, 8, 2, 7];
+        let mut forest = Forest::<Dynamic>::new();
+        for &x in &values {
+            forest.add_node(set.value(x));
+        }
+        // 0 - 1 - 2, 1 - 3, 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        let m = (1i128 << 61) - 1;
+        let hash = |path: &[usize]| path.iter().fold(0, |h, &v| (h * 131 + values[v] as i128).rem_euclid(m)) as i64;
+        let sum = forest.path_query(3, 2).unwrap();
+        assert_eq!(set.get(&sum, "sum"), Some(7));
+        assert_eq!(set.get(&sum, "max"), Some(8));
+        assert_eq!(set.get(&sum, "last"), Some(8));
+        assert_eq!(set.get(&sum, "hash"), Some(hash(&[3, 1, 2])));
+        assert_ne!(set.get(&forest.path_query(2, 3).unwrap(), "hash"), Some(hash(&[3, 1, 2])));
+        assert_eq!(set.get(&sum, "min"), None);
+        assert_eq!(set.get(&forest.path_query_open(0, 1).unwrap(), "max"), Some(i64::MIN));
+        forest.set_value(1, set.value(10));
+        assert_eq!(set.get(&forest.path_query(0, 2).unwrap(), "sum"), Some(23));
+        assert_eq!(forest.path_query(0, 4), None);
+    }
+
+    #[test]
+    fn component_count() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        assert_eq!(forest.component_count(), 5);
+        forest.link(1, 0);
+        forest.link(2, 1);
+        assert!(forest.union(3, 4));
+        assert!(!forest.union(0, 2));
+        assert_eq!((forest.edge_count(), forest.component_count()), (3, 2));
+        forest.cut(2);
+        assert_eq!(forest.component_count(), 3);
+        forest.add_node(());
+        assert_eq!(forest.component_count(), 4);
+        forest.clear_edges();
+        assert_eq!(forest.component_count(), 6);
+    }
+
+    #[test]
+    fn try_link_reports_cycle() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        assert_eq!(forest.try_link(1, 0), Ok(()));
+        assert_eq!(forest.try_link(2, 1), Ok(()));
+        assert_eq!(forest.try_link(3, 1), Ok(()));
+        let Err(LinkError::Cycle(path)) = forest.try_link(2, 3) else { panic!() };
+        assert_eq!(path.vertices, vec![2, 1, 3]);
+        assert_eq!(path.edges, vec![forest.edge(2, 1).unwrap(), forest.edge(1, 3).unwrap()]);
+        assert_eq!(forest.edge_count(), 3);
+        assert_eq!(forest.try_link(4, 4), Err(LinkError::Cycle(CyclePath { vertices: vec![4], edges: vec![] })));
+        assert_eq!(forest.try_link(0, 4), Ok(()));
+        assert!(forest.has_edge(0, 4));
+    }
+
+    #[test]
+    fn degree_limit() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        forest.set_degree_limit(0, Some(2));
+        assert_eq!(forest.degree_limit(0), Some(2));
+        assert_eq!(forest.connect(1, 0), Ok(()));
+        assert_eq!(forest.try_link(2, 0), Ok(()));
+        assert_eq!(forest.connect(3, 0), Err(LctError::DegreeLimitExceeded(0)));
+        assert_eq!(forest.try_link(0, 3), Err(LinkError::DegreeLimitExceeded(0)));
+        assert_eq!(forest.edge_count(), 2);
+        // 根を変えても次数は変わらない
+        forest.evert(1);
+        assert_eq!(forest.connect(0, 3), Err(LctError::DegreeLimitExceeded(0)));
+        forest.set_degree_limit(3, Some(0));
+        assert_eq!(forest.connect(3, 4), Err(LctError::DegreeLimitExceeded(3)));
+        forest.cut(2);
+        assert_eq!(forest.connect(3, 0), Err(LctError::DegreeLimitExceeded(3)));
+        forest.set_degree_limit(3, None);
+        assert_eq!(forest.connect(3, 0), Ok(()));
+        assert_eq!(forest.degree(0), 2);
+    }
+}
@@ -0,0 +1,460 @@
+use std::ops::{Div, Mul, Rem};
+
+use super::{Forest, ForestNodeOf};
+use crate::action::{Action, CompensatedAdd};
+use crate::monoid::{AtLeast, CompensatedSum, Count, Gcd, HyperLogLog, Lcm, Modulus, Monoid, Prod, RollingHash, Sum, Thresholds};
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+impl<T, E, A, S, N: NodeStore<ForestNodeOf<Gcd<T>, E, A, S>>> Forest<Gcd<T>, E, A, S, N>
+where
+    T: Clone + Default + PartialEq + Rem<Output = T>,
+    E: Monoid,
+    A: Action<Gcd<T>, E>,
+    S: Subtree<Gcd<T>, E>,
+{
+    /// u から v へのパス上の頂点の値の最大公約数
+    pub fn path_gcd(&mut self, u: usize, v: usize) -> Option<T> {
+        self.path_query(u, v)
+    }
+}
+
+impl<T, E, A, S, N: NodeStore<ForestNodeOf<Lcm<T>, E, A, S>>> Forest<Lcm<T>, E, A, S, N>
+where
+    T: Clone + Default + PartialEq + Rem<Output = T> + Div<Output = T> + Mul<Output = T> + From<u8>,
+    E: Monoid,
+    A: Action<Lcm<T>, E>,
+    S: Subtree<Lcm<T>, E>,
+{
+    /// u から v へのパス上の頂点の値の最小公倍数
+    pub fn path_lcm(&mut self, u: usize, v: usize) -> Option<T> {
+        self.path_query(u, v)
+    }
+}
+
+impl<E: Monoid, A: Action<Count, E>, S: Subtree<Count, E>, N: NodeStore<ForestNodeOf<Count, E, A, S>>> Forest<Count, E, A, S, N> {
+    /// v に印を付ける、または外す
+    pub fn set_flag(&mut self, v: usize, flag: bool) {
+        self.set_value(v, flag as usize);
+    }
+
+    /// u から v へのパス上の印の付いた頂点の数
+    pub fn path_count(&mut self, u: usize, v: usize) -> Option<usize> {
+        self.path_query(u, v)
+    }
+}
+
+impl<M: Monoid, T: Thresholds, A: Action<M, AtLeast<T>>, S: Subtree<M, AtLeast<T>>, N: NodeStore<ForestNodeOf<M, AtLeast<T>, A, S>>> Forest<M, AtLeast<T>, A, S, N> {
+    /// u から v へのパス上で重みが x 以上の辺の数（x は T::thresholds() のどれかであること）
+    pub fn path_edges_at_least(&mut self, u: usize, v: usize, x: i64) -> Option<usize> {
+        let i = T::thresholds().iter().position(|&t| t == x).expect("x must be one of the thresholds");
+        self.path_query_edges(u, v).map(|counts| counts[i])
+    }
+}
+
+impl<P, const BASE: u64, E, A, S, N: NodeStore<ForestNodeOf<RollingHash<P, BASE>, E, A, S>>> Forest<RollingHash<P, BASE>, E, A, S, N>
+where
+    P: Modulus,
+    E: Monoid,
+    A: Action<RollingHash<P, BASE>, E>,
+    S: Subtree<RollingHash<P, BASE>, E>,
+{
+    /// u から v へのパス上の頂点の値を u から並べた列のハッシュ
+    pub fn path_hash(&mut self, u: usize, v: usize) -> Option<u64> {
+        self.path_query(u, v).map(|(hash, _)| hash)
+    }
+
+    /// u から v へのパス上の値の列が回文か（ハッシュが一致するかで判定する）
+    /// splay 木が持つ正順と逆順の集約を比べるので、expose は一度で済む
+    pub fn is_palindrome_path(&mut self, u: usize, v: usize) -> Option<bool> {
+        self.with_path(u, v, |forest, top| forest.nodes[top].sum == forest.nodes[top].rev_sum)
+    }
+
+    /// 頂点 v の値を x にする
+    pub fn set_hash_value(&mut self, v: usize, x: u64) {
+        self.set_value(v, RollingHash::<P, BASE>::of(x));
+    }
+}
+
+impl<const BITS: u32, E: Monoid, A: Action<HyperLogLog<BITS>, E>, S: Subtree<HyperLogLog<BITS>, E>, N: NodeStore<ForestNodeOf<HyperLogLog<BITS>, E, A, S>>> Forest<HyperLogLog<BITS>, E, A, S, N> {
+    /// u から v へのパス上の頂点の異なる値の個数の推定値（異なる木なら None）
+    /// 頂点の値は HyperLogLog::of で作ること
+    pub fn path_count_distinct_approx(&mut self, u: usize, v: usize) -> Option<f64> {
+        self.path_query(u, v).map(|sketch| HyperLogLog::<BITS>::estimate(&sketch))
+    }
+}
+
+impl<E: Monoid, A: Action<Sum<u64>, E>, S: Subtree<Sum<u64>, E>, N: NodeStore<ForestNodeOf<Sum<u64>, E, A, S>>> Forest<Sum<u64>, E, A, S, N> {
+    /// u から v へのパス上の頂点を、値に比例する確率で一つ選ぶ（値の和が 0 か異なる木なら None）
+    /// rng は一様な 64 ビットの乱数を返すこと
+    pub fn sample_path(&mut self, u: usize, v: usize, rng: &mut impl FnMut() -> u64) -> Option<usize> {
+        self.with_path(u, v, |forest, top| {
+            let total = forest.nodes[top].sum;
+            if total == 0 {
+                return None;
+            }
+            // [0, total) の一様な値を取り、先頭からの和がそれを超える最初の頂点へ降りる
+            let r = ((rng() as u128 * total as u128) >> 64) as u64;
+            forest.search(top, Sum::<u64>::op, 0, |node| &node.sum, |node| &node.value, |&s| s <= r)
+        })
+        .flatten()
+        .map(|x| x >> 1)
+    }
+}
+
+impl<T, E, A, S, N: NodeStore<ForestNodeOf<Prod<T>, E, A, S>>> Forest<Prod<T>, E, A, S, N>
+where
+    T: Clone + Mul<Output = T> + From<u8>,
+    E: Monoid,
+    A: Action<Prod<T>, E>,
+    S: Subtree<Prod<T>, E>,
+{
+    /// u から v へのパス上の頂点の値の積
+    pub fn path_product(&mut self, u: usize, v: usize) -> Option<T> {
+        self.path_query(u, v)
+    }
+}
+
+impl<E: Monoid, A: Action<CompensatedSum, E>, S: Subtree<CompensatedSum, E>, N: NodeStore<ForestNodeOf<CompensatedSum, E, A, S>>> Forest<CompensatedSum, E, A, S, N> {
+    /// 頂点 v の値を x にする
+    pub fn set_float(&mut self, v: usize, x: f64) {
+        self.set_value(v, CompensatedSum::of(x));
+    }
+
+    /// u から v へのパス上の頂点の値の、誤差を補正した和
+    pub fn path_float_sum(&mut self, u: usize, v: usize) -> Option<f64> {
+        self.path_query(u, v).map(|sum| CompensatedSum::total(&sum))
+    }
+}
+
+impl<E: Monoid, N: NodeStore<ForestNodeOf<CompensatedSum, E, CompensatedAdd, ()>>> Forest<CompensatedSum, E, CompensatedAdd, (), N> {
+    /// u から v へのパス上の頂点の値に x を足す（異なる木なら false）
+    pub fn path_add_float(&mut self, u: usize, v: usize, x: f64) -> bool {
+        self.path_apply(u, v, CompensatedSum::of(x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Affine;
+    use crate::monoid::{AffineComposite, ConstModulus, Max, ModProd, ModSum, Sum};
+    use crate::forest::tests::Naive;
+    use crate::test_util::{xorshift, xorshift64};
+
+    type P = ConstModulus<998244353>;
+
+    struct Congestion;
+
+    impl Thresholds for Congestion {
+        fn thresholds() -> &'static [i64] {
+            &[10, 50, 100]
+        }
+    }
+
+    #[test]
+    fn path_edges_at_least() {
+        type W = AtLeast<Congestion>;
+        let mut forest = Forest::<(), W>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 -5- 1 -60- 2 -100- 3, 1 -10- 4
+        forest.link_weighted(1, 0, W::of(5));
+        forest.link_weighted(2, 1, W::of(60));
+        forest.link_weighted(3, 2, W::of(100));
+        forest.link_weighted(4, 1, W::of(10));
+        assert_eq!(forest.path_edges_at_least(0, 3, 10), Some(2));
+        assert_eq!(forest.path_edges_at_least(0, 3, 50), Some(2));
+        assert_eq!(forest.path_edges_at_least(3, 4, 10), Some(3));
+        assert_eq!(forest.path_edges_at_least(3, 4, 100), Some(1));
+        forest.set_edge_weight(2, 1, W::of(20)).unwrap();
+        assert_eq!(forest.path_edges_at_least(4, 3, 50), Some(1));
+        assert_eq!(forest.path_edges_at_least(0, 0, 10), Some(0));
+        forest.cut(4);
+        assert_eq!(forest.path_edges_at_least(0, 4, 10), None);
+    }
+
+    #[test]
+    fn path_gcd_lcm() {
+        let mut forest = Forest::<Gcd<u64>>::new();
+        for x in [12, 18, 0, 8, 30] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2 - 3, 1 - 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 2);
+        forest.link(4, 1);
+        assert_eq!(forest.path_gcd(0, 1), Some(6));
+        assert_eq!(forest.path_gcd(2, 2), Some(0));
+        assert_eq!(forest.path_gcd(3, 2), Some(8));
+        assert_eq!(forest.path_gcd(3, 4), Some(2));
+
+        let mut forest = Forest::<Lcm<u64>>::new();
+        for x in [4, 6, 10, 1] {
+            forest.add_node(x);
+        }
+        forest.link(1, 0);
+        forest.link(2, 1);
+        assert_eq!(forest.path_lcm(0, 2), Some(60));
+        assert_eq!(forest.path_lcm(3, 3), Some(1));
+        assert_eq!(forest.path_lcm(0, 3), None);
+    }
+
+    #[test]
+    fn modular_aggregates() {
+        let mut forest = Forest::<AffineComposite<P>>::new();
+        for f in [(2, 1), (3, 4), (5, 6), (1, 998244352)] {
+            forest.add_node(f);
+        }
+        // 0 - 1 - 2, 1 - 3
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        let eval = |(a, b): (u64, u64), x: u64| (a * x + b) % 998244353;
+        // x ↦ 5(3(2x + 1) + 4) + 6 = 30x + 41
+        assert_eq!(forest.path_query(0, 2), Some((30, 41)));
+        // x ↦ 2(3(5x + 6) + 4) + 1 = 30x + 45
+        assert_eq!(forest.path_query(2, 0), Some((30, 45)));
+        assert_eq!(eval(forest.path_query(3, 0).unwrap(), 10), (3 * 9 + 4) * 2 + 1);
+        forest.evert(3);
+        assert_eq!(forest.path_query(0, 2), Some((30, 41)));
+
+        let mut forest = Forest::<ModProd<P>>::new();
+        for x in [998244352, 2, 998244352] {
+            forest.add_node(x);
+        }
+        forest.link(1, 0);
+        forest.link(2, 1);
+        assert_eq!(forest.path_query(0, 2), Some(2));
+
+        let mut forest = Forest::<ModSum<P>, (), Affine<P>>::new();
+        for x in [1, 2, 3, 4] {
+            forest.add_node(x);
+        }
+        for v in 1 .. 4 {
+            forest.link(v, v - 1);
+        }
+        assert!(forest.path_apply(1, 3, (2, 998244352)));
+        assert!(forest.path_apply(0, 2, (3, 1)));
+        // [1, 2, 3, 4] -> [1, 3, 5, 7] -> [4, 10, 16, 7]
+        assert_eq!(forest.path_query(0, 3), Some(37));
+        assert_eq!(forest.path_query(3, 2), Some(23));
+        assert_eq!(*forest.value(1), 10);
+    }
+
+    #[test]
+    fn path_count() {
+        let mut forest = Forest::<Count>::new();
+        for _ in 0 .. 6 {
+            forest.add_node(0);
+        }
+        for v in 1 .. 6 {
+            forest.link(v, (v - 1) / 2);
+        }
+        forest.set_flag(1, true);
+        forest.set_flag(4, true);
+        forest.set_flag(5, true);
+        assert_eq!(forest.path_count(4, 5), Some(3));
+        assert_eq!(forest.path_count(3, 2), Some(1));
+        assert_eq!(forest.path_count(0, 0), Some(0));
+        forest.set_flag(1, false);
+        assert_eq!(forest.path_count(4, 5), Some(2));
+
+        let mut forest = Forest::<(Count, Sum<i64>)>::new();
+        for x in [5, 7, 9] {
+            forest.add_node(((x > 6) as usize, x));
+        }
+        forest.link(1, 0);
+        forest.link(2, 1);
+        assert_eq!(forest.path_query(0, 2), Some((2, 21)));
+    }
+
+    #[test]
+    fn palindrome_paths() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 30;
+        let mut forest = Forest::<RollingHash<P, 10007>>::new();
+        let mut naive = Naive { parent: vec![None; n] };
+        let mut values = vec![];
+        for v in 0 .. n {
+            let x = rand(2) as u64;
+            values.push(x);
+            forest.add_node(RollingHash::<P, 10007>::of(x));
+            if v > 0 {
+                let p = rand(v);
+                forest.link(v, p);
+                naive.parent[v] = Some(p);
+            }
+        }
+        let mut palindromes = 0;
+        for _ in 0 .. 2000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    values[u] = rand(2) as u64;
+                    forest.set_hash_value(u, values[u]);
+                }
+                1 => {
+                    forest.evert(u);
+                    naive.evert(u);
+                }
+                _ => {
+                    let path: Vec<u64> = naive.path(u, v).unwrap().into_iter().map(|w| values[w]).collect();
+                    let expected = path.iter().eq(path.iter().rev());
+                    palindromes += expected as usize;
+                    assert_eq!(forest.is_palindrome_path(u, v), Some(expected));
+                    let hash = path.iter().fold(0, |h, &x| (h * 10007 + x) % 998244353);
+                    assert_eq!(forest.path_hash(u, v), Some(hash));
+                }
+            }
+        }
+        assert!(palindromes > 0);
+    }
+
+    #[test]
+    fn sample_path_follows_values() {
+        let mut rng = xorshift64(88172645463325252);
+        let mut forest = Forest::<Sum<u64>>::new();
+        for x in [1, 0, 3, 6, 5] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2 - 3, 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 2);
+        let mut counts = [0; 4];
+        for _ in 0 .. 10000 {
+            counts[forest.sample_path(3, 0, &mut rng).unwrap()] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        for (v, x) in [(0, 1), (2, 3), (3, 6)] {
+            assert!((counts[v] as i64 - 1000 * x).abs() < 300, "{:?}", counts);
+        }
+        assert_eq!(forest.sample_path(1, 1, &mut rng), None);
+        assert_eq!(forest.sample_path(0, 4, &mut rng), None);
+        assert_eq!(forest.sample_path(1, 2, &mut rng), Some(2));
+    }
+
+    #[test]
+    fn path_count_distinct() {
+        let n = 1200;
+        let mut forest = Forest::<HyperLogLog<8>>::new();
+        for v in 0 .. n {
+            forest.add_node(HyperLogLog::<8>::of(v as u64 % 400));
+        }
+        for v in 1 .. n {
+            forest.link(v, v - 1);
+        }
+        for (u, v, distinct) in [(0, n - 1, 400.0), (0, 99, 100.0), (500, 519, 20.0), (7, 7, 1.0)] {
+            let estimate = forest.path_count_distinct_approx(u, v).unwrap();
+            assert!((estimate - distinct).abs() <= distinct * 0.15 + 1.0, "{} {}", estimate, distinct);
+        }
+        assert_eq!(HyperLogLog::<8>::estimate(&[]), 0.0);
+        let mut forest = Forest::<Max<i64>>::new();
+        for x in [3, 1, 3, 2, 1, 5] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2 - 3 - 4, 5
+        for v in 1 .. 5 {
+            forest.link(v, v - 1);
+        }
+        assert_eq!(forest.path_count_distinct(0, 4), Some(3));
+        assert_eq!(forest.path_count_distinct(2, 4), Some(3));
+        assert_eq!(forest.path_count_distinct(0, 2), Some(2));
+        assert_eq!(forest.path_count_distinct(0, 5), None);
+    }
+
+    #[test]
+    fn wide_products() {
+        let mut rand = xorshift(88172645463325252);
+        // 15^30 は i64 にも u64 にも収まらない
+        let n = 30;
+        let mut forest = Forest::<Prod<i128>>::new();
+        let mut values = vec![];
+        let mut naive = Naive { parent: vec![None; n] };
+        for v in 0 .. n {
+            values.push(rand(15) as i128 + 1);
+            forest.add_node(values[v]);
+            if v > 0 {
+                naive.parent[v] = Some(v - 1);
+                forest.link(v, v - 1);
+            }
+        }
+        for _ in 0 .. 200 {
+            let (u, v) = (rand(n), rand(n));
+            let expected = naive.path(u, v).map(|path| path.iter().map(|&w| values[w]).product::<i128>());
+            assert_eq!(forest.path_product(u, v), expected);
+        }
+        assert!(forest.path_product(0, n - 1).unwrap() > i64::MAX as i128);
+    }
+
+    #[test]
+    fn compensated_float_sums() {
+        // 素朴に足すと 1.0 が二つとも消える
+        let mut forest = Forest::<CompensatedSum, (), CompensatedAdd>::new();
+        for x in [1.0, 1e100, 1.0, -1e100] {
+            forest.add_node(CompensatedSum::of(x));
+        }
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 2);
+        assert_eq!(1.0 + 1e100 + 1.0 - 1e100, 0.0);
+        assert_eq!(forest.path_float_sum(0, 3), Some(2.0));
+        assert_eq!(forest.path_float_sum(3, 0), Some(2.0));
+
+        let mut rand = xorshift(88172645463325252);
+        // 値は整数にして i128 で正確な和と比べる（大きな値と小さな値を混ぜ、打ち消し合わせる）
+        let random_value = |rand: &mut dyn FnMut(usize) -> usize| {
+            let x = rand(1000) as i128 - 500;
+            if rand(2) == 0 { x << 50 } else { x }
+        };
+        let n = 30;
+        let mut forest = Forest::<CompensatedSum, (), CompensatedAdd>::new();
+        let mut values = vec![];
+        let mut naive = Naive { parent: vec![None; n] };
+        for v in 0 .. n {
+            values.push(random_value(&mut rand));
+            forest.add_node(CompensatedSum::of(values[v] as f64));
+        }
+        for _ in 0 .. 3000 {
+            let (x, y) = (random_value(&mut rand), random_value(&mut rand));
+            let (u, v) = (rand(n), rand(n));
+            match rand(5) {
+                0 if naive.root(u) != naive.root(v) => {
+                    naive.evert(u);
+                    forest.evert(u);
+                    naive.parent[u] = Some(v);
+                    forest.link(u, v);
+                }
+                1 if naive.parent[u].is_some() => {
+                    naive.parent[u] = None;
+                    forest.cut(u);
+                }
+                2 => {
+                    values[u] = x;
+                    forest.set_float(u, x as f64);
+                }
+                3 => {
+                    if let Some(path) = naive.path(u, v) {
+                        for w in path {
+                            values[w] += y;
+                        }
+                    }
+                    assert_eq!(forest.path_add_float(u, v, y as f64), naive.root(u) == naive.root(v));
+                }
+                _ => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+            }
+            let (u, v) = (rand(n), rand(n));
+            let expected = naive.path(u, v).map(|path| path.iter().map(|&w| values[w]).sum::<i128>() as f64);
+            match (forest.path_float_sum(u, v), expected) {
+                (Some(sum), Some(expected)) => assert!((sum - expected).abs() <= expected.abs() * 1e-15, "{} {}", sum, expected),
+                (sum, expected) => assert_eq!(sum, expected),
+            }
+        }
+    }
+}
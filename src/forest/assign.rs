@@ -0,0 +1,56 @@
+use super::{Forest, ForestNodeOf};
+use crate::action::{Action, EdgeAssign};
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+
+impl<M: Monoid, E: Monoid, N: NodeStore<ForestNodeOf<M, E, EdgeAssign, ()>>> Forest<M, E, EdgeAssign, (), N>
+where
+    EdgeAssign: Action<M, E, Map = i64>,
+{
+    /// u から v へのパス上の辺の重みをすべて w にする
+    pub fn path_assign_edges(&mut self, u: usize, v: usize, w: i64) -> bool {
+        self.path_apply(u, v, w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::{Max, Sum};
+
+    #[test]
+    fn path_assign_edges() {
+        let mut forest = Forest::<(), Sum<i64>, EdgeAssign>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 -1- 1 -2- 2 -3- 3, 1 -4- 4
+        forest.link_weighted(1, 0, 1);
+        forest.link_weighted(2, 1, 2);
+        forest.link_weighted(3, 2, 3);
+        forest.link_weighted(4, 1, 4);
+        assert!(forest.path_assign_edges(3, 4, 10));
+        assert_eq!(forest.path_query_edges(0, 3), Some(21));
+        assert_eq!(forest.edge_weight(0, 1), Some(&1));
+        assert_eq!(forest.edge_weight(1, 4), Some(&10));
+        assert!(forest.path_assign_edges(0, 2, 0));
+        assert_eq!(forest.path_query_edges(4, 3), Some(20));
+        forest.set_edge_weight(1, 2, 5).unwrap();
+        assert_eq!(forest.path_query_edges(0, 3), Some(15));
+        assert!(forest.path_assign_edges(0, 0, 7));
+        assert_eq!(forest.path_query_edges(0, 0), Some(0));
+        forest.cut(3);
+        assert!(!forest.path_assign_edges(0, 3, 1));
+
+        let mut forest = Forest::<(), Max<i64>, EdgeAssign>::new();
+        for _ in 0 .. 3 {
+            forest.add_node(());
+        }
+        forest.link_weighted(1, 0, 8);
+        forest.link_weighted(2, 1, 1);
+        assert!(forest.path_assign_edges(1, 2, 3));
+        assert_eq!(forest.path_query_edges(0, 2), Some(8));
+        assert!(forest.path_assign_edges(0, 2, 2));
+        assert_eq!(forest.path_query_edges(0, 2), Some(2));
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use super::{Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::splay::SplayTree;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// set_value をまとめて行い始める
+    /// end_batch まで（あるいは次に splay するまで）集約を直さずに値を溜めておく
+    /// 溜めている間、&self で読む値（`self[v]` など）は古いまま
+    pub fn begin_batch(&mut self) {
+        self.batching = true;
+    }
+
+    /// 溜めた値を書き込み、書き換えた節点から上の集約を下から順に一度ずつ直す
+    pub fn end_batch(&mut self) {
+        self.batching = false;
+        self.flush_batch();
+    }
+
+    pub fn in_batch(&self) -> bool {
+        self.batching
+    }
+
+    /// 溜めた値を書き込む
+    pub(super) fn flush_batch(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        if pending.is_empty() {
+            return;
+        }
+        // 書き換える節点から親をたどった節点を、上にあるものが先になるように並べ、
+        // 上から順に遅延した作用と反転（軽い子に溜めた部分木への写像も）を伝播しておく
+        let mut visited = HashSet::new();
+        let mut order = vec![];
+        for &(x, _) in &pending {
+            let start = order.len();
+            let mut cur = Some(x);
+            while let Some(y) = cur {
+                if !visited.insert(y) {
+                    break;
+                }
+                order.push(y);
+                cur = self.nodes[y].parent;
+            }
+            order[start ..].reverse();
+            for &y in &order[start ..] {
+                self.settle_node(y);
+            }
+        }
+        for (x, value) in pending {
+            self.nodes[x].value = value;
+        }
+        // 軽い子は親の virt に集約が入っているので、入れ替える
+        for &y in order.iter().rev() {
+            let light = self.dir(y).is_none().then_some(self.nodes[y].parent).flatten();
+            let old = light.map(|_| self.nodes[y].total.clone());
+            self.update(y);
+            if let (Some(p), Some(old)) = (light, old) {
+                let total = self.nodes[y].total.clone();
+                S::remove(&mut self.nodes[p].virt, &old);
+                S::add(&mut self.nodes[p].virt, &total);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Affine;
+    use crate::monoid::{ConstModulus, ModSum, Sum};
+    use crate::subtree::SubtreeSum;
+    use crate::test_util::xorshift;
+
+    type P = ConstModulus<998244353>;
+
+    #[test]
+    fn batch_with_actions() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 40;
+        let mut batched = Forest::<ModSum<P>, (), Affine<P>>::new();
+        let mut immediate = Forest::<ModSum<P>, (), Affine<P>>::new();
+        for v in 0 .. n {
+            batched.add_node(v as u64);
+            immediate.add_node(v as u64);
+            if v > 0 {
+                let p = rand(v);
+                batched.link(v, p);
+                immediate.link(v, p);
+            }
+        }
+        for _ in 0 .. 300 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    let f = (rand(5) as u64 + 1, rand(100) as u64);
+                    batched.path_apply(u, v, f);
+                    immediate.path_apply(u, v, f);
+                }
+                1 => {
+                    batched.evert(u);
+                    immediate.evert(u);
+                }
+                2 => {
+                    batched.begin_batch();
+                    for _ in 0 .. rand(20) {
+                        let (w, x) = (rand(n), rand(1000) as u64);
+                        batched.set_value(w, x);
+                        immediate.set_value(w, x);
+                    }
+                    assert!(batched.in_batch());
+                    batched.end_batch();
+                }
+                _ => assert_eq!(batched.path_query(u, v), immediate.path_query(u, v)),
+            }
+        }
+        // 溜めている途中に問い合わせても、そこまでの値が書き込まれる
+        batched.begin_batch();
+        batched.set_value(0, 5);
+        immediate.set_value(0, 5);
+        assert_eq!(batched.path_query(0, n - 1), immediate.path_query(0, n - 1));
+        batched.end_batch();
+        assert!(!batched.in_batch());
+    }
+
+    #[test]
+    fn batch_with_subtree_sums() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 40;
+        let mut batched = Forest::<Sum<i64>, (), (), SubtreeSum<i64>>::new();
+        let mut immediate = Forest::<Sum<i64>, (), (), SubtreeSum<i64>>::new();
+        for v in 0 .. n {
+            batched.add_node(v as i64);
+            immediate.add_node(v as i64);
+            if v > 0 {
+                let p = rand(v);
+                batched.link(v, p);
+                immediate.link(v, p);
+            }
+        }
+        for _ in 0 .. 300 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(3) {
+                0 => {
+                    batched.evert(u);
+                    immediate.evert(u);
+                }
+                1 => {
+                    batched.begin_batch();
+                    for _ in 0 .. rand(20) {
+                        let (w, x) = (rand(n), rand(1000) as i64);
+                        batched.set_value(w, x);
+                        immediate.set_value(w, x);
+                    }
+                    batched.end_batch();
+                }
+                _ => {
+                    assert_eq!(batched.subtree_sum(u), immediate.subtree_sum(u));
+                    assert_eq!(batched.path_query(u, v), immediate.path_query(u, v));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use super::{Forest, ForestNodeOf};
+use crate::action::Clamp;
+use crate::monoid::{ChminChmax, Monoid};
+use crate::store::NodeStore;
+
+impl<E: Monoid, N: NodeStore<ForestNodeOf<ChminChmax, E, Clamp, ()>>> Forest<ChminChmax, E, Clamp, (), N> {
+    /// u から v へのパス上の頂点の値を x 以下にする
+    pub fn path_chmin(&mut self, u: usize, v: usize, x: i64) -> bool {
+        self.path_apply(u, v, Clamp::chmin(x))
+    }
+
+    /// u から v へのパス上の頂点の値を x 以上にする
+    pub fn path_chmax(&mut self, u: usize, v: usize, x: i64) -> bool {
+        self.path_apply(u, v, Clamp::chmax(x))
+    }
+
+    /// u から v へのパス上の頂点の値の和
+    pub fn path_sum(&mut self, u: usize, v: usize) -> Option<i64> {
+        self.path_query(u, v).map(|s| s.sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Beats;
+    use crate::test_util::xorshift64;
+
+    #[test]
+    fn path_chmin_chmax_against_naive() {
+        let n = 40;
+        let mut rand = xorshift64(0x2545f4914f6cdd1d);
+        let mut next = move |m: u64| rand() % m;
+        let mut forest = Forest::<ChminChmax, (), Clamp>::new();
+        let mut values = vec![];
+        let mut parent = vec![None; n];
+        for _ in 0 .. n {
+            let x = next(100) as i64;
+            forest.add_node(Beats::new(x));
+            values.push(x);
+        }
+        for (v, slot) in parent.iter_mut().enumerate().skip(1) {
+            let p = next(v as u64) as usize;
+            forest.link(v, p);
+            *slot = Some(p);
+        }
+        // 素朴に u から v へのパスを求める
+        let path = |u: usize, v: usize| {
+            let up = |mut x: usize| {
+                let mut path = vec![x];
+                while let Some(p) = parent[x] {
+                    path.push(p);
+                    x = p;
+                }
+                path
+            };
+            let (mut a, mut b) = (up(u), up(v));
+            while a.len() > 1 && b.len() > 1 && a[a.len() - 2] == b[b.len() - 2] {
+                a.pop();
+                b.pop();
+            }
+            b.pop();
+            a.extend(b.into_iter().rev());
+            a
+        };
+        for _ in 0 .. 500 {
+            let (u, v) = (next(n as u64) as usize, next(n as u64) as usize);
+            let x = next(100) as i64;
+            match next(3) {
+                0 => {
+                    assert!(forest.path_chmin(u, v, x));
+                    for w in path(u, v) {
+                        values[w] = values[w].min(x);
+                    }
+                }
+                1 => {
+                    assert!(forest.path_chmax(u, v, x));
+                    for w in path(u, v) {
+                        values[w] = values[w].max(x);
+                    }
+                }
+                _ => {
+                    forest.evert(u);
+                }
+            }
+            let (a, b) = (next(n as u64) as usize, next(n as u64) as usize);
+            let expected: i64 = path(a, b).into_iter().map(|w| values[w]).sum();
+            assert_eq!(forest.path_sum(a, b), Some(expected));
+            let w = next(n as u64) as usize;
+            assert_eq!(forest.value(w).sum, values[w]);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::marker::PhantomData;
+
+use super::{Forest, ForestNodeOf, OpStats};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+/// 設定を決めてから森を作る
+/// 集約の種類は型引数で選ぶ（`Forest::<Sum<i64>>::builder()` など）
+/// 添字の幅は選べない（節点の親と子を usize で持つので、幅を変えるには節点の型から変える必要がある）
+pub struct ForestBuilder<M: Monoid = (), E: Monoid = (), A: Action<M, E> = (), S: Subtree<M, E> = (), N: NodeStore<ForestNodeOf<M, E, A, S>> = Vec<ForestNodeOf<M, E, A, S>>> {
+    capacity: usize,
+    ordered: bool,
+    sorted: bool,
+    statistics: bool,
+    marker: PhantomData<(M, E, A, S, N)>,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Default for ForestBuilder<M, E, A, S, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> ForestBuilder<M, E, A, S, N> {
+    pub fn new() -> Self {
+        Self { capacity: 0, ordered: false, sorted: false, statistics: false, marker: PhantomData }
+    }
+
+    /// 頂点 n 個分の領域をあらかじめ確保する
+    pub fn capacity(mut self, n: usize) -> Self {
+        self.capacity = n;
+        self
+    }
+
+    /// 子をつないだ順に保つか（Forest::new_ordered を参照）
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// 子を番号の順に保つか（Forest::new_sorted を参照）
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    /// 操作の回数を数えるか（Forest::op_stats で読む）
+    pub fn statistics(mut self, statistics: bool) -> Self {
+        self.statistics = statistics;
+        self
+    }
+
+    pub fn build(self) -> Forest<M, E, A, S, N>
+    where
+        N: Default,
+    {
+        let mut forest: Forest<M, E, A, S, N> = Forest { ordered: self.ordered, sorted: self.sorted, stats: self.statistics.then(OpStats::default), ..Forest::default() };
+        forest.nodes.reserve(2 * self.capacity);
+        forest.adjacency.reserve(self.capacity);
+        forest.edges.reserve(self.capacity);
+        forest
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    pub fn builder() -> ForestBuilder<M, E, A, S, N> {
+        ForestBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    #[test]
+    fn build() {
+        let mut forest = Forest::<Sum<i64>>::builder().capacity(100).ordered(true).build();
+        assert!(forest.is_empty());
+        assert!(forest.nodes.capacity() >= 200);
+        for x in 0 .. 4 {
+            forest.add_node(x);
+        }
+        for v in 1 .. 4 {
+            forest.link(v, 0);
+        }
+        forest.cut(1);
+        assert_eq!(forest.children(0), vec![2, 3]);
+        assert_eq!(forest.path_query(2, 3), Some(5));
+        assert_eq!(forest.op_stats(), None);
+        let forest: Forest = ForestBuilder::default().build();
+        assert!(!forest.ordered);
+    }
+
+    #[test]
+    fn statistics() {
+        let mut forest = Forest::<Sum<i64>>::builder().statistics(true).build();
+        assert_eq!(forest.op_stats(), Some(OpStats::default()));
+        for x in 0 .. 8 {
+            forest.add_node(x);
+        }
+        for v in 1 .. 8 {
+            forest.link(v, v - 1);
+        }
+        let before = forest.op_stats().unwrap();
+        assert!(before.exposes > 0 && before.splays > 0);
+        assert_eq!(forest.path_query(0, 7), Some(28));
+        let after = forest.op_stats().unwrap();
+        assert!(after.exposes > before.exposes);
+        assert!(after.splays > before.splays && after.rotations >= before.rotations);
+        forest.set_statistics(false);
+        forest.path_query(3, 5);
+        assert_eq!(forest.op_stats(), None);
+        forest.set_statistics(true);
+        assert_eq!(forest.op_stats(), Some(OpStats::default()));
+    }
+}
@@ -0,0 +1,85 @@
+use super::{Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 森の重心分解を行い、重心木での各頂点の親を返す（各木の最初の重心は None）
+    pub fn centroid_decomposition(&self) -> Vec<Option<usize>> {
+        let n = self.len();
+        let mut removed = vec![false; n];
+        let mut parent = vec![None; n];
+        let mut size = vec![0; n];
+        let mut stack: Vec<(usize, Option<usize>)> = vec![];
+        for v in 0 .. n {
+            if removed[v] {
+                continue;
+            }
+            stack.push((v, None));
+            while let Some((start, centroid_parent)) = stack.pop() {
+                // 取り除いていない頂点だけをたどって部分木の大きさを求める
+                let mut order = vec![(start, start)];
+                let mut i = 0;
+                while i < order.len() {
+                    let (x, from) = order[i];
+                    for w in self.neighbors(x) {
+                        if w != from && !removed[w] {
+                            order.push((w, x));
+                        }
+                    }
+                    i += 1;
+                }
+                for &(x, from) in order.iter().rev() {
+                    size[x] = 1 + self.neighbors(x).filter(|&w| w != from && !removed[w]).map(|w| size[w]).sum::<usize>();
+                }
+                let total = order.len();
+                let &(centroid, _) = order
+                    .iter()
+                    .find(|&&(x, from)| {
+                        let largest = self.neighbors(x).filter(|&w| w != from && !removed[w]).map(|w| size[w]).max().unwrap_or(0);
+                        largest.max(total - size[x]) <= total / 2
+                    })
+                    .unwrap();
+                removed[centroid] = true;
+                parent[centroid] = centroid_parent;
+                for w in self.neighbors(centroid) {
+                    if !removed[w] {
+                        stack.push((w, Some(centroid)));
+                    }
+                }
+            }
+        }
+        parent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn centroid_tree_of_path_and_star() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 10 {
+            forest.add_node(());
+        }
+        // 0 - 1 - ... - 6 の道と、7 を中心とする星 7 - 8, 7 - 9
+        for v in 1 .. 7 {
+            forest.link(v, v - 1);
+        }
+        forest.link(8, 7);
+        forest.link(9, 7);
+        let parent = forest.centroid_decomposition();
+        assert_eq!(parent[3], None);
+        assert_eq!(parent[1], Some(3));
+        assert_eq!(parent[5], Some(3));
+        assert_eq!(parent[0], Some(1));
+        assert_eq!(parent[2], Some(1));
+        assert_eq!(parent[4], Some(5));
+        assert_eq!(parent[6], Some(5));
+        assert_eq!(parent[7], None);
+        assert_eq!(parent[8], Some(7));
+        assert_eq!(parent[9], Some(7));
+    }
+}
@@ -0,0 +1,102 @@
+use super::{ComponentId, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+/// 木ごとに一つの値を付ける
+/// 値は木の根に置かれ、evert では新しい根に移り、cut では切り離した木にも複製される
+/// link では親の側の木の値が残る（親の側になければ子の側の値を引き継ぐ）
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// v を含む木に付けた値
+    pub fn component_data(&mut self, v: usize) -> Option<u64> {
+        let root = self.root(v);
+        self.component_data.get(&root).copied()
+    }
+
+    /// v を含む木に値 data を付け、前に付けていた値を返す
+    pub fn set_component_data(&mut self, v: usize, data: u64) -> Option<u64> {
+        let root = self.root(v);
+        self.component_data.insert(root, data)
+    }
+
+    /// v を含む木に付けた値を外して返す
+    pub fn take_component_data(&mut self, v: usize) -> Option<u64> {
+        let root = self.root(v);
+        self.component_data.remove(&root)
+    }
+
+    /// v を含む木の番号
+    /// 同じ木の頂点には同じ番号が返り、evert では変わらない
+    /// link・cut の後は、関わった木に一度も使っていない番号が振られる
+    pub fn component_id(&mut self, v: usize) -> ComponentId {
+        let root = self.root(v);
+        let next = &mut self.next_component_id;
+        *self.component_ids.entry(root).or_insert_with(|| {
+            *next += 1;
+            ComponentId(*next - 1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_root() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 6 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2, 3 - 4, 5
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(4, 3);
+        assert_eq!(forest.set_component_data(2, 7), None);
+        assert_eq!(forest.set_component_data(4, 8), None);
+        forest.evert(2);
+        assert_eq!(forest.component_data(0), Some(7));
+        assert_eq!(forest.path_query(0, 2), Some(()));
+        assert_eq!(forest.component_data(1), Some(7));
+        forest.cut(0);
+        assert_eq!(forest.component_data(0), Some(7));
+        assert_eq!(forest.component_data(2), Some(7));
+        forest.link(3, 2);
+        assert_eq!(forest.component_data(4), Some(7));
+        forest.link(5, 4);
+        assert_eq!(forest.component_data(5), Some(7));
+        assert_eq!(forest.take_component_data(1), Some(7));
+        assert_eq!(forest.component_data(3), None);
+        assert_eq!(forest.component_data(0), Some(7));
+        forest.link(0, 5);
+        assert_eq!(forest.component_data(3), Some(7));
+    }
+
+    #[test]
+    fn component_id() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2, 3, 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        let a = forest.component_id(2);
+        let b = forest.component_id(3);
+        assert_ne!(a, b);
+        assert_eq!(forest.component_id(0), a);
+        forest.evert(2);
+        forest.evert(1);
+        assert_eq!(forest.component_id(0), a);
+        forest.cut(2);
+        let (c, d) = (forest.component_id(0), forest.component_id(2));
+        assert!(c != d && c != a && d != a);
+        forest.link(3, 2);
+        let e = forest.component_id(3);
+        assert!(e != b && e != d);
+        assert_eq!(forest.component_id(2), e);
+        assert_eq!(forest.component_id(1), c);
+        assert_ne!(forest.component_id(4), e);
+    }
+}
@@ -0,0 +1,66 @@
+use std::io::BufRead;
+use std::str::FromStr;
+
+use super::{Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::CsvError;
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>> + Default> Forest<M, E, A, S, N>
+where
+    E::Value: FromStr,
+{
+    /// `u,v` か `u,v,w` の行を connect_weighted で順に加えて森を作る
+    /// 頂点の値は単位元、w を省いた辺の重みは単位元で、空行は読み飛ばす
+    pub fn from_csv(reader: impl BufRead) -> Result<Self, CsvError> {
+        let mut forest = Self::default();
+        for (i, line) in reader.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.map_err(|e| CsvError::Io { line: line_no, message: e.to_string() })?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parse_error = |message: String| CsvError::Parse { line: line_no, message };
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if !(2 ..= 3).contains(&fields.len()) {
+                return Err(parse_error(format!("expected 2 or 3 fields, found {}", fields.len())));
+            }
+            let vertex = |field: &str| field.parse::<usize>().map_err(|_| parse_error(format!("invalid vertex {:?}", field)));
+            let (u, v) = (vertex(fields[0])?, vertex(fields[1])?);
+            let weight = match fields.get(2) {
+                Some(field) => field.parse().map_err(|_| parse_error(format!("invalid weight {:?}", field)))?,
+                None => E::identity(),
+            };
+            forest.grow(u.max(v) + 1);
+            forest.connect_weighted(u, v, weight).map_err(|error| CsvError::Edge { line: line_no, error })?;
+        }
+        Ok(forest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+    use crate::LctError;
+
+    #[test]
+    fn from_csv() {
+        let csv = "0,1,5\n1, 2\n\n3,1,2\n";
+        let mut forest = Forest::<(), Sum<i64>>::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(forest.len(), 4);
+        assert_eq!(forest.path_query_edges(0, 3), Some(7));
+        assert_eq!(forest.path_query_edges(2, 3), Some(2));
+        let error = Forest::<(), Sum<i64>>::from_csv("0,1\n1,2\n2,0,4\n".as_bytes()).err();
+        assert_eq!(error, Some(CsvError::Edge { line: 3, error: LctError::Connected(2, 0) }));
+        let error = Forest::<(), Sum<i64>>::from_csv("0,1\n1,x\n".as_bytes()).err();
+        assert_eq!(error, Some(CsvError::Parse { line: 2, message: "invalid vertex \"x\"".into() }));
+        let error = Forest::<(), Sum<i64>>::from_csv("0,1,1,1\n".as_bytes()).err();
+        assert!(matches!(error, Some(CsvError::Parse { line: 1, .. })));
+        let error = Forest::<(), Sum<i64>>::from_csv("0,1,1.5\n".as_bytes()).err();
+        assert!(matches!(error, Some(CsvError::Parse { line: 1, .. })));
+    }
+}
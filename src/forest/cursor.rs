@@ -0,0 +1,112 @@
+use super::{vertex_node, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+/// 根付き木の頂点を一つずつたどるカーソル
+/// 下りてきた道を覚えておき、来た道を戻るときや子を数えるときには splay しない
+pub struct Cursor<'a, M: Monoid, E: Monoid, A: Action<M, E> = (), S: Subtree<M, E> = (), N: NodeStore<ForestNodeOf<M, E, A, S>> = Vec<ForestNodeOf<M, E, A, S>>> {
+    forest: &'a mut Forest<M, E, A, S, N>,
+    vertex: usize,
+    /// 下りてきた道の頂点（最後が今の頂点の親）
+    ancestors: Vec<usize>,
+    /// 求めてあれば今の頂点の親
+    parent: Option<Option<usize>>,
+    /// 求めてあれば今の頂点の深さ
+    depth: Option<usize>,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 頂点 v から始めるカーソル（たどる間は森を書き換えられない）
+    pub fn cursor(&mut self, v: usize) -> Cursor<'_, M, E, A, S, N> {
+        Cursor { forest: self, vertex: v, ancestors: vec![], parent: None, depth: None }
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Cursor<'_, M, E, A, S, N> {
+    /// 今の頂点
+    pub fn vertex(&self) -> usize {
+        self.vertex
+    }
+
+    pub fn value(&mut self) -> &M::Value {
+        self.forest.value(self.vertex)
+    }
+
+    /// 今の頂点の根からの辺の数（初めて求めるときだけ expose する）
+    pub fn depth(&mut self) -> usize {
+        *self.depth.get_or_insert_with(|| {
+            let x = vertex_node(self.vertex);
+            self.forest.expose(x);
+            self.forest.nodes[x].len - 1
+        })
+    }
+
+    pub fn parent(&mut self) -> Option<usize> {
+        *self.parent.get_or_insert_with(|| self.forest.parent(self.vertex))
+    }
+
+    /// 今の頂点の子（隣接リストの順）
+    pub fn children(&mut self) -> Vec<usize> {
+        let parent = self.parent();
+        self.forest.neighbors(self.vertex).filter(|&w| Some(w) != parent).collect()
+    }
+
+    /// 親に移る（根なら移らずに false を返す）
+    pub fn move_to_parent(&mut self) -> bool {
+        let Some(parent) = self.parent() else {
+            return false;
+        };
+        self.ancestors.pop();
+        self.vertex = parent;
+        self.parent = self.ancestors.last().map(|&p| Some(p));
+        self.depth = self.depth.map(|d| d - 1);
+        true
+    }
+
+    /// i 番目の子に移る（子が i 個以下なら移らずに false を返す）
+    pub fn move_to_child(&mut self, i: usize) -> bool {
+        let Some(&child) = self.children().get(i) else {
+            return false;
+        };
+        self.ancestors.push(self.vertex);
+        self.parent = Some(Some(self.vertex));
+        self.vertex = child;
+        self.depth = self.depth.map(|d| d + 1);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    #[test]
+    fn walk() {
+        let mut forest = Forest::<Sum<i64>>::new_ordered();
+        for x in [10, 11, 12, 13, 14] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 3, 1 - 4, 0 - 2
+        forest.link(1, 0);
+        forest.link(2, 0);
+        forest.link(3, 1);
+        forest.link(4, 1);
+        let mut cursor = forest.cursor(1);
+        assert_eq!(cursor.depth(), 1);
+        assert_eq!(cursor.children(), vec![3, 4]);
+        assert!(cursor.move_to_child(1));
+        assert_eq!((cursor.vertex(), *cursor.value(), cursor.depth()), (4, 14, 2));
+        assert!(!cursor.move_to_child(0));
+        assert!(cursor.move_to_parent());
+        assert!(cursor.move_to_parent());
+        assert_eq!((cursor.vertex(), cursor.depth()), (0, 0));
+        assert!(!cursor.move_to_parent());
+        assert!(cursor.move_to_child(1));
+        assert_eq!((cursor.vertex(), cursor.parent()), (2, Some(0)));
+        assert!(cursor.move_to_parent());
+        assert_eq!(cursor.children(), vec![1, 2]);
+    }
+}
@@ -0,0 +1,48 @@
+use super::{Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+/// Union-Find と同じ名前の操作
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// u と v の属する木を辺 (u, v) でつなぎ、つないだかを返す（すでに同じ木なら何もしない）
+    pub fn union(&mut self, u: usize, v: usize) -> bool {
+        self.connect(u, v).is_ok()
+    }
+
+    /// u の属する木の代表（木の根なので、evert や link で変わりうる）
+    pub fn find(&mut self, u: usize) -> usize {
+        self.root(u)
+    }
+
+    /// u と v が同じ木に属するか
+    pub fn same(&mut self, u: usize, v: usize) -> bool {
+        self.connected(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_find_then_cut() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        assert!(forest.union(0, 1));
+        assert!(forest.union(2, 1));
+        assert!(!forest.union(0, 2));
+        assert!(forest.same(0, 2));
+        assert!(!forest.same(0, 3));
+        assert_eq!(forest.find(2), forest.find(0));
+        assert!(forest.union(3, 4));
+        assert_ne!(forest.find(3), forest.find(0));
+        // Union-Find ではできない、併合の取り消し
+        assert_eq!(forest.toggle_edge(2, 1), Ok(crate::Toggle::Cut));
+        assert!(!forest.same(0, 2));
+        assert!(forest.same(0, 1));
+    }
+}
@@ -0,0 +1,357 @@
+use std::fmt::Write;
+
+use super::{edge_node, is_vertex, vertex_node, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::splay::SplayTree;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::Dir;
+
+/// GraphML の属性として書き出せる値
+pub trait GraphmlValue {
+    /// 属性の型（None なら属性を書かない）
+    const TYPE: Option<&'static str>;
+
+    fn text(&self) -> String;
+}
+
+impl GraphmlValue for () {
+    const TYPE: Option<&'static str> = None;
+
+    fn text(&self) -> String { String::new() }
+}
+
+macro_rules! impl_graphml_value {
+    ($($t:ty => $name:literal),*) => {
+        $(impl GraphmlValue for $t {
+            const TYPE: Option<&'static str> = Some($name);
+
+            fn text(&self) -> String { self.to_string() }
+        })*
+    };
+}
+
+impl_graphml_value!(
+    bool => "boolean", i8 => "int", i16 => "int", i32 => "int", u8 => "int", u16 => "int",
+    i64 => "long", u32 => "long", u64 => "long", isize => "long", usize => "long", f32 => "float", f64 => "double"
+);
+
+impl GraphmlValue for String {
+    const TYPE: Option<&'static str> = Some("string");
+
+    fn text(&self) -> String {
+        self.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+    }
+}
+
+impl<M: Monoid, E: Monoid, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, (), S>>> Forest<M, E, (), S, N>
+where
+    M::Value: GraphmlValue,
+    E::Value: GraphmlValue,
+{
+    /// 頂点の値と辺の重みを属性にして GraphML で書き出す
+    /// 辺はつないだときの子から親へ向ける
+    pub fn to_graphml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        if let Some(ty) = M::Value::TYPE {
+            writeln!(xml, "  <key id=\"value\" for=\"node\" attr.name=\"value\" attr.type=\"{}\"/>", ty).unwrap();
+        }
+        if let Some(ty) = E::Value::TYPE {
+            writeln!(xml, "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"{}\"/>", ty).unwrap();
+        }
+        xml.push_str("  <graph edgedefault=\"directed\">\n");
+        for v in 0 .. self.len() {
+            match M::Value::TYPE {
+                Some(_) => {
+                    let value = self.nodes[vertex_node(v)].value.text();
+                    writeln!(xml, "    <node id=\"n{}\"><data key=\"value\">{}</data></node>", v, value).unwrap()
+                }
+                None => writeln!(xml, "    <node id=\"n{}\"/>", v).unwrap(),
+            }
+        }
+        let mut edges: Vec<_> = self.edge_ids.iter().map(|(id, &e)| (id.0, e)).collect();
+        edges.sort_unstable();
+        for (id, e) in edges {
+            let [child, parent] = self.edges[e].ends;
+            write!(xml, "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\"", id, child, parent).unwrap();
+            match E::Value::TYPE {
+                Some(_) => {
+                    let weight = self.nodes[edge_node(e)].weight.text();
+                    writeln!(xml, "><data key=\"weight\">{}</data></edge>", weight).unwrap()
+                }
+                None => xml.push_str("/>\n"),
+            }
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+
+    /// 木を層に分けて SVG で描く（木の構造は変えない）
+    /// 根を上にして深さごとに一段ずつ下げ、葉を左から順に並べて親を子の真ん中に置く
+    /// 頂点には番号と値を、辺には重みを添える
+    pub fn to_svg(&self) -> String {
+        const GAP: f64 = 60.0;
+        const RADIUS: f64 = 16.0;
+        let n = self.len();
+        // 横の位置（葉の並びでの番号）と深さ
+        let mut x = vec![0.0; n];
+        let mut depth = vec![0; n];
+        let mut children = vec![vec![]; n];
+        let mut edges = vec![];
+        let mut visited = vec![false; n];
+        let mut slots = 0;
+        for v in 0 .. n {
+            if visited[v] {
+                continue;
+            }
+            let order = self.bfs(self.root_of(v));
+            for &(w, from) in &order {
+                visited[w] = true;
+                if let Some(e) = from {
+                    let p = self.opposite(e, w);
+                    depth[w] = depth[p] + 1;
+                    children[p].push(w);
+                    edges.push((p, w, e));
+                }
+            }
+            // 行きがけ順に葉を並べる
+            let mut stack = vec![order[0].0];
+            while let Some(w) = stack.pop() {
+                if children[w].is_empty() {
+                    x[w] = slots as f64;
+                    slots += 1;
+                }
+                stack.extend(children[w].iter().rev());
+            }
+            for &(w, _) in order.iter().rev() {
+                if let (Some(&first), Some(&last)) = (children[w].first(), children[w].last()) {
+                    x[w] = (x[first] + x[last]) / 2.0;
+                }
+            }
+        }
+        let levels = depth.iter().max().map_or(0, |d| d + 1);
+        let at = |v: usize| (GAP / 2.0 + x[v] * GAP, GAP / 2.0 + depth[v] as f64 * GAP);
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"12\" text-anchor=\"middle\">",
+            slots as f64 * GAP,
+            levels as f64 * GAP
+        )
+        .unwrap();
+        for &(p, c, e) in &edges {
+            let ((x1, y1), (x2, y2)) = (at(p), at(c));
+            writeln!(svg, "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\"/>", x1, y1, x2, y2).unwrap();
+            if E::Value::TYPE.is_some() {
+                let weight = self.nodes[edge_node(e)].weight.text();
+                writeln!(svg, "  <text x=\"{}\" y=\"{}\" fill=\"gray\">{}</text>", (x1 + x2) / 2.0 + 8.0, (y1 + y2) / 2.0, weight).unwrap();
+            }
+        }
+        for v in 0 .. n {
+            let (cx, cy) = at(v);
+            writeln!(svg, "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"white\" stroke=\"black\"/>", cx, cy, RADIUS).unwrap();
+            writeln!(svg, "  <text x=\"{}\" y=\"{}\">{}</text>", cx, cy + 4.0, v).unwrap();
+            if M::Value::TYPE.is_some() {
+                let value = self.nodes[vertex_node(v)].value.text();
+                writeln!(svg, "  <text x=\"{}\" y=\"{}\" fill=\"blue\">{}</text>", cx, cy + RADIUS + 12.0, value).unwrap();
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// v を含む木の根（木の構造は変えない）
+    fn root_of(&self, v: usize) -> usize {
+        // 根を含む優先パスの splay 木まで上り、その先頭の頂点を反転の偶奇を見ながら探す
+        let mut x = self.splay_root(vertex_node(v));
+        while let Some(parent) = self.nodes[x].parent {
+            x = self.splay_root(parent);
+        }
+        let mut flip = false;
+        loop {
+            let dir = if flip { Dir::Right } else { Dir::Left };
+            match self.child(x, dir) {
+                Some(child) => {
+                    flip ^= self.nodes[x].rev;
+                    x = child;
+                }
+                None => return x >> 1,
+            }
+        }
+    }
+
+    /// v を含む木を根から罫線で描く（頂点ごとに `番号: format(値)` を一行に書く）
+    pub fn to_ascii(&mut self, v: usize, format: impl Fn(&M::Value) -> String) -> String {
+        let root = self.root(v);
+        let mut text = String::new();
+        // (頂点, たどってきた辺, 上の行から続く罫線, 兄弟の最後か)
+        let mut stack = vec![(root, None, String::new(), true)];
+        while let Some((x, from, prefix, last)) = stack.pop() {
+            let branch = match from {
+                None => "",
+                Some(_) if last => "└── ",
+                Some(_) => "├── ",
+            };
+            writeln!(text, "{}{}{}: {}", prefix, branch, x, format(self.value(x))).unwrap();
+            let prefix = match from {
+                None => prefix,
+                Some(_) if last => prefix + "    ",
+                Some(_) => prefix + "│   ",
+            };
+            let children: Vec<_> = self.adjacency[x].iter().filter(|&&e| Some(e) != from).map(|&e| (self.opposite(e, x), e)).collect();
+            for (i, &(y, e)) in children.iter().enumerate().rev() {
+                stack.push((y, Some(e), prefix.clone(), i + 1 == children.len()));
+            }
+        }
+        text
+    }
+
+    /// v を含む木を値の Debug 表示とともに標準出力に描く
+    pub fn print_tree(&mut self, v: usize)
+    where
+        M::Value: std::fmt::Debug,
+    {
+        print!("{}", self.to_ascii(v, |value| format!("{:?}", value)));
+    }
+
+    /// 節点 x の DOT での名前（頂点 v は v、辺 e は e）
+    fn dot_name(x: usize) -> String {
+        if is_vertex(x) { format!("v{}", x >> 1) } else { format!("e{}", x >> 1) }
+    }
+
+    /// 内部の splay 木を DOT で描く（木の構造は変えない）
+    /// splay 木の子への辺を実線で、splay 木の根から優先パスの上の節点への辺を破線で描き、
+    /// 反転を子に伝播していない節点には * を付ける
+    pub fn splay_dot(&self) -> String {
+        let mut edges: Vec<usize> = self.edge_ids.values().copied().collect();
+        edges.sort_unstable();
+        let nodes = (0 .. self.len()).map(vertex_node).chain(edges.into_iter().map(|e| 2 * e + 1));
+        let mut dot = String::from("digraph {\n");
+        for x in nodes {
+            let name = Self::dot_name(x);
+            let shape = if is_vertex(x) { "circle" } else { "box" };
+            let mark = if self.nodes[x].rev { "*" } else { "" };
+            writeln!(dot, "    {} [shape={}, label=\"{}{}\"];", name, shape, name, mark).unwrap();
+            for (child, label) in self.nodes[x].children.iter().zip(["L", "R"]) {
+                if let Some(child) = *child {
+                    writeln!(dot, "    {} -> {} [label=\"{}\"];", name, Self::dot_name(child), label).unwrap();
+                }
+            }
+            if let (None, Some(parent)) = (self.dir(x), self.nodes[x].parent) {
+                writeln!(dot, "    {} -> {} [style=dashed];", name, Self::dot_name(parent)).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    #[test]
+    fn to_graphml() {
+        let mut forest = Forest::<Sum<i64>, Sum<f64>>::new();
+        for x in [3, -1, 4] {
+            forest.add_node(x);
+        }
+        forest.link_weighted(1, 0, 0.5);
+        forest.link_weighted(2, 0, 2.0);
+        let xml = forest.to_graphml();
+        assert!(xml.contains("<key id=\"value\" for=\"node\" attr.name=\"value\" attr.type=\"long\"/>"));
+        assert!(xml.contains("<key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>"));
+        assert!(xml.contains("<node id=\"n1\"><data key=\"value\">-1</data></node>"));
+        assert!(xml.contains("<edge id=\"e0\" source=\"n1\" target=\"n0\"><data key=\"weight\">0.5</data></edge>"));
+        assert!(xml.ends_with("</graphml>\n"));
+        let mut forest = Forest::<()>::new();
+        forest.add_node(());
+        forest.add_node(());
+        forest.link(0, 1);
+        let xml = forest.to_graphml();
+        assert!(!xml.contains("<key"));
+        assert!(xml.contains("<edge id=\"e0\" source=\"n0\" target=\"n1\"/>"));
+    }
+
+    #[test]
+    fn to_svg() {
+        let mut forest = Forest::<Sum<i64>, Sum<i64>>::new();
+        for x in [3, -1, 4, 1, 5] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 3, 0 - 2, 4
+        forest.link_weighted(1, 0, 7);
+        forest.link_weighted(2, 0, 8);
+        forest.link_weighted(3, 1, 9);
+        forest.evert(3);
+        forest.root(2);
+        for v in 0 .. 5 {
+            let root = forest.root_of(v);
+            assert_eq!(root, forest.root(v));
+        }
+        let svg = forest.to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"120\" height=\"240\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<circle").count(), 5);
+        assert_eq!(svg.matches("<line").count(), 3);
+        // 根 3 が一番上にあり、一番下の葉 2 の右に孤立点 4 が並ぶ
+        assert!(svg.contains("<circle cx=\"30\" cy=\"30\""));
+        assert!(svg.contains("<circle cx=\"30\" cy=\"210\""));
+        assert!(svg.contains("<circle cx=\"90\" cy=\"30\""));
+        assert!(svg.contains(">-1</text>"));
+        assert!(svg.contains("fill=\"gray\">9</text>"));
+        let mut forest = Forest::<()>::new();
+        forest.add_node(());
+        assert!(!forest.to_svg().contains("fill=\"blue\""));
+    }
+
+    #[test]
+    fn to_ascii() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        for x in [3, -1, 4, 1, 5, 9] {
+            forest.add_node(x);
+        }
+        forest.link(1, 0);
+        forest.link(2, 0);
+        forest.link(3, 1);
+        forest.link(4, 1);
+        forest.link(5, 2);
+        let expected = "\
+0: 3
+├── 1: -1
+│   ├── 3: 1
+│   └── 4: 5
+└── 2: 4
+    └── 5: 9
+";
+        assert_eq!(forest.to_ascii(4, |x| x.to_string()), expected);
+        forest.evert(5);
+        assert!(forest.to_ascii(0, |x| format!("<{}>", x)).starts_with("5: <9>\n└── 2: <4>\n    └── 0: <3>\n"));
+    }
+
+    #[test]
+    fn splay_dot() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 3 {
+            forest.add_node(());
+        }
+        forest.link(1, 0);
+        forest.link(2, 0);
+        forest.root(2);
+        let dot = forest.splay_dot();
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.contains("    v1 [shape=circle, label=\"v1\"];\n"));
+        assert!(dot.contains("    e0 [shape=box"));
+        // 0 - 2 の優先パスに、1 への辺と 1 がそれぞれ一つの優先パスとして垂れ下がる
+        assert_eq!(dot.matches("style=dashed").count(), 2);
+        assert_eq!(dot.matches("label=\"L\"").count() + dot.matches("label=\"R\"").count(), 2);
+        for line in forest.splay_dot().lines().filter(|line| line.contains("->")) {
+            assert!(line.ends_with("];"));
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::{vertex_node, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+/// u から v へのパスを一つの splay 木にまとめたまま、何度も問い合わせるための借用
+/// 捨てたときに根を元に戻す
+pub struct ExposedPath<'a, M: Monoid, E: Monoid, A: Action<M, E> = (), S: Subtree<M, E> = (), N: NodeStore<ForestNodeOf<M, E, A, S>> = Vec<ForestNodeOf<M, E, A, S>>> {
+    forest: &'a mut Forest<M, E, A, S, N>,
+    /// パスの splay 木の根
+    top: usize,
+    /// 元の根の節点
+    root: usize,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// u から v へのパスを expose したままにする（異なる木に属するなら None）
+    pub fn expose_path(&mut self, u: usize, v: usize) -> Option<ExposedPath<'_, M, E, A, S, N>> {
+        let (x, y) = (vertex_node(u), vertex_node(v));
+        let root = self.root_node(x);
+        if self.root_node(y) != root {
+            return None;
+        }
+        self.evert_node(x);
+        self.expose(y);
+        Some(ExposedPath { forest: self, top: y, root })
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> ExposedPath<'_, M, E, A, S, N> {
+    /// パス上の頂点の数
+    pub fn len(&self) -> usize {
+        self.forest.nodes[self.top].len
+    }
+
+    /// パスは必ず頂点を含むので常に偽
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// パス上の頂点の値の集約
+    pub fn query(&self) -> &M::Value {
+        &self.forest.nodes[self.top].sum
+    }
+
+    /// パス上の辺の重みの集約
+    pub fn query_edges(&self) -> &E::Value {
+        &self.forest.nodes[self.top].weight_sum
+    }
+
+    /// u から数えて k 番目（0 始まり）の頂点
+    pub fn kth(&mut self, k: usize) -> Option<usize> {
+        if k >= self.len() {
+            return None;
+        }
+        self.top = self.forest.splay_kth(self.top, k);
+        Some(self.top >> 1)
+    }
+}
+
+/// path_apply と同じく、部分木全体の集約を持たない場合に限る
+impl<M: Monoid, E: Monoid, A: Action<M, E>, N: NodeStore<ForestNodeOf<M, E, A, ()>>> ExposedPath<'_, M, E, A, (), N> {
+    /// パス上の頂点の値と辺の重みに f を作用させる
+    pub fn apply(&mut self, f: &A::Map) {
+        self.forest.apply(self.top, f);
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Drop for ExposedPath<'_, M, E, A, S, N> {
+    fn drop(&mut self) {
+        self.forest.evert_node(self.root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::EdgeAdd;
+    use crate::monoid::{Min, Sum};
+
+    #[test]
+    fn several_queries() {
+        let mut forest = Forest::<Sum<i64>, Min<i64>, EdgeAdd>::new();
+        for x in [1, 2, 4, 8, 16, 32] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2 - 3, 1 - 4, 5
+        forest.link_weighted(1, 0, 5);
+        forest.link_weighted(2, 1, 3);
+        forest.link_weighted(3, 2, 7);
+        forest.link_weighted(4, 1, 2);
+        assert!(forest.expose_path(3, 5).is_none());
+        let mut path = forest.expose_path(3, 4).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(*path.query(), 30);
+        assert_eq!(*path.query_edges(), 2);
+        assert_eq!((0 .. 5).map(|k| path.kth(k)).collect::<Vec<_>>(), vec![Some(3), Some(2), Some(1), Some(4), None]);
+        assert_eq!(*path.query(), 30);
+        path.apply(&10);
+        assert_eq!(*path.query_edges(), 12);
+        assert_eq!(path.kth(1), Some(2));
+        drop(path);
+        assert_eq!(forest.root(3), 0);
+        assert_eq!(forest.path_query_edges(0, 1), Some(5));
+        assert_eq!(forest.path_query_edges(1, 2), Some(13));
+        assert_eq!(forest.path_query_edges(4, 1), Some(12));
+    }
+}
@@ -0,0 +1,73 @@
+use super::{edge_node, is_vertex, Forest, ForestNodeOf};
+use crate::action::EdgeAdd;
+use crate::monoid::{Min, Monoid};
+use crate::splay::SplayTree;
+use crate::store::NodeStore;
+use crate::Dir;
+
+impl<M: Monoid, N: NodeStore<ForestNodeOf<M, Min<i64>, EdgeAdd, ()>>> Forest<M, Min<i64>, EdgeAdd, (), N> {
+    /// u から v へのパス上の辺の容量の最小値（辺がなければ i64::MAX）
+    pub fn path_min_capacity(&mut self, u: usize, v: usize) -> Option<i64> {
+        self.path_query_edges(u, v)
+    }
+
+    /// u から v へのパス上の辺の容量に delta を足し、容量が 0 以下になった辺の両端を返す
+    pub fn add_along_path(&mut self, u: usize, v: usize, delta: i64) -> Option<Vec<(usize, usize)>> {
+        self.with_path(u, v, |forest, top| {
+            forest.apply(top, &delta);
+            // 容量の最小値が 0 以下の部分木だけを降りる
+            let mut saturated = vec![];
+            let mut stack = vec![top];
+            while let Some(x) = stack.pop() {
+                if forest.nodes[x].weight_sum > 0 {
+                    continue;
+                }
+                forest.push(x);
+                if !is_vertex(x) && forest.nodes[x].weight <= 0 {
+                    let [a, b] = forest.edges[x >> 1].ends;
+                    saturated.push((a, b));
+                }
+                stack.extend([Dir::Right, Dir::Left].into_iter().filter_map(|dir| forest.child(x, dir)));
+            }
+            saturated
+        })
+    }
+
+    /// u と v を結ぶ辺の容量
+    pub fn capacity(&mut self, u: usize, v: usize) -> Option<i64> {
+        let e = self.adjacency[u].iter().copied().find(|&e| self.opposite(e, u) == v)?;
+        Some(*self.weight(edge_node(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bottleneck_and_saturation() {
+        let mut forest = Forest::<(), Min<i64>, EdgeAdd>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 -5- 1 -3- 2 -7- 3, 1 -3- 4
+        forest.link_weighted(1, 0, 5);
+        forest.link_weighted(2, 1, 3);
+        forest.link_weighted(3, 2, 7);
+        forest.link_weighted(4, 1, 3);
+        assert_eq!(forest.path_min_capacity(0, 3), Some(3));
+        assert_eq!(forest.path_min_capacity(2, 2), Some(i64::MAX));
+        assert_eq!(forest.add_along_path(3, 4, -2), Some(vec![]));
+        assert_eq!(forest.path_min_capacity(0, 3), Some(1));
+        let mut saturated = forest.add_along_path(0, 3, -1).unwrap();
+        saturated.sort_unstable();
+        assert_eq!(saturated, vec![(2, 1)]);
+        assert_eq!(forest.capacity(0, 1), Some(4));
+        assert_eq!(forest.capacity(3, 2), Some(4));
+        assert_eq!(forest.capacity(4, 1), Some(1));
+        assert_eq!(forest.capacity(4, 0), None);
+        forest.cut(2);
+        assert_eq!(forest.add_along_path(0, 3, 1), None);
+        assert_eq!(forest.root(4), 0);
+    }
+}
@@ -0,0 +1,365 @@
+use super::{edge_node, vertex_node, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+mod diff;
+mod order;
+#[cfg(feature = "snapshot")]
+mod snapshot;
+
+pub use diff::{edit_script, Edit};
+pub use order::PathOrder;
+
+#[cfg(feature = "snapshot")]
+pub use snapshot::{FrozenView, SnapshotError, SnapshotValue};
+
+/// 森の構造を固定した読み取り専用の写し
+/// 問い合わせは splay 木を組み替えないので、&self で複数のスレッドから同時に行える
+pub struct Frozen<M: Monoid = (), E: Monoid = ()> {
+    depth: Vec<usize>,
+    root: Vec<usize>,
+    /// ancestor[k][v] は v の 2^k 個上の祖先（根を越える場合は根）
+    ancestor: Vec<Vec<usize>>,
+    /// up[k][v] は v から上向きに 2^k 個の頂点の値を集約したもの（down は逆向き）
+    up: Vec<Vec<M::Value>>,
+    down: Vec<Vec<M::Value>>,
+    /// weight_up[k][v] は v から上向きに 2^k 本の辺の重みを集約したもの（weight_down は逆向き）
+    weight_up: Vec<Vec<E::Value>>,
+    weight_down: Vec<Vec<E::Value>>,
+}
+
+/// Frozen に対する問い合わせ
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrozenQuery {
+    /// u から v へのパス上の頂点の値の集約
+    Path(usize, usize),
+    /// u から v へのパス上の辺の重みの集約
+    PathEdges(usize, usize),
+    /// u と v の最小共通祖先
+    Lca(usize, usize),
+}
+
+/// FrozenQuery の答え（異なる木に属する頂点についての問い合わせは None）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrozenAnswer<T, W> {
+    Path(Option<T>),
+    PathEdges(Option<W>),
+    Lca(Option<usize>),
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 現在の森の読み取り専用の写しを作る
+    pub fn freeze(&mut self) -> Frozen<M, E> {
+        let n = self.len();
+        let mut parent: Vec<usize> = (0 .. n).collect();
+        let mut depth = vec![0; n];
+        let mut root = vec![0; n];
+        let mut value: Vec<M::Value> = (0 .. n).map(|v| self.value(v).clone()).collect();
+        let mut weight = vec![E::identity(); n];
+        for r in self.roots() {
+            for (x, from) in self.bfs(r) {
+                root[x] = r;
+                if let Some(e) = from {
+                    let p = self.opposite(e, x);
+                    parent[x] = p;
+                    depth[x] = depth[p] + 1;
+                    weight[x] = self.weight(edge_node(e)).clone();
+                }
+            }
+        }
+        let mut ancestor = vec![parent];
+        let mut up = vec![std::mem::take(&mut value)];
+        let mut down = up.clone();
+        let mut weight_up = vec![weight];
+        let mut weight_down = weight_up.clone();
+        while 1 << ancestor.len() <= n {
+            let k = ancestor.len() - 1;
+            let (a, u, d, wu, wd) = (&ancestor[k], &up[k], &down[k], &weight_up[k], &weight_down[k]);
+            let next_ancestor = (0 .. n).map(|v| a[a[v]]).collect();
+            let next_up = (0 .. n).map(|v| M::op(&u[v], &u[a[v]])).collect();
+            let next_down = (0 .. n).map(|v| M::op(&d[a[v]], &d[v])).collect();
+            let next_weight_up = (0 .. n).map(|v| E::op(&wu[v], &wu[a[v]])).collect();
+            let next_weight_down = (0 .. n).map(|v| E::op(&wd[a[v]], &wd[v])).collect();
+            ancestor.push(next_ancestor);
+            up.push(next_up);
+            down.push(next_down);
+            weight_up.push(next_weight_up);
+            weight_down.push(next_weight_down);
+        }
+        Frozen { depth, root, ancestor, up, down, weight_up, weight_down }
+    }
+
+    /// queries にまとめて答え、同じ順に並べて返す（森の形は変えない）
+    /// パスの問い合わせは始点ごとにまとめ、始点を一度だけ根にして各終点を露出し、根を戻すのも一度だけにする
+    /// Lca は現在の根についての問い合わせなので、根を変えずに一つずつ答える
+    pub fn path_queries(&mut self, queries: &[FrozenQuery]) -> Vec<FrozenAnswer<M::Value, E::Value>> {
+        let mut answers: Vec<Option<FrozenAnswer<M::Value, E::Value>>> = (0 .. queries.len()).map(|_| None).collect();
+        let mut order = vec![];
+        for (i, &query) in queries.iter().enumerate() {
+            match query {
+                FrozenQuery::Lca(u, v) => answers[i] = Some(FrozenAnswer::Lca(self.lca(u, v))),
+                FrozenQuery::Path(u, v) | FrozenQuery::PathEdges(u, v) => order.push((u, v, i)),
+            }
+        }
+        order.sort_unstable();
+        let mut k = 0;
+        while k < order.len() {
+            let x = vertex_node(order[k].0);
+            let root = self.root_node(x);
+            self.evert_node(x);
+            while k < order.len() && vertex_node(order[k].0) == x {
+                let (_, v, i) = order[k];
+                let y = vertex_node(v);
+                let connected = self.root_node(y) == x;
+                if connected {
+                    self.expose(y);
+                }
+                answers[i] = Some(match queries[i] {
+                    FrozenQuery::Path(..) => FrozenAnswer::Path(connected.then(|| self.nodes[y].sum.clone())),
+                    _ => FrozenAnswer::PathEdges(connected.then(|| self.nodes[y].weight_sum.clone())),
+                });
+                k += 1;
+            }
+            self.evert_node(root);
+        }
+        answers.into_iter().map(Option::unwrap).collect()
+    }
+}
+
+impl<M: Monoid, E: Monoid> Frozen<M, E> {
+    pub fn len(&self) -> usize {
+        self.depth.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.depth.is_empty()
+    }
+
+    /// v を含む木の根
+    pub fn root(&self, v: usize) -> usize {
+        self.root[v]
+    }
+
+    /// 根からの辺の数
+    pub fn depth(&self, v: usize) -> usize {
+        self.depth[v]
+    }
+
+    /// v の親
+    pub fn parent(&self, v: usize) -> Option<usize> {
+        (self.depth[v] > 0).then(|| self.ancestor[0][v])
+    }
+
+    /// v の d 個上の祖先
+    fn lift(&self, mut v: usize, d: usize) -> usize {
+        for (k, ancestor) in self.ancestor.iter().enumerate() {
+            if d >> k & 1 == 1 {
+                v = ancestor[v];
+            }
+        }
+        v
+    }
+
+    pub fn lca(&self, u: usize, v: usize) -> Option<usize> {
+        if self.root[u] != self.root[v] {
+            return None;
+        }
+        let (mut u, mut v) = (u, v);
+        if self.depth[u] < self.depth[v] {
+            std::mem::swap(&mut u, &mut v);
+        }
+        u = self.lift(u, self.depth[u] - self.depth[v]);
+        if u == v {
+            return Some(u);
+        }
+        for ancestor in self.ancestor.iter().rev() {
+            if ancestor[u] != ancestor[v] {
+                u = ancestor[u];
+                v = ancestor[v];
+            }
+        }
+        Some(self.ancestor[0][u])
+    }
+
+    /// v から上向きに count 個の要素を table で集約する（up が真なら上向きの順）
+    fn fold<T>(table: &[Vec<T>], ancestor: &[Vec<usize>], mut v: usize, count: usize, up: bool, op: fn(&T, &T) -> T, mut acc: T) -> T {
+        for k in 0 .. table.len() {
+            if count >> k & 1 == 1 {
+                acc = if up { op(&acc, &table[k][v]) } else { op(&table[k][v], &acc) };
+                v = ancestor[k][v];
+            }
+        }
+        acc
+    }
+
+    /// u から v へのパス上の頂点の値の集約
+    pub fn path_query(&self, u: usize, v: usize) -> Option<M::Value> {
+        let l = self.lca(u, v)?;
+        let first = Self::fold(&self.up, &self.ancestor, u, self.depth[u] - self.depth[l] + 1, true, M::op, M::identity());
+        let second = Self::fold(&self.down, &self.ancestor, v, self.depth[v] - self.depth[l], false, M::op, M::identity());
+        Some(M::op(&first, &second))
+    }
+
+    /// u から v へのパス上の辺の重みの集約
+    pub fn path_query_edges(&self, u: usize, v: usize) -> Option<E::Value> {
+        let l = self.lca(u, v)?;
+        let first = Self::fold(&self.weight_up, &self.ancestor, u, self.depth[u] - self.depth[l], true, E::op, E::identity());
+        let second = Self::fold(&self.weight_down, &self.ancestor, v, self.depth[v] - self.depth[l], false, E::op, E::identity());
+        Some(E::op(&first, &second))
+    }
+
+    pub fn query(&self, query: FrozenQuery) -> FrozenAnswer<M::Value, E::Value> {
+        match query {
+            FrozenQuery::Path(u, v) => FrozenAnswer::Path(self.path_query(u, v)),
+            FrozenQuery::PathEdges(u, v) => FrozenAnswer::PathEdges(self.path_query_edges(u, v)),
+            FrozenQuery::Lca(u, v) => FrozenAnswer::Lca(self.lca(u, v)),
+        }
+    }
+}
+
+/// par_path_queries が一つのスレッドに任せる問い合わせの数の下限
+/// これより少ないと、スレッドを立てる手間が問い合わせに答える時間を上回る
+#[cfg(feature = "parallel")]
+const PAR_MIN_CHUNK: usize = 1024;
+
+/// queries をスレッドに分けて並列に答え、同じ順に並べて返す
+/// 問い合わせが PAR_MIN_CHUNK の二倍に満たなければ、スレッドを立てずにこのスレッドで順に答える
+#[cfg(feature = "parallel")]
+pub fn par_path_queries<M, E>(frozen: &Frozen<M, E>, queries: &[FrozenQuery]) -> Vec<FrozenAnswer<M::Value, E::Value>>
+where
+    M: Monoid,
+    E: Monoid,
+    M::Value: Send + Sync,
+    E::Value: Send + Sync,
+{
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = queries.len().div_ceil(threads).max(PAR_MIN_CHUNK);
+    if chunk >= queries.len() {
+        return queries.iter().map(|&query| frozen.query(query)).collect();
+    }
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = queries
+            .chunks(chunk)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|&query| frozen.query(query)).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::{AffineComposite, ConstModulus, Sum};
+    use crate::test_util::xorshift64;
+
+    #[test]
+    fn frozen_matches_forest() {
+        let n = 50;
+        let mut rand = xorshift64(0x9e3779b97f4a7c15);
+        let mut next = move |m: u64| rand() % m;
+        let mut forest = Forest::<AffineComposite<ConstModulus<1000003>>, Sum<u64>>::new();
+        for _ in 0 .. n {
+            forest.add_node((next(1000) + 1, next(1000)));
+        }
+        for v in 1 .. n {
+            if next(5) > 0 {
+                let p = next(v as u64) as usize;
+                forest.link_weighted(v, p, next(100));
+            }
+        }
+        forest.evert(n - 1);
+        let frozen = forest.freeze();
+        let mut queries = vec![];
+        for _ in 0 .. 300 {
+            let (u, v) = (next(n as u64) as usize, next(n as u64) as usize);
+            queries.push(match next(3) {
+                0 => FrozenQuery::Path(u, v),
+                1 => FrozenQuery::PathEdges(u, v),
+                _ => FrozenQuery::Lca(u, v),
+            });
+        }
+        let answers: Vec<_> = queries.iter().map(|&query| frozen.query(query)).collect();
+        #[cfg(feature = "parallel")]
+        assert_eq!(par_path_queries(&frozen, &queries), answers);
+        let parents = forest.to_parents();
+        assert_eq!(forest.path_queries(&queries), answers);
+        assert_eq!(forest.to_parents(), parents);
+        for (&query, answer) in queries.iter().zip(answers) {
+            let expected = match query {
+                FrozenQuery::Path(u, v) => FrozenAnswer::Path(forest.path_query(u, v)),
+                FrozenQuery::PathEdges(u, v) => FrozenAnswer::PathEdges(forest.path_query_edges(u, v)),
+                FrozenQuery::Lca(u, v) => FrozenAnswer::Lca(forest.lca(u, v)),
+            };
+            assert_eq!(answer, expected);
+        }
+        for v in 0 .. n {
+            assert_eq!(frozen.parent(v), forest.parent(v));
+            assert_eq!(frozen.root(v), forest.root(v));
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn par_path_queries_splits_large_batches() {
+        let n = 1000;
+        let mut rand = xorshift64(0x243f6a8885a308d3);
+        let mut next = move |m: u64| rand() % m;
+        let mut forest = Forest::<Sum<u64>, Sum<u64>>::new();
+        for v in 0 .. n {
+            forest.add_node(v as u64);
+            if v > 0 {
+                let p = next(v as u64) as usize;
+                forest.link_weighted(v, p, next(100));
+            }
+        }
+        let frozen = forest.freeze();
+        // 下限より少なければこのスレッドで、多ければスレッドに分けて答える
+        for count in [0, 1, PAR_MIN_CHUNK, 4 * PAR_MIN_CHUNK + 3] {
+            let queries: Vec<_> = (0 .. count)
+                .map(|i| {
+                    let (u, v) = (next(n as u64) as usize, next(n as u64) as usize);
+                    [FrozenQuery::Path(u, v), FrozenQuery::PathEdges(u, v), FrozenQuery::Lca(u, v)][i % 3]
+                })
+                .collect();
+            let expected: Vec<_> = queries.iter().map(|&query| frozen.query(query)).collect();
+            assert_eq!(par_path_queries(&frozen, &queries), expected);
+        }
+    }
+
+    #[test]
+    fn path_queries_share_everts() {
+        let n = 200;
+        let mut rand = xorshift64(0x9e3779b97f4a7c15);
+        let mut next = move |m: u64| rand() % m;
+        let parents: Vec<(u64, Option<usize>)> = (0 .. n).map(|v| (v as u64, (v > 0).then(|| next(v as u64) as usize))).collect();
+        let build = || {
+            let mut forest = Forest::<Sum<u64>>::builder().statistics(true).build();
+            for &(x, _) in &parents {
+                forest.add_node(x);
+            }
+            for (v, &(_, p)) in parents.iter().enumerate() {
+                if let Some(p) = p {
+                    forest.link(v, p);
+                }
+            }
+            forest
+        };
+        // 始点は 4 つだけで、終点はばらばら
+        let queries: Vec<FrozenQuery> = (0 .. 400).map(|i| FrozenQuery::Path(i % 4 * 50, next(n as u64) as usize)).collect();
+        let mut batch = build();
+        let mut single = build();
+        let before = (batch.op_stats().unwrap(), single.op_stats().unwrap());
+        let answers = batch.path_queries(&queries);
+        for (&query, answer) in queries.iter().zip(&answers) {
+            let FrozenQuery::Path(u, v) = query else { unreachable!() };
+            assert_eq!(answer, &FrozenAnswer::Path(single.path_query(u, v)));
+        }
+        let (batch_stats, single_stats) = (batch.op_stats().unwrap(), single.op_stats().unwrap());
+        let batch_rotations = batch_stats.rotations - before.0.rotations;
+        let single_rotations = single_stats.rotations - before.1.rotations;
+        assert!(batch_rotations < single_rotations, "{} >= {}", batch_rotations, single_rotations);
+        assert_eq!(batch.to_parents(), single.to_parents());
+    }
+}
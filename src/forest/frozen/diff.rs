@@ -0,0 +1,97 @@
+use super::Frozen;
+use crate::action::Action;
+use crate::forest::{Forest, ForestNodeOf};
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+/// 森の構造を書き換える一つの操作
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// v を親から切り離す
+    Cut(usize),
+    /// 木の根 child の親を parent にする
+    Link(usize, usize),
+}
+
+/// 親の配列 from の森を to の森に書き換える操作の列
+/// 親が変わる頂点ごとに、切り離してからつなぎ直す（根の選び方も含めて一致させる）
+/// 先にすべての cut を並べるので、どの link の時点でも child は根になっている
+pub fn edit_script(from: &[Option<usize>], to: &[Option<usize>]) -> Vec<Edit> {
+    assert_eq!(from.len(), to.len(), "forests must have the same number of vertices");
+    let changed: Vec<usize> = (0 .. from.len()).filter(|&v| from[v] != to[v]).collect();
+    let cuts = changed.iter().filter(|&&v| from[v].is_some()).map(|&v| Edit::Cut(v));
+    let links = changed.iter().filter_map(|&v| Some(Edit::Link(v, to[v]?)));
+    cuts.chain(links).collect()
+}
+
+impl<M: Monoid, E: Monoid> Frozen<M, E> {
+    /// 各頂点の親
+    pub fn parents(&self) -> Vec<Option<usize>> {
+        (0 .. self.len()).map(|v| self.parent(v)).collect()
+    }
+
+    /// この写しの森を other の森に書き換える操作の列
+    pub fn diff<M2: Monoid, E2: Monoid>(&self, other: &Frozen<M2, E2>) -> Vec<Edit> {
+        edit_script(&self.parents(), &other.parents())
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 写し snapshot の森を今の森に書き換える操作の列
+    pub fn diff_from<M2: Monoid, E2: Monoid>(&mut self, snapshot: &Frozen<M2, E2>) -> Vec<Edit> {
+        let parents: Vec<_> = (0 .. self.len()).map(|v| self.parent(v)).collect();
+        edit_script(&snapshot.parents(), &parents)
+    }
+
+    /// 操作の列を順に行う（新しい辺の重みは単位元）
+    pub fn apply_edits(&mut self, edits: &[Edit]) {
+        for &edit in edits {
+            match edit {
+                Edit::Cut(v) => {
+                    self.cut(v);
+                }
+                Edit::Link(child, parent) => self.link(child, parent),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn replica_follows_diffs() {
+        let n = 30;
+        let mut next = xorshift(0x9e3779b97f4a7c15);
+        let mut forest = Forest::<()>::new();
+        let mut replica = Forest::<()>::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+            replica.add_node(());
+        }
+        for _ in 0 .. 20 {
+            let before = forest.freeze();
+            for _ in 0 .. 5 {
+                let (u, v) = (next(n), next(n));
+                match next(3) {
+                    0 if forest.root(u) == u && forest.root(v) != u => forest.link(u, v),
+                    1 if forest.parent(u).is_some() => {
+                        forest.cut(u);
+                    }
+                    _ => forest.evert(u),
+                }
+            }
+            let edits = forest.diff_from(&before);
+            assert_eq!(edits, before.diff(&forest.freeze()));
+            replica.apply_edits(&edits);
+            for v in 0 .. n {
+                assert_eq!(replica.parent(v), forest.parent(v));
+            }
+        }
+        assert_eq!(edit_script(&[None, Some(0)], &[None, Some(0)]), vec![]);
+        assert_eq!(edit_script(&[None, Some(0)], &[Some(1), None]), vec![Edit::Cut(1), Edit::Link(0, 1)]);
+    }
+}
@@ -0,0 +1,128 @@
+use super::Frozen;
+use crate::monoid::Monoid;
+
+/// Frozen の上でパスの頂点の値の順序統計を答える表
+/// 各頂点に根からのパスの値の個数を持つ永続セグメント木を作り、O(log n) で答える
+pub struct PathOrder<'a, M: Monoid, E: Monoid> {
+    frozen: &'a Frozen<M, E>,
+    /// 値を重複なく小さい順に並べたもの
+    keys: Vec<M::Value>,
+    /// 永続セグメント木の節点 (左の子, 右の子, 個数)（0 番は空の木）
+    nodes: Vec<(usize, usize, usize)>,
+    /// version[v] は根から v までの値を数えた木
+    version: Vec<usize>,
+}
+
+impl<M: Monoid, E: Monoid> Frozen<M, E>
+where
+    M::Value: Ord,
+{
+    /// パスの k 番目に小さい値を答える表を作る
+    pub fn path_order(&self) -> PathOrder<'_, M, E> {
+        let n = self.len();
+        let values = &self.up[0];
+        let mut keys = values.clone();
+        keys.sort();
+        keys.dedup();
+        let mut order: Vec<usize> = (0 .. n).collect();
+        order.sort_by_key(|&v| self.depth[v]);
+        let mut table = PathOrder { frozen: self, keys, nodes: vec![(0, 0, 0)], version: vec![0; n] };
+        for v in order {
+            let base = self.parent(v).map_or(0, |p| table.version[p]);
+            let rank = table.keys.binary_search(&values[v]).unwrap();
+            table.version[v] = table.insert(base, 0, table.keys.len(), rank);
+        }
+        table
+    }
+}
+
+impl<M: Monoid, E: Monoid> PathOrder<'_, M, E>
+where
+    M::Value: Ord,
+{
+    /// 区間 [lo, hi) を受け持つ木 x に rank を一つ加えた木
+    fn insert(&mut self, x: usize, lo: usize, hi: usize, rank: usize) -> usize {
+        let (left, right, count) = self.nodes[x];
+        let node = if hi - lo == 1 {
+            (0, 0, count + 1)
+        } else {
+            let mid = (lo + hi) / 2;
+            if rank < mid {
+                (self.insert(left, lo, mid, rank), right, count + 1)
+            } else {
+                (left, self.insert(right, mid, hi, rank), count + 1)
+            }
+        };
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// u から v へのパス上の頂点の値のうち k 番目（0 始まり）に小さいもの
+    /// 異なる木に属するか、k がパスの頂点数以上なら None
+    pub fn path_kth_smallest(&self, u: usize, v: usize, mut k: usize) -> Option<&M::Value> {
+        let l = self.frozen.lca(u, v)?;
+        let above = self.frozen.parent(l).map_or(0, |p| self.version[p]);
+        let mut trees = [self.version[u], self.version[v], self.version[l], above];
+        let count = |nodes: &[(usize, usize, usize)], trees: [usize; 4]| {
+            nodes[trees[0]].2 + nodes[trees[1]].2 - nodes[trees[2]].2 - nodes[trees[3]].2
+        };
+        if k >= count(&self.nodes, trees) {
+            return None;
+        }
+        let (mut lo, mut hi) = (0, self.keys.len());
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            let left = trees.map(|x| self.nodes[x].0);
+            let left_count = count(&self.nodes, left);
+            if k < left_count {
+                trees = left;
+                hi = mid;
+            } else {
+                k -= left_count;
+                trees = trees.map(|x| self.nodes[x].1);
+                lo = mid;
+            }
+        }
+        Some(&self.keys[lo])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monoid::Max;
+    use crate::test_util::xorshift64;
+    use crate::Forest;
+
+    #[test]
+    fn path_order_matches_sorting() {
+        let n = 40;
+        let mut rand = xorshift64(0x9e3779b97f4a7c15);
+        let mut next = move |m: u64| rand() % m;
+        let mut forest = Forest::<Max<i64>>::new();
+        for _ in 0 .. n {
+            forest.add_node(next(20) as i64 - 10);
+        }
+        for v in 1 .. n {
+            if next(6) > 0 {
+                forest.link(v, next(v as u64) as usize);
+            }
+        }
+        forest.evert(n - 1);
+        let frozen = forest.freeze();
+        let order = frozen.path_order();
+        for u in 0 .. n {
+            for v in 0 .. n {
+                let Some(path) = forest.path_iter(u, v).map(|path| path.collect::<Vec<_>>()) else {
+                    assert_eq!(order.path_kth_smallest(u, v, 0), None);
+                    continue;
+                };
+                let mut values: Vec<i64> = path.iter().map(|&w| *forest.value(w)).collect();
+                values.sort();
+                for k in 0 ..= values.len() {
+                    assert_eq!(order.path_kth_smallest(u, v, k), values.get(k));
+                    assert_eq!(forest.path_kth_smallest(u, v, k), values.get(k).copied());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+
+use super::Frozen;
+use crate::monoid::Monoid;
+
+const MAGIC: &[u8; 4] = b"LCTF";
+const HEADER: usize = 4 + 8 + 8;
+
+/// 写しに書き出せる固定長の値（リトルエンディアン）
+pub trait SnapshotValue: Sized {
+    const SIZE: usize;
+
+    fn write(&self, out: &mut Vec<u8>);
+    /// bytes の長さは SIZE
+    fn read(bytes: &[u8]) -> Self;
+}
+
+impl SnapshotValue for () {
+    const SIZE: usize = 0;
+
+    fn write(&self, _: &mut Vec<u8>) {}
+    fn read(_: &[u8]) {}
+}
+
+macro_rules! impl_snapshot_value {
+    ($($t:ty),*) => {
+        $(impl SnapshotValue for $t {
+            const SIZE: usize = std::mem::size_of::<$t>();
+
+            fn write(&self, out: &mut Vec<u8>) { out.extend_from_slice(&self.to_le_bytes()) }
+            fn read(bytes: &[u8]) -> Self { <$t>::from_le_bytes(bytes.try_into().unwrap()) }
+        })*
+    };
+}
+
+impl_snapshot_value!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+impl SnapshotValue for usize {
+    const SIZE: usize = 8;
+
+    fn write(&self, out: &mut Vec<u8>) { write_u64(out, *self) }
+    fn read(bytes: &[u8]) -> Self { read_u64(bytes, 0) }
+}
+
+impl<A: SnapshotValue, B: SnapshotValue> SnapshotValue for (A, B) {
+    const SIZE: usize = A::SIZE + B::SIZE;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        self.0.write(out);
+        self.1.write(out);
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        (A::read(&bytes[.. A::SIZE]), B::read(&bytes[A::SIZE ..]))
+    }
+}
+
+/// バイト列を写しとして読めなかった理由
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// 先頭が写しの印でない
+    Magic,
+    /// 長さが見出しと合わない
+    Length { expected: usize, actual: usize },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Magic => write!(f, "not a forest snapshot"),
+            SnapshotError::Length { expected, actual } => write!(f, "snapshot has {} bytes, expected {}", actual, expected),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+fn write_u64(out: &mut Vec<u8>, x: usize) {
+    out.extend_from_slice(&(x as u64).to_le_bytes());
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> usize {
+    u64::from_le_bytes(bytes[offset .. offset + 8].try_into().unwrap()) as usize
+}
+
+impl<M: Monoid, E: Monoid> Frozen<M, E>
+where
+    M::Value: SnapshotValue,
+    E::Value: SnapshotValue,
+{
+    /// FrozenView で読めるバイト列に書き出す
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.write_to(&mut out).unwrap();
+        out
+    }
+
+    /// to_bytes と同じバイト列を out に少しずつ書き出す（全体をメモリに持たない）
+    /// ファイルに書き出してメモリに写せば、メモリに収まらない森も FrozenView で読める
+    pub fn write_to(&self, mut out: impl Write) -> io::Result<()> {
+        const CHUNK: usize = 1 << 16;
+        let mut buf = MAGIC.to_vec();
+        write_u64(&mut buf, self.len());
+        write_u64(&mut buf, self.ancestor.len());
+        let mut flush = |buf: &mut Vec<u8>, force: bool| {
+            if force || buf.len() >= CHUNK {
+                out.write_all(buf)?;
+                buf.clear();
+            }
+            io::Result::Ok(())
+        };
+        for &x in self.depth.iter().chain(&self.root).chain(self.ancestor.iter().flatten()) {
+            write_u64(&mut buf, x);
+            flush(&mut buf, false)?;
+        }
+        for x in self.up.iter().chain(&self.down).flatten() {
+            x.write(&mut buf);
+            flush(&mut buf, false)?;
+        }
+        for x in self.weight_up.iter().chain(&self.weight_down).flatten() {
+            x.write(&mut buf);
+            flush(&mut buf, false)?;
+        }
+        flush(&mut buf, true)
+    }
+}
+
+/// Frozen::to_bytes で書き出したバイト列を、復元せずにその場で読む写し
+/// メモリに写したファイルをそのまま渡せる
+pub struct FrozenView<'a, M: Monoid = (), E: Monoid = ()> {
+    bytes: &'a [u8],
+    len: usize,
+    levels: usize,
+    _marker: PhantomData<(M, E)>,
+}
+
+impl<'a, M: Monoid, E: Monoid> FrozenView<'a, M, E>
+where
+    M::Value: SnapshotValue,
+    E::Value: SnapshotValue,
+{
+    /// 見出しと長さを確かめる（中身は確かめない）
+    pub fn new(bytes: &'a [u8]) -> Result<Self, SnapshotError> {
+        if bytes.len() < HEADER || &bytes[.. 4] != MAGIC {
+            return Err(SnapshotError::Magic);
+        }
+        let (len, levels) = (read_u64(bytes, 4), read_u64(bytes, 12));
+        let view = Self { bytes, len, levels, _marker: PhantomData };
+        let expected = view.weight_offset(2 * levels);
+        if bytes.len() != expected {
+            return Err(SnapshotError::Length { expected, actual: bytes.len() });
+        }
+        Ok(view)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// k 番目の頂点の表の始まり（0: depth、1: root、2 以降: ancestor）
+    fn index_offset(&self, k: usize) -> usize {
+        HEADER + k * self.len * 8
+    }
+
+    /// k 番目の値の表の始まり（up の後に down が続く）
+    fn value_offset(&self, k: usize) -> usize {
+        self.index_offset(2 + self.levels) + k * self.len * M::Value::SIZE
+    }
+
+    /// k 番目の重みの表の始まり（weight_up の後に weight_down が続く）
+    fn weight_offset(&self, k: usize) -> usize {
+        self.value_offset(2 * self.levels) + k * self.len * E::Value::SIZE
+    }
+
+    fn index(&self, k: usize, v: usize) -> usize {
+        assert!(v < self.len, "vertex {} is out of range", v);
+        read_u64(self.bytes, self.index_offset(k) + v * 8)
+    }
+
+    fn ancestor(&self, k: usize, v: usize) -> usize {
+        self.index(2 + k, v)
+    }
+
+    fn value(&self, k: usize, v: usize) -> M::Value {
+        let offset = self.value_offset(k) + v * M::Value::SIZE;
+        M::Value::read(&self.bytes[offset .. offset + M::Value::SIZE])
+    }
+
+    fn weight(&self, k: usize, v: usize) -> E::Value {
+        let offset = self.weight_offset(k) + v * E::Value::SIZE;
+        E::Value::read(&self.bytes[offset .. offset + E::Value::SIZE])
+    }
+
+    /// v を含む木の根
+    pub fn root(&self, v: usize) -> usize {
+        self.index(1, v)
+    }
+
+    /// 根からの辺の数
+    pub fn depth(&self, v: usize) -> usize {
+        self.index(0, v)
+    }
+
+    /// v の親
+    pub fn parent(&self, v: usize) -> Option<usize> {
+        (self.depth(v) > 0).then(|| self.ancestor(0, v))
+    }
+
+    pub fn lca(&self, u: usize, v: usize) -> Option<usize> {
+        if self.root(u) != self.root(v) {
+            return None;
+        }
+        let (mut u, mut v) = (u, v);
+        if self.depth(u) < self.depth(v) {
+            std::mem::swap(&mut u, &mut v);
+        }
+        let d = self.depth(u) - self.depth(v);
+        for k in 0 .. self.levels {
+            if d >> k & 1 == 1 {
+                u = self.ancestor(k, u);
+            }
+        }
+        if u == v {
+            return Some(u);
+        }
+        for k in (0 .. self.levels).rev() {
+            if self.ancestor(k, u) != self.ancestor(k, v) {
+                u = self.ancestor(k, u);
+                v = self.ancestor(k, v);
+            }
+        }
+        Some(self.ancestor(0, u))
+    }
+
+    /// u から v へのパス上の頂点の値の集約
+    pub fn path_query(&self, u: usize, v: usize) -> Option<M::Value> {
+        let l = self.lca(u, v)?;
+        let (mut x, mut first) = (u, M::identity());
+        let count = self.depth(u) - self.depth(l) + 1;
+        for k in 0 .. self.levels {
+            if count >> k & 1 == 1 {
+                first = M::op(&first, &self.value(k, x));
+                x = self.ancestor(k, x);
+            }
+        }
+        let (mut x, mut second) = (v, M::identity());
+        let count = self.depth(v) - self.depth(l);
+        for k in 0 .. self.levels {
+            if count >> k & 1 == 1 {
+                second = M::op(&self.value(self.levels + k, x), &second);
+                x = self.ancestor(k, x);
+            }
+        }
+        Some(M::op(&first, &second))
+    }
+
+    /// u から v へのパス上の辺の重みの集約
+    pub fn path_query_edges(&self, u: usize, v: usize) -> Option<E::Value> {
+        let l = self.lca(u, v)?;
+        let (mut x, mut first) = (u, E::identity());
+        let count = self.depth(u) - self.depth(l);
+        for k in 0 .. self.levels {
+            if count >> k & 1 == 1 {
+                first = E::op(&first, &self.weight(k, x));
+                x = self.ancestor(k, x);
+            }
+        }
+        let (mut x, mut second) = (v, E::identity());
+        let count = self.depth(v) - self.depth(l);
+        for k in 0 .. self.levels {
+            if count >> k & 1 == 1 {
+                second = E::op(&self.weight(self.levels + k, x), &second);
+                x = self.ancestor(k, x);
+            }
+        }
+        Some(E::op(&first, &second))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::{Max, Sum};
+    use crate::test_util::xorshift;
+    use crate::Forest;
+
+    #[test]
+    fn view_matches_frozen() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 40;
+        let mut forest = Forest::<Sum<i64>, Max<i64>>::new();
+        for v in 0 .. n {
+            forest.add_node(v as i64 * 3 - 20);
+        }
+        for v in 1 .. n {
+            if rand(5) > 0 {
+                let p = rand(v);
+                forest.link_weighted(v, p, rand(100) as i64);
+            }
+        }
+        let frozen = forest.freeze();
+        let bytes = frozen.to_bytes();
+        let view = FrozenView::<Sum<i64>, Max<i64>>::new(&bytes).unwrap();
+        assert_eq!(view.len(), n);
+        for u in 0 .. n {
+            assert_eq!(view.root(u), frozen.root(u));
+            assert_eq!(view.depth(u), frozen.depth(u));
+            assert_eq!(view.parent(u), frozen.parent(u));
+            for v in 0 .. n {
+                assert_eq!(view.lca(u, v), frozen.lca(u, v));
+                assert_eq!(view.path_query(u, v), frozen.path_query(u, v));
+                assert_eq!(view.path_query_edges(u, v), frozen.path_query_edges(u, v));
+            }
+        }
+    }
+
+    #[test]
+    fn write_to_streams_chunks() {
+        /// 書き出しの一回分ずつを覚える
+        struct Chunks(Vec<Vec<u8>>);
+
+        impl Write for Chunks {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.push(buf.to_vec());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let n = 5000;
+        let mut forest = Forest::<Sum<i64>, Max<i64>>::new();
+        for v in 0 .. n {
+            forest.add_node(v as i64);
+            if v > 0 {
+                forest.link_weighted(v, v / 2, v as i64 % 7);
+            }
+        }
+        let frozen = forest.freeze();
+        let mut chunks = Chunks(vec![]);
+        frozen.write_to(&mut chunks).unwrap();
+        assert!(chunks.0.len() > 1);
+        assert!(chunks.0.iter().all(|chunk| chunk.len() <= (1 << 16) + 8));
+        let bytes = chunks.0.concat();
+        assert_eq!(bytes, frozen.to_bytes());
+        let path = std::env::temp_dir().join(format!("link-cut-tree-snapshot-{}", std::process::id()));
+        frozen.write_to(io::BufWriter::new(std::fs::File::create(&path).unwrap())).unwrap();
+        let file = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let view = FrozenView::<Sum<i64>, Max<i64>>::new(&file).unwrap();
+        assert_eq!(view.lca(4999, 3000), frozen.lca(4999, 3000));
+        assert_eq!(view.path_query(4999, 1234), frozen.path_query(4999, 1234));
+    }
+
+    #[test]
+    fn rejects_bad_bytes() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        forest.add_node(1);
+        forest.add_node(2);
+        forest.link(1, 0);
+        let bytes = forest.freeze().to_bytes();
+        assert!(FrozenView::<Sum<i64>>::new(&bytes).is_ok());
+        assert_eq!(FrozenView::<Sum<i64>>::new(b"nope").err(), Some(SnapshotError::Magic));
+        let expected = bytes.len();
+        assert_eq!(
+            FrozenView::<Sum<i64>>::new(&bytes[.. expected - 1]).err(),
+            Some(SnapshotError::Length { expected, actual: expected - 1 })
+        );
+    }
+}
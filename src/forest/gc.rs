@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use super::{Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::IdRemap;
+
+/// 頂点に pin を付けて使っている印にし、pin の付いた頂点を含まない木を gc でまとめて捨てる
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// v の pin を一つ増やす
+    pub fn pin(&mut self, v: usize) {
+        assert!(v < self.len(), "vertex {} is out of range", v);
+        *self.pins.entry(v).or_insert(0) += 1;
+    }
+
+    /// v の pin を一つ減らし、減らせたかを返す
+    pub fn unpin(&mut self, v: usize) -> bool {
+        let Some(count) = self.pins.get_mut(&v) else { return false };
+        *count -= 1;
+        if *count == 0 {
+            self.pins.remove(&v);
+        }
+        true
+    }
+
+    pub fn pin_count(&self, v: usize) -> usize {
+        self.pins.get(&v).copied().unwrap_or(0)
+    }
+
+    /// pin の付いた頂点を含まない木をすべて取り除き、残った頂点を元の番号の順に詰める
+    /// 残った木の形と根、値、辺の重みと多重度、pin、次数の上限、木に付けた値は保たれる
+    /// 何か取り除いたときは辺の番号を振り直し、森の番号も変わるので、それまでの Handle は ForeignHandle になる
+    pub fn gc(&mut self) -> IdRemap
+    where
+        N: Default,
+    {
+        let n = self.len();
+        let mut keep = vec![false; n];
+        let mut pinned: Vec<usize> = self.pins.keys().copied().collect();
+        pinned.sort_unstable();
+        for v in pinned {
+            if !keep[v] {
+                for (x, _) in self.bfs(v) {
+                    keep[x] = true;
+                }
+            }
+        }
+        if keep.iter().all(|&k| k) {
+            return IdRemap::new((0 .. n).map(|v| (v, v)).collect());
+        }
+        let mut new_id = vec![None; n];
+        let mut forest = Forest { ordered: self.ordered, sorted: self.sorted, update_hook: self.update_hook.take(), stats: self.stats, ..Forest::default() };
+        for v in (0 .. n).filter(|&v| keep[v]) {
+            new_id[v] = Some(forest.add_node(self.value(v).clone()));
+        }
+        for v in (0 .. n).filter(|&v| keep[v]) {
+            if self.root(v) != v {
+                continue;
+            }
+            // 根から幅優先順に、子を親につなぐ
+            for (parent, child, weight) in self.tree_edges(v) {
+                let (c, p) = (new_id[child].unwrap(), new_id[parent].unwrap());
+                forest.link_weighted(c, p, weight);
+                let (old, new) = (self.edge_slot(child, parent).unwrap(), forest.edge_slot(c, p).unwrap());
+                forest.edges[new].copies = self.edges[old].copies;
+            }
+            if let Some(&data) = self.component_data.get(&v) {
+                forest.component_data.insert(new_id[v].unwrap(), data);
+            }
+        }
+        let renumber = |map: &HashMap<usize, usize>| -> HashMap<usize, usize> {
+            map.iter().filter_map(|(&v, &x)| Some((new_id[v]?, x))).collect()
+        };
+        forest.pins = renumber(&self.pins);
+        forest.degree_limits = renumber(&self.degree_limits);
+        forest.super_root = self.super_root.and_then(|s| new_id[s]);
+        forest.version = self.version + 1;
+        forest.observers = std::mem::take(&mut self.observers);
+        *self = forest;
+        IdRemap::new((0 .. n).filter_map(|v| Some((v, new_id[v]?))).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+    use crate::LctError;
+
+    #[test]
+    fn gc_drops_unpinned_trees() {
+        let mut forest = Forest::<Sum<i64>, Sum<i64>>::new();
+        for x in 0 .. 8 {
+            forest.add_node(10 * x);
+        }
+        // 0 - 1 - 2, 3 - 4, 5 - 6 - 7, 根は 2, 3, 6
+        forest.link_weighted(0, 1, 1);
+        forest.link_weighted(1, 2, 2);
+        forest.link_weighted(4, 3, 3);
+        forest.link_weighted(5, 6, 4);
+        forest.link_weighted(7, 6, 5);
+        forest.add_multi_edge(5, 6).unwrap();
+        forest.set_degree_limit(6, Some(2));
+        forest.pin(0);
+        forest.pin(7);
+        forest.pin(7);
+        forest.pin(4);
+        assert!(forest.unpin(4));
+        assert!(!forest.unpin(4));
+        assert_eq!(forest.pin_count(7), 2);
+        let handle = forest.handle(0);
+        let remap = forest.gc();
+        assert_eq!(remap.iter().collect::<Vec<_>>(), vec![(0, 0), (1, 1), (2, 2), (5, 3), (6, 4), (7, 5)]);
+        assert_eq!(forest.len(), 6);
+        assert_eq!(forest.to_parents(), vec![Some(1), Some(2), None, Some(4), None, Some(4)]);
+        assert_eq!(forest.value(3), &50);
+        assert_eq!(forest.path_query_edges(0, 2), Some(3));
+        assert_eq!(forest.path_query_edges(3, 5), Some(9));
+        assert_eq!(forest.multiplicity(3, 4), 2);
+        assert_eq!(forest.degree_limit(4), Some(2));
+        assert_eq!(forest.pin_count(5), 2);
+        assert_eq!(forest.resolve(handle), Err(LctError::ForeignHandle(0)));
+        // 何も取り除かなければ番号はそのまま
+        let handle = forest.handle(1);
+        let remap = forest.gc();
+        assert_eq!(remap.iter().count(), 6);
+        assert_eq!(forest.resolve(handle), Ok(1));
+        forest.unpin(0);
+        forest.gc();
+        assert_eq!(forest.len(), 3);
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::LctError;
+
+static NEXT_FOREST_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub(super) fn new_forest_id() -> usize {
+    NEXT_FOREST_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// どの森の頂点かを覚えた頂点の番号
+/// 別の森に渡すと resolve が LctError::ForeignHandle を返す
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    forest: usize,
+    vertex: usize,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 頂点 v の Handle
+    pub fn handle(&self, v: usize) -> Handle {
+        assert!(v < self.len(), "vertex {} is out of range", v);
+        Handle { forest: self.id, vertex: v }
+    }
+
+    /// この森の Handle なら頂点の番号を返す
+    pub fn resolve(&self, handle: Handle) -> Result<usize, LctError> {
+        if handle.forest != self.id {
+            return Err(LctError::ForeignHandle(handle.vertex));
+        }
+        Ok(handle.vertex)
+    }
+
+    /// Handle で指定して、木の根 child の親を parent にする
+    pub fn link_handles(&mut self, child: Handle, parent: Handle) -> Result<(), LctError> {
+        let (child, parent) = (self.resolve(child)?, self.resolve(parent)?);
+        self.link(child, parent);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn foreign_handles() {
+        let mut a = Forest::<()>::new();
+        let mut b = Forest::<()>::new();
+        for _ in 0 .. 3 {
+            a.add_node(());
+            b.add_node(());
+        }
+        let (a0, a1) = (a.handle(0), a.handle(1));
+        let b2 = b.handle(2);
+        assert_eq!(a.resolve(a1), Ok(1));
+        assert_eq!(a.resolve(b2), Err(LctError::ForeignHandle(2)));
+        assert_eq!(b.link_handles(a1, b2), Err(LctError::ForeignHandle(1)));
+        assert_eq!(a.link_handles(a1, a0), Ok(()));
+        assert_eq!(a.parent(1), Some(0));
+        assert_eq!(b.parent(1), None);
+    }
+}
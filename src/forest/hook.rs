@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use super::{is_vertex, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::splay::SplayTree;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::EdgeId;
+
+/// 更新の hook に渡す節点（頂点ならその値、辺ならその重み）
+#[derive(Debug, PartialEq, Eq)]
+pub enum HookNode<'a, T, W> {
+    Vertex(usize, &'a T),
+    Edge(EdgeId, &'a W),
+}
+
+/// 節点と、splay 木の左右の子の要約から、節点の要約を求める
+pub(super) type UpdateHook<T, W> = Arc<dyn Fn(HookNode<'_, T, W>, Option<u64>, Option<u64>) -> u64 + Send + Sync>;
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// hook で節点 x の要約を求め直す（子の要約は求め終えていること）
+    pub(super) fn run_update_hook(&mut self, x: usize) {
+        let Some(hook) = &self.update_hook else { return };
+        if self.summaries.len() < self.nodes.len() {
+            self.summaries.resize(self.nodes.len(), 0);
+        }
+        let node = &self.nodes[x];
+        let [left, right] = node.children.map(|child| child.map(|child| self.summaries[child]));
+        let target = if is_vertex(x) { HookNode::Vertex(x >> 1, &node.value) } else { HookNode::Edge(self.edges[x >> 1].id, &node.weight) };
+        self.summaries[x] = hook(target, left, right);
+    }
+
+    /// すべての節点の要約を、splay 木の深い節点から順に求め直す
+    pub(super) fn refresh_summaries(&mut self) {
+        if self.update_hook.is_none() {
+            return;
+        }
+        let n = self.nodes.len();
+        let mut depth: Vec<Option<usize>> = vec![None; n];
+        for x in 0 .. n {
+            // 深さのわからない祖先を積み、上から深さを付ける
+            let (mut y, mut stack) = (x, vec![]);
+            let base = loop {
+                if let Some(d) = depth[y] {
+                    break d + 1;
+                }
+                stack.push(y);
+                if self.dir(y).is_none() {
+                    break 0;
+                }
+                y = self.nodes[y].parent.unwrap();
+            };
+            for (i, &z) in stack.iter().rev().enumerate() {
+                depth[z] = Some(base + i);
+            }
+        }
+        let mut live = vec![true; n];
+        for &e in &self.free_edges {
+            live[2 * e + 1] = false;
+        }
+        let mut order: Vec<usize> = (0 .. n).filter(|&x| live[x] && (is_vertex(x) || x >> 1 < self.edges.len())).collect();
+        order.sort_unstable_by_key(|&x| std::cmp::Reverse(depth[x]));
+        for x in order {
+            self.run_update_hook(x);
+        }
+    }
+
+    /// u から v へのパスの、hook で求めた要約（異なる木なら None）
+    pub fn path_summary(&mut self, u: usize, v: usize) -> Option<u64> {
+        self.update_hook.as_ref()?;
+        self.with_path(u, v, |forest, top| forest.summaries[top])
+    }
+
+    pub fn clear_update_hook(&mut self) {
+        self.update_hook = None;
+        self.summaries.clear();
+    }
+}
+
+/// 写像を遅延させると hook に渡す値が古くなるので、写像を持たない場合に限る
+impl<M: Monoid, E: Monoid, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, (), S>>> Forest<M, E, (), S, N> {
+    /// 節点を直すたびに hook を呼び、splay 木の部分木ごとの要約を u64 で持つ（path_summary で読む）
+    /// splay 木の左右は反転の遅延で入れ替わるので、hook は左右の子を入れ替えても同じ値を返すものに限る
+    pub fn set_update_hook(&mut self, hook: impl Fn(HookNode<'_, M::Value, E::Value>, Option<u64>, Option<u64>) -> u64 + Send + Sync + 'static) {
+        self.update_hook = Some(Arc::new(hook));
+        self.refresh_summaries();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::Naive;
+    use super::*;
+    use crate::monoid::Sum;
+    use crate::test_util::xorshift;
+
+    /// 下位 32 ビットに値の集合、上位 32 ビットに頂点の数を詰める
+    fn summarize(node: HookNode<'_, u64, ()>, left: Option<u64>, right: Option<u64>) -> u64 {
+        let own = match node {
+            HookNode::Vertex(_, &value) => (1 << 32) | (1 << (value % 32)),
+            HookNode::Edge(..) => 0,
+        };
+        let [left, right] = [left, right].map(|x| x.unwrap_or(0));
+        let count = (own >> 32) + (left >> 32) + (right >> 32);
+        (count << 32) | ((own | left | right) & 0xffff_ffff)
+    }
+
+    #[test]
+    fn path_summary_against_naive() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 30;
+        let mut forest = Forest::<Sum<u64>>::new();
+        let mut values = vec![];
+        let mut naive = Naive { parent: vec![None; n] };
+        for v in 0 .. n {
+            values.push(rand(100) as u64);
+            forest.add_node(values[v]);
+            if v > 0 && rand(3) > 0 {
+                naive.parent[v] = Some(rand(v));
+                forest.link(v, naive.parent[v].unwrap());
+            }
+        }
+        assert_eq!(forest.path_summary(0, 0), None);
+        // 森を作ってから hook を付けても要約を求め直す
+        forest.set_update_hook(summarize);
+        for _ in 0 .. 2000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 if naive.root(u) != naive.root(v) => {
+                    naive.evert(u);
+                    forest.evert(u);
+                    naive.parent[u] = Some(v);
+                    forest.link(u, v);
+                }
+                1 if naive.parent[u].is_some() => {
+                    naive.parent[u] = None;
+                    forest.cut(u);
+                }
+                2 => {
+                    values[u] = rand(100) as u64;
+                    forest.set_value(u, values[u]);
+                }
+                _ => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+            }
+            let (u, v) = (rand(n), rand(n));
+            let expected = naive.path(u, v).map(|path| {
+                let set = path.iter().fold(0, |set, &w| set | 1 << (values[w] % 32));
+                ((path.len() as u64) << 32) | set
+            });
+            assert_eq!(forest.path_summary(u, v), expected);
+        }
+        forest.compact();
+        assert_eq!(forest.path_summary(0, 0), Some((1 << 32) | 1 << (values[0] % 32)));
+        forest.clear_update_hook();
+        assert_eq!(forest.path_summary(0, 0), None);
+    }
+}
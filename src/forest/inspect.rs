@@ -0,0 +1,138 @@
+//! 実装の内部を覗く読み取り専用の操作
+//!
+//! どれも splay 木や優先パスの今の形を返すので、問い合わせのたびに変わりうる。
+//! 実装を調べるためのもので、結果は版によって変わりうる（安定した API ではない）。
+
+use super::{is_vertex, vertex_node, EdgeId, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::splay::SplayTree;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::Dir;
+
+/// splay 木の節点（頂点か辺）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SplayNode {
+    Vertex(usize),
+    Edge(EdgeId),
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    fn splay_node(&self, x: usize) -> SplayNode {
+        if is_vertex(x) { SplayNode::Vertex(x >> 1) } else { SplayNode::Edge(self.edges[x >> 1].id) }
+    }
+
+    /// 節点 x の反転の偶奇（splay 木の祖先に溜まった反転の排他的論理和）
+    pub(super) fn pending_flip(&self, mut x: usize) -> bool {
+        let mut flip = false;
+        while self.dir(x).is_some() {
+            x = self.nodes[x].parent.unwrap();
+            flip ^= self.nodes[x].rev;
+        }
+        flip
+    }
+
+    /// 節点 x の、優先パスの列で根から遠い側の隣の節点（木の構造は変えない）
+    fn next_in_path(&self, x: usize) -> Option<usize> {
+        self.neighbor_in_path(x, Dir::Right)
+    }
+
+    /// v と同じ優先パスにある v の子（なければ None）
+    pub fn preferred_child(&self, v: usize) -> Option<usize> {
+        let e = self.next_in_path(vertex_node(v))?;
+        self.next_in_path(e).map(|x| x >> 1)
+    }
+
+    /// v の節点の splay 木での親（splay 木の根なら None、優先パスの上への辺はたどらない）
+    pub fn splay_parent(&self, v: usize) -> Option<SplayNode> {
+        let x = vertex_node(v);
+        self.dir(x)?;
+        Some(self.splay_node(self.nodes[x].parent.unwrap()))
+    }
+
+    /// v を含む splay 木の根から、優先パスの上の節点への辺の先（木の根を含むなら None）
+    pub fn path_parent(&self, v: usize) -> Option<SplayNode> {
+        let top = self.splay_root(vertex_node(v));
+        self.nodes[top].parent.map(|x| self.splay_node(x))
+    }
+
+    /// 隣接する u と v を結ぶ辺が軽い辺（優先パスに含まれない辺）か（隣接しなければ None）
+    pub fn is_light_edge(&self, u: usize, v: usize) -> Option<bool> {
+        self.edge(u, v)?;
+        Some(self.splay_root(vertex_node(u)) != self.splay_root(vertex_node(v)))
+    }
+
+    /// v の節点の splay 木での深さ（splay 木の根なら 0）
+    pub fn splay_depth(&self, v: usize) -> usize {
+        let mut x = vertex_node(v);
+        let mut depth = 0;
+        while self.dir(x).is_some() {
+            x = self.nodes[x].parent.unwrap();
+            depth += 1;
+        }
+        depth
+    }
+
+    /// v の節点の補助木（splay 木を優先パスの上への辺でつないだ木）での深さ
+    pub fn aux_depth(&self, v: usize) -> usize {
+        let mut x = vertex_node(v);
+        let mut depth = 0;
+        while let Some(parent) = self.nodes[x].parent {
+            x = parent;
+            depth += 1;
+        }
+        depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn inspectors_match_preferred_paths() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 30;
+        let mut forest = Forest::<Sum<i64>>::new();
+        for v in 0 .. n {
+            forest.add_node(v as i64);
+            if v > 0 {
+                forest.link(v, rand(v));
+            }
+        }
+        for _ in 0 .. 300 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(3) {
+                0 => forest.evert(u),
+                1 => {
+                    forest.path_query(u, v);
+                }
+                _ => {
+                    forest.root(u);
+                }
+            }
+            for w in 0 .. n {
+                let path = forest.preferred_path(w);
+                let i = path.iter().position(|&x| x == w).unwrap();
+                assert_eq!(forest.preferred_child(w), path.get(i + 1).copied());
+                assert!(forest.splay_depth(w) <= forest.aux_depth(w));
+                assert_eq!(forest.splay_parent(w).is_none(), forest.splay_depth(w) == 0);
+                for x in forest.neighbors(w).collect::<Vec<_>>() {
+                    let heavy = path.contains(&x);
+                    assert_eq!(forest.is_light_edge(w, x), Some(!heavy));
+                }
+            }
+        }
+        let (a, b) = (0, forest.neighbors(0).next().unwrap());
+        assert!(forest.is_light_edge(a, b).is_some());
+        let far = (0 .. n).find(|&x| x != 0 && !forest.neighbors(0).any(|y| y == x)).unwrap();
+        assert_eq!(forest.is_light_edge(0, far), None);
+        forest.evert(5);
+        assert_eq!(forest.splay_depth(5), 0);
+        assert_eq!(forest.splay_parent(5), None);
+        assert_eq!(forest.path_parent(5), None);
+    }
+}
@@ -0,0 +1,102 @@
+use super::{is_vertex, vertex_node, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::{Count, Monoid};
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+/// 頂点の値を印（0 か 1）として、印の付いた祖先を探す
+impl<E: Monoid, A: Action<Count, E>, S: Subtree<Count, E>, N: NodeStore<ForestNodeOf<Count, E, A, S>>> Forest<Count, E, A, S, N> {
+    pub fn set_marked(&mut self, v: usize, marked: bool) {
+        self.set_value(v, marked as usize);
+    }
+
+    pub fn is_marked(&mut self, v: usize) -> bool {
+        *self.value(v) > 0
+    }
+
+    /// v の祖先（v 自身も含む）のうち、印の付いた最も深い頂点
+    pub fn nearest_marked_ancestor(&mut self, v: usize) -> Option<usize> {
+        let x = vertex_node(v);
+        self.expose(x);
+        let total = self.nodes[x].sum;
+        if total == 0 {
+            return None;
+        }
+        // 根からの印の数が total に届く最初の頂点
+        let y = self.search(x, Count::op, 0, |node| &node.sum, |node| &node.value, |&c| c < total).unwrap();
+        debug_assert!(is_vertex(y));
+        Some(y >> 1)
+    }
+
+    /// u から v へのパス上で u に最も近い印の付いた頂点
+    pub fn nearest_marked_on_path(&mut self, u: usize, v: usize) -> Option<usize> {
+        self.with_path(u, v, |forest, top| forest.search(top, Count::op, 0, |node| &node.sum, |node| &node.value, |&c| c == 0))
+            .flatten()
+            .map(|y| y >> 1)
+    }
+
+    /// v の祖先（v 自身も含む）のうち、印の付いた最も浅い頂点
+    pub fn farthest_marked_ancestor(&mut self, v: usize) -> Option<usize> {
+        let x = vertex_node(v);
+        self.expose(x);
+        let y = self.search(x, Count::op, 0, |node| &node.sum, |node| &node.value, |&c| c == 0)?;
+        Some(y >> 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::Naive;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn marked_ancestors_against_naive() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 40;
+        let mut forest = Forest::<Count>::new();
+        let mut naive = Naive { parent: vec![None; n] };
+        let mut marked = vec![false; n];
+        for v in 0 .. n {
+            forest.add_node(0);
+            if v > 0 && rand(4) > 0 {
+                let p = rand(v);
+                forest.link(v, p);
+                naive.parent[v] = Some(p);
+            }
+        }
+        for _ in 0 .. 2000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(5) {
+                0 => {
+                    marked[u] ^= true;
+                    forest.set_marked(u, marked[u]);
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        forest.cut(u);
+                        naive.parent[u] = None;
+                    } else if naive.root(v) != u {
+                        forest.link(u, v);
+                        naive.parent[u] = Some(v);
+                    }
+                }
+                2 => {
+                    forest.evert(u);
+                    naive.evert(u);
+                }
+                _ => {
+                    let mut ancestors = vec![u];
+                    while let Some(p) = naive.parent[*ancestors.last().unwrap()] {
+                        ancestors.push(p);
+                    }
+                    assert_eq!(forest.nearest_marked_ancestor(u), ancestors.iter().copied().find(|&w| marked[w]));
+                    assert_eq!(forest.farthest_marked_ancestor(u), ancestors.iter().copied().rev().find(|&w| marked[w]));
+                    assert_eq!(forest.is_marked(u), marked[u]);
+                    let path = naive.path(u, v);
+                    assert_eq!(forest.nearest_marked_on_path(u, v), path.and_then(|path| path.into_iter().find(|&w| marked[w])));
+                }
+            }
+        }
+    }
+}
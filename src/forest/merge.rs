@@ -0,0 +1,285 @@
+use super::{is_vertex, Edge, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::IdRemap;
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// other の頂点と辺をすべてこの森に移す
+    /// 木の形と根、辺の重み、pin、次数の上限はそのまま保たれる（辺の番号は振り直す）
+    /// 超根はこの森にあればこの森のものを使い、other の超根はただの頂点になる
+    pub fn absorb(&mut self, other: Self) -> IdRemap {
+        let (vertices, edges) = (self.len(), self.edges.len());
+        let shift = |x: usize| if is_vertex(x) { x + 2 * vertices } else { x + 2 * edges };
+        let len = 2 * (vertices + other.len());
+        while self.nodes.len() < len {
+            self.nodes.alloc(Self::new_node(M::identity(), E::identity(), 0));
+        }
+        let used_edges = other.edges.len();
+        let mut other = other;
+        for x in 0 .. other.nodes.len() {
+            if !is_vertex(x) && x >> 1 >= used_edges {
+                continue;
+            }
+            let mut node = std::mem::replace(&mut other.nodes[x], Self::new_node(M::identity(), E::identity(), 0));
+            node.parent = node.parent.map(shift);
+            node.children = node.children.map(|child| child.map(shift));
+            self.nodes[shift(x)] = node;
+        }
+        self.edges.extend(other.edges.into_iter().map(|edge| Edge { ends: edge.ends.map(|v| v + vertices), ..edge }));
+        // 移した辺には新しい番号を振る
+        let other_free: std::collections::HashSet<usize> = other.free_edges.iter().copied().collect();
+        for e in (0 .. used_edges).filter(|e| !other_free.contains(e)) {
+            self.edges[e + edges].id = self.new_edge_id(e + edges);
+        }
+        self.free_edges.extend(other.free_edges.into_iter().map(|e| e + edges));
+        self.adjacency.extend(other.adjacency.into_iter().map(|list| list.into_iter().map(|e| e + edges).collect()));
+        self.dirty.extend(other.dirty.into_iter().map(shift));
+        self.component_data.extend(other.component_data.into_iter().map(|(v, data)| (v + vertices, data)));
+        self.pins.extend(other.pins.into_iter().map(|(v, count)| (v + vertices, count)));
+        self.degree_limits.extend(other.degree_limits.into_iter().map(|(v, limit)| (v + vertices, limit)));
+        self.super_root = self.super_root.or(other.super_root.map(|s| s + vertices));
+        // 移した節点には軽い子に遅延させた部分木への写像が残っていることがある
+        self.subtree_ops = self.subtree_ops.or(other.subtree_ops);
+        self.refresh_summaries();
+        IdRemap::new((0 .. self.len() - vertices).map(|v| (v, v + vertices)).collect())
+    }
+
+    /// 切った辺が残した穴を詰め、使っている辺を添字の小さい方へ寄せる
+    /// 頂点の番号も EdgeId も変わらないので、それまでの番号をそのまま使える
+    pub fn compact(&mut self) {
+        if !self.pending.is_empty() {
+            self.flush_batch();
+        }
+        if !self.dirty.is_empty() {
+            self.flush();
+        }
+        let mut free = vec![false; self.edges.len()];
+        for &e in &self.free_edges {
+            free[e] = true;
+        }
+        // 古い辺の添字から新しい辺の添字
+        let mut new_edge = vec![usize::MAX; self.edges.len()];
+        let live: Vec<usize> = (0 .. self.edges.len()).filter(|&e| !free[e]).collect();
+        for (i, &e) in live.iter().enumerate() {
+            new_edge[e] = i;
+        }
+        let map = |x: usize| if is_vertex(x) { x } else { 2 * new_edge[x >> 1] + 1 };
+        for (i, &e) in live.iter().enumerate() {
+            if i != e {
+                // 2i + 1 にあった節点は使っていない辺のもの（単位元）なので上書きしてよい
+                let node = std::mem::replace(&mut self.nodes[2 * e + 1], Self::new_node(M::identity(), E::identity(), 0));
+                self.nodes[2 * i + 1] = node;
+            }
+        }
+        for x in 0 .. self.nodes.len() {
+            let node = &mut self.nodes[x];
+            node.parent = node.parent.map(map);
+            node.children = node.children.map(|child| child.map(map));
+        }
+        self.edges = live.iter().map(|&e| std::mem::take(&mut self.edges[e])).collect();
+        self.free_edges.clear();
+        for list in &mut self.adjacency {
+            for e in list.iter_mut() {
+                *e = new_edge[*e];
+            }
+            list.shrink_to_fit();
+        }
+        for e in self.edge_ids.values_mut() {
+            *e = new_edge[*e];
+        }
+        self.edges.shrink_to_fit();
+        self.free_edges.shrink_to_fit();
+        self.refresh_summaries();
+    }
+
+    /// 木ごとに別の森へ分ける
+    /// 各森では元の番号の順に頂点を並べ、根と辺の重みと多重度、pin、次数の上限、木に付けた値を保つ
+    /// 超根はそれを含む木の森に移り、更新の hook はすべての森で共有する
+    pub fn split_components(mut self) -> Vec<(Self, IdRemap)>
+    where
+        N: Default,
+    {
+        let n = self.len();
+        let mut new_id = vec![None; n];
+        let mut result = vec![];
+        for v in 0 .. n {
+            if new_id[v].is_some() {
+                continue;
+            }
+            let mut vertices: Vec<usize> = self.bfs(v).into_iter().map(|(x, _)| x).collect();
+            vertices.sort_unstable();
+            let mut forest = Forest { ordered: self.ordered, sorted: self.sorted, update_hook: self.update_hook.clone(), ..Forest::default() };
+            for &x in &vertices {
+                new_id[x] = Some(forest.add_node(self.value(x).clone()));
+            }
+            for (parent, child, weight) in self.tree_edges(v) {
+                let (c, p) = (new_id[child].unwrap(), new_id[parent].unwrap());
+                forest.link_weighted(c, p, weight);
+                let (old, new) = (self.edge_slot(child, parent).unwrap(), forest.edge_slot(c, p).unwrap());
+                forest.edges[new].copies = self.edges[old].copies;
+            }
+            let root = self.root(v);
+            if let Some(&data) = self.component_data.get(&root) {
+                forest.component_data.insert(new_id[root].unwrap(), data);
+            }
+            for &x in &vertices {
+                if let Some(&count) = self.pins.get(&x) {
+                    forest.pins.insert(new_id[x].unwrap(), count);
+                }
+                if let Some(&limit) = self.degree_limits.get(&x) {
+                    forest.degree_limits.insert(new_id[x].unwrap(), limit);
+                }
+            }
+            forest.super_root = self.super_root.filter(|&s| vertices.binary_search(&s).is_ok()).and_then(|s| new_id[s]);
+            let pairs = vertices.iter().map(|&x| (x, new_id[x].unwrap())).collect();
+            result.push((forest, IdRemap::new(pairs)));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HookNode;
+    use crate::monoid::Sum;
+    use crate::LctError;
+
+    #[test]
+    fn absorb() {
+        let mut a = Forest::<Sum<i64>, Sum<u64>>::new();
+        for i in 0 .. 3 {
+            a.add_node(i);
+        }
+        a.link_weighted(1, 0, 1);
+        a.link_weighted(2, 0, 2);
+        a.cut(1);
+        let mut b = Forest::<Sum<i64>, Sum<u64>>::new();
+        for i in 0 .. 4 {
+            b.add_node(10 + i);
+        }
+        b.link_weighted(1, 0, 3);
+        b.link_weighted(2, 1, 4);
+        b.link_weighted(3, 1, 5);
+        b.evert(3);
+        b[2] = 100;
+        b.set_degree_limit(0, Some(1));
+        b.pin(2);
+
+        let remap = a.absorb(b);
+        assert_eq!(a.len(), 7);
+        assert_eq!(remap.get(0), Some(3));
+        assert_eq!(remap.get(4), None);
+        assert_eq!(remap.iter().collect::<Vec<_>>(), vec![(0, 3), (1, 4), (2, 5), (3, 6)]);
+        assert_eq!(a.roots(), vec![0, 1, 6]);
+        assert_eq!(a.path_query(3, 5), Some(10 + 11 + 100));
+        assert_eq!(a.jump_weighted(6, 3, &7), Some(4));
+        assert_eq!(a.jump_weighted(6, 3, &8), Some(3));
+        assert_eq!(a.path_query(2, 0), Some(2));
+        assert_eq!(a.degree_limit(3), Some(1));
+        assert_eq!(a.connect(1, 3), Err(LctError::DegreeLimitExceeded(3)));
+        assert_eq!(a.pin_count(5), 1);
+        a.link_weighted(1, 5, 6);
+        assert_eq!(a.tree_edges(1), vec![(6, 4, 5), (4, 3, 3), (4, 5, 4), (5, 1, 6)]);
+    }
+
+    #[test]
+    fn absorb_super_root() {
+        let mut a = Forest::<Sum<i64>>::new();
+        a.add_node(1);
+        let other = || {
+            let mut b = Forest::<Sum<i64>>::new();
+            b.add_node(2);
+            b.attach_to_super_root(0);
+            b
+        };
+        // 超根は other のものを引き継ぐ
+        a.absorb(other());
+        assert!(a.is_super_root(2));
+        // どちらにもあればこの森のものを使う
+        a.absorb(other());
+        assert!(a.is_super_root(2));
+        assert!(!a.is_super_root(4));
+        assert_eq!(a.detach_from_super_root(), vec![1]);
+        assert!(a.connected(3, 4));
+    }
+
+    #[test]
+    fn compact() {
+        let mut forest = Forest::<Sum<i64>, Sum<u64>>::new();
+        for i in 0 .. 8 {
+            forest.add_node(i);
+        }
+        for v in 1 .. 8 {
+            forest.link_weighted(v, v - 1, v as u64);
+        }
+        forest.cut(2);
+        forest.cut(5);
+        forest.cut(1);
+        forest.evert(4);
+        forest[6] = 60;
+        let id = forest.edge(6, 7).unwrap();
+        forest.compact();
+        assert_eq!(forest.edges.len(), forest.edge_count());
+        assert!(forest.free_edges.is_empty());
+        assert_eq!(forest.edge(6, 7), Some(id));
+        assert_eq!(forest.endpoints(id), Some((7, 6)));
+        assert_eq!(forest.roots(), vec![0, 1, 4, 5]);
+        assert_eq!(forest.path_query(2, 4), Some(2 + 3 + 4));
+        assert_eq!(forest.path_query_edges(5, 7), Some(6 + 7));
+        assert_eq!(forest.path_query(5, 7), Some(5 + 60 + 7));
+        assert_eq!(forest.tree_edges(3), vec![(4, 3, 4), (3, 2, 3)]);
+        forest.link_weighted(5, 3, 9);
+        forest.link_weighted(1, 0, 10);
+        assert_eq!(forest.path_query_edges(2, 7), Some(3 + 9 + 6 + 7));
+        forest.cut(7);
+        assert_eq!(forest.path_query(4, 6), Some(4 + 3 + 5 + 60));
+    }
+
+    #[test]
+    fn split_components() {
+        let mut forest = Forest::<Sum<i64>, Sum<u64>>::new();
+        for i in 0 .. 6 {
+            forest.add_node(i);
+        }
+        forest.link_weighted(4, 1, 7);
+        forest.link_weighted(5, 4, 8);
+        forest.link_weighted(0, 2, 9);
+        forest.evert(5);
+        forest.add_multi_edge(4, 5).unwrap();
+        forest.pin(4);
+        forest.pin(4);
+        forest.set_degree_limit(1, Some(1));
+        // 3 を超根 6 の下に入れる
+        forest.attach_to_super_root(3);
+        // パス上の頂点の数を数える
+        forest.set_update_hook(|node, left, right| {
+            let own = matches!(node, HookNode::Vertex(..)) as u64;
+            own + left.unwrap_or(0) + right.unwrap_or(0)
+        });
+        let parts = forest.split_components();
+        assert_eq!(parts.len(), 3);
+        let mut parts = parts.into_iter();
+        let (mut first, _) = parts.next().unwrap();
+        assert_eq!(first.super_root, None);
+        let (mut second, remap) = parts.next().unwrap();
+        assert_eq!(remap.iter().collect::<Vec<_>>(), vec![(1, 0), (4, 1), (5, 2)]);
+        assert_eq!(second.roots(), vec![2]);
+        assert_eq!(second.tree_edges(0), vec![(2, 1, 8), (1, 0, 7)]);
+        assert_eq!(second.path_query(0, 2), Some(10));
+        assert_eq!(second.multiplicity(1, 2), 2);
+        assert_eq!(second.pin_count(1), 2);
+        assert_eq!(second.degree_limit(0), Some(1));
+        let w = second.add_node(0);
+        assert_eq!(second.connect(w, 0), Err(LctError::DegreeLimitExceeded(0)));
+        assert_eq!(second.path_summary(0, 2), Some(3));
+        assert_eq!(first.path_summary(0, 1), Some(2));
+        let (mut third, remap) = parts.next().unwrap();
+        assert_eq!(remap.iter().collect::<Vec<_>>(), vec![(3, 0), (6, 1)]);
+        assert!(third.is_super_root(1));
+        assert_eq!(third.super_root(), 1);
+        assert_eq!(third.detach_from_super_root(), vec![0]);
+    }
+}
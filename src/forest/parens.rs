@@ -0,0 +1,165 @@
+use super::{edge_node, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+/// 根付き木を括弧列と値の列で表したもの（簡潔表現）
+/// 根から行きがけ順にたどり、頂点に入るときに 1、出るときに 0 を書く（n 頂点で 2n ビット）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parentheses<T, W> {
+    /// 括弧列を 64 ビットごとに下位のビットから詰めたもの
+    pub bits: Vec<u64>,
+    /// 行きがけ順の頂点の値
+    pub values: Vec<T>,
+    /// 行きがけ順に、根以外の頂点と親を結ぶ辺の重み
+    pub weights: Vec<W>,
+}
+
+impl<T, W> Parentheses<T, W> {
+    /// 頂点の数
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// i 番目の括弧が開き括弧か
+    pub fn bit(&self, i: usize) -> bool {
+        self.bits[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    /// 括弧が釣り合い、一つの木をなし、値と重みの数が合っているか
+    fn is_valid(&self) -> bool {
+        let n = self.len();
+        if n == 0 || self.bits.len() < (2 * n).div_ceil(64) || self.weights.len() != n - 1 {
+            return false;
+        }
+        let mut depth = 0usize;
+        for i in 0 .. 2 * n {
+            if self.bit(i) {
+                depth += 1;
+            } else if depth == 0 {
+                return false;
+            } else {
+                depth -= 1;
+            }
+            // 根を閉じるのは最後の括弧だけ
+            if depth == 0 && i + 1 < 2 * n {
+                return false;
+            }
+        }
+        depth == 0
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// v を含む木を、現在の根から子を隣接リストの順にたどって括弧列で書き出す
+    pub fn to_parentheses(&mut self, v: usize) -> Parentheses<M::Value, E::Value> {
+        let root = self.root(v);
+        let mut bits = vec![];
+        let mut len = 0;
+        let mut push = |open: bool| {
+            if len % 64 == 0 {
+                bits.push(0);
+            }
+            *bits.last_mut().unwrap() |= (open as u64) << (len % 64);
+            len += 1;
+        };
+        let mut order = vec![];
+        // (頂点, たどってきた辺)、None は直前に入った頂点から出ることを表す
+        let mut stack = vec![Some((root, None))];
+        while let Some(item) = stack.pop() {
+            let Some((x, from)) = item else {
+                push(false);
+                continue;
+            };
+            push(true);
+            order.push((x, from));
+            stack.push(None);
+            for &e in self.adjacency[x].iter().rev() {
+                if Some(e) != from {
+                    stack.push(Some((self.opposite(e, x), Some(e))));
+                }
+            }
+        }
+        let values = order.iter().map(|&(x, _)| self.value(x).clone()).collect();
+        let weights = order.iter().filter_map(|&(_, from)| Some(self.weight(edge_node(from?)).clone())).collect();
+        Parentheses { bits, values, weights }
+    }
+
+    /// 括弧列で表した木を新しい頂点として行きがけ順に追加し、その根を返す
+    /// 括弧が釣り合わないか、値や重みの数が合わなければ何もせずに None
+    pub fn add_parentheses(&mut self, tree: &Parentheses<M::Value, E::Value>) -> Option<usize> {
+        if !tree.is_valid() {
+            return None;
+        }
+        let root = self.len();
+        let mut path = vec![];
+        let (mut values, mut weights) = (tree.values.iter(), tree.weights.iter());
+        for i in 0 .. 2 * tree.len() {
+            if !tree.bit(i) {
+                path.pop();
+                continue;
+            }
+            let v = self.add_node(values.next().unwrap().clone());
+            if let Some(&parent) = path.last() {
+                self.link_weighted(v, parent, weights.next().unwrap().clone());
+            }
+            path.push(v);
+        }
+        Some(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn parentheses_round_trip() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 100;
+        let mut forest = Forest::<Sum<i64>, Sum<i64>>::new();
+        for v in 0 .. n {
+            forest.add_node(v as i64);
+            if v > 0 && rand(10) > 0 {
+                forest.link_weighted(v, rand(v), rand(50) as i64);
+            }
+        }
+        forest.evert(rand(n));
+        for v in 0 .. n {
+            let tree = forest.to_parentheses(v);
+            assert_eq!(tree.bits.len(), (2 * tree.len()).div_ceil(64));
+            let mut copy = Forest::<Sum<i64>, Sum<i64>>::new();
+            assert_eq!(copy.add_parentheses(&tree), Some(0));
+            let root = forest.root(v);
+            assert_eq!(copy.len(), forest.subtree_iter(root).count());
+            assert_eq!(copy.to_parentheses(0), tree);
+            let order: Vec<usize> = forest.preorder_iter(v).collect();
+            for (i, &x) in order.iter().enumerate() {
+                assert_eq!(copy.value(i), forest.value(x));
+            }
+        }
+        // 0 - 1, 0 - 2 - 3
+        let tree = Parentheses { bits: vec![0b0001_1011], values: vec![1, 2, 3, 4], weights: vec![5, 6, 7] };
+        let mut copy = Forest::<Sum<i64>, Sum<i64>>::new();
+        copy.add_node(0);
+        assert_eq!(copy.add_parentheses(&tree), Some(1));
+        assert_eq!(copy.to_parents(), vec![None, None, Some(1), Some(1), Some(3)]);
+        assert_eq!(copy.path_query_edges(2, 4), Some(18));
+        for bad in [
+            Parentheses { bits: vec![0b10], values: vec![1], weights: vec![] },
+            Parentheses { bits: vec![0b0101], values: vec![1, 2], weights: vec![3] },
+            Parentheses { bits: vec![0b0011], values: vec![1, 2], weights: vec![] },
+            Parentheses { bits: vec![], values: vec![], weights: vec![] },
+        ] {
+            assert_eq!(copy.add_parentheses(&bad), None);
+        }
+        assert_eq!(copy.len(), 5);
+    }
+}
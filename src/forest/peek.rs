@@ -0,0 +1,126 @@
+//! 森を組み替えずに &self で答える問い合わせ
+//!
+//! splay も優先パスの付け替えもしないので、複数のスレッドから同時に呼べる。
+//! その代わり splay 木の今の深さに比例する時間がかかり、最悪で O(n) になる。
+//! パスの集約など、ここにない問い合わせを &self で行うには freeze で Frozen を作る。
+
+use super::{vertex_node, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::splay::SplayTree;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::Dir;
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 節点 x の、優先パスの列で dir の側の隣の節点（木の構造は変えない）
+    pub(super) fn neighbor_in_path(&self, x: usize, dir: Dir) -> Option<usize> {
+        let flip = self.pending_flip(x);
+        if let Some(mut y) = self.child(x, if flip { dir.flip() } else { dir }) {
+            let mut flip = flip ^ self.nodes[x].rev;
+            loop {
+                let Some(child) = self.child(y, if flip { dir } else { dir.flip() }) else { return Some(y) };
+                flip ^= self.nodes[y].rev;
+                y = child;
+            }
+        }
+        // 反対側の子として上がれるところまで上る
+        let (mut y, mut flip) = (x, flip);
+        while let Some(side) = self.dir(y) {
+            let parent = self.nodes[y].parent.unwrap();
+            flip ^= self.nodes[parent].rev;
+            if side == if flip { dir } else { dir.flip() } {
+                return Some(parent);
+            }
+            y = parent;
+        }
+        None
+    }
+
+    /// 節点 x の、木の根の側の隣の節点（優先パスの先頭なら、そのパスがぶら下がる節点）
+    fn above(&self, x: usize) -> Option<usize> {
+        self.neighbor_in_path(x, Dir::Left).or_else(|| self.nodes[self.splay_root(x)].parent)
+    }
+
+    /// v を含む木の根（木の構造は変えない）
+    pub fn peek_root(&self, v: usize) -> usize {
+        // 補助木の根の splay 木が木の根を含む優先パスで、その先頭が根
+        let mut x = vertex_node(v);
+        while let Some(parent) = self.nodes[x].parent {
+            x = parent;
+        }
+        let mut flip = false;
+        while let Some(child) = self.child(x, if flip { Dir::Right } else { Dir::Left }) {
+            flip ^= self.nodes[x].rev;
+            x = child;
+        }
+        x >> 1
+    }
+
+    /// 現在の根に関する v の親（木の構造は変えない）
+    pub fn peek_parent(&self, v: usize) -> Option<usize> {
+        let e = self.above(vertex_node(v))?;
+        self.above(e).map(|x| x >> 1)
+    }
+
+    /// u と v が同じ木に属するか（木の構造は変えない）
+    pub fn peek_connected(&self, u: usize, v: usize) -> bool {
+        u == v || self.peek_root(u) == self.peek_root(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::Naive;
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn peek_against_naive() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 30;
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(5) {
+                0 if naive.root(u) != naive.root(v) => {
+                    naive.evert(u);
+                    forest.evert(u);
+                    naive.parent[u] = Some(v);
+                    forest.link(u, v);
+                }
+                1 if naive.parent[u].is_some() => {
+                    naive.parent[u] = None;
+                    forest.cut(u);
+                }
+                2 => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+                3 => {
+                    // 問い合わせで優先パスを組み替える
+                    forest.path_query(u, v);
+                }
+                _ => {}
+            }
+            for w in 0 .. n {
+                assert_eq!(forest.peek_root(w), naive.root(w));
+                assert_eq!(forest.peek_parent(w), naive.parent[w]);
+            }
+            assert_eq!(forest.peek_connected(u, v), naive.root(u) == naive.root(v));
+        }
+        // &self だけで読むので、複数のスレッドから同時に問い合わせられる
+        let forest = &forest;
+        let roots: Vec<Vec<usize>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0 .. 4).map(|_| scope.spawn(move || (0 .. n).map(|w| forest.peek_root(w)).collect())).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        for root in roots {
+            assert_eq!(root, (0 .. n).map(|w| naive.root(w)).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,1419 @@
+use std::ops::{Add, BitXor, Sub};
+
+use super::{edge_node, is_vertex, vertex_node, Forest, ForestNodeOf, SubtreeOps};
+use crate::action::{Action, AssignAdd, AssignAddMap};
+use crate::monoid::{Bounded, First, ModSum, Modulus, Monoid, Sum};
+use crate::splay::SplayTree;
+use crate::store::NodeStore;
+use crate::subtree::{DepthParity, Distances, Dp, ExprOp, Expression, Farthest, FarthestSum, Height, Leaves, NearestMarked, Subtree, SubtreeMax, SubtreeMin, SubtreeAction, SubtreeSum, SubtreeXor, TreeDp, TreeHash};
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 現在の根に関する v の部分木の集約
+    pub fn subtree_query(&mut self, v: usize) -> S::Sum {
+        let x = vertex_node(v);
+        // expose すると v の子はすべて軽い子になる
+        self.expose(x);
+        let node = &self.nodes[x];
+        S::vertex(&node.value, &node.virt)
+    }
+
+    /// v を含む木全体の集約（根によらない）
+    pub fn component_query(&mut self, v: usize) -> S::Sum {
+        let x = vertex_node(v);
+        // expose すると x の splay 木が根からの列になり、木全体がそこに垂れ下がる
+        self.expose(x);
+        self.nodes[x].total.clone()
+    }
+
+    /// 現在の根に関する v の部分木の頂点の値と辺の重みに f を作用させる
+    /// 軽い子の部分木には遅延させるので、部分木の大きさによらず償却 O(log n)
+    pub fn subtree_apply(&mut self, v: usize, f: A::Map)
+    where
+        A: SubtreeAction<M, E, S>,
+    {
+        self.subtree_ops.get_or_insert(SubtreeOps { act_virtual: A::act_virtual, act_subtree: A::act_subtree, since: A::since });
+        let x = vertex_node(v);
+        // expose すると v の子はすべて軽い子になり、v の splay 木の左には v より上の頂点だけが残る
+        self.expose(x);
+        let node = &mut self.nodes[x];
+        node.value = A::act(&f, &node.value, 1).expect("an action on a single vertex must succeed");
+        node.virt = A::act_virtual(&f, &node.virt, node.virt_len);
+        node.light = Some(match &node.light {
+            Some(g) => A::compose(&f, g),
+            None => f,
+        });
+        self.update(x);
+    }
+
+    /// 現在の根に関する v の部分木で、値が x 以上の頂点の数
+    /// 値の順序は部分木の集約にまとめられないので、部分木の頂点ごとに調べる（部分木の大きさを k として O(k log n)）
+    /// 形を変えずに多くの問い合わせに答えるなら subtree_counts_ge でまとめる
+    pub fn subtree_count_ge(&mut self, v: usize, x: &M::Value) -> usize
+    where
+        M::Value: PartialOrd,
+    {
+        let vertices: Vec<usize> = self.subtree_iter(v).collect();
+        vertices.into_iter().filter(|&w| self.value(w) >= x).count()
+    }
+
+    /// queries の (v, x) ごとに subtree_count_ge(v, x) を求め、同じ順に返す（森の形は変えない）
+    /// 森を行きがけ順に並べ、値と x の大きい順に Fenwick 木へ足しながら数えるので、全体で O((n + q) log n)
+    pub fn subtree_counts_ge(&mut self, queries: &[(usize, M::Value)]) -> Vec<usize>
+    where
+        M::Value: Ord,
+    {
+        let n = self.len();
+        // 現在の根に関する部分木は、行きがけ順の区間 [tin, tout) になる
+        let (mut tin, mut tout) = (vec![0; n], vec![0; n]);
+        let mut time = 0;
+        for r in self.roots() {
+            let mut stack = vec![(r, None, false)];
+            while let Some((x, from, done)) = stack.pop() {
+                if done {
+                    tout[x] = time;
+                    continue;
+                }
+                tin[x] = time;
+                time += 1;
+                stack.push((x, from, true));
+                for &e in &self.adjacency[x] {
+                    if Some(e) != from {
+                        stack.push((self.opposite(e, x), Some(e), false));
+                    }
+                }
+            }
+        }
+        let values: Vec<M::Value> = (0 .. n).map(|v| self.value(v).clone()).collect();
+        let mut vertices: Vec<usize> = (0 .. n).collect();
+        vertices.sort_unstable_by(|&a, &b| values[b].cmp(&values[a]));
+        let mut order: Vec<usize> = (0 .. queries.len()).collect();
+        order.sort_unstable_by(|&i, &j| queries[j].1.cmp(&queries[i].1));
+        let mut fenwick = vec![0; n + 1];
+        let prefix = |fenwick: &[usize], mut i: usize| {
+            let mut count = 0;
+            while i > 0 {
+                count += fenwick[i];
+                i &= i - 1;
+            }
+            count
+        };
+        let mut answers = vec![0; queries.len()];
+        let mut added = 0;
+        for i in order {
+            let (v, x) = (queries[i].0, &queries[i].1);
+            while added < n && values[vertices[added]] >= *x {
+                let mut j = tin[vertices[added]] + 1;
+                while j <= n {
+                    fenwick[j] += 1;
+                    j += j & j.wrapping_neg();
+                }
+                added += 1;
+            }
+            answers[i] = prefix(&fenwick, tout[v]) - prefix(&fenwick, tin[v]);
+        }
+        answers
+    }
+}
+
+impl<P: Modulus, const BASE: u64, E: Monoid, A: Action<ModSum<P>, E>, N: NodeStore<ForestNodeOf<ModSum<P>, E, A, TreeHash<P, BASE>>>> Forest<ModSum<P>, E, A, TreeHash<P, BASE>, N> {
+    /// 現在の根に関する v の部分木のハッシュ
+    pub fn subtree_hash(&mut self, v: usize) -> u64 {
+        self.subtree_query(v).0
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, N: NodeStore<ForestNodeOf<M, E, A, Height>>> Forest<M, E, A, Height, N> {
+    /// v を含む木の、現在の根からの深さの最大値
+    pub fn component_height(&mut self, v: usize) -> usize {
+        self.component_query(v).top as usize
+    }
+
+    /// v を含む木で、根からの深さが d の頂点を nodes_at_depth と同じ順に返す
+    /// 部分木の高さを見て深さ d に届かない枝には降りないので、降りた頂点の子ごとに O(log n) しかかからない
+    pub fn nodes_at_depth_pruned(&mut self, v: usize, d: usize) -> Vec<usize> {
+        let root = self.root(v);
+        let mut result = vec![];
+        let mut stack = vec![(root, None, 0)];
+        while let Some((x, from, depth)) = stack.pop() {
+            if depth == d {
+                result.push(x);
+                continue;
+            }
+            for i in (0 .. self.adjacency[x].len()).rev() {
+                let e = self.adjacency[x][i];
+                if Some(e) == from {
+                    continue;
+                }
+                let y = self.opposite(e, x);
+                if self.subtree_query(y).top >= (d - depth - 1) as i64 {
+                    stack.push((y, Some(e), depth + 1));
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, N: NodeStore<ForestNodeOf<M, E, A, DepthParity>>> Forest<M, E, A, DepthParity, N> {
+    /// v を含む木の、現在の根からの深さが (偶数, 奇数) の頂点の数
+    pub fn depth_parity_counts(&mut self, v: usize) -> (usize, usize) {
+        let [even, odd] = self.component_query(v).top;
+        (even, odd)
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, N: NodeStore<ForestNodeOf<M, E, A, Leaves>>> Forest<M, E, A, Leaves, N> {
+    /// v を含む木の、現在の根に関する葉の数（孤立点は葉一つと数える）
+    pub fn component_leaves(&mut self, v: usize) -> usize {
+        self.component_query(v).leaves
+    }
+}
+
+impl<T: Clone + Default + Add<Output = T> + Sub<Output = T>, M: Monoid<Value = T>, E: Monoid, A: Action<M, E>, N: NodeStore<ForestNodeOf<M, E, A, SubtreeSum<T>>>> Forest<M, E, A, SubtreeSum<T>, N> {
+    /// 現在の根に関する v の部分木の値の和
+    pub fn subtree_sum(&mut self, v: usize) -> T {
+        self.subtree_query(v)
+    }
+}
+
+impl<E: Monoid, S: Subtree<Sum<i64>, E>, N: NodeStore<ForestNodeOf<Sum<i64>, E, AssignAdd, S>>> Forest<Sum<i64>, E, AssignAdd, S, N>
+where
+    AssignAdd: SubtreeAction<Sum<i64>, E, S> + Action<Sum<i64>, E, Map = AssignAddMap>,
+{
+    /// 現在の根に関する v の部分木の頂点の値をすべて x にする
+    pub fn subtree_assign(&mut self, v: usize, x: i64) {
+        self.subtree_apply(v, AssignAddMap::assign(x));
+    }
+
+    /// 現在の根に関する v の部分木の頂点の値すべてに x を足す
+    pub fn subtree_add(&mut self, v: usize, x: i64) {
+        self.subtree_apply(v, AssignAddMap::add(x));
+    }
+}
+
+impl<T: Clone + Default + BitXor<Output = T>, M: Monoid<Value = T>, E: Monoid, A: Action<M, E>, N: NodeStore<ForestNodeOf<M, E, A, SubtreeXor<T>>>> Forest<M, E, A, SubtreeXor<T>, N> {
+    /// 現在の根に関する v の部分木の値の排他的論理和
+    pub fn subtree_xor(&mut self, v: usize) -> T {
+        self.subtree_query(v)
+    }
+}
+
+impl<T: Clone + Ord + Bounded, M: Monoid<Value = T>, E: Monoid, A: Action<M, E>, N: NodeStore<ForestNodeOf<M, E, A, SubtreeMin<T>>>> Forest<M, E, A, SubtreeMin<T>, N> {
+    /// 現在の根に関する v の部分木の値の最小値
+    pub fn subtree_min(&mut self, v: usize) -> T {
+        self.subtree_query(v)
+    }
+
+    /// v を含む木の頂点の値の最小値（根によらない）
+    /// 比べるのは頂点の値そのもので、Subtree は頂点の番号を知らない
+    /// 木の代表の名前に使うなら、頂点の値を番号か (鍵, 番号) の組にしておく（set_value で値を変えると代表も変わる）
+    pub fn component_min(&mut self, v: usize) -> T {
+        self.component_query(v)
+    }
+}
+
+impl<T: Clone + Ord + Bounded, M: Monoid<Value = T>, E: Monoid, A: Action<M, E>, N: NodeStore<ForestNodeOf<M, E, A, SubtreeMax<T>>>> Forest<M, E, A, SubtreeMax<T>, N> {
+    /// 現在の根に関する v の部分木の値の最大値
+    pub fn subtree_max(&mut self, v: usize) -> T {
+        self.subtree_query(v)
+    }
+}
+
+impl<M: Monoid, A: Action<M, Sum<i64>>, N: NodeStore<ForestNodeOf<M, Sum<i64>, A, Farthest>>> Forest<M, Sum<i64>, A, Farthest, N> {
+    /// v から最も遠い同じ木の頂点までの距離
+    pub fn eccentricity(&mut self, v: usize) -> i64 {
+        let x = vertex_node(v);
+        // expose すると v は根からの列の末尾になる
+        self.expose(x);
+        self.nodes[x].total.bottom
+    }
+
+    /// v を含む木の直径
+    pub fn component_diameter(&mut self, v: usize) -> i64 {
+        let x = vertex_node(v);
+        self.expose(x);
+        self.nodes[x].total.diameter
+    }
+
+    /// 現在の根に関する v の部分木の直径
+    pub fn subtree_diameter(&mut self, v: usize) -> i64 {
+        self.subtree_query(v).diameter
+    }
+
+    /// v から最も遠い同じ木の頂点とその距離（同じ距離の頂点が複数あればどれか一つ）
+    /// 集約の距離をたどって降り、見つけた頂点を露出するので償却 O(log n)（通る頂点の軽い子を探す分だけ次数がかかる）
+    pub fn farthest_vertex(&mut self, v: usize) -> (usize, i64) {
+        let x = vertex_node(v);
+        // expose すると v は根からの列の末尾になり、木全体が v からの距離で集約される
+        self.expose(x);
+        let distance = self.nodes[x].total.bottom;
+        let y = self.farthest_in(x, distance, false);
+        self.expose(y);
+        (y >> 1, distance)
+    }
+
+    /// 根 x の splay 木とそこに垂れ下がる部分木で、列の端（from_top なら根に近い端）から距離 t にある頂点の節点
+    fn farthest_in(&mut self, mut x: usize, mut t: i64, mut from_top: bool) -> usize {
+        loop {
+            self.push(x);
+            let [left, right] = self.nodes[x].children;
+            let (near, far) = if from_top { (left, right) } else { (right, left) };
+            let end = |sum: &FarthestSum| if from_top { sum.top } else { sum.bottom };
+            if let Some(near) = near.filter(|&near| end(&self.nodes[near].total) == t) {
+                x = near;
+                continue;
+            }
+            let near_len = near.map_or(0, |near| self.nodes[near].total.len);
+            let node = &self.nodes[x];
+            let own = if is_vertex(x) { <Farthest as Subtree<M, Sum<i64>>>::vertex(&node.value, &node.virt) } else { <Farthest as Subtree<M, Sum<i64>>>::edge(&node.weight, &node.virt) };
+            if end(&own) + near_len == t {
+                if is_vertex(x) && near_len == t {
+                    return x;
+                }
+                // 軽い子は辺なら根から遠い端に、頂点ならその頂点に垂れ下がる
+                let hang = near_len + if !is_vertex(x) && from_top { node.weight } else { 0 };
+                let candidates: Vec<usize> = if is_vertex(x) {
+                    self.adjacency[x >> 1].iter().map(|&e| edge_node(e)).collect()
+                } else {
+                    self.edges[x >> 1].ends.iter().map(|&w| vertex_node(w)).collect()
+                };
+                let light = candidates.into_iter().map(|y| self.splay_root(y)).find(|&root| self.nodes[root].parent == Some(x) && self.nodes[root].total.top == t - hang);
+                (x, t, from_top) = (light.expect("the aggregate points to a light child"), t - hang, true);
+                continue;
+            }
+            t -= near_len + own.len;
+            x = far.expect("the aggregate points to the far side");
+        }
+    }
+
+    /// v を含む木で、最も遠い頂点までの距離が最小の頂点（1-center）とその距離（半径）
+    /// 中心は直径の上にあり、直径の端 a, b からの距離の大きい方が半径になるので、
+    /// farthest_vertex で a, b を求め、a から距離が半分以下で最も遠い頂点とその次の頂点のうち近い方を選ぶ
+    /// 集約から呼ぶたびに求め、森の形と根、値は変えない（償却 O(log n) に、farthest_vertex の次数の分がかかる）
+    pub fn center(&mut self, v: usize) -> (usize, i64) {
+        let (a, _) = self.farthest_vertex(v);
+        let (b, diameter) = self.farthest_vertex(a);
+        if diameter == 0 {
+            return (a, 0);
+        }
+        let near = self.jump_weighted(a, b, &(diameter / 2)).unwrap();
+        // 距離が半分を超える頂点のうち a に最も近いものは、b から距離が diameter - diameter / 2 - 1 以下で最も遠いもの
+        let far = self.jump_weighted(b, a, &(diameter - diameter / 2 - 1)).unwrap();
+        let [near_radius, far_radius] = [near, far].map(|c| {
+            let from_a = self.path_query_edges(a, c).unwrap();
+            from_a.max(diameter - from_a)
+        });
+        if near_radius <= far_radius { (near, near_radius) } else { (far, far_radius) }
+    }
+}
+
+impl<M: Monoid, A: Action<M, Sum<i64>>, N: NodeStore<ForestNodeOf<M, Sum<i64>, A, Distances>>> Forest<M, Sum<i64>, A, Distances, N> {
+    /// v から同じ木の各頂点までの距離の和
+    pub fn distance_sum(&mut self, v: usize) -> i64 {
+        let x = vertex_node(v);
+        // expose すると v は根からの列の末尾になる
+        self.expose(x);
+        self.nodes[x].total.bottom
+    }
+
+    /// v を含む木の、頂点の組すべてについての距離の和
+    pub fn component_distance_sum(&mut self, v: usize) -> i64 {
+        self.component_query(v).pairs
+    }
+}
+
+impl<A: Action<First<usize>, Sum<i64>>, N: NodeStore<ForestNodeOf<First<usize>, Sum<i64>, A, NearestMarked>>> Forest<First<usize>, Sum<i64>, A, NearestMarked, N> {
+    /// v に印を付ける・外す（頂点の値を Some(v) か None にする）
+    pub fn set_marked(&mut self, v: usize, marked: bool) {
+        self.set_value(v, marked.then_some(v));
+    }
+
+    pub fn is_marked(&mut self, v: usize) -> bool {
+        self.value(v).is_some()
+    }
+
+    /// v を含む木で v に最も近い印の付いた頂点とその距離（同じ距離なら番号の小さい方）
+    pub fn nearest_marked_in_component(&mut self, v: usize) -> Option<(usize, i64)> {
+        let x = vertex_node(v);
+        // expose すると v は根からの列の末尾になる
+        self.expose(x);
+        let (dist, w) = self.nodes[x].total.bottom;
+        (w != usize::MAX).then_some((w, dist))
+    }
+
+    /// 現在の根に関する v の部分木で v に最も近い印の付いた頂点とその距離（同じ距離なら番号の小さい方）
+    pub fn nearest_marked_in_subtree(&mut self, v: usize) -> Option<(usize, i64)> {
+        let (dist, w) = self.subtree_query(v).top;
+        (w != usize::MAX).then_some((w, dist))
+    }
+
+    /// u から v へのパス上で u に最も近い印の付いた頂点
+    pub fn nearest_marked_on_path(&mut self, u: usize, v: usize) -> Option<usize> {
+        self.path_query(u, v).flatten()
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, T: TreeDp<M>, N: NodeStore<ForestNodeOf<M, E, A, Dp<T>>>> Forest<M, E, A, Dp<T>, N> {
+    /// 現在の根に関する v の部分木の DP
+    pub fn subtree_dp(&mut self, v: usize) -> T::Point {
+        T::finish(&self.subtree_query(v))
+    }
+
+    /// v を含む木全体の DP
+    pub fn component_dp(&mut self, v: usize) -> T::Point {
+        T::finish(&self.component_query(v))
+    }
+}
+
+impl<P: Modulus, E: Monoid, A: Action<First<ExprOp>, E>, N: NodeStore<ForestNodeOf<First<ExprOp>, E, A, Expression<P>>>> Forest<First<ExprOp>, E, A, Expression<P>, N> {
+    /// 現在の根に関する v の部分木を式木として評価した値
+    pub fn subtree_eval(&mut self, v: usize) -> u64 {
+        self.subtree_query(v).end.unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::tests::Naive;
+    use super::*;
+    use crate::monoid::{ConstModulus, Max, Min, Xor};
+    use crate::subtree::MaxIndependentSet;
+    use crate::test_util::xorshift;
+
+    const P: u64 = 1_000_000_007;
+    const BASE: u64 = 131;
+
+    /// 素朴に求めた v の部分木のハッシュ
+    fn naive_hash(naive: &Naive, values: &[u64], v: usize) -> u64 {
+        let children: Vec<usize> = (0 .. values.len()).filter(|&w| naive.parent[w] == Some(v)).collect();
+        children.into_iter().fold(values[v], |hash, w| (hash + BASE * naive_hash(naive, values, w)) % P)
+    }
+
+    #[test]
+    fn subtree_hash_against_naive() {
+        let mut rand = xorshift(0x853c49e6748fea9b);
+        let n = 25;
+        let mut forest = Forest::<ModSum<ConstModulus<P>>, (), (), TreeHash<ConstModulus<P>, BASE>>::new();
+        let mut values = vec![];
+        for _ in 0 .. n {
+            values.push(rand(1000) as u64);
+            forest.add_node(*values.last().unwrap());
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        forest.link(u, v);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+                _ => {
+                    values[u] = rand(1000) as u64;
+                    forest.set_value(u, values[u]);
+                }
+            }
+            let w = rand(n);
+            assert_eq!(forest.subtree_hash(w), naive_hash(&naive, &values, w));
+            assert_eq!(forest.path_query(u, v), naive.path(u, v).map(|path| path.iter().map(|&x| values[x]).sum::<u64>() % P));
+        }
+    }
+
+    #[test]
+    fn subtree_min_against_naive() {
+        let mut rand = xorshift(0x3c6ef372fe94f82b);
+        fn naive_min(naive: &Naive, values: &[i64], v: usize) -> i64 {
+            let children = (0 .. values.len()).filter(|&w| naive.parent[w] == Some(v));
+            children.map(|w| naive_min(naive, values, w)).fold(values[v], i64::min)
+        }
+        let n = 25;
+        let mut forest = Forest::<Min<i64>, (), (), SubtreeMin<i64>>::new();
+        let mut values = vec![];
+        for _ in 0 .. n {
+            values.push(rand(1000) as i64);
+            forest.add_node(*values.last().unwrap());
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        forest.link(u, v);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+                _ => {
+                    values[u] = rand(1000) as i64;
+                    forest.set_value(u, values[u]);
+                }
+            }
+            let w = rand(n);
+            assert_eq!(forest.subtree_min(w), naive_min(&naive, &values, w));
+            assert_eq!(forest.component_query(u), naive_min(&naive, &values, naive.root(u)));
+            assert_eq!(forest.component_min(w), naive_min(&naive, &values, naive.root(w)));
+        }
+    }
+
+    #[test]
+    fn component_min_follows_links_and_cuts() {
+        // 値を頂点の番号にすると、木の代表は最小の番号になる
+        let mut forest = Forest::<Min<usize>, (), (), SubtreeMin<usize>>::new();
+        for v in 0 .. 8 {
+            forest.add_node(v);
+        }
+        assert!((0 .. 8).all(|v| forest.component_min(v) == v));
+        // 3 - 5 - 7, 5 - 6, 2 - 4
+        forest.link(7, 5);
+        forest.link(5, 3);
+        forest.link(6, 5);
+        forest.link(4, 2);
+        assert!([3, 5, 6, 7].iter().all(|&v| forest.component_min(v) == 3));
+        assert_eq!(forest.component_min(4), 2);
+        forest.cut(5);
+        assert_eq!(forest.component_min(3), 3);
+        assert!([5, 6, 7].iter().all(|&v| forest.component_min(v) == 5));
+        forest.evert(6);
+        forest.link(6, 4);
+        assert!([2, 4, 5, 6, 7].iter().all(|&v| forest.component_min(v) == 2));
+        forest.cut(4);
+        assert_eq!(forest.component_min(2), 2);
+        assert!([4, 5, 6, 7].iter().all(|&v| forest.component_min(v) == 4));
+        forest.link(2, 1);
+        forest.evert(7);
+        forest.link(7, 1);
+        assert!([1, 2, 4, 5, 6, 7].iter().all(|&v| forest.component_min(v) == 1));
+        assert_eq!(forest.component_min(0), 0);
+        assert_eq!(forest.component_min(3), 3);
+        // 値を変えると代表も変わる
+        forest.set_value(1, 9);
+        assert_eq!(forest.component_min(6), 2);
+    }
+
+    #[test]
+    fn component_query() {
+        let mut forest = Forest::<Min<i64>, (), (), SubtreeMin<i64>>::new();
+        for x in [5, 1, 7, 3, 2, 4] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2, 1 - 3 - 4, 5
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        forest.link(4, 3);
+        for v in 0 .. 5 {
+            assert_eq!(forest.component_query(v), 1);
+        }
+        assert_eq!(forest.component_query(5), 4);
+        forest.cut(1);
+        forest.evert(4);
+        assert_eq!(forest.component_query(2), 1);
+        assert_eq!(forest.component_query(0), 5);
+        forest.set_value(1, 9);
+        assert_eq!(forest.component_query(4), 2);
+    }
+
+    #[test]
+    fn component_height() {
+        let mut forest = Forest::<(), (), (), Height>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        assert_eq!(forest.component_height(0), 0);
+        // 0 - 1 - 2 - 3, 1 - 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 2);
+        forest.link(4, 1);
+        assert_eq!(forest.component_height(4), 3);
+        forest.evert(2);
+        assert_eq!(forest.component_height(0), 2);
+        forest.evert(3);
+        assert_eq!(forest.component_height(1), 3);
+        forest.cut(2);
+        assert_eq!(forest.component_height(2), 2);
+        assert_eq!(forest.component_height(3), 0);
+    }
+
+    #[test]
+    fn nodes_at_depth_pruned() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 40;
+        let mut forest = Forest::<(), (), (), Height>::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        for v in 1 .. n {
+            if rand(8) > 0 {
+                forest.link(v, rand(v));
+            }
+        }
+        for _ in 0 .. 50 {
+            forest.evert(rand(n));
+            let v = rand(n);
+            let height = forest.component_height(v);
+            for d in 0 ..= height + 1 {
+                let expected: Vec<usize> = forest.nodes_at_depth(v, d).collect();
+                assert_eq!(forest.nodes_at_depth_pruned(v, d), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn nodes_at_depth_edge_cases() {
+        let mut forest = Forest::<(), (), (), Height>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 2, 1 - 3, 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        let both = |forest: &mut Forest<(), (), (), Height>, v: usize, d: usize| {
+            let plain: Vec<usize> = forest.nodes_at_depth(v, d).collect();
+            assert_eq!(forest.nodes_at_depth_pruned(v, d), plain);
+            plain
+        };
+        // 辺のない木は根だけが深さ 0 にある
+        assert_eq!(both(&mut forest, 4, 0), vec![4]);
+        assert_eq!(both(&mut forest, 4, 1), vec![]);
+        // 深さ 0 は v によらず根
+        assert_eq!(both(&mut forest, 3, 0), vec![0]);
+        assert_eq!(both(&mut forest, 3, 2), vec![2, 3]);
+        // 高さを超える深さには頂点がない
+        assert_eq!(forest.component_height(3), 2);
+        assert_eq!(both(&mut forest, 3, 3), vec![]);
+        assert_eq!(both(&mut forest, 3, usize::MAX), vec![]);
+        forest.evert(2);
+        assert_eq!(both(&mut forest, 0, 0), vec![2]);
+        assert_eq!(both(&mut forest, 0, 2), vec![0, 3]);
+    }
+
+    #[test]
+    fn depth_parity_counts() {
+        let mut forest = Forest::<(), (), (), DepthParity>::new();
+        for _ in 0 .. 6 {
+            forest.add_node(());
+        }
+        assert_eq!(forest.depth_parity_counts(0), (1, 0));
+        // 0 - 1 - 2 - 3, 1 - 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 2);
+        forest.link(4, 1);
+        assert_eq!(forest.depth_parity_counts(3), (3, 2));
+        forest.evert(1);
+        assert_eq!(forest.depth_parity_counts(0), (2, 3));
+        forest.evert(2);
+        assert_eq!(forest.depth_parity_counts(4), (3, 2));
+        forest.link(5, 4);
+        assert_eq!(forest.depth_parity_counts(2), (3, 3));
+        forest.cut(1);
+        assert_eq!(forest.depth_parity_counts(2), (1, 1));
+        assert_eq!(forest.depth_parity_counts(5), (2, 2));
+    }
+
+    #[test]
+    fn component_leaves() {
+        let mut forest = Forest::<(), (), (), Leaves>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        assert_eq!(forest.component_leaves(0), 1);
+        // 0 - 1 - 2, 1 - 3
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        assert_eq!(forest.component_leaves(2), 2);
+        forest.evert(2);
+        assert_eq!(forest.component_leaves(0), 2);
+        forest.evert(1);
+        assert_eq!(forest.component_leaves(1), 3);
+        forest.cut(3);
+        assert_eq!(forest.component_leaves(0), 2);
+        assert_eq!(forest.component_leaves(3), 1);
+    }
+
+    #[test]
+    fn component_aggregates_against_naive() {
+        let mut rand = xorshift(0x510e527fade682d1);
+        let n = 20;
+        let mut forest = Forest::<Sum<i64>, (), (), (Leaves, (SubtreeMin<i64>, Height))>::new();
+        for v in 0 .. n {
+            forest.add_node(v as i64);
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(3) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        forest.link(u, v);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                _ => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+            }
+            let w = rand(n);
+            let tree: Vec<usize> = (0 .. n).filter(|&x| naive.root(x) == naive.root(w)).collect();
+            let leaves = tree.iter().filter(|&&x| !naive.parent.contains(&Some(x))).count();
+            let depth = |mut x: usize| {
+                let mut depth = 0;
+                while let Some(p) = naive.parent[x] {
+                    x = p;
+                    depth += 1;
+                }
+                depth
+            };
+            let height = tree.iter().map(|&x| depth(x)).max().unwrap();
+            let (sum, (min, top)) = forest.component_query(w);
+            assert_eq!(sum.leaves, leaves);
+            assert_eq!(min, tree[0] as i64);
+            assert_eq!(top.top, height);
+        }
+    }
+
+    #[test]
+    fn subtree_assign() {
+        let mut forest = Forest::<Sum<i64>, (), AssignAdd, SubtreeSum<i64>>::new();
+        for x in [5, 1, 7, 3, 2] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2, 1 - 3 - 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        forest.link(4, 3);
+        forest.subtree_assign(3, 10);
+        assert_eq!(forest.subtree_sum(3), 20);
+        assert_eq!(forest.subtree_sum(0), 33);
+        assert_eq!(forest.path_query(0, 4), Some(26));
+        assert_eq!(forest.subtree_count_ge(1, &5), 3);
+        assert_eq!(forest.subtree_count_ge(2, &8), 0);
+        forest.evert(4);
+        forest.subtree_assign(1, 0);
+        assert_eq!(forest.path_query(4, 0), Some(20));
+        assert_eq!(forest.subtree_sum(3), 10);
+        assert_eq!(forest.value(4), &10);
+        assert_eq!(forest.subtree_count_ge(3, &1), 1);
+        assert_eq!(forest.subtree_count_ge(4, &0), 5);
+    }
+
+    #[test]
+    fn subtree_count_ge_edge_cases() {
+        let mut forest = Forest::<Max<i64>>::new();
+        assert_eq!(forest.subtree_counts_ge(&[]), vec![]);
+        for x in [4, -2, 7, 4, 0] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2, 1 - 3, 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        // どの値より大きいしきい値なら 0、どの値以下なら部分木の大きさ
+        let queries = [(0, 8), (1, i64::MAX), (0, -2), (1, i64::MIN), (4, 1), (4, 0), (2, 7), (3, 5)];
+        let expected = vec![0, 0, 4, 3, 0, 1, 1, 0];
+        assert_eq!(forest.subtree_counts_ge(&queries), expected);
+        for (&(v, x), &count) in queries.iter().zip(&expected) {
+            assert_eq!(forest.subtree_count_ge(v, &x), count);
+        }
+        forest.evert(2);
+        assert_eq!(forest.subtree_count_ge(1, &4), 2);
+        assert_eq!(forest.subtree_counts_ge(&[(1, 4), (2, 8), (0, -1)]), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn subtree_counts_ge() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 40;
+        let mut forest = Forest::<Max<i64>>::new();
+        for _ in 0 .. n {
+            forest.add_node(rand(20) as i64);
+        }
+        for v in 1 .. n {
+            if rand(6) > 0 {
+                forest.link(v, rand(v));
+            }
+        }
+        for _ in 0 .. 20 {
+            forest.evert(rand(n));
+            let queries: Vec<(usize, i64)> = (0 .. 30).map(|_| (rand(n), rand(22) as i64 - 1)).collect();
+            let answers = forest.subtree_counts_ge(&queries);
+            for (&(v, x), &count) in queries.iter().zip(&answers) {
+                assert_eq!(count, forest.subtree_count_ge(v, &x));
+            }
+        }
+    }
+
+    /// 現在の根に関する v の部分木の頂点
+    fn naive_subtree(naive: &Naive, v: usize) -> Vec<usize> {
+        let mut children = vec![vec![]; naive.parent.len()];
+        for (w, p) in naive.parent.iter().enumerate() {
+            if let Some(p) = *p {
+                children[p].push(w);
+            }
+        }
+        let mut stack = vec![v];
+        let mut vertices = vec![];
+        while let Some(w) = stack.pop() {
+            vertices.push(w);
+            stack.extend(&children[w]);
+        }
+        vertices
+    }
+
+    /// parent で与えた形の木に、部分木への代入と加算を混ぜた操作を素朴な森と比べる
+    fn subtree_actions_against_naive(parent: Vec<Option<usize>>, seed: u64) {
+        let mut rand = xorshift(seed);
+        let n = parent.len();
+        let mut forest = Forest::<Sum<i64>, (), AssignAdd, SubtreeSum<i64>>::new();
+        let mut values: Vec<i64> = (0 .. n).map(|_| rand(100) as i64).collect();
+        for &x in &values {
+            forest.add_node(x);
+        }
+        for (v, p) in parent.iter().enumerate() {
+            if let Some(p) = *p {
+                forest.link(v, p);
+            }
+        }
+        let mut naive = Naive { parent };
+        for _ in 0 .. 1500 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(8) {
+                0 | 1 => {
+                    // 葉に近い頂点が多いので、根に近い頂点もよく選ぶ
+                    let v = if rand(2) == 0 { naive.root(v) } else { v };
+                    let x = rand(100) as i64 - 50;
+                    for w in naive_subtree(&naive, v) {
+                        values[w] = x;
+                    }
+                    forest.subtree_assign(v, x);
+                }
+                2 | 3 => {
+                    let v = if rand(2) == 0 { naive.root(v) } else { v };
+                    let x = rand(100) as i64 - 50;
+                    for w in naive_subtree(&naive, v) {
+                        values[w] += x;
+                    }
+                    forest.subtree_add(v, x);
+                }
+                4 => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+                5 => {
+                    // 辺を一本切り、同じ頂点を別の場所につなぎ直す
+                    if let Some(p) = naive.parent[u] {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                        let w = if naive.root(v) != u { v } else { p };
+                        naive.parent[u] = Some(w);
+                        forest.link(u, w);
+                    }
+                }
+                6 => {
+                    values[u] = rand(100) as i64;
+                    values[v] = rand(100) as i64;
+                    forest.begin_batch();
+                    forest.set_value(u, values[u]);
+                    forest.set_value(v, values[v]);
+                    forest.end_batch();
+                }
+                _ => {
+                    assert_eq!(forest.value(u), &values[u]);
+                }
+            }
+            let w = rand(n);
+            assert_eq!(forest.subtree_sum(w), naive_subtree(&naive, w).iter().map(|&x| values[x]).sum::<i64>());
+            assert_eq!(forest.path_query(u, v), naive.path(u, v).map(|path| path.iter().map(|&x| values[x]).sum()));
+        }
+        assert!((0 .. n).all(|v| forest.value(v) == &values[v]));
+    }
+
+    #[test]
+    fn subtree_actions_on_star() {
+        let n = 2000;
+        subtree_actions_against_naive((0 .. n).map(|v| (v > 0).then_some(0)).collect(), 0x6a09e667f3bcc908);
+    }
+
+    #[test]
+    fn subtree_actions_on_caterpillar() {
+        // 0 - 2 - 4 - ... の背骨の各頂点に葉を一つずつ付ける
+        let n: usize = 1000;
+        subtree_actions_against_naive((0 .. n).map(|v| if v % 2 == 0 { v.checked_sub(2) } else { Some(v - 1) }).collect(), 0xbb67ae8584caa73b);
+    }
+
+    #[test]
+    fn subtree_and_path_actions() {
+        // 部分木の集約を持たなければ、パスへの作用と混ぜられる
+        let mut rand = xorshift(0x3c6ef372fe94f82b);
+        let n = 60;
+        let mut forest = Forest::<Sum<i64>, (), AssignAdd>::new();
+        let mut values = vec![0; n];
+        let mut naive = Naive { parent: vec![None; n] };
+        for v in 0 .. n {
+            forest.add_node(0);
+            if v > 0 {
+                let p = rand(v);
+                naive.parent[v] = Some(p);
+                forest.link(v, p);
+            }
+        }
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            let x = rand(20) as i64 - 10;
+            match rand(4) {
+                0 => {
+                    for w in naive_subtree(&naive, v) {
+                        values[w] = x;
+                    }
+                    forest.subtree_assign(v, x);
+                }
+                1 => {
+                    for w in naive_subtree(&naive, v) {
+                        values[w] += x;
+                    }
+                    forest.subtree_add(v, x);
+                }
+                2 => {
+                    for w in naive.path(u, v).unwrap() {
+                        values[w] = x;
+                    }
+                    forest.path_apply(u, v, AssignAddMap::assign(x));
+                }
+                _ => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+            }
+            let (u, v) = (rand(n), rand(n));
+            assert_eq!(forest.path_query(u, v), naive.path(u, v).map(|path| path.iter().map(|&x| values[x]).sum()));
+            assert_eq!(forest.value(u), &values[u]);
+        }
+    }
+
+    #[test]
+    fn subtree_xor_against_naive() {
+        let mut rand = xorshift(0xa54ff53a5f1d36f1);
+        fn naive_xor(naive: &Naive, values: &[u32], v: usize) -> u32 {
+            let children = (0 .. values.len()).filter(|&w| naive.parent[w] == Some(v));
+            children.map(|w| naive_xor(naive, values, w)).fold(values[v], |acc, x| acc ^ x)
+        }
+        let n = 25;
+        let mut forest = Forest::<Xor<u32>, (), (), SubtreeXor<u32>>::new();
+        let mut values = vec![];
+        for _ in 0 .. n {
+            values.push(rand(1 << 16) as u32);
+            forest.add_node(*values.last().unwrap());
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        forest.link(u, v);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+                _ => {
+                    values[u] = rand(1 << 16) as u32;
+                    forest.set_value(u, values[u]);
+                }
+            }
+            let w = rand(n);
+            assert_eq!(forest.subtree_xor(w), naive_xor(&naive, &values, w));
+            assert_eq!(forest.path_query(u, v), naive.path(u, v).map(|path| path.iter().fold(0, |acc, &x| acc ^ values[x])));
+        }
+    }
+
+    #[test]
+    fn subtree_sum_and_add() {
+        let mut forest = Forest::<Sum<i64>, (), AssignAdd, SubtreeSum<i64>>::new();
+        for x in [1, 2, 4, 8, 16] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2, 1 - 3 - 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        forest.link(4, 3);
+        assert_eq!(forest.subtree_sum(1), 30);
+        assert_eq!(forest.subtree_sum(3), 24);
+        forest.subtree_add(3, 100);
+        assert_eq!(forest.subtree_sum(1), 230);
+        assert_eq!(forest.path_query(0, 4), Some(1 + 2 + 108 + 116));
+        forest.evert(4);
+        assert_eq!(forest.subtree_sum(1), 7);
+        forest.cut(1);
+        assert_eq!(forest.subtree_sum(4), 224);
+        assert_eq!(forest.component_query(0), 7);
+    }
+
+    #[test]
+    fn max_independent_set_against_naive() {
+        let mut rand = xorshift(0x3c6ef372fe94f82b);
+        /// [v を選ばないとき, v を選ぶとき] の v の部分木での最大値
+        fn naive_dp(naive: &Naive, values: &[i64], v: usize) -> [i64; 2] {
+            let mut dp = [0, values[v]];
+            for w in (0 .. values.len()).filter(|&w| naive.parent[w] == Some(v)) {
+                let child = naive_dp(naive, values, w);
+                dp[0] += child[0].max(child[1]);
+                dp[1] += child[0];
+            }
+            dp
+        }
+        let n = 20;
+        let mut forest = Forest::<Sum<i64>, (), (), Dp<MaxIndependentSet>>::new();
+        let mut values = vec![];
+        for _ in 0 .. n {
+            values.push(rand(100) as i64);
+            forest.add_node(*values.last().unwrap());
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        forest.link(u, v);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+                _ => {
+                    values[u] = rand(100) as i64;
+                    forest.set_value(u, values[u]);
+                }
+            }
+            let w = rand(n);
+            let expected = naive_dp(&naive, &values, w);
+            assert_eq!(forest.subtree_dp(w), [expected[0].max(expected[1]), expected[0]]);
+            let root = naive_dp(&naive, &values, naive.root(w));
+            assert_eq!(forest.component_dp(w)[0], root[0].max(root[1]));
+        }
+    }
+
+    #[test]
+    fn subtree_max() {
+        let mut forest = Forest::<Max<i64>, (), (), SubtreeMax<i64>>::new();
+        for x in [5, 1, 7, 3, 2] {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2, 1 - 3 - 4
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.link(3, 1);
+        forest.link(4, 3);
+        assert_eq!(forest.subtree_max(0), 7);
+        assert_eq!(forest.subtree_max(1), 7);
+        assert_eq!(forest.subtree_max(3), 3);
+        forest.cut(2);
+        assert_eq!(forest.subtree_max(1), 3);
+        forest.evert(4);
+        assert_eq!(forest.subtree_max(3), 5);
+        assert_eq!(forest.subtree_max(0), 5);
+        forest.set_value(4, 9);
+        assert_eq!(forest.subtree_max(1), 5);
+        assert_eq!(forest.subtree_max(4), 9);
+    }
+
+    #[test]
+    fn eccentricity_against_naive() {
+        let mut rand = xorshift(0xda942042e4dd58b5);
+        let n = 20;
+        let mut forest = Forest::<(), Sum<i64>, (), Farthest>::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        let mut weight = HashMap::new();
+        // 素朴に v からの距離を求める
+        let dist = |naive: &Naive, weight: &HashMap<(usize, usize), i64>, v: usize| {
+            (0 .. n)
+                .filter_map(|w| {
+                    let path = naive.path(v, w)?;
+                    Some(path.windows(2).map(|e| weight[&(e[0].min(e[1]), e[0].max(e[1]))]).sum::<i64>())
+                })
+                .collect::<Vec<_>>()
+        };
+        for _ in 0 .. 2000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        let w = rand(10) as i64;
+                        weight.insert((u.min(v), u.max(v)), w);
+                        forest.link_weighted(u, v, w);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    if let Some(p) = naive.parent[u] {
+                        let w = rand(10) as i64;
+                        weight.insert((u.min(p), u.max(p)), w);
+                        forest.set_edge_weight(u, p, w).unwrap();
+                    }
+                }
+                _ => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+            }
+            let w = rand(n);
+            let from_w = dist(&naive, &weight, w);
+            assert_eq!(forest.eccentricity(w), *from_w.iter().max().unwrap());
+            let (far, distance) = forest.farthest_vertex(w);
+            assert_eq!(distance, *from_w.iter().max().unwrap());
+            assert_eq!(forest.path_query_edges(w, far), Some(distance));
+            let diameter = (0 .. n).filter(|&x| naive.root(x) == naive.root(w)).map(|x| *dist(&naive, &weight, x).iter().max().unwrap()).max();
+            assert_eq!(forest.component_diameter(w), diameter.unwrap());
+            // 部分木の頂点は根へのパスが w を通る
+            let root = naive.root(w);
+            let below: Vec<usize> = (0 .. n).filter(|&x| naive.path(x, root).is_some_and(|path| path.contains(&w))).collect();
+            let length = |x: usize, y: usize| naive.path(x, y).unwrap().windows(2).map(|e| weight[&(e[0].min(e[1]), e[0].max(e[1]))]).sum::<i64>();
+            let diameter = below.iter().flat_map(|&x| below.iter().map(move |&y| (x, y))).map(|(x, y)| length(x, y)).max();
+            assert_eq!(forest.subtree_diameter(w), diameter.unwrap());
+            let radius = (0 .. n).filter(|&x| naive.root(x) == naive.root(w)).map(|x| *dist(&naive, &weight, x).iter().max().unwrap()).min();
+            let (center, r) = forest.center(w);
+            assert_eq!(Some(r), radius);
+            assert_eq!(naive.root(center), naive.root(w));
+            assert_eq!(forest.eccentricity(center), r);
+        }
+    }
+
+    #[test]
+    fn center_on_paths_and_stars() {
+        let mut forest = Forest::<(), Sum<i64>, (), Farthest>::new();
+        for _ in 0 .. 10 {
+            forest.add_node(());
+        }
+        assert_eq!(forest.center(9), (9, 0));
+        // 0 -1- 1 -5- 2 -1- 3 -1- 4
+        for (v, w) in [(1, 1), (2, 5), (3, 1), (4, 1)] {
+            forest.link_weighted(v, v - 1, w);
+        }
+        assert_eq!(forest.farthest_vertex(2), (0, 6));
+        assert_eq!(forest.center(0), (2, 6));
+        assert_eq!(forest.center(4), (2, 6));
+        // 5 を中心に 6, 7, 8 を重み 3, 3, 2 でつなぐ星
+        for (v, w) in [(6, 3), (7, 3), (8, 2)] {
+            forest.link_weighted(v, 5, w);
+        }
+        assert_eq!(forest.center(8), (5, 3));
+        // 8 を 6 の下へ重み 10 でつなぎ直すと、直径は 8 - 6 - 5 - 7 になる
+        forest.cut(8);
+        forest.link_weighted(8, 6, 10);
+        assert_eq!(forest.farthest_vertex(7), (8, 16));
+        assert_eq!(forest.center(5), (6, 10));
+        forest.set_edge_weight(8, 6, 1).unwrap();
+        assert_eq!(forest.center(8), (5, 4));
+        // 重み 0 の辺だけなら半径は 0
+        forest.cut(4);
+        forest.set_edge_weight(3, 2, 0).unwrap();
+        forest.link_weighted(4, 3, 0);
+        forest.cut(2);
+        assert_eq!(forest.center(4).1, 0);
+    }
+
+    #[test]
+    fn subtree_diameter_edge_cases() {
+        let mut forest = Forest::<(), Sum<i64>, (), Farthest>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(());
+        }
+        // 0 -2- 1 -3- 2, 1 -4- 3, 4
+        forest.link_weighted(1, 0, 2);
+        forest.link_weighted(2, 1, 3);
+        forest.link_weighted(3, 1, 4);
+        // 辺のない木と葉の部分木の直径は 0
+        assert_eq!(forest.subtree_diameter(4), 0);
+        assert_eq!(forest.subtree_diameter(2), 0);
+        assert_eq!(forest.subtree_diameter(0), 7);
+        assert_eq!(forest.subtree_diameter(1), 7);
+        // 根を変えると 1 の部分木から 2 が抜ける
+        forest.evert(2);
+        assert_eq!(forest.subtree_diameter(1), 6);
+        assert_eq!(forest.subtree_diameter(2), 7);
+        forest.cut(3);
+        assert_eq!(forest.subtree_diameter(2), 5);
+        assert_eq!(forest.subtree_diameter(3), 0);
+        forest.link_weighted(3, 4, 1);
+        assert_eq!(forest.subtree_diameter(4), 1);
+        assert_eq!(forest.subtree_diameter(3), 0);
+    }
+
+    #[test]
+    fn distance_sums_against_naive() {
+        let mut rand = xorshift(0x2545f4914f6cdd1d);
+        let n = 20;
+        let mut forest = Forest::<(), Sum<i64>, (), Distances>::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        let mut weight = HashMap::new();
+        // 素朴に v から同じ木の各頂点までの距離の和を求める
+        let dist = |naive: &Naive, weight: &HashMap<(usize, usize), i64>, v: usize| {
+            (0 .. n)
+                .filter_map(|w| {
+                    let path = naive.path(v, w)?;
+                    Some(path.windows(2).map(|e| weight[&(e[0].min(e[1]), e[0].max(e[1]))]).sum::<i64>())
+                })
+                .sum::<i64>()
+        };
+        for _ in 0 .. 2000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        let w = rand(10) as i64;
+                        weight.insert((u.min(v), u.max(v)), w);
+                        forest.link_weighted(u, v, w);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    if let Some(p) = naive.parent[u] {
+                        let w = rand(10) as i64;
+                        weight.insert((u.min(p), u.max(p)), w);
+                        forest.set_edge_weight(u, p, w).unwrap();
+                    }
+                }
+                _ => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+            }
+            let w = rand(n);
+            assert_eq!(forest.distance_sum(w), dist(&naive, &weight, w));
+            let pairs = (0 .. n).filter(|&x| naive.root(x) == naive.root(w)).map(|x| dist(&naive, &weight, x)).sum::<i64>() / 2;
+            assert_eq!(forest.component_distance_sum(w), pairs);
+        }
+    }
+
+    #[test]
+    fn nearest_marked_against_naive() {
+        let mut rand = xorshift(0x3c6ef372fe94f82b);
+        let n = 20;
+        let mut forest = Forest::<First<usize>, Sum<i64>, (), NearestMarked>::new();
+        for _ in 0 .. n {
+            forest.add_node(None);
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        let mut weight = HashMap::new();
+        let mut marked = vec![false; n];
+        for _ in 0 .. 2000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(5) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        let w = rand(10) as i64;
+                        weight.insert((u.min(v), u.max(v)), w);
+                        forest.link_weighted(u, v, w);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    marked[u] ^= true;
+                    forest.set_marked(u, marked[u]);
+                }
+                _ => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+            }
+            let (u, v) = (rand(n), rand(n));
+            let nearest = (0 .. n)
+                .filter(|&w| marked[w])
+                .filter_map(|w| {
+                    let path = naive.path(u, w)?;
+                    Some((path.windows(2).map(|e| weight[&(e[0].min(e[1]), e[0].max(e[1]))]).sum::<i64>(), w))
+                })
+                .min();
+            assert_eq!(forest.nearest_marked_in_component(u), nearest.map(|(d, w)| (w, d)));
+            // 部分木の頂点は根へのパスが u を通る
+            let root = naive.root(u);
+            let below = (0 .. n)
+                .filter(|&w| marked[w] && naive.path(w, root).unwrap_or_default().contains(&u))
+                .map(|w| (naive.path(u, w).unwrap().windows(2).map(|e| weight[&(e[0].min(e[1]), e[0].max(e[1]))]).sum::<i64>(), w))
+                .min();
+            assert_eq!(forest.nearest_marked_in_subtree(u), below.map(|(d, w)| (w, d)));
+            let on_path = naive.path(u, v).and_then(|path| path.into_iter().find(|&w| marked[w]));
+            assert_eq!(forest.nearest_marked_on_path(u, v), on_path);
+            assert_eq!(forest.is_marked(v), marked[v]);
+        }
+    }
+
+    #[test]
+    fn nearest_marked_in_subtree_edge_cases() {
+        let mut forest = Forest::<First<usize>, Sum<i64>, (), NearestMarked>::new();
+        for _ in 0 .. 5 {
+            forest.add_node(None);
+        }
+        // 0 -1- 1 -5- 2, 1 -2- 3, 4
+        forest.link_weighted(1, 0, 1);
+        forest.link_weighted(2, 1, 5);
+        forest.link_weighted(3, 1, 2);
+        // 印がなければどこにもない
+        assert!((0 .. 5).all(|v| forest.nearest_marked_in_subtree(v).is_none()));
+        // 辺のない木では自身に印があるときだけ見つかる
+        forest.set_marked(4, true);
+        assert_eq!(forest.nearest_marked_in_subtree(4), Some((4, 0)));
+        // 祖先の印は部分木の外にある
+        forest.set_marked(0, true);
+        assert_eq!(forest.nearest_marked_in_subtree(1), None);
+        assert_eq!(forest.nearest_marked_in_subtree(0), Some((0, 0)));
+        forest.set_marked(2, true);
+        forest.set_marked(3, true);
+        assert_eq!(forest.nearest_marked_in_subtree(1), Some((3, 2)));
+        // 同じ距離なら番号の小さい方
+        forest.set_edge_weight(2, 1, 2).unwrap();
+        assert_eq!(forest.nearest_marked_in_subtree(1), Some((2, 2)));
+        forest.evert(3);
+        assert_eq!(forest.nearest_marked_in_subtree(1), Some((0, 1)));
+        assert_eq!(forest.nearest_marked_in_subtree(3), Some((3, 0)));
+        forest.set_marked(3, false);
+        assert_eq!(forest.nearest_marked_in_subtree(3), Some((0, 3)));
+    }
+
+    #[test]
+    fn expression_against_naive() {
+        const Q: u64 = 998244353;
+        let mut rand = xorshift(0x6a09e667f3bcc909);
+        let random_op = |r: usize| match r % 3 {
+            0 => ExprOp::Add((r / 3) as u64),
+            1 => ExprOp::Mul((r / 3) as u64),
+            _ => ExprOp::Mul(Q - 1),
+        };
+        fn eval(naive: &Naive, ops: &[ExprOp], v: usize) -> u64 {
+            let children = (0 .. ops.len()).filter(|&w| naive.parent[w] == Some(v)).map(|w| eval(naive, ops, w));
+            match ops[v] {
+                ExprOp::Add(k) => children.fold(k, |acc, x| (acc + x) % Q),
+                ExprOp::Mul(k) => children.fold(k, |acc, x| acc * x % Q),
+            }
+        }
+        let n = 20;
+        let mut forest = Forest::<First<ExprOp>, (), (), Expression<ConstModulus<Q>>>::new();
+        let mut ops = vec![];
+        for _ in 0 .. n {
+            ops.push(random_op(rand(15)));
+            forest.add_node(Some(*ops.last().unwrap()));
+        }
+        let mut naive = Naive { parent: vec![None; n] };
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    if naive.root(u) != naive.root(v) {
+                        naive.evert(u);
+                        forest.evert(u);
+                        naive.parent[u] = Some(v);
+                        forest.link(u, v);
+                    }
+                }
+                1 => {
+                    if naive.parent[u].is_some() {
+                        naive.parent[u] = None;
+                        forest.cut(u);
+                    }
+                }
+                2 => {
+                    naive.evert(u);
+                    forest.evert(u);
+                }
+                _ => {
+                    ops[u] = random_op(rand(15));
+                    forest.set_value(u, Some(ops[u]));
+                }
+            }
+            let w = rand(n);
+            assert_eq!(forest.subtree_eval(w), eval(&naive, &ops, w));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use super::{Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+/// 複数の木を一つの番兵の下にまとめる
+/// 番兵の値と番兵につなぐ辺の重みは単位元なので、パス上の集約には影響しない
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// 番兵の頂点（なければ値が単位元の頂点として追加する）
+    pub fn super_root(&mut self) -> usize {
+        match self.super_root {
+            Some(s) => s,
+            None => {
+                let s = self.add_node(M::identity());
+                self.super_root = Some(s);
+                s
+            }
+        }
+    }
+
+    /// v が番兵か
+    pub fn is_super_root(&self, v: usize) -> bool {
+        self.super_root == Some(v)
+    }
+
+    /// v を含む木の根を番兵の子にする（すでにつながっていれば何もしない）
+    pub fn attach_to_super_root(&mut self, v: usize) {
+        let s = self.super_root();
+        if !self.connected(v, s) {
+            let root = self.root(v);
+            self.link(root, s);
+        }
+    }
+
+    /// 番兵から木をすべて切り離し、それぞれの木の根になった頂点を返す
+    pub fn detach_from_super_root(&mut self) -> Vec<usize> {
+        let Some(s) = self.super_root else { return vec![] };
+        self.evert(s);
+        let roots = self.children(s);
+        for &root in &roots {
+            self.cut(root);
+        }
+        roots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    #[test]
+    fn attach_and_detach() {
+        let mut forest = Forest::<Sum<i64>, Sum<i64>>::new();
+        for x in 1 ..= 5 {
+            forest.add_node(x);
+        }
+        // 0 - 1, 2 - 3, 4
+        forest.link_weighted(1, 0, 10);
+        forest.link_weighted(3, 2, 20);
+        for v in [1, 3, 4, 2] {
+            forest.attach_to_super_root(v);
+        }
+        let s = forest.super_root();
+        assert!(forest.is_super_root(s));
+        assert_eq!(forest.root(4), s);
+        assert_eq!(forest.path_query(1, 3), Some(2 + 1 + 3 + 4));
+        assert_eq!(forest.path_query_edges(1, 3), Some(30));
+        assert_eq!(forest.path_query(4, 4), Some(5));
+        let mut roots = forest.detach_from_super_root();
+        roots.sort_unstable();
+        assert_eq!(roots, vec![0, 2, 4]);
+        assert!(!forest.connected(1, 3));
+        assert_eq!(forest.degree(s), 0);
+        assert_eq!(forest.super_root(), s);
+    }
+}
@@ -0,0 +1,203 @@
+use super::{Edge, EdgeId, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::LctError;
+
+/// 取り消すための記録
+enum Undo<V, W> {
+    /// child を切れば戻る
+    Link(usize),
+    /// child を parent に重み weight でつなぎ、番号・多重度・隣接リストでの位置を edge に戻せば戻る
+    Cut { child: usize, parent: usize, weight: W, edge: Edge },
+    /// この頂点を根にすれば戻る
+    Evert(usize),
+    /// 頂点の値を戻す
+    Value(usize, V),
+}
+
+/// Forest::transaction の中で森を操作する
+/// 操作は検査してから行い、失敗すれば森を変えずに Err を返す
+pub struct Transaction<'a, M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>> = Vec<ForestNodeOf<M, E, A, S>>> {
+    forest: &'a mut Forest<M, E, A, S, N>,
+    log: Vec<Undo<M::Value, E::Value>>,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// f の中の操作をまとめて行う
+    /// f が Err を返したら、それまでの操作を逆順に取り消してから Err を返す
+    /// 森の構造・頂点の値・辺の重みに加え、切った辺の EdgeId・多重度・隣接リストでの位置も元に戻る
+    pub fn transaction<R, X: From<LctError>>(&mut self, f: impl FnOnce(&mut Transaction<'_, M, E, A, S, N>) -> Result<R, X>) -> Result<R, X> {
+        let mut txn = Transaction { forest: self, log: vec![] };
+        let result = f(&mut txn);
+        if result.is_err() {
+            txn.rollback();
+        }
+        result
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Transaction<'_, M, E, A, S, N> {
+    fn rollback(&mut self) {
+        while let Some(undo) = self.log.pop() {
+            match undo {
+                Undo::Link(child) => {
+                    self.forest.cut(child);
+                }
+                Undo::Cut { child, parent, weight, edge } => self.forest.restore_edge(child, parent, weight, edge),
+                Undo::Evert(root) => self.forest.evert(root),
+                Undo::Value(v, value) => self.forest.set_value(v, value),
+            }
+        }
+    }
+
+    pub fn root(&mut self, v: usize) -> usize {
+        self.forest.root(v)
+    }
+
+    pub fn parent(&mut self, v: usize) -> Option<usize> {
+        self.forest.parent(v)
+    }
+
+    pub fn connected(&mut self, u: usize, v: usize) -> bool {
+        self.forest.connected(u, v)
+    }
+
+    pub fn value(&mut self, v: usize) -> &M::Value {
+        self.forest.value(v)
+    }
+
+    /// 木の根 child の親を parent にする
+    pub fn link(&mut self, child: usize, parent: usize) -> Result<(), LctError> {
+        self.link_weighted(child, parent, E::identity())
+    }
+
+    /// 木の根 child の親を parent にし、その辺の重みを weight にする
+    pub fn link_weighted(&mut self, child: usize, parent: usize, weight: E::Value) -> Result<(), LctError> {
+        if self.forest.root(child) != child {
+            return Err(LctError::NotRoot(child));
+        }
+        if self.forest.root(parent) == child {
+            return Err(LctError::Connected(child, parent));
+        }
+        self.forest.check_degree(child, parent)?;
+        self.forest.link_weighted(child, parent, weight);
+        self.log.push(Undo::Link(child));
+        Ok(())
+    }
+
+    /// v を親から切り離し、切った辺の番号を返す
+    pub fn cut(&mut self, v: usize) -> Result<EdgeId, LctError> {
+        let parent = self.forest.parent(v).ok_or(LctError::IsRoot(v))?;
+        let weight = self.forest.edge_weight(v, parent).unwrap().clone();
+        let edge = self.forest.edges[self.forest.edge_slot(v, parent).unwrap()].clone();
+        let id = self.forest.cut(v);
+        self.log.push(Undo::Cut { child: v, parent, weight, edge });
+        Ok(id)
+    }
+
+    /// v を根にする
+    pub fn evert(&mut self, v: usize) {
+        let root = self.forest.root(v);
+        self.forest.evert(v);
+        self.log.push(Undo::Evert(root));
+    }
+
+    pub fn set_value(&mut self, v: usize, value: M::Value) {
+        let old = self.forest.value(v).clone();
+        self.forest.set_value(v, value);
+        self.log.push(Undo::Value(v, old));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    #[test]
+    fn failed_transaction_rolls_back() {
+        let mut forest = Forest::<Sum<i64>, Sum<i64>>::new();
+        for x in 0 .. 6 {
+            forest.add_node(x);
+        }
+        // 0 - 1 - 2 - 3, 1 - 4, 5
+        forest.link_weighted(1, 0, 10);
+        forest.link_weighted(2, 1, 20);
+        forest.link_weighted(3, 2, 30);
+        forest.link_weighted(4, 1, 40);
+        let parents: Vec<_> = (0 .. 6).map(|v| forest.parent(v)).collect();
+        let result: Result<(), LctError> = forest.transaction(|txn| {
+            txn.cut(2)?;
+            txn.evert(3);
+            txn.link_weighted(3, 5, 7)?;
+            txn.set_value(4, 100);
+            txn.cut(4)?;
+            txn.link(4, 2)?;
+            txn.link(1, 3)?;
+            Ok(())
+        });
+        assert_eq!(result, Err(LctError::NotRoot(1)));
+        assert_eq!((0 .. 6).map(|v| forest.parent(v)).collect::<Vec<_>>(), parents);
+        assert_eq!(forest.path_query(3, 4), Some(2 + 1 + 4 + 3));
+        assert_eq!(forest.path_query_edges(3, 4), Some(30 + 20 + 40));
+        assert_eq!(forest.edge_count(), 4);
+
+        let result: Result<usize, LctError> = forest.transaction(|txn| {
+            txn.cut(2)?;
+            txn.link(2, 5)?;
+            assert_eq!(txn.link(5, 2), Err(LctError::Connected(5, 2)));
+            assert_eq!(txn.cut(0), Err(LctError::IsRoot(0)));
+            Ok(txn.root(3))
+        });
+        assert_eq!(result, Ok(5));
+        assert_eq!(forest.parent(2), Some(5));
+    }
+
+    #[test]
+    fn rollback_restores_cut_edges() {
+        for ordered in [false, true] {
+            let mut forest = if ordered { Forest::<Sum<i64>, Sum<i64>>::new_ordered() } else { Forest::new() };
+            for x in 0 .. 7 {
+                forest.add_node(x);
+            }
+            // 0 の子を 1, 2, 3, 4, 5 の順につなぎ、0 - 2 を三重の辺にする
+            for c in 1 .. 6 {
+                forest.link_weighted(c, 0, c as i64);
+            }
+            forest.link(6, 4);
+            assert_eq!(forest.add_multi_edge(0, 2), Ok(2));
+            assert_eq!(forest.add_multi_edge(2, 0), Ok(3));
+            let parents: Vec<_> = (0 .. 7).map(|v| forest.parent(v)).collect();
+            let ids: Vec<_> = (1 .. 7).map(|c| forest.edge(c, parents[c].unwrap()).unwrap()).collect();
+            let neighbors: Vec<Vec<_>> = (0 .. 7).map(|v| forest.neighbors(v).collect()).collect();
+            let result: Result<(), LctError> = forest.transaction(|txn| {
+                txn.cut(2)?;
+                txn.evert(4);
+                // 0 の親は 4 になっていて、つないだときとは逆向きに切る
+                txn.cut(0)?;
+                txn.cut(1)?;
+                txn.link(2, 1)?;
+                txn.cut(6)?;
+                txn.link(0, 6)?;
+                txn.cut(5)?;
+                txn.cut(5)?;
+                Ok(())
+            });
+            assert_eq!(result, Err(LctError::IsRoot(5)));
+            assert_eq!((0 .. 7).map(|v| forest.neighbors(v).collect::<Vec<_>>()).collect::<Vec<_>>(), neighbors);
+            assert_eq!(forest.children(0), vec![1, 2, 3, 4, 5]);
+            assert_eq!(forest.multiplicity(0, 2), 3);
+            assert_eq!(forest.multiplicity(0, 1), 1);
+            assert_eq!((0 .. 7).map(|v| forest.parent(v)).collect::<Vec<_>>(), parents);
+            for (c, id) in (1 .. 7).zip(ids) {
+                assert_eq!(forest.endpoints(id), Some((c, parents[c].unwrap())));
+                assert_eq!(forest.edge(c, parents[c].unwrap()), Some(id));
+            }
+            assert_eq!(forest.path_query_edges(6, 2), Some(4 + 2));
+            assert_eq!(forest.path_reversed_edges(0, 4), Some(vec![]));
+            assert_eq!(forest.edge_count(), 6);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use super::{Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::{Monoid, Transform};
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+
+impl<const D: usize, E, A, S, N: NodeStore<ForestNodeOf<Transform<D>, E, A, S>>> Forest<Transform<D>, E, A, S, N>
+where
+    E: Monoid,
+    A: Action<Transform<D>, E>,
+    S: Subtree<Transform<D>, E>,
+{
+    /// 根から v までの局所変換を合成した、v の座標系からの変換
+    pub fn world_transform(&mut self, v: usize) -> [[f64; D]; D] {
+        self.root_path_query(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Transform2;
+
+    fn translate(x: f64, y: f64) -> [[f64; 3]; 3] {
+        [[1.0, 0.0, x], [0.0, 1.0, y], [0.0, 0.0, 1.0]]
+    }
+
+    /// 90 度回転
+    fn rotate() -> [[f64; 3]; 3] {
+        [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]
+    }
+
+    fn apply(m: [[f64; 3]; 3], (x, y): (f64, f64)) -> (f64, f64) {
+        (m[0][0] * x + m[0][1] * y + m[0][2], m[1][0] * x + m[1][1] * y + m[1][2])
+    }
+
+    #[test]
+    fn world_transform() {
+        let mut forest = Forest::<Transform2>::new();
+        let root = forest.add_node(translate(10.0, 0.0));
+        let arm = forest.add_node(rotate());
+        let hand = forest.add_node(translate(1.0, 0.0));
+        forest.link(arm, root);
+        forest.link(hand, arm);
+        // hand の原点は arm の座標系で (1, 0)、回転して (0, 1)、平行移動して (10, 1)
+        assert_eq!(apply(forest.world_transform(hand), (0.0, 0.0)), (10.0, 1.0));
+        forest.set_value(arm, translate(0.0, 5.0));
+        assert_eq!(apply(forest.world_transform(hand), (0.0, 0.0)), (11.0, 5.0));
+        let other = forest.add_node(translate(-3.0, -3.0));
+        forest.cut(arm);
+        forest.link(arm, other);
+        assert_eq!(apply(forest.world_transform(hand), (2.0, 0.0)), (0.0, 2.0));
+        assert_eq!(apply(forest.world_transform(root), (0.0, 0.0)), (10.0, 0.0));
+    }
+}
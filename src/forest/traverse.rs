@@ -0,0 +1,327 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use super::{is_vertex, vertex_node, Forest, ForestNodeOf};
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::splay::SplayTree;
+use crate::store::NodeStore;
+use crate::subtree::Subtree;
+use crate::Dir;
+
+/// 根付き木の頂点を行きがけ順にたどる
+pub struct Preorder<'a, M: Monoid, E: Monoid, A: Action<M, E> = (), S: Subtree<M, E> = (), N: NodeStore<ForestNodeOf<M, E, A, S>> = Vec<ForestNodeOf<M, E, A, S>>> {
+    forest: &'a Forest<M, E, A, S, N>,
+    stack: Vec<(usize, Option<usize>)>,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Iterator for Preorder<'_, M, E, A, S, N> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let (x, from) = self.stack.pop()?;
+        // 隣接リストの順に訪れるよう、逆順に積む
+        for &e in self.forest.adjacency[x].iter().rev() {
+            if Some(e) != from {
+                self.stack.push((self.forest.opposite(e, x), Some(e)));
+            }
+        }
+        Some(x)
+    }
+}
+
+/// 根付き木の頂点を幅優先順にたどる
+pub struct Bfs<'a, M: Monoid, E: Monoid, A: Action<M, E> = (), S: Subtree<M, E> = (), N: NodeStore<ForestNodeOf<M, E, A, S>> = Vec<ForestNodeOf<M, E, A, S>>> {
+    forest: &'a Forest<M, E, A, S, N>,
+    queue: VecDeque<(usize, Option<usize>)>,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Iterator for Bfs<'_, M, E, A, S, N> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let (x, from) = self.queue.pop_front()?;
+        for &e in &self.forest.adjacency[x] {
+            if Some(e) != from {
+                self.queue.push_back((self.forest.opposite(e, x), Some(e)));
+            }
+        }
+        Some(x)
+    }
+}
+
+/// 根からの深さがちょうど d の頂点を行きがけ順にたどる（深さ d より下には降りない）
+pub struct AtDepth<'a, M: Monoid, E: Monoid, A: Action<M, E> = (), S: Subtree<M, E> = (), N: NodeStore<ForestNodeOf<M, E, A, S>> = Vec<ForestNodeOf<M, E, A, S>>> {
+    forest: &'a Forest<M, E, A, S, N>,
+    depth: usize,
+    /// (頂点, 入ってきた辺, 根からの深さ)
+    stack: Vec<(usize, Option<usize>, usize)>,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Iterator for AtDepth<'_, M, E, A, S, N> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while let Some((x, from, depth)) = self.stack.pop() {
+            if depth == self.depth {
+                return Some(x);
+            }
+            for &e in self.forest.adjacency[x].iter().rev() {
+                if Some(e) != from {
+                    self.stack.push((self.forest.opposite(e, x), Some(e), depth + 1));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// パス上の頂点を両端からたどる（捨てたときに根を元に戻す）
+pub struct PathIter<'a, M: Monoid, E: Monoid, A: Action<M, E> = (), S: Subtree<M, E> = (), N: NodeStore<ForestNodeOf<M, E, A, S>> = Vec<ForestNodeOf<M, E, A, S>>> {
+    forest: &'a mut Forest<M, E, A, S, N>,
+    root: usize,
+    /// 前と後ろからの通りがけ順の途中の節点
+    front: Vec<usize>,
+    back: Vec<usize>,
+    /// まだ返していない頂点の数
+    remaining: usize,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> PathIter<'_, M, E, A, S, N> {
+    /// x から dir の向きの端まで降りながら積む
+    fn descend(&mut self, mut cur: Option<usize>, dir: Dir) {
+        while let Some(x) = cur {
+            self.forest.push(x);
+            match dir {
+                Dir::Left => self.front.push(x),
+                Dir::Right => self.back.push(x),
+            }
+            cur = self.forest.child(x, dir);
+        }
+    }
+
+    /// dir の側から次の頂点を取り出す
+    fn step(&mut self, dir: Dir) -> Option<usize> {
+        while self.remaining > 0 {
+            let x = match dir {
+                Dir::Left => self.front.pop(),
+                Dir::Right => self.back.pop(),
+            }?;
+            self.descend(self.forest.child(x, dir.flip()), dir);
+            if is_vertex(x) {
+                self.remaining -= 1;
+                return Some(x >> 1);
+            }
+        }
+        None
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Iterator for PathIter<'_, M, E, A, S, N> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.step(Dir::Left)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> DoubleEndedIterator for PathIter<'_, M, E, A, S, N> {
+    fn next_back(&mut self) -> Option<usize> {
+        self.step(Dir::Right)
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> ExactSizeIterator for PathIter<'_, M, E, A, S, N> {}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Drop for PathIter<'_, M, E, A, S, N> {
+    fn drop(&mut self) {
+        self.forest.evert_node(self.root);
+    }
+}
+
+/// 森のすべての頂点を番号の順にたどる
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> IntoIterator for &Forest<M, E, A, S, N> {
+    type Item = usize;
+    type IntoIter = Range<usize>;
+
+    fn into_iter(self) -> Range<usize> {
+        self.iter()
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>, N: NodeStore<ForestNodeOf<M, E, A, S>>> Forest<M, E, A, S, N> {
+    /// すべての頂点を番号の順にたどる（木ごとにたどるなら preorder_iter）
+    pub fn iter(&self) -> Range<usize> {
+        0 .. self.len()
+    }
+
+    /// v を含む木を根から行きがけ順にたどる
+    pub fn preorder_iter(&mut self, v: usize) -> Preorder<'_, M, E, A, S, N> {
+        let root = self.root(v);
+        Preorder { forest: self, stack: vec![(root, None)] }
+    }
+
+    /// 現在の根に関する v の部分木を v から行きがけ順にたどる
+    pub fn subtree_iter(&mut self, v: usize) -> Preorder<'_, M, E, A, S, N> {
+        let from = self.parent_edge(vertex_node(v));
+        Preorder { forest: self, stack: vec![(v, from)] }
+    }
+
+    /// v を含む木を根から幅優先順にたどる
+    pub fn bfs_iter(&mut self, v: usize) -> Bfs<'_, M, E, A, S, N> {
+        let root = self.root(v);
+        Bfs { forest: self, queue: VecDeque::from([(root, None)]) }
+    }
+
+    /// v を含む木で、根からの深さが d の頂点を行きがけ順にたどる
+    /// 深さ d までの頂点をすべて訪れるので、返す頂点の数ではなく深さ d までの頂点の数に比例する時間がかかる
+    /// 深さ d に届かない枝を飛ばすには、Height を持つ森の nodes_at_depth_pruned を使う
+    pub fn nodes_at_depth(&mut self, v: usize, d: usize) -> AtDepth<'_, M, E, A, S, N> {
+        let root = self.root(v);
+        AtDepth { forest: self, depth: d, stack: vec![(root, None, 0)] }
+    }
+
+    /// u から v へのパス上の頂点を u に近い順にたどる（rev() で v の側から）
+    /// 異なる木に属するなら None
+    pub fn path_iter(&mut self, u: usize, v: usize) -> Option<PathIter<'_, M, E, A, S, N>> {
+        let (x, y) = (vertex_node(u), vertex_node(v));
+        let root = self.root_node(x);
+        if self.root_node(y) != root {
+            return None;
+        }
+        self.evert_node(x);
+        self.expose(y);
+        let remaining = self.nodes[y].len;
+        let mut iter = PathIter { forest: self, root, front: vec![], back: vec![], remaining };
+        iter.descend(Some(y), Dir::Left);
+        iter.descend(Some(y), Dir::Right);
+        Some(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn traversal_orders() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 7 {
+            forest.add_node(());
+        }
+        // 0 - 1 - 3, 1 - 4, 0 - 2 - 5, 6
+        forest.link(1, 0);
+        forest.link(2, 0);
+        forest.link(3, 1);
+        forest.link(4, 1);
+        forest.link(5, 2);
+        assert_eq!(forest.preorder_iter(5).collect::<Vec<_>>(), vec![0, 1, 3, 4, 2, 5]);
+        assert_eq!(forest.bfs_iter(5).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(forest.subtree_iter(1).collect::<Vec<_>>(), vec![1, 3, 4]);
+        assert_eq!(forest.subtree_iter(0).count(), 6);
+        assert_eq!(forest.nodes_at_depth(5, 0).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(forest.nodes_at_depth(3, 1).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(forest.nodes_at_depth(0, 2).collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(forest.nodes_at_depth(0, 3).count(), 0);
+        assert_eq!((&forest).into_iter().filter(|&v| forest.degree(v) == 1).collect::<Vec<_>>(), vec![3, 4, 5]);
+        let mut roots = vec![];
+        for v in &forest {
+            if forest.degree(v) == 0 {
+                roots.push(v);
+            }
+        }
+        assert_eq!(roots, vec![6]);
+        forest.evert(3);
+        assert_eq!(forest.subtree_iter(1).collect::<Vec<_>>(), vec![1, 0, 2, 5, 4]);
+        forest.evert(4);
+        assert_eq!(forest.preorder_iter(0).collect::<Vec<_>>(), vec![4, 1, 0, 2, 5, 3]);
+        assert_eq!(forest.bfs_iter(0).collect::<Vec<_>>(), vec![4, 1, 0, 3, 2, 5]);
+        assert_eq!(forest.nodes_at_depth(2, 2).collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(forest.nodes_at_depth(2, 4).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(forest.nodes_at_depth(6, 0).collect::<Vec<_>>(), vec![6]);
+        assert_eq!(forest.preorder_iter(6).collect::<Vec<_>>(), vec![6]);
+    }
+
+    #[test]
+    fn iter_adapters_match_to_parents() {
+        let mut rand = xorshift(0x6c62272e07bb0142);
+        let n = 40;
+        let mut forest = Forest::<()>::new();
+        assert_eq!(forest.iter().count(), 0);
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        for v in 1 .. n {
+            if rand(4) > 0 {
+                forest.link(v, rand(v));
+            }
+        }
+        for _ in 0 .. 5 {
+            let parents = forest.to_parents();
+            assert_eq!(forest.iter().map(|v| forest.parent(v)).collect::<Vec<_>>(), parents);
+            let roots: Vec<_> = (0 .. n).filter(|&v| parents[v].is_none()).collect();
+            assert_eq!(forest.iter().filter(|&v| forest.parent(v).is_none()).collect::<Vec<_>>(), roots);
+            let edges: Vec<_> = parents.iter().enumerate().filter_map(|(v, &p)| Some((v, p?))).collect();
+            assert_eq!((&forest).into_iter().filter_map(|v| forest.parent(v).map(|p| (v, p))).collect::<Vec<_>>(), edges);
+            let mut reversed = parents.clone();
+            reversed.reverse();
+            assert_eq!(forest.iter().rev().map(|v| forest.parent(v)).collect::<Vec<_>>(), reversed);
+            assert_eq!(forest.iter().len(), n);
+            forest.evert(rand(n));
+        }
+    }
+
+    #[test]
+    fn path_iter_both_ends() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 30;
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        for v in 1 .. n {
+            if rand(6) > 0 {
+                forest.link(v, rand(v));
+            }
+        }
+        for _ in 0 .. 300 {
+            let (u, v) = (rand(n), rand(n));
+            let root = forest.root(u);
+            let Some(mut path) = forest.path_iter(u, v).map(|iter| iter.collect::<Vec<_>>()) else {
+                assert!(!forest.connected(u, v));
+                continue;
+            };
+            let mut expected = vec![];
+            forest.path_for_each(u, v, |x, _| expected.push(x));
+            assert_eq!(path, expected);
+            assert_eq!(forest.root(u), root);
+            let mut iter = forest.path_iter(u, v).unwrap();
+            assert_eq!(iter.len(), path.len());
+            // 前と後ろからでたらめな順に取り出す
+            let (mut front, mut back) = (vec![], vec![]);
+            while iter.len() > 0 {
+                if rand(2) == 0 {
+                    front.push(iter.next().unwrap());
+                } else {
+                    back.push(iter.next_back().unwrap());
+                }
+            }
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next_back(), None);
+            drop(iter);
+            back.reverse();
+            front.extend(back);
+            assert_eq!(front, path);
+            path.reverse();
+            assert_eq!(forest.path_iter(u, v).unwrap().rev().collect::<Vec<_>>(), path);
+            if rand(3) == 0 {
+                forest.evert(rand(n));
+            }
+        }
+    }
+}
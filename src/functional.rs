@@ -0,0 +1,159 @@
+use crate::monoid::Count;
+use crate::Forest;
+
+/// 各頂点の出る辺が高々一本のグラフ（functional graph）
+///
+/// 頂点 v の後者 u を森の親として持つ。辺 v → u が閉路を閉じるときは v が木の根なので、
+/// その辺だけを森の外に印として残す。各木は根で終わるか、根からの印の辺で閉路になる。
+pub struct FunctionalGraph {
+    /// 頂点の値を 1 にして、パスの頂点の数で距離を求める
+    forest: Forest<Count>,
+    successor: Vec<Option<usize>>,
+    /// 閉路を閉じるので森に入れていない、根から出る辺の行き先
+    closing: Vec<Option<usize>>,
+}
+
+impl FunctionalGraph {
+    /// n 頂点で、どの頂点にも後者がない状態
+    pub fn new(n: usize) -> Self {
+        let mut forest = Forest::new();
+        for _ in 0 .. n {
+            forest.add_node(1);
+        }
+        Self { forest, successor: vec![None; n], closing: vec![None; n] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.successor.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.successor.is_empty()
+    }
+
+    pub fn successor(&self, v: usize) -> Option<usize> {
+        self.successor[v]
+    }
+
+    /// v の後者を u にする（None なら後者をなくす）
+    pub fn set_successor(&mut self, v: usize, u: Option<usize>) {
+        if self.closing[v].take().is_none() && self.successor[v].is_some() {
+            let root = self.forest.root(v);
+            self.forest.cut(v);
+            // 閉路が切れたなら、根から出る辺を森に入れられる
+            if let Some(w) = self.closing[root] {
+                if !self.forest.connected(root, w) {
+                    self.closing[root] = None;
+                    self.forest.link(root, w);
+                }
+            }
+        }
+        self.successor[v] = u;
+        if let Some(u) = u {
+            if self.forest.connected(v, u) {
+                self.closing[v] = Some(u);
+            } else {
+                self.forest.link(v, u);
+            }
+        }
+    }
+
+    /// 森でのパスの辺の数
+    fn distance(&mut self, u: usize, v: usize) -> usize {
+        self.forest.path_query(u, v).unwrap() - 1
+    }
+
+    /// v から後者をたどって最初に着く閉路上の頂点（閉路に着かなければ None）
+    pub fn cycle_entry(&mut self, v: usize) -> Option<usize> {
+        let root = self.forest.root(v);
+        // 閉路は印の辺の行き先 w から根までのパスと印の辺からなる
+        let w = self.closing[root]?;
+        self.forest.lca(v, w)
+    }
+
+    /// v から閉路に着くまでの辺の数
+    pub fn steps_to_cycle(&mut self, v: usize) -> Option<usize> {
+        let entry = self.cycle_entry(v)?;
+        Some(self.distance(v, entry))
+    }
+
+    /// v からたどり着く閉路の長さ
+    pub fn cycle_len(&mut self, v: usize) -> Option<usize> {
+        let root = self.forest.root(v);
+        let w = self.closing[root]?;
+        Some(self.distance(w, root) + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    /// 後者をたどって (閉路に着くまでの辺の数, 着いた頂点, 閉路の長さ) を求める
+    fn naive(successor: &[Option<usize>], v: usize) -> Option<(usize, usize, usize)> {
+        let mut seen = vec![None; successor.len()];
+        let (mut x, mut step) = (v, 0usize);
+        loop {
+            if let Some(first) = seen[x] {
+                // x は閉路上で、閉路に初めて着いた頂点をもう一度たどって求める
+                let len = step - first;
+                let mut y = v;
+                for s in 0 .. {
+                    let mut z = y;
+                    let on_cycle = (0 .. len).any(|_| {
+                        z = successor[z].unwrap();
+                        z == y
+                    });
+                    if on_cycle {
+                        return Some((s, y, len));
+                    }
+                    y = successor[y].unwrap();
+                }
+            }
+            seen[x] = Some(step);
+            x = successor[x]?;
+            step += 1;
+        }
+    }
+
+    #[test]
+    fn against_naive() {
+        let mut rand = xorshift(0xbb67ae8584caa73b);
+        let n = 12;
+        let mut graph = FunctionalGraph::new(n);
+        let mut successor = vec![None; n];
+        for _ in 0 .. 3000 {
+            let v = rand(n);
+            let u = if rand(5) == 0 { None } else { Some(rand(n)) };
+            graph.set_successor(v, u);
+            successor[v] = u;
+            let w = rand(n);
+            let expected = naive(&successor, w);
+            assert_eq!(graph.successor(w), successor[w]);
+            assert_eq!(graph.steps_to_cycle(w), expected.map(|e| e.0));
+            assert_eq!(graph.cycle_entry(w), expected.map(|e| e.1));
+            assert_eq!(graph.cycle_len(w), expected.map(|e| e.2));
+        }
+    }
+
+    #[test]
+    fn rho() {
+        // 0 → 1 → 2 → 3 → 1
+        let mut graph = FunctionalGraph::new(4);
+        graph.set_successor(0, Some(1));
+        graph.set_successor(1, Some(2));
+        graph.set_successor(2, Some(3));
+        graph.set_successor(3, Some(1));
+        assert_eq!(graph.cycle_entry(0), Some(1));
+        assert_eq!(graph.steps_to_cycle(0), Some(1));
+        assert_eq!(graph.cycle_len(0), Some(3));
+        // 2 → 0 にすると 0 → 1 → 2 → 0 が閉路になる
+        graph.set_successor(2, Some(0));
+        assert_eq!(graph.steps_to_cycle(3), Some(1));
+        assert_eq!(graph.cycle_entry(3), Some(1));
+        graph.set_successor(1, None);
+        assert_eq!(graph.steps_to_cycle(3), None);
+        assert_eq!(graph.cycle_len(2), None);
+    }
+}
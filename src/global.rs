@@ -0,0 +1,102 @@
+/// スレッドごとの森を一つ宣言し、頂点番号で操作する関数をまとめて定義する
+///
+/// `mod lct { link_cut_tree::global_forest!(Sum<i64>); }` のように、値のモノイド（と辺のモノイド）を与えて
+/// モジュールの中で使う。定義される関数は add_node, link, cut, evert, root, connected, lca, set_value, query と、
+/// 森そのものに触れる with_forest、森を空にする reset。
+#[macro_export]
+macro_rules! global_forest {
+    ($m:ty) => {
+        $crate::global_forest!($m, ());
+    };
+    ($m:ty, $e:ty) => {
+        type Value = <$m as $crate::Monoid>::Value;
+
+        thread_local! {
+            static FOREST: ::std::cell::RefCell<$crate::Forest<$m, $e>> = ::std::cell::RefCell::new($crate::Forest::new());
+        }
+
+        #[allow(dead_code)]
+        pub fn with_forest<R>(f: impl FnOnce(&mut $crate::Forest<$m, $e>) -> R) -> R {
+            FOREST.with(|forest| f(&mut forest.borrow_mut()))
+        }
+
+        #[allow(dead_code)]
+        pub fn reset() {
+            with_forest(|forest| forest.clear())
+        }
+
+        #[allow(dead_code)]
+        pub fn add_node(value: Value) -> usize {
+            with_forest(|forest| forest.add_node(value))
+        }
+
+        #[allow(dead_code)]
+        pub fn link(child: usize, parent: usize) {
+            with_forest(|forest| forest.link(child, parent))
+        }
+
+        #[allow(dead_code)]
+        pub fn cut(v: usize) {
+            with_forest(|forest| {
+                forest.cut(v);
+            })
+        }
+
+        #[allow(dead_code)]
+        pub fn evert(v: usize) {
+            with_forest(|forest| forest.evert(v))
+        }
+
+        #[allow(dead_code)]
+        pub fn root(v: usize) -> usize {
+            with_forest(|forest| forest.root(v))
+        }
+
+        #[allow(dead_code)]
+        pub fn connected(u: usize, v: usize) -> bool {
+            with_forest(|forest| forest.connected(u, v))
+        }
+
+        #[allow(dead_code)]
+        pub fn lca(u: usize, v: usize) -> Option<usize> {
+            with_forest(|forest| forest.lca(u, v))
+        }
+
+        #[allow(dead_code)]
+        pub fn set_value(v: usize, value: Value) {
+            with_forest(|forest| forest.set_value(v, value))
+        }
+
+        #[allow(dead_code)]
+        pub fn query(u: usize, v: usize) -> Option<Value> {
+            with_forest(|forest| forest.path_query(u, v))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    mod lct {
+        crate::global_forest!(crate::monoid::Sum<i64>);
+    }
+
+    #[test]
+    fn global_forest() {
+        lct::reset();
+        for x in 0 .. 4 {
+            lct::add_node(x);
+        }
+        lct::link(1, 0);
+        lct::link(2, 1);
+        lct::link(3, 1);
+        assert_eq!(lct::query(2, 3), Some(6));
+        assert_eq!(lct::lca(2, 3), Some(1));
+        lct::cut(1);
+        assert!(!lct::connected(0, 2));
+        lct::evert(2);
+        assert_eq!(lct::root(3), 2);
+        lct::set_value(1, 10);
+        assert_eq!(lct::query(3, 2), Some(15));
+        assert_eq!(lct::with_forest(|forest| forest.len()), 4);
+    }
+}
@@ -1,22 +1,125 @@
 use std::rc::*;
 use std::cell::*;
 
-pub type RefNode = RefCell<LCTNode>;
-pub type RcNode = Rc<RefNode>;
-pub type WeakNode = Weak<RefNode>;
+pub mod action;
+mod any_forest;
+#[cfg(feature = "animation")]
+pub mod animation;
+mod bipartite;
+mod cache;
+#[cfg(feature = "compat")]
+pub mod compat;
+mod error;
+mod fixed;
+mod forest;
+mod functional;
+mod global;
+mod macros;
+mod marked_lca;
+pub mod monoid;
+mod msf;
+mod offline;
+mod observer;
+mod policy;
+mod potential;
+mod remap;
+mod retro;
+mod sequence;
+pub mod script;
+mod splay;
+mod spt;
+mod store;
+pub mod subtree;
+mod sync;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "tracing")]
+pub mod trace;
+mod view;
+#[cfg(feature = "wal")]
+mod wal;
+
+pub use action::Action;
+pub use any_forest::AnyForest;
+pub use bipartite::DynamicBipartite;
+pub use cache::CachedForest;
+pub use error::{CsvError, LctError};
+pub use fixed::FixedForest;
+pub use forest::{edit_script, AtDepth, Bfs, ComponentId, Cursor, CyclePath, DecompositionStats, Edit, EdgeId, ExposedPath, Forest, ForestBuilder, ForestNode, ForestNodeOf, Frozen, FrozenAnswer, FrozenQuery, GraphmlValue, Handle, HookNode, LinkError, OpStats, Parentheses, PathIter, PathOrder, Preorder, SplayNode, Toggle, Transaction, TreeRepr};
+#[cfg(feature = "parallel")]
+pub use forest::par_path_queries;
+#[cfg(feature = "snapshot")]
+pub use forest::{FrozenView, SnapshotError, SnapshotValue};
+pub use functional::FunctionalGraph;
+pub use marked_lca::MarkedLca;
+pub use monoid::Monoid;
+pub use msf::DynamicMsf;
+pub use offline::{offline_connectivity, ConnectivityEvent};
+pub use observer::Observer;
+pub use policy::{Fallible, Panic, Policy, PolicyForest};
+pub use potential::PotentialForest;
+pub use remap::IdRemap;
+pub use retro::{RetroEvent, RetroactiveForest};
+pub use sequence::{SequenceNode, SplaySequences};
+pub use splay::SplayTree;
+pub use spt::ShortestPathTree;
+pub use store::{NodeStore, Slab};
+#[cfg(feature = "snapshot")]
+pub use store::FileStore;
+pub use subtree::{Subtree, SubtreeAction};
+pub use sync::ForestSync;
+pub use view::{ForestRead, ForestWrite};
+#[cfg(feature = "wal")]
+pub use wal::{WalError, WalForest};
+
+type RefNode = RefCell<LCTNode>;
+type RcNode = Rc<RefNode>;
+type WeakNode = Weak<RefNode>;
 
-pub struct LCTNode {
+/// 子の向き
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Dir {
+    Left,
+    Right,
+}
+
+impl Dir {
+    /// 逆向き
+    pub fn flip(self) -> Self {
+        match self {
+            Dir::Left => Dir::Right,
+            Dir::Right => Dir::Left,
+        }
+    }
+
+    pub(crate) fn index(self) -> usize {
+        match self {
+            Dir::Left => 0,
+            Dir::Right => 1,
+        }
+    }
+}
+
+/// 次に作る節点の通し番号
+static NEXT_NODE_ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+struct LCTNode {
     parent: Option<WeakNode>,
     children: [Option<RcNode>; 2],
     len: usize,
+    /// 作った順の通し番号
+    id: usize,
 }
 
-pub trait LinkCutTree: std::ops::Deref<Target = RefNode> {
+#[allow(clippy::len_without_is_empty)]
+trait LinkCutTree: std::ops::Deref<Target = RefNode> {
+    #[allow(clippy::new_ret_no_self)]
     fn new() -> RcNode {
         Rc::new(RefCell::new(LCTNode {
             parent: None,
             children: [None, None],
             len: 1,
+            id: NEXT_NODE_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
         }))
     }
 
@@ -32,7 +135,7 @@ pub trait LinkCutTree: std::ops::Deref<Target = RefNode> {
         self.get().len
     }
 
-    fn len_mut(&self) -> RefMut<usize> {
+    fn len_mut(&self) -> RefMut<'_, usize> {
         RefMut::map(self.get_mut(), |node| &mut node.len)
     }
 
@@ -40,40 +143,37 @@ pub trait LinkCutTree: std::ops::Deref<Target = RefNode> {
         self.get().parent.as_ref().and_then(Weak::upgrade)
     }
 
-    fn parent_mut(&self) -> RefMut<Option<WeakNode>> {
+    fn parent_mut(&self) -> RefMut<'_, Option<WeakNode>> {
         RefMut::map(self.get_mut(), |node| &mut node.parent)
     }
 
-    fn child(&self, dir: usize) -> Option<RcNode> {
-        assert!(dir < 2);
-        Some(self.get().children[dir].as_ref()?.rc())
+    fn child(&self, dir: Dir) -> Option<RcNode> {
+        Some(self.get().children[dir.index()].as_ref()?.rc())
     }
 
-    fn child_mut(&self, dir: usize) -> RefMut<Option<RcNode>> {
-        assert!(dir < 2);
-        RefMut::map(self.get_mut(), |node| &mut node.children[dir])
+    fn child_mut(&self, dir: Dir) -> RefMut<'_, Option<RcNode>> {
+        RefMut::map(self.get_mut(), |node| &mut node.children[dir.index()])
     }
 
     /// 親から見た自分の向き
-    fn dir(&self) -> Option<usize> {
+    /// 親と、親から見た自分の向き（親が light edge でつながっている場合は向きが None）
+    fn parent_dir(&self) -> Option<(RcNode, Option<Dir>)> {
         let parent = self.get().parent.as_ref()?.upgrade()?;
-        for dir in 0 .. 2 {
-            if let Some(child) = &parent.get().children[dir] {
-                if Rc::ptr_eq(self.ref_rc(), child) {
-                    return Some(dir);
-                }
-            }
-        }
-        // 親が light edge でつながっている場合
-        None
+        let dir = {
+            let node = parent.get();
+            let is_self = |dir: Dir| node.children[dir.index()].as_ref().is_some_and(|child| Rc::ptr_eq(self.ref_rc(), child));
+            [Dir::Left, Dir::Right].into_iter().find(|&dir| is_self(dir))
+        };
+        Some((parent, dir))
     }
 
-    fn is_path_root(&self) -> bool {
-        self.dir().is_none()
+    /// 親から見た自分の向き
+    fn dir(&self) -> Option<Dir> {
+        self.parent_dir()?.1
     }
 
-    fn path_parent(&self) -> Option<RcNode> {
-        self.dir().and_then(|_| self.parent())
+    fn is_path_root(&self) -> bool {
+        self.dir().is_none()
     }
 
     fn update(&self) {
@@ -84,34 +184,50 @@ pub trait LinkCutTree: std::ops::Deref<Target = RefNode> {
         *self.len_mut() = len;
     }
 
-    fn rotate(&self) {
-        if let Some(dir) = self.dir() {
-            let parent_weak = self.parent_mut().take().unwrap();
-            let parent = parent_weak.upgrade().unwrap();
-            let child = self.child_mut(1 ^ dir).take();
-            if let Some(child) = child.as_ref() {
-                *child.parent_mut() = Some(parent_weak.clone());
-            }
-            *parent.child_mut(dir) = child.clone();
-            if let Some(parent_dir) = parent.dir() {
-                let ancestor = parent.parent().unwrap();
-                *ancestor.child_mut(parent_dir) = Some(self.rc());
+    /// 自身が dir 側の子である parent との間で回転する
+    /// grand は parent の親と、そこから見た parent の向き（parent が splay 木の根なら None）
+    /// 付け替える強い参照と弱い参照は使い回し、複製はしない
+    fn rotate_up(&self, parent: RcNode, dir: Dir, grand: Option<(&RcNode, Dir)>) {
+        let me = self.ref_rc();
+        {
+            let mut node = me.borrow_mut();
+            let mut p = parent.borrow_mut();
+            let own = p.children[dir.index()].take();
+            let to_parent = node.parent.take().unwrap();
+            let inner = node.children[dir.flip().index()].take();
+            let to_me = match &inner {
+                Some(inner) => inner.borrow_mut().parent.replace(to_parent).unwrap(),
+                None => Rc::downgrade(me),
+            };
+            node.parent = p.parent.replace(to_me);
+            p.children[dir.index()] = inner;
+            if let Some((grand, parent_dir)) = grand {
+                grand.borrow_mut().children[parent_dir.index()] = own;
             }
-            *self.parent_mut() = parent.parent_mut().replace(self.weak());
-            parent.update();
-            self.update();
         }
+        parent.update();
+        me.borrow_mut().children[dir.flip().index()] = Some(parent);
+        self.update();
     }
 
     fn splay(&self) {
-        while let Some(parent) = self.path_parent() {
-            if parent.is_path_root() {
-            } else if self.dir() == parent.dir() {
-                parent.rotate();
+        while let Some((parent, Some(dir))) = self.parent_dir() {
+            let Some((grand, Some(parent_dir))) = parent.parent_dir() else {
+                self.rotate_up(parent, dir, None);
+                break;
+            };
+            let great = grand.parent_dir();
+            let great = match &great {
+                Some((node, Some(dir))) => Some((node, *dir)),
+                _ => None,
+            };
+            if dir == parent_dir {
+                parent.rotate_up(grand, parent_dir, great);
+                self.rotate_up(parent, dir, great);
             } else {
-                self.rotate();
+                self.rotate_up(parent, dir, Some((&grand, parent_dir)));
+                self.rotate_up(grand, parent_dir, great);
             }
-            self.rotate();
         }
     }
 
@@ -119,11 +235,11 @@ pub trait LinkCutTree: std::ops::Deref<Target = RefNode> {
     fn expose(&self) {
         loop {
             self.splay();
-            self.child_mut(1).take();
+            self.child_mut(Dir::Right).take();
             self.update();
             if let Some(parent) = self.parent() {
                 parent.splay();
-                parent.child_mut(1).replace(self.rc());
+                parent.child_mut(Dir::Right).replace(self.rc());
                 parent.update();
             } else {
                 break;
@@ -136,12 +252,12 @@ pub trait LinkCutTree: std::ops::Deref<Target = RefNode> {
         self.expose();
         new_parent.expose();
         self.parent_mut().replace(new_parent.weak());
-        new_parent.child_mut(1).replace(self.rc());
+        new_parent.child_mut(Dir::Right).replace(self.rc());
     }
 
     /// 自身を親から切り離す
     fn cut(&self) {
-        self.child_mut(0).take().unwrap().parent_mut().take();
+        self.child_mut(Dir::Left).take().unwrap().parent_mut().take();
     }
 }
 
@@ -149,11 +265,168 @@ impl LinkCutTree for RcNode {
     fn ref_rc(&self) -> &Self { self }
 }
 
+/// 一つの節点だけを持つ link-cut tree の節点
+/// 複製しても同じ節点を指し、== は同じ節点かどうかを比べる
+/// 順序とハッシュは作った順の通し番号によるので、HashMap や BTreeMap の鍵にできる
+#[derive(Clone)]
+pub struct Node(RcNode);
+
+impl Default for Node {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Node {}
+
+impl std::hash::Hash for Node {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 先に作った節点ほど小さい
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.id().cmp(&other.id())
+    }
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl Node {
+    pub fn new() -> Self {
+        Node(<RcNode as LinkCutTree>::new())
+    }
+
+    /// 作った順の通し番号（プロセスの中で節点ごとに異なる）
+    pub fn id(&self) -> usize {
+        self.0.get().id
+    }
+
+    /// 自身を根とする splay 木の節点の数
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// splay 木の親か、light edge でつながった親
+    pub fn parent(&self) -> Option<Node> {
+        self.0.parent().map(Node)
+    }
+
+    pub fn child(&self, dir: Dir) -> Option<Node> {
+        self.0.child(dir).map(Node)
+    }
+
+    /// 親から見た自分の向き（splay 木の根なら None）
+    pub fn dir(&self) -> Option<Dir> {
+        self.0.dir()
+    }
+
+    pub fn is_path_root(&self) -> bool {
+        self.0.is_path_root()
+    }
+
+    pub fn splay(&self) {
+        self.0.splay();
+    }
+
+    /// 自身を木の根のパスにつなげ、そのパスの根にする
+    pub fn expose(&self) {
+        self.0.expose();
+    }
+
+    /// 自身の親を new_parent にする
+    pub fn link(&self, new_parent: &Node) {
+        self.0.link(&new_parent.0);
+    }
+
+    /// 自身を親から切り離す（expose した後に呼ぶ）
+    pub fn cut(&self) {
+        self.0.cut();
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn expose_path_len() {
+        let nodes: Vec<Node> = (0 .. 5).map(|_| Node::new()).collect();
+        for i in 1 .. 5 {
+            nodes[i].link(&nodes[i - 1]);
+        }
+        nodes[4].expose();
+        assert_eq!(nodes[4].len(), 5);
+        assert!(nodes[4].child(Dir::Right).is_none());
+        nodes[2].expose();
+        assert_eq!(nodes[2].len(), 3);
+    }
+
+    #[test]
+    fn node_identity_and_cut() {
+        let (a, b) = (Node::new(), Node::new());
+        b.link(&a);
+        assert!(b.parent() == Some(a.clone()));
+        assert!(a.clone() != b);
+        b.expose();
+        assert_eq!(b.len(), 2);
+        b.cut();
+        assert!(b.parent().is_none() && a.parent().is_none());
+    }
+
+    #[test]
+    // ハッシュと順序は変わらない通し番号だけで決まる
+    #[allow(clippy::mutable_key_type)]
+    fn nodes_as_keys() {
+        let nodes: Vec<Node> = (0 .. 4).map(|_| Node::new()).collect();
+        let mut labels = std::collections::HashMap::new();
+        for (i, node) in nodes.iter().enumerate() {
+            labels.insert(node.clone(), i);
+        }
+        nodes[1].link(&nodes[0]);
+        nodes[1].expose();
+        assert_eq!(labels[&nodes[1].child(Dir::Left).unwrap()], 0);
+        let sorted: std::collections::BTreeSet<Node> = nodes.iter().rev().cloned().collect();
+        assert!(sorted.into_iter().eq(nodes.iter().cloned()));
+        assert!(nodes[0] < nodes[3] && nodes[0].id() < nodes[3].id());
+    }
+
+    #[test]
+    fn expose_random_tree() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 40;
+        let nodes: Vec<Node> = (0 .. n).map(|_| Node::new()).collect();
+        let mut depth = vec![0; n];
+        for i in 1 .. n {
+            let p = rand(i);
+            nodes[i].link(&nodes[p]);
+            depth[i] = depth[p] + 1;
+        }
+        for _ in 0 .. 500 {
+            let v = rand(n);
+            nodes[v].expose();
+            assert_eq!(nodes[v].len(), depth[v] + 1);
+            assert!(nodes[v].is_path_root());
+        }
+    }
+
     #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+    fn dir_flip() {
+        assert_eq!(Dir::Left.flip(), Dir::Right);
+        assert_eq!(Dir::Right.flip().flip(), Dir::Right);
     }
 }
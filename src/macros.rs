@@ -0,0 +1,46 @@
+/// 親と子の並びから森を作る
+///
+/// `forest! { 0 => [1, 2], 1 => [3] }` は頂点 0 の子を 1, 2 に、1 の子を 3 にした森で、
+/// `forest! { [5, 6, 7, 8]; 0 => [1, 2], 1 => [3] }` のように先頭に頂点の値を並べることもできる。
+/// 値を省いた頂点や、値の並びより大きな番号の頂点の値は単位元になる。
+/// 森の型は使う側で決める（`let forest: Forest = forest! { ... };` など）。
+#[macro_export]
+macro_rules! forest {
+    ([$($value:expr),* $(,)?]; $($parent:expr => [$($child:expr),* $(,)?]),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut forest = $crate::Forest::new();
+        $(forest.add_node($value);)*
+        $(
+            let parent: usize = $parent;
+            let children: &[usize] = &[$($child),*];
+            forest.grow(children.iter().fold(parent, |n, &child| n.max(child)) + 1);
+            for &child in children {
+                forest.link(child, parent);
+            }
+        )*
+        forest
+    }};
+    ($($parent:expr => [$($child:expr),* $(,)?]),* $(,)?) => {
+        $crate::forest!([]; $($parent => [$($child),*]),*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monoid::Sum;
+    use crate::Forest;
+
+    #[test]
+    fn forest_literal() {
+        let mut forest: Forest = forest! { 0 => [1, 2], 1 => [3], 5 => [] };
+        assert_eq!(forest.len(), 6);
+        assert_eq!(forest.to_parents(), vec![None, Some(0), Some(0), Some(1), None, None]);
+        let mut forest: Forest<Sum<i64>> = forest! { [5, 6, 7]; 0 => [1, 2], 1 => [3] };
+        assert_eq!(forest.path_query(2, 3), Some(18));
+        let mut forest: Forest<Sum<i64>> = forest! { [1, 2]; };
+        assert_eq!(forest.len(), 2);
+        assert!(!forest.connected(0, 1));
+        let empty: Forest = forest! {};
+        assert!(empty.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+
+use crate::action::Action;
+use crate::monoid::{Count, Monoid};
+use crate::subtree::SubtreeSum;
+use crate::Forest;
+
+/// 印の付いた頂点の多重集合の最小共通祖先を、森を変えながら求める
+/// 頂点の値を印の数として部分木の印の数を持ち、根から印の付いた頂点へのパスを二分探索する
+/// 構造を変える操作は Deref で Forest のものを使う（頂点の値は印の数なので set_value で書き換えないこと）
+pub struct MarkedLca<E: Monoid = (), A: Action<Count, E> = ()> {
+    forest: Forest<Count, E, A, SubtreeSum<usize>>,
+    /// 印の付いた頂点と、その印の数
+    marked: BTreeMap<usize, usize>,
+    total: usize,
+}
+
+impl<E: Monoid, A: Action<Count, E>> Deref for MarkedLca<E, A> {
+    type Target = Forest<Count, E, A, SubtreeSum<usize>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.forest
+    }
+}
+
+impl<E: Monoid, A: Action<Count, E>> DerefMut for MarkedLca<E, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.forest
+    }
+}
+
+impl<E: Monoid, A: Action<Count, E>> MarkedLca<E, A> {
+    /// n 頂点で辺も印もない状態
+    pub fn new(n: usize) -> Self {
+        let mut forest = Forest::new();
+        for _ in 0 .. n {
+            forest.add_node(0);
+        }
+        Self { forest, marked: BTreeMap::new(), total: 0 }
+    }
+
+    /// 印の数の合計
+    pub fn marks(&self) -> usize {
+        self.total
+    }
+
+    /// v の印の数
+    pub fn mark_count(&self, v: usize) -> usize {
+        self.marked.get(&v).copied().unwrap_or(0)
+    }
+
+    /// v に印を一つ加える
+    pub fn mark(&mut self, v: usize) {
+        let count = self.marked.entry(v).or_insert(0);
+        *count += 1;
+        self.forest.set_value(v, *count);
+        self.total += 1;
+    }
+
+    /// v の印を一つ取り除き、取り除けたかを返す
+    pub fn unmark(&mut self, v: usize) -> bool {
+        let Some(count) = self.marked.get_mut(&v) else { return false };
+        *count -= 1;
+        let count = *count;
+        if count == 0 {
+            self.marked.remove(&v);
+        }
+        self.forest.set_value(v, count);
+        self.total -= 1;
+        true
+    }
+
+    /// 印の付いた頂点すべての、現在の根に関する最小共通祖先（印がないか、異なる木にまたがるなら None）
+    /// 部分木の印の数が合計に等しい頂点は根からのパスをなすので、その最も深い頂点を二分探索で求める
+    pub fn lca(&mut self) -> Option<usize> {
+        let &m = self.marked.keys().next()?;
+        let root = self.forest.root(m);
+        if self.forest.subtree_sum(root) != self.total {
+            return None;
+        }
+        // パスの先頭から数えて lo 番目は条件を満たし、hi 番目は満たさない
+        let (mut lo, mut hi) = (0, self.forest.expose_path(root, m).unwrap().len());
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            let w = self.forest.expose_path(root, m).unwrap().kth(mid).unwrap();
+            if self.forest.subtree_sum(w) == self.total {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        self.forest.expose_path(root, m).unwrap().kth(lo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    /// 根から v までの祖先の列
+    fn ancestors(parent: &[Option<usize>], mut v: usize) -> Vec<usize> {
+        let mut path = vec![v];
+        while let Some(p) = parent[v] {
+            path.push(p);
+            v = p;
+        }
+        path.reverse();
+        path
+    }
+
+    fn evert(parent: &mut [Option<usize>], v: usize) {
+        let path = ancestors(parent, v);
+        for w in path.windows(2) {
+            parent[w[0]] = Some(w[1]);
+        }
+        parent[v] = None;
+    }
+
+    #[test]
+    fn lca_against_naive() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 30;
+        let mut lca = MarkedLca::<()>::new(n);
+        let mut parent = vec![None; n];
+        let mut marks = vec![0usize; n];
+        for _ in 0 .. 3000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(6) {
+                0 if ancestors(&parent, u)[0] != ancestors(&parent, v)[0] => {
+                    evert(&mut parent, u);
+                    parent[u] = Some(v);
+                    lca.evert(u);
+                    lca.link(u, v);
+                }
+                1 if parent[u].is_some() => {
+                    parent[u] = None;
+                    lca.cut(u);
+                }
+                2 => {
+                    evert(&mut parent, u);
+                    lca.evert(u);
+                }
+                3 => {
+                    marks[u] += 1;
+                    lca.mark(u);
+                }
+                4 => {
+                    assert_eq!(lca.unmark(u), marks[u] > 0);
+                    marks[u] = marks[u].saturating_sub(1);
+                }
+                _ => {}
+            }
+            let marked: Vec<usize> = (0 .. n).filter(|&w| marks[w] > 0).collect();
+            // 印の付いた頂点の祖先の列に共通する最も長い先頭部分の末尾
+            let paths: Vec<Vec<usize>> = marked.iter().map(|&w| ancestors(&parent, w)).collect();
+            let expected = paths.first().and_then(|first| {
+                let common = (0 .. first.len()).take_while(|&i| paths.iter().all(|path| path.get(i) == Some(&first[i]))).count();
+                common.checked_sub(1).map(|i| first[i])
+            });
+            assert_eq!(lca.lca(), expected);
+            assert_eq!(lca.marks(), marks.iter().sum::<usize>());
+            assert_eq!(lca.mark_count(u), marks[u]);
+        }
+    }
+}
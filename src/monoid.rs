@@ -0,0 +1,589 @@
+use std::marker::PhantomData;
+use std::ops::{Add, BitXor, Div, Mul, Neg, Rem, Sub};
+use std::sync::Arc;
+
+/// パス上で集約する値の演算
+pub trait Monoid {
+    type Value: Clone;
+
+    fn identity() -> Self::Value;
+    fn op(a: &Self::Value, b: &Self::Value) -> Self::Value;
+}
+
+/// 集約しない
+impl Monoid for () {
+    type Value = ();
+
+    fn identity() {}
+    fn op(_: &(), _: &()) {}
+}
+
+/// 二つの集約を同時に持つ
+impl<A: Monoid, B: Monoid> Monoid for (A, B) {
+    type Value = (A::Value, B::Value);
+
+    fn identity() -> Self::Value { (A::identity(), B::identity()) }
+    fn op(a: &Self::Value, b: &Self::Value) -> Self::Value { (A::op(&a.0, &b.0), B::op(&a.1, &b.1)) }
+}
+
+/// 印の付いた頂点の数（値は 0 か 1）
+pub struct Count;
+
+impl Monoid for Count {
+    type Value = usize;
+
+    fn identity() -> usize { 0 }
+    fn op(a: &usize, b: &usize) -> usize { a + b }
+}
+
+/// 和（i64 であふれるなら i128 や多倍長整数の型をそのまま使える）
+pub struct Sum<T>(PhantomData<T>);
+
+impl<T: Clone + Default + Add<Output = T>> Monoid for Sum<T> {
+    type Value = T;
+
+    fn identity() -> T { T::default() }
+    fn op(a: &T, b: &T) -> T { a.clone() + b.clone() }
+}
+
+/// 積（i64 であふれるなら i128 や多倍長整数の型をそのまま使える）
+pub struct Prod<T>(PhantomData<T>);
+
+impl<T: Clone + Mul<Output = T> + From<u8>> Monoid for Prod<T> {
+    type Value = T;
+
+    fn identity() -> T { T::from(1) }
+    fn op(a: &T, b: &T) -> T { a.clone() * b.clone() }
+}
+
+/// 誤差を補正しながら求める f64 の和（Neumaier の方法）
+/// 値は (和, 和に足し損ねた丸め誤差) で、CompensatedSum::total で一つの f64 にする
+/// 合成は和の順によらないので、列の向きによらない
+pub struct CompensatedSum;
+
+impl CompensatedSum {
+    /// 値 x だけの和
+    pub fn of(x: f64) -> (f64, f64) {
+        (x, 0.0)
+    }
+
+    /// 補正した和
+    pub fn total(value: &(f64, f64)) -> f64 {
+        value.0 + value.1
+    }
+
+    /// a + b と、その丸め誤差
+    pub(crate) fn two_sum(a: f64, b: f64) -> (f64, f64) {
+        let s = a + b;
+        let err = if a.abs() >= b.abs() { (a - s) + b } else { (b - s) + a };
+        (s, err)
+    }
+}
+
+impl Monoid for CompensatedSum {
+    type Value = (f64, f64);
+
+    fn identity() -> (f64, f64) { (0.0, 0.0) }
+    fn op(a: &(f64, f64), b: &(f64, f64)) -> (f64, f64) {
+        let (s, err) = Self::two_sum(a.0, b.0);
+        (s, a.1 + b.1 + err)
+    }
+}
+
+/// 向きのある差 (x, y, d)（x から y へ進むと d 増え、逆に進むと d 減る）を辺に載せ、パスに沿って足す
+/// 隣り合う辺は端点を共有するので、共有する端点から向きを決める
+/// パスの辺の集約では、パスの両端の頂点と、一方から他方への差が求まる
+pub struct Potential<T>(PhantomData<T>);
+
+impl<T: Clone + Add<Output = T> + Sub<Output = T> + Neg<Output = T>> Monoid for Potential<T> {
+    type Value = Option<(usize, usize, T)>;
+
+    fn identity() -> Self::Value { None }
+    fn op(a: &Self::Value, b: &Self::Value) -> Self::Value {
+        let (Some((x1, y1, d1)), Some((x2, y2, d2))) = (a, b) else { return a.clone().or_else(|| b.clone()) };
+        let (d1, d2) = (d1.clone(), d2.clone());
+        Some(if y1 == x2 {
+            (*x1, *y2, d1 + d2)
+        } else if y1 == y2 {
+            (*x1, *x2, d1 - d2)
+        } else if x1 == x2 {
+            (*y1, *y2, d2 - d1)
+        } else {
+            debug_assert_eq!(x1, y2, "edges must be adjacent");
+            (*y1, *x2, -(d1 + d2))
+        })
+    }
+}
+
+/// 排他的論理和
+pub struct Xor<T>(PhantomData<T>);
+
+impl<T: Clone + Default + BitXor<Output = T>> Monoid for Xor<T> {
+    type Value = T;
+
+    fn identity() -> T { T::default() }
+    fn op(a: &T, b: &T) -> T { a.clone() ^ b.clone() }
+}
+
+/// 列で最初に現れる値（None は単位元）
+pub struct First<T>(PhantomData<T>);
+
+impl<T: Clone> Monoid for First<T> {
+    type Value = Option<T>;
+
+    fn identity() -> Option<T> { None }
+    fn op(a: &Option<T>, b: &Option<T>) -> Option<T> { a.clone().or_else(|| b.clone()) }
+}
+
+/// 最小値・最大値を持つ型
+pub trait Bounded {
+    const MIN: Self;
+    const MAX: Self;
+}
+
+macro_rules! impl_bounded {
+    ($($t:ty),*) => {
+        $(impl Bounded for $t {
+            const MIN: Self = <$t>::MIN;
+            const MAX: Self = <$t>::MAX;
+        })*
+    };
+}
+
+impl_bounded!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// 辞書式順序での最小値・最大値
+impl<A: Bounded, B: Bounded> Bounded for (A, B) {
+    const MIN: Self = (A::MIN, B::MIN);
+    const MAX: Self = (A::MAX, B::MAX);
+}
+
+/// 最小値
+pub struct Min<T>(PhantomData<T>);
+
+impl<T: Clone + Ord + Bounded> Monoid for Min<T> {
+    type Value = T;
+
+    fn identity() -> T { T::MAX }
+    fn op(a: &T, b: &T) -> T { a.min(b).clone() }
+}
+
+/// 最大値
+pub struct Max<T>(PhantomData<T>);
+
+impl<T: Clone + Ord + Bounded> Monoid for Max<T> {
+    type Value = T;
+
+    fn identity() -> T { T::MIN }
+    fn op(a: &T, b: &T) -> T { a.max(b).clone() }
+}
+
+/// chmin・chmax を Segment Tree Beats で扱うための集約（action::Clamp と組み合わせる）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Beats {
+    pub sum: i64,
+    pub max: i64,
+    /// 最大値の次に大きい値
+    pub max2: i64,
+    pub max_count: usize,
+    pub min: i64,
+    /// 最小値の次に小さい値
+    pub min2: i64,
+    pub min_count: usize,
+}
+
+impl Beats {
+    /// 一つの頂点の値 x
+    pub fn new(x: i64) -> Self {
+        Self { sum: x, max: x, max2: i64::MIN, max_count: 1, min: x, min2: i64::MAX, min_count: 1 }
+    }
+}
+
+/// 和と最大値・最小値
+pub struct ChminChmax;
+
+impl Monoid for ChminChmax {
+    type Value = Beats;
+
+    fn identity() -> Beats {
+        Beats { sum: 0, max: i64::MIN, max2: i64::MIN, max_count: 0, min: i64::MAX, min2: i64::MAX, min_count: 0 }
+    }
+
+    fn op(a: &Beats, b: &Beats) -> Beats {
+        let (max, max2, max_count) = match a.max.cmp(&b.max) {
+            std::cmp::Ordering::Less => (b.max, a.max.max(b.max2), b.max_count),
+            std::cmp::Ordering::Greater => (a.max, a.max2.max(b.max), a.max_count),
+            std::cmp::Ordering::Equal => (a.max, a.max2.max(b.max2), a.max_count + b.max_count),
+        };
+        let (min, min2, min_count) = match a.min.cmp(&b.min) {
+            std::cmp::Ordering::Greater => (b.min, a.min.min(b.min2), b.min_count),
+            std::cmp::Ordering::Less => (a.min, a.min2.min(b.min), a.min_count),
+            std::cmp::Ordering::Equal => (a.min, a.min2.min(b.min2), a.min_count + b.min_count),
+        };
+        Beats { sum: a.sum + b.sum, max, max2, max_count, min, min2, min_count }
+    }
+}
+
+/// a と b の最大公約数（0 は単位元）
+fn gcd<T: Clone + Default + PartialEq + Rem<Output = T>>(mut a: T, mut b: T) -> T {
+    while b != T::default() {
+        let r = a % b.clone();
+        a = std::mem::replace(&mut b, r);
+    }
+    a
+}
+
+/// 最大公約数（値は非負であること）
+pub struct Gcd<T>(PhantomData<T>);
+
+impl<T: Clone + Default + PartialEq + Rem<Output = T>> Monoid for Gcd<T> {
+    type Value = T;
+
+    fn identity() -> T { T::default() }
+    fn op(a: &T, b: &T) -> T { gcd(a.clone(), b.clone()) }
+}
+
+/// 最小公倍数（値は正であること）
+pub struct Lcm<T>(PhantomData<T>);
+
+impl<T: Clone + Default + PartialEq + Rem<Output = T> + Div<Output = T> + Mul<Output = T> + From<u8>> Monoid for Lcm<T> {
+    type Value = T;
+
+    fn identity() -> T { T::from(1) }
+    fn op(a: &T, b: &T) -> T { a.clone() / gcd(a.clone(), b.clone()) * b.clone() }
+}
+
+/// AtLeast で数える閾値の並び
+pub trait Thresholds {
+    fn thresholds() -> &'static [i64];
+}
+
+/// 閾値ごとに、それ以上の重みを持つ要素の数
+/// 閾値の並び T::thresholds() と同じ順に数を並べる
+pub struct AtLeast<T>(PhantomData<T>);
+
+impl<T: Thresholds> AtLeast<T> {
+    /// 重み w の要素一つ
+    pub fn of(w: i64) -> Vec<usize> {
+        T::thresholds().iter().map(|&t| (w >= t) as usize).collect()
+    }
+}
+
+impl<T: Thresholds> Monoid for AtLeast<T> {
+    type Value = Vec<usize>;
+
+    fn identity() -> Vec<usize> { vec![0; T::thresholds().len()] }
+    fn op(a: &Vec<usize>, b: &Vec<usize>) -> Vec<usize> { a.iter().zip(b).map(|(x, y)| x + y).collect() }
+}
+
+/// 法（素数であること）
+pub trait Modulus {
+    fn modulus() -> u64;
+}
+
+/// コンパイル時に決まる法 P
+pub struct ConstModulus<const P: u64>;
+
+impl<const P: u64> Modulus for ConstModulus<P> {
+    fn modulus() -> u64 { P }
+}
+
+/// 法 P で a * b
+pub(crate) fn mul_mod<P: Modulus>(a: u64, b: u64) -> u64 {
+    (a as u128 * b as u128 % P::modulus() as u128) as u64
+}
+
+/// 法 P で a^e
+pub(crate) fn pow_mod<P: Modulus>(mut a: u64, mut e: u64) -> u64 {
+    let mut result = 1 % P::modulus();
+    while e > 0 {
+        if e & 1 == 1 {
+            result = mul_mod::<P>(result, a);
+        }
+        a = mul_mod::<P>(a, a);
+        e >>= 1;
+    }
+    result
+}
+
+/// 法 P での和
+pub struct ModSum<P>(PhantomData<P>);
+
+impl<P: Modulus> Monoid for ModSum<P> {
+    type Value = u64;
+
+    fn identity() -> u64 { 0 }
+    fn op(a: &u64, b: &u64) -> u64 { (a + b) % P::modulus() }
+}
+
+/// 法 P での積
+pub struct ModProd<P>(PhantomData<P>);
+
+impl<P: Modulus> Monoid for ModProd<P> {
+    type Value = u64;
+
+    fn identity() -> u64 { 1 % P::modulus() }
+    fn op(a: &u64, b: &u64) -> u64 { mul_mod::<P>(*a, *b) }
+}
+
+/// 法 P で基数 BASE の多項式ハッシュを (ハッシュ, BASE^長さ) で表し、列の順に合成する
+/// 森は列の逆順の集約も持つので、パスの向きによらず両方向のハッシュが求まる
+pub struct RollingHash<P, const BASE: u64>(PhantomData<P>);
+
+impl<P: Modulus, const BASE: u64> RollingHash<P, BASE> {
+    /// 値 x の頂点一つ
+    pub fn of(x: u64) -> (u64, u64) {
+        (x % P::modulus(), BASE % P::modulus())
+    }
+}
+
+impl<P: Modulus, const BASE: u64> Monoid for RollingHash<P, BASE> {
+    type Value = (u64, u64);
+
+    fn identity() -> (u64, u64) { (0, 1 % P::modulus()) }
+    fn op(a: &(u64, u64), b: &(u64, u64)) -> (u64, u64) {
+        ((mul_mod::<P>(a.0, b.1) + b.0) % P::modulus(), mul_mod::<P>(a.1, b.1))
+    }
+}
+
+/// 異なる値の個数を近似する HyperLogLog のスケッチ（レジスタは 2^B 個、4 <= B <= 16）
+/// 値はレジスタの列で、単位元は空の列
+/// 合成はレジスタごとの最大値なので、列の向きによらない
+pub struct HyperLogLog<const B: u32>;
+
+impl<const B: u32> HyperLogLog<B> {
+    /// 値 x を一つだけ含むスケッチ
+    pub fn of(x: u64) -> Vec<u8> {
+        // splitmix64 で混ぜる
+        let mut h = x.wrapping_add(0x9e3779b97f4a7c15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+        h ^= h >> 31;
+        let mut registers = vec![0; 1 << B];
+        registers[(h >> (64 - B)) as usize] = ((h << B).leading_zeros() + 1).min(64 - B + 1) as u8;
+        registers
+    }
+
+    /// スケッチに含まれる異なる値の個数の推定値
+    pub fn estimate(sketch: &[u8]) -> f64 {
+        if sketch.is_empty() {
+            return 0.0;
+        }
+        let m = sketch.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let raw = alpha * m * m / sketch.iter().map(|&r| (-(r as f64)).exp2()).sum::<f64>();
+        let zeros = sketch.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            // 小さい範囲は線形計数で補正する
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+}
+
+impl<const B: u32> Monoid for HyperLogLog<B> {
+    type Value = Vec<u8>;
+
+    fn identity() -> Vec<u8> { vec![] }
+    fn op(a: &Vec<u8>, b: &Vec<u8>) -> Vec<u8> {
+        if a.is_empty() {
+            return b.clone();
+        }
+        if b.is_empty() {
+            return a.clone();
+        }
+        a.iter().zip(b).map(|(&x, &y)| x.max(y)).collect()
+    }
+}
+
+/// 法 P での一次関数 x ↦ ax + b を (a, b) で表し、列の順に合成する
+pub struct AffineComposite<P>(PhantomData<P>);
+
+impl<P: Modulus> Monoid for AffineComposite<P> {
+    type Value = (u64, u64);
+
+    fn identity() -> (u64, u64) { (1 % P::modulus(), 0) }
+    fn op(f: &(u64, u64), g: &(u64, u64)) -> (u64, u64) {
+        (mul_mod::<P>(f.0, g.0), (mul_mod::<P>(f.1, g.0) + g.1) % P::modulus())
+    }
+}
+
+/// 同次座標の D 次正方行列による変換の合成（列の先の変換ほど先に作用する）
+/// 2 次元なら D = 3、3 次元なら D = 4
+pub struct Transform<const D: usize>;
+
+impl<const D: usize> Monoid for Transform<D> {
+    type Value = [[f64; D]; D];
+
+    fn identity() -> Self::Value {
+        let mut m = [[0.0; D]; D];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        m
+    }
+
+    fn op(a: &Self::Value, b: &Self::Value) -> Self::Value {
+        let mut m = [[0.0; D]; D];
+        for i in 0 .. D {
+            for j in 0 .. D {
+                m[i][j] = (0 .. D).map(|k| a[i][k] * b[k][j]).sum();
+            }
+        }
+        m
+    }
+}
+
+pub type Transform2 = Transform<3>;
+pub type Transform3 = Transform<4>;
+
+/// 実行時に登録できる集約の種類
+#[derive(Clone, Copy, Debug)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+    Xor,
+    /// 法 2^61 - 1 での列のローリングハッシュ（基数）
+    Hash(u64),
+    /// 単位元と演算を与えた集約
+    Custom { identity: i64, op: fn(i64, i64) -> i64 },
+}
+
+const HASH_MOD: u64 = (1 << 61) - 1;
+
+fn hash_mul(a: u64, b: u64) -> u64 {
+    (a as u128 * b as u128 % HASH_MOD as u128) as u64
+}
+
+impl Aggregate {
+    /// 値の列で使う欄の数
+    fn width(self) -> usize {
+        match self {
+            Aggregate::Hash(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// 一つの値 x を欄に書く
+    fn lift(self, x: i64, out: &mut Vec<i64>) {
+        match self {
+            Aggregate::Hash(base) => out.extend([x.rem_euclid(HASH_MOD as i64), base as i64]),
+            _ => out.push(x),
+        }
+    }
+
+    /// 空の列での値
+    fn empty(self) -> i64 {
+        match self {
+            Aggregate::Sum | Aggregate::Xor | Aggregate::Hash(_) => 0,
+            Aggregate::Min => i64::MAX,
+            Aggregate::Max => i64::MIN,
+            Aggregate::Custom { identity, .. } => identity,
+        }
+    }
+
+    /// 列で a の後に b が続くときの欄
+    fn op(self, a: &[i64], b: &[i64], out: &mut Vec<i64>) {
+        match self {
+            Aggregate::Sum => out.push(a[0] + b[0]),
+            Aggregate::Min => out.push(a[0].min(b[0])),
+            Aggregate::Max => out.push(a[0].max(b[0])),
+            Aggregate::Xor => out.push(a[0] ^ b[0]),
+            Aggregate::Hash(_) => {
+                // (ハッシュ, 基数の長さ乗)
+                let (ha, pa, hb, pb) = (a[0] as u64, a[1] as u64, b[0] as u64, b[1] as u64);
+                out.extend([((hash_mul(ha, pb) + hb) % HASH_MOD) as i64, hash_mul(pa, pb) as i64]);
+            }
+            Aggregate::Custom { op, .. } => out.push(op(a[0], b[0])),
+        }
+    }
+}
+
+/// 実行時に名前を付けて登録した集約の組
+#[derive(Clone, Debug, Default)]
+pub struct Aggregates {
+    kinds: Vec<(String, Aggregate)>,
+    /// 各集約の欄の始まり
+    offsets: Vec<usize>,
+    width: usize,
+}
+
+impl Aggregates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 集約を加え、その番号を返す
+    pub fn register(&mut self, name: &str, kind: Aggregate) -> usize {
+        self.kinds.push((name.to_string(), kind));
+        self.offsets.push(self.width);
+        self.width += kind.width();
+        self.kinds.len() - 1
+    }
+
+    /// name という名前の集約の番号
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.kinds.iter().position(|(n, _)| n == name)
+    }
+
+    /// 集約した値 value のうち、name という名前の集約の値（value が空の列なら単位元）
+    pub fn get(&self, value: &DynValue, name: &str) -> Option<i64> {
+        let i = self.index(name)?;
+        let kind = self.kinds[i].1;
+        Some(if value.set.is_some() { value.slots[self.offsets[i]] } else { kind.empty() })
+    }
+
+    /// 値 x の頂点の値を作る（作った後に登録した集約は含まれない）
+    pub fn value(self: &Arc<Self>, x: i64) -> DynValue {
+        let mut slots = Vec::with_capacity(self.width);
+        for &(_, kind) in &self.kinds {
+            kind.lift(x, &mut slots);
+        }
+        DynValue { set: Some(Arc::clone(self)), slots }
+    }
+}
+
+/// Dynamic の値（単位元はどの集約の組にも属さない）
+/// 集約ごとの値は Aggregates::get で読む
+#[derive(Clone, Debug, Default)]
+pub struct DynValue {
+    set: Option<Arc<Aggregates>>,
+    slots: Vec<i64>,
+}
+
+/// 同じ Aggregates から作った値どうしを欄ごとに比べる
+impl PartialEq for DynValue {
+    fn eq(&self, other: &Self) -> bool {
+        let same_set = match (&self.set, &other.set) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        same_set && self.slots == other.slots
+    }
+}
+
+/// 実行時に登録した Aggregates の集約をまとめて持つ
+/// 同じ森の値はすべて同じ Aggregates から作ること
+pub struct Dynamic;
+
+impl Monoid for Dynamic {
+    type Value = DynValue;
+
+    fn identity() -> DynValue { DynValue::default() }
+
+    fn op(a: &DynValue, b: &DynValue) -> DynValue {
+        let set = match (&a.set, &b.set) {
+            (None, _) => return b.clone(),
+            (_, None) => return a.clone(),
+            (Some(set), _) => set,
+        };
+        let mut slots = Vec::with_capacity(set.width);
+        for (&(_, kind), &offset) in set.kinds.iter().zip(&set.offsets) {
+            let range = offset .. offset + kind.width();
+            kind.op(&a.slots[range.clone()], &b.slots[range], &mut slots);
+        }
+        DynValue { set: Some(Arc::clone(set)), slots }
+    }
+}
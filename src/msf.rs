@@ -0,0 +1,237 @@
+use crate::monoid::Max;
+use crate::Forest;
+
+/// 辺の追加・削除と重みの変更のもとで最小全域森を保つ
+/// 森の辺の重みを (重み, 辺の番号) で持ち、閉路ができたらパス上の最大の辺と入れ替える
+/// 森の辺を削除するか重くしたときは、森に入っていない辺をすべて調べて置き換える辺を探す
+pub struct DynamicMsf {
+    forest: Forest<(), Max<(i64, usize)>>,
+    edges: Vec<Option<MsfEdge>>,
+    total: i64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct MsfEdge {
+    ends: [usize; 2],
+    weight: i64,
+    in_forest: bool,
+}
+
+impl DynamicMsf {
+    /// n 頂点で辺のない状態
+    pub fn new(n: usize) -> Self {
+        let mut forest = Forest::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        Self { forest, edges: vec![], total: 0 }
+    }
+
+    /// 最小全域森の辺の重みの和
+    pub fn total_weight(&self) -> i64 {
+        self.total
+    }
+
+    /// グラフの連結成分の数（最小全域森の木の数と等しい）
+    pub fn component_count(&self) -> usize {
+        self.forest.component_count()
+    }
+
+    /// 辺 i が最小全域森に入っているか（削除した辺なら false）
+    pub fn in_forest(&self, i: usize) -> bool {
+        self.edges[i].is_some_and(|edge| edge.in_forest)
+    }
+
+    /// 辺 i の重み（削除した辺なら None）
+    pub fn weight(&self, i: usize) -> Option<i64> {
+        self.edges[i].map(|edge| edge.weight)
+    }
+
+    /// 重み w の辺 (u, v) を加え、辺の番号を返す
+    pub fn add_edge(&mut self, u: usize, v: usize, w: i64) -> usize {
+        self.edges.push(Some(MsfEdge { ends: [u, v], weight: w, in_forest: false }));
+        let i = self.edges.len() - 1;
+        self.offer(i);
+        i
+    }
+
+    /// 辺 i を削除する
+    pub fn remove_edge(&mut self, i: usize) {
+        let edge = self.edges[i].expect("edge must not be removed");
+        if edge.in_forest {
+            self.unlink(i);
+        }
+        self.edges[i] = None;
+        if edge.in_forest {
+            self.replace(edge.ends);
+        }
+    }
+
+    /// 辺 i の重みを w にする
+    pub fn set_weight(&mut self, i: usize, w: i64) {
+        let edge = self.edges[i].expect("edge must not be removed");
+        let [u, v] = edge.ends;
+        self.edges[i].as_mut().unwrap().weight = w;
+        if !edge.in_forest {
+            // 森の外の辺は軽くなったときだけ入りうる
+            if w < edge.weight {
+                self.offer(i);
+            }
+        } else if w <= edge.weight {
+            // 森の辺は軽くしても森に残る
+            self.total += w - edge.weight;
+            self.forest.set_edge_weight(u, v, (w, i)).unwrap();
+        } else {
+            self.total += w - edge.weight;
+            self.unlink(i);
+            self.replace(edge.ends);
+        }
+    }
+
+    /// 辺 i を森に入れられるなら入れる（閉路ができればパス上で最も重い辺と比べる）
+    fn offer(&mut self, i: usize) {
+        let edge = self.edges[i].unwrap();
+        let [u, v] = edge.ends;
+        if u == v {
+            return;
+        }
+        if let Some((weight, j)) = self.forest.path_query_edges(u, v) {
+            if (weight, j) < (edge.weight, i) {
+                return;
+            }
+            self.unlink(j);
+        }
+        self.link(i);
+    }
+
+    fn link(&mut self, i: usize) {
+        let edge = self.edges[i].as_mut().unwrap();
+        edge.in_forest = true;
+        let ([u, v], weight) = (edge.ends, edge.weight);
+        self.total += weight;
+        self.forest.connect_weighted(u, v, (weight, i)).unwrap();
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let edge = self.edges[i].as_mut().unwrap();
+        edge.in_forest = false;
+        let ([u, v], weight) = (edge.ends, edge.weight);
+        self.total -= weight;
+        self.forest.toggle_edge(u, v).unwrap();
+    }
+
+    /// 切り離した ends の両側を結ぶ辺のうち最も軽いものを森に入れる
+    fn replace(&mut self, [u, v]: [usize; 2]) {
+        let mut best: Option<(i64, usize)> = None;
+        for i in 0 .. self.edges.len() {
+            let Some(edge) = self.edges[i] else { continue };
+            if edge.in_forest || best.is_some_and(|best| best <= (edge.weight, i)) {
+                continue;
+            }
+            let [a, b] = edge.ends;
+            let (ra, rb) = (self.forest.root(a), self.forest.root(b));
+            let (ru, rv) = (self.forest.root(u), self.forest.root(v));
+            if (ra == ru && rb == rv) || (ra == rv && rb == ru) {
+                best = Some((edge.weight, i));
+            }
+        }
+        if let Some((_, i)) = best {
+            self.link(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    /// Kruskal 法で求めた最小全域森の重み
+    fn kruskal(n: usize, edges: &[Option<(usize, usize, i64)>]) -> i64 {
+        let mut order: Vec<_> = edges.iter().enumerate().filter_map(|(i, e)| e.map(|(u, v, w)| (w, i, u, v))).collect();
+        order.sort();
+        let mut leader: Vec<usize> = (0 .. n).collect();
+        fn find(leader: &mut [usize], v: usize) -> usize {
+            if leader[v] != v {
+                leader[v] = find(leader, leader[v]);
+            }
+            leader[v]
+        }
+        let mut total = 0;
+        for (w, _, u, v) in order {
+            let (a, b) = (find(&mut leader, u), find(&mut leader, v));
+            if a != b {
+                leader[a] = b;
+                total += w;
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn against_kruskal() {
+        let mut rand = xorshift(0x2545f4914f6cdd1d);
+        let n = 12;
+        let mut msf = DynamicMsf::new(n);
+        let mut edges: Vec<Option<(usize, usize, i64)>> = vec![];
+        for _ in 0 .. 2000 {
+            let alive: Vec<usize> = (0 .. edges.len()).filter(|&i| edges[i].is_some()).collect();
+            match rand(4) {
+                0 | 1 if alive.len() < 30 => {
+                    let (u, v, w) = (rand(n), rand(n), rand(50) as i64);
+                    assert_eq!(msf.add_edge(u, v, w), edges.len());
+                    edges.push(Some((u, v, w)));
+                }
+                2 if !alive.is_empty() => {
+                    let i = alive[rand(alive.len())];
+                    let w = rand(50) as i64;
+                    msf.set_weight(i, w);
+                    edges[i].as_mut().unwrap().2 = w;
+                }
+                3 if !alive.is_empty() => {
+                    let i = alive[rand(alive.len())];
+                    msf.remove_edge(i);
+                    edges[i] = None;
+                }
+                _ => {}
+            }
+            assert_eq!(msf.total_weight(), kruskal(n, &edges));
+            let tree_edges = (0 .. edges.len()).filter(|&i| msf.in_forest(i)).count();
+            let components = {
+                let mut forest = Forest::<()>::new();
+                for _ in 0 .. n {
+                    forest.add_node(());
+                }
+                for &(u, v, _) in edges.iter().flatten() {
+                    if !forest.connected(u, v) {
+                        forest.connect(u, v).unwrap();
+                    }
+                }
+                (0 .. n).filter(|&v| forest.root(v) == v).count()
+            };
+            assert_eq!(tree_edges, n - components);
+            assert_eq!(msf.component_count(), components);
+        }
+    }
+
+    #[test]
+    fn weight_updates() {
+        let mut msf = DynamicMsf::new(3);
+        let a = msf.add_edge(0, 1, 1);
+        let b = msf.add_edge(1, 2, 2);
+        let c = msf.add_edge(0, 2, 5);
+        assert_eq!(msf.total_weight(), 3);
+        assert!(!msf.in_forest(c));
+        // 森の辺を重くすると森の外の辺と入れ替わる
+        msf.set_weight(b, 10);
+        assert_eq!(msf.total_weight(), 6);
+        assert!(msf.in_forest(c) && !msf.in_forest(b));
+        // 森の外の辺を軽くすると入れ替わる
+        msf.set_weight(b, 0);
+        assert_eq!(msf.total_weight(), 1);
+        assert!(!msf.in_forest(c));
+        msf.remove_edge(a);
+        assert_eq!(msf.total_weight(), 5);
+        assert_eq!(msf.weight(a), None);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::EdgeId;
+
+/// 森の形が変わるたびに呼ばれる
+/// Forest::add_observer で登録する（何もしない既定の実装がある）
+pub trait Observer {
+    /// 木の根 child の親を parent にした
+    fn on_link(&mut self, child: usize, parent: usize, id: EdgeId) {
+        let _ = (child, parent, id);
+    }
+
+    /// child を親 parent から切り離した
+    fn on_cut(&mut self, child: usize, parent: usize, id: EdgeId) {
+        let _ = (child, parent, id);
+    }
+
+    /// 根が old_root だった木の根を v にした
+    fn on_evert(&mut self, v: usize, old_root: usize) {
+        let _ = (v, old_root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::Forest;
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Event {
+        Link(usize, usize, EdgeId),
+        Cut(usize, usize, EdgeId),
+        Evert(usize, usize),
+    }
+
+    struct Log(Arc<Mutex<Vec<Event>>>);
+
+    impl Observer for Log {
+        fn on_link(&mut self, child: usize, parent: usize, id: EdgeId) {
+            self.0.lock().unwrap().push(Event::Link(child, parent, id));
+        }
+
+        fn on_cut(&mut self, child: usize, parent: usize, id: EdgeId) {
+            self.0.lock().unwrap().push(Event::Cut(child, parent, id));
+        }
+
+        fn on_evert(&mut self, v: usize, old_root: usize) {
+            self.0.lock().unwrap().push(Event::Evert(v, old_root));
+        }
+    }
+
+    #[test]
+    fn structural_changes() {
+        let mut forest = Forest::<()>::new();
+        for _ in 0 .. 3 {
+            forest.add_node(());
+        }
+        let log = Arc::new(Mutex::new(vec![]));
+        forest.add_observer(Log(log.clone()));
+        forest.link(1, 0);
+        forest.link(2, 1);
+        // 問い合わせでは呼ばれない
+        assert_eq!(forest.path_query(0, 2), Some(()));
+        forest.evert(2);
+        let id = forest.cut(0);
+        forest.clear_observers();
+        forest.link(0, 2);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                Event::Link(1, 0, EdgeId(0)),
+                Event::Link(2, 1, EdgeId(1)),
+                Event::Evert(2, 0),
+                Event::Cut(0, 1, id),
+            ]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use crate::Forest;
+
+/// 時刻順に並べた辺の追加・削除と連結性の問い合わせ
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectivityEvent {
+    /// 辺 (u, v) を加える（多重辺も加えられる）
+    Insert(usize, usize),
+    /// 辺 (u, v) を一本取り除く
+    Delete(usize, usize),
+    /// u と v が連結か
+    Query(usize, usize),
+}
+
+/// n 頂点のグラフに events を順に行い、問い合わせの答えを順に返す
+///
+/// 各辺が存在する時刻の区間を時刻の segment tree に載せ、根から下りながら辺をつなぎ、
+/// 戻るときにつないだ辺を切って元に戻す（分割統治）。全体で O((n + q) log q log n)。
+pub fn offline_connectivity(n: usize, events: &[ConnectivityEvent]) -> Vec<bool> {
+    let time = events.len();
+    // 区間 [l, r) の時刻に存在する辺
+    let mut intervals = vec![];
+    let mut alive: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (t, &event) in events.iter().enumerate() {
+        match event {
+            ConnectivityEvent::Insert(u, v) => alive.entry((u.min(v), u.max(v))).or_default().push(t),
+            ConnectivityEvent::Delete(u, v) => {
+                let start = alive.get_mut(&(u.min(v), u.max(v))).and_then(Vec::pop).expect("edge must be inserted before it is deleted");
+                intervals.push((start, t, u, v));
+            }
+            ConnectivityEvent::Query(..) => {}
+        }
+    }
+    for ((u, v), starts) in alive {
+        intervals.extend(starts.into_iter().map(|start| (start, time, u, v)));
+    }
+    let size = time.next_power_of_two();
+    let mut segments = vec![vec![]; 2 * size];
+    for (l, r, u, v) in intervals {
+        let (mut l, mut r) = (l + size, r + size);
+        while l < r {
+            if l & 1 == 1 {
+                segments[l].push((u, v));
+                l += 1;
+            }
+            if r & 1 == 1 {
+                r -= 1;
+                segments[r].push((u, v));
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+    }
+    let mut forest = Forest::<()>::new();
+    for _ in 0 .. n {
+        forest.add_node(());
+    }
+    let mut answers = vec![];
+    if time > 0 {
+        visit(1, size, &segments, events, &mut forest, &mut answers);
+    }
+    answers
+}
+
+/// segment tree の節点 k より下の時刻を順に処理する
+fn visit(k: usize, size: usize, segments: &[Vec<(usize, usize)>], events: &[ConnectivityEvent], forest: &mut Forest, answers: &mut Vec<bool>) {
+    // 同じ木に属する辺はつながなくても連結性は変わらない
+    let linked: Vec<(usize, usize)> = segments[k].iter().copied().filter(|&(u, v)| forest.union(u, v)).collect();
+    if k >= size {
+        if let Some(&ConnectivityEvent::Query(u, v)) = events.get(k - size) {
+            answers.push(forest.connected(u, v));
+        }
+    } else {
+        visit(2 * k, size, segments, events, forest, answers);
+        visit(2 * k + 1, size, segments, events, forest, answers);
+    }
+    for &(u, v) in linked.iter().rev() {
+        forest.toggle_edge(u, v).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+    use ConnectivityEvent::*;
+
+    #[test]
+    fn against_naive() {
+        let mut rand = xorshift(0x9e3779b97f4a7c15);
+        let n = 10;
+        let mut events = vec![];
+        let mut edges: Vec<(usize, usize)> = vec![];
+        let mut expected = vec![];
+        for _ in 0 .. 600 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(3) {
+                0 => {
+                    events.push(Insert(u, v));
+                    edges.push((u, v));
+                }
+                1 if !edges.is_empty() => {
+                    let (a, b) = edges.swap_remove(rand(edges.len()));
+                    // 向きを入れ替えても同じ辺として扱う
+                    events.push(Delete(b, a));
+                }
+                _ => {
+                    events.push(Query(u, v));
+                    let mut seen = vec![false; n];
+                    let mut stack = vec![u];
+                    seen[u] = true;
+                    while let Some(x) = stack.pop() {
+                        for &(a, b) in &edges {
+                            for (p, q) in [(a, b), (b, a)] {
+                                if p == x && !seen[q] {
+                                    seen[q] = true;
+                                    stack.push(q);
+                                }
+                            }
+                        }
+                    }
+                    expected.push(seen[v]);
+                }
+            }
+        }
+        assert_eq!(offline_connectivity(n, &events), expected);
+    }
+
+    #[test]
+    fn multi_edges() {
+        let events = [Insert(0, 1), Insert(1, 0), Delete(0, 1), Query(0, 1), Delete(0, 1), Query(1, 0), Query(2, 2)];
+        assert_eq!(offline_connectivity(3, &events), vec![true, false, true]);
+        assert_eq!(offline_connectivity(3, &[]), vec![]);
+    }
+}
@@ -0,0 +1,217 @@
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::subtree::Subtree;
+use crate::{EdgeId, Forest, LctError};
+
+/// 操作が前提を満たさなかったときの扱い
+pub trait Policy {
+    /// 結果が T の操作の戻り値の型
+    type Output<T>;
+    /// 前提を調べるか（調べないなら Forest の assert に任せる）
+    const CHECKED: bool;
+
+    fn ok<T>(value: T) -> Self::Output<T>;
+    fn fail<T>(error: LctError) -> Self::Output<T>;
+}
+
+/// 前提を調べずに Forest の操作をそのまま呼び、破れていれば panic する（競技プログラミング向け）
+pub struct Panic;
+
+impl Policy for Panic {
+    type Output<T> = T;
+    const CHECKED: bool = false;
+
+    fn ok<T>(value: T) -> T {
+        value
+    }
+
+    fn fail<T>(error: LctError) -> T {
+        panic!("{}", error)
+    }
+}
+
+/// 前提を調べ、破れていれば Err を返して森を変えない
+pub struct Fallible;
+
+impl Policy for Fallible {
+    type Output<T> = Result<T, LctError>;
+    const CHECKED: bool = true;
+
+    fn ok<T>(value: T) -> Result<T, LctError> {
+        Ok(value)
+    }
+
+    fn fail<T>(error: LctError) -> Result<T, LctError> {
+        Err(error)
+    }
+}
+
+/// 失敗の扱いを型引数 P で選ぶ森
+/// 構造を変える操作の戻り値が P::Output になり、それ以外の操作は Deref で Forest のものを使う
+/// `type MyForest = PolicyForest<Fallible, Sum<i64>>;` のように別名を変えるだけで呼び出し側を保てる
+pub struct PolicyForest<P: Policy, M: Monoid = (), E: Monoid = (), A: Action<M, E> = (), S: Subtree<M, E> = ()> {
+    forest: Forest<M, E, A, S>,
+    policy: PhantomData<P>,
+}
+
+impl<P: Policy, M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> Default for PolicyForest<P, M, E, A, S> {
+    fn default() -> Self {
+        Self::new(Forest::new())
+    }
+}
+
+impl<P: Policy, M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> Deref for PolicyForest<P, M, E, A, S> {
+    type Target = Forest<M, E, A, S>;
+
+    fn deref(&self) -> &Forest<M, E, A, S> {
+        &self.forest
+    }
+}
+
+impl<P: Policy, M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> DerefMut for PolicyForest<P, M, E, A, S> {
+    fn deref_mut(&mut self) -> &mut Forest<M, E, A, S> {
+        &mut self.forest
+    }
+}
+
+impl<P: Policy, M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> PolicyForest<P, M, E, A, S> {
+    pub fn new(forest: Forest<M, E, A, S>) -> Self {
+        Self { forest, policy: PhantomData }
+    }
+
+    pub fn into_inner(self) -> Forest<M, E, A, S> {
+        self.forest
+    }
+
+    /// 頂点の番号が範囲外なら Err
+    fn check_vertex(&self, v: usize) -> Result<(), LctError> {
+        if v < self.forest.len() { Ok(()) } else { Err(LctError::OutOfRange(v)) }
+    }
+
+    /// 前提を調べる（P::CHECKED が偽なら調べない）
+    fn check<T>(&mut self, check: impl FnOnce(&mut Self) -> Result<(), LctError>, f: impl FnOnce(&mut Forest<M, E, A, S>) -> T) -> P::Output<T> {
+        if P::CHECKED {
+            if let Err(error) = check(self) {
+                return P::fail(error);
+            }
+        }
+        P::ok(f(&mut self.forest))
+    }
+
+    /// 木の根 child の親を parent にする
+    pub fn link(&mut self, child: usize, parent: usize) -> P::Output<()> {
+        self.link_weighted(child, parent, E::identity())
+    }
+
+    /// 木の根 child の親を parent にし、その辺の重みを weight にする
+    pub fn link_weighted(&mut self, child: usize, parent: usize, weight: E::Value) -> P::Output<()> {
+        let check = |this: &mut Self| {
+            this.check_vertex(child)?;
+            this.check_vertex(parent)?;
+            if this.forest.root(child) != child {
+                return Err(LctError::NotRoot(child));
+            }
+            if this.forest.root(parent) == child {
+                return Err(LctError::Connected(child, parent));
+            }
+            this.forest.check_degree(child, parent)
+        };
+        self.check(check, |forest| forest.link_weighted(child, parent, weight))
+    }
+
+    /// v を親から切り離し、切った辺の番号を返す
+    pub fn cut(&mut self, v: usize) -> P::Output<EdgeId> {
+        let check = |this: &mut Self| {
+            this.check_vertex(v)?;
+            this.forest.parent(v).map(|_| ()).ok_or(LctError::IsRoot(v))
+        };
+        self.check(check, |forest| forest.cut(v))
+    }
+
+    /// v を根にする
+    pub fn evert(&mut self, v: usize) -> P::Output<()> {
+        self.check(|this| this.check_vertex(v), |forest| forest.evert(v))
+    }
+
+    /// 異なる木に属する u と v を辺で結ぶ（Forest::connect を参照）
+    pub fn connect(&mut self, u: usize, v: usize) -> P::Output<()> {
+        let check = |this: &mut Self| {
+            this.check_vertex(u)?;
+            this.check_vertex(v)?;
+            if this.forest.connected(u, v) {
+                return Err(LctError::Connected(u, v));
+            }
+            this.forest.check_degree(u, v)
+        };
+        self.check(check, |forest| forest.connect(u, v).unwrap())
+    }
+
+    pub fn set_value(&mut self, v: usize, value: M::Value) -> P::Output<()> {
+        self.check(|this| this.check_vertex(v), |forest| forest.set_value(v, value))
+    }
+
+    /// u と v を結ぶ辺の重みを weight にする
+    pub fn set_edge_weight(&mut self, u: usize, v: usize, weight: E::Value) -> P::Output<()> {
+        let check = |this: &mut Self| {
+            this.check_vertex(u)?;
+            this.check_vertex(v)?;
+            this.forest.edge(u, v).map(|_| ()).ok_or(LctError::NotAdjacent(u, v))
+        };
+        self.check(check, |forest| forest.set_edge_weight(u, v, weight).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    #[test]
+    fn fallible_reports_errors() {
+        let mut forest = PolicyForest::<Fallible, Sum<i64>, Sum<i64>>::default();
+        for x in 0 .. 4 {
+            forest.add_node(x);
+        }
+        assert_eq!(forest.link_weighted(1, 0, 5), Ok(()));
+        assert_eq!(forest.link(2, 1), Ok(()));
+        assert_eq!(forest.link(2, 3), Err(LctError::NotRoot(2)));
+        assert_eq!(forest.link(0, 2), Err(LctError::Connected(0, 2)));
+        assert_eq!(forest.link(4, 0), Err(LctError::OutOfRange(4)));
+        assert_eq!(forest.cut(0), Err(LctError::IsRoot(0)));
+        assert_eq!(forest.connect(3, 2), Ok(()));
+        assert_eq!(forest.connect(0, 3), Err(LctError::Connected(0, 3)));
+        assert_eq!(forest.set_edge_weight(0, 2, 1), Err(LctError::NotAdjacent(0, 2)));
+        assert_eq!(forest.set_value(9, 1), Err(LctError::OutOfRange(9)));
+        assert_eq!(forest.evert(3), Ok(()));
+        assert!(forest.cut(2).is_ok());
+        assert_eq!(forest.path_query_edges(0, 2), Some(5));
+        assert_eq!(forest.path_query(1, 3), None);
+    }
+
+    #[test]
+    fn panic_returns_values() {
+        let mut forest = PolicyForest::<Panic, Sum<i64>>::default();
+        for x in 0 .. 3 {
+            forest.add_node(x);
+        }
+        forest.link(1, 0);
+        forest.link(2, 1);
+        let _: EdgeId = forest.cut(2);
+        assert_eq!(forest.path_query(0, 1), Some(1));
+        assert_eq!(forest.into_inner().len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "child must be the root of its tree")]
+    fn panic_panics() {
+        let mut forest = PolicyForest::<Panic>::default();
+        for _ in 0 .. 3 {
+            forest.add_node(());
+        }
+        forest.link(1, 0);
+        forest.link(1, 2);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::monoid::Potential;
+use crate::{Forest, LctError};
+
+/// 頂点のポテンシャルの差の制約 value(v) − value(u) = d を保ち、制約の取り消しもできる（重み付き Union-Find の拡張）
+/// 制約を向きのある差として辺に載せ、パスに沿って足して二頂点の差を求める
+/// 既存の制約から導ける制約は辺にしないので、取り消せるのは辺になった制約だけ
+pub struct PotentialForest {
+    forest: Forest<(), Potential<i64>>,
+}
+
+impl PotentialForest {
+    /// n 頂点で制約のない状態
+    pub fn new(n: usize) -> Self {
+        let mut forest = Forest::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        Self { forest }
+    }
+
+    pub fn len(&self) -> usize {
+        self.forest.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forest.is_empty()
+    }
+
+    /// value(v) − value(u)（制約から決まらなければ None）
+    pub fn diff(&mut self, u: usize, v: usize) -> Option<i64> {
+        match self.forest.path_query_edges(u, v)? {
+            None => Some(0),
+            Some((x, _, d)) => Some(if x == u { d } else { -d }),
+        }
+    }
+
+    /// 制約 value(v) − value(u) = d を加え、辺にしたかを返す
+    /// すでに導ける制約なら何もせず Ok(false)、矛盾するなら今の差を Err で返す
+    pub fn relate(&mut self, u: usize, v: usize, d: i64) -> Result<bool, i64> {
+        match self.diff(u, v) {
+            Some(current) if current == d => Ok(false),
+            Some(current) => Err(current),
+            None => {
+                self.forest.connect_weighted(u, v, Some((u, v, d))).unwrap();
+                Ok(true)
+            }
+        }
+    }
+
+    /// 辺にした u と v の制約を取り除く
+    pub fn unrelate(&mut self, u: usize, v: usize) -> Result<(), LctError> {
+        self.forest.cut_edges(&[(u, v)]).pop().unwrap()
+    }
+
+    /// u と v の差が制約から決まるか
+    pub fn same(&mut self, u: usize, v: usize) -> bool {
+        self.forest.connected(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn potentials_match_naive() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 20;
+        // 辺にした制約だけを持つ素朴な実装（差は連結成分ごとに幅優先探索で求める）
+        let mut edges: Vec<(usize, usize, i64)> = vec![];
+        let naive = |edges: &[(usize, usize, i64)], u: usize, v: usize| {
+            let mut value = vec![None; n];
+            value[u] = Some(0);
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for &(a, b, d) in edges {
+                    match (value[a], value[b]) {
+                        (Some(x), None) => value[b] = Some(x + d),
+                        (None, Some(y)) => value[a] = Some(y - d),
+                        _ => continue,
+                    }
+                    changed = true;
+                }
+            }
+            value[v]
+        };
+        let mut potentials = PotentialForest::new(n);
+        for _ in 0 .. 2000 {
+            let (u, v) = (rand(n), rand(n));
+            match rand(4) {
+                0 => {
+                    let d = rand(21) as i64 - 10;
+                    let expected = naive(&edges, u, v);
+                    let result = potentials.relate(u, v, d);
+                    match expected {
+                        None => {
+                            assert_eq!(result, Ok(true));
+                            edges.push((u, v, d));
+                        }
+                        Some(x) if x == d => assert_eq!(result, Ok(false)),
+                        Some(x) => assert_eq!(result, Err(x)),
+                    }
+                }
+                1 => {
+                    let found = edges.iter().position(|&(a, b, _)| (a, b) == (u, v) || (a, b) == (v, u));
+                    match found {
+                        Some(i) => {
+                            assert_eq!(potentials.unrelate(u, v), Ok(()));
+                            edges.swap_remove(i);
+                        }
+                        None => assert_eq!(potentials.unrelate(u, v), Err(LctError::NotAdjacent(u, v))),
+                    }
+                }
+                _ => {
+                    assert_eq!(potentials.diff(u, v), naive(&edges, u, v));
+                    assert_eq!(potentials.same(u, v), naive(&edges, u, v).is_some());
+                }
+            }
+        }
+        assert_eq!(potentials.len(), n);
+    }
+}
@@ -0,0 +1,24 @@
+/// 古い頂点番号から新しい頂点番号への対応
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IdRemap {
+    /// 古い番号の順に並べた (古い番号, 新しい番号)
+    pairs: Vec<(usize, usize)>,
+}
+
+impl IdRemap {
+    pub(crate) fn new(pairs: Vec<(usize, usize)>) -> Self {
+        debug_assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0));
+        Self { pairs }
+    }
+
+    /// 古い番号 old の新しい番号（対応する頂点がなければ None）
+    pub fn get(&self, old: usize) -> Option<usize> {
+        let i = self.pairs.binary_search_by_key(&old, |&(old, _)| old).ok()?;
+        Some(self.pairs[i].1)
+    }
+
+    /// (古い番号, 新しい番号) の組を古い番号の順に並べる
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.pairs.iter().copied()
+    }
+}
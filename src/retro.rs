@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use crate::monoid::Monoid;
+use crate::{Forest, LctError};
+
+/// 時刻つきの辺の追加・削除
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetroEvent {
+    /// u と v を辺で結ぶ
+    Link(usize, usize),
+    /// u と v を結ぶ辺を取り除く
+    Cut(usize, usize),
+}
+
+/// 過去の時刻に辺の追加・削除を差し込んだり取り消したりできる森（部分的に遡及的）
+/// 最新の時刻の森を保ち、過去の時刻の操作があれば全体を再生し直す（O(m log n)）
+/// 最新の時刻以降の操作は再生せずにそのまま行う
+pub struct RetroactiveForest<M: Monoid = ()> {
+    values: Vec<M::Value>,
+    /// 時刻ごとの操作（同じ時刻の操作は加えた順）
+    events: BTreeMap<u64, Vec<RetroEvent>>,
+    present: Forest<M>,
+}
+
+impl<M: Monoid> RetroactiveForest<M> {
+    /// 頂点の値が values で、辺のない森
+    pub fn new(values: Vec<M::Value>) -> Self {
+        let present = Self::build(&values, &BTreeMap::new(), u64::MAX).unwrap();
+        Self { values, events: BTreeMap::new(), present }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// 時刻 time までの操作を行った森
+    fn build(values: &[M::Value], events: &BTreeMap<u64, Vec<RetroEvent>>, time: u64) -> Result<Forest<M>, LctError> {
+        let mut forest = Forest::new();
+        for value in values {
+            forest.add_node(value.clone());
+        }
+        for event in events.range(..= time).flat_map(|(_, events)| events) {
+            Self::apply(&mut forest, *event)?;
+        }
+        Ok(forest)
+    }
+
+    fn apply(forest: &mut Forest<M>, event: RetroEvent) -> Result<(), LctError> {
+        match event {
+            RetroEvent::Link(u, v) => forest.connect(u, v),
+            RetroEvent::Cut(u, v) => forest.cut_edges(&[(u, v)]).pop().unwrap(),
+        }
+    }
+
+    /// 時刻 time に event を加える
+    /// そのせいでどこかの操作が行えなくなる（閉路ができる、ない辺を切る）なら、何も変えずに Err を返す
+    pub fn insert(&mut self, time: u64, event: RetroEvent) -> Result<(), LctError> {
+        if self.events.last_key_value().is_none_or(|(&last, _)| last <= time) {
+            Self::apply(&mut self.present, event)?;
+            self.events.entry(time).or_default().push(event);
+            return Ok(());
+        }
+        self.events.entry(time).or_default().push(event);
+        match Self::build(&self.values, &self.events, u64::MAX) {
+            Ok(forest) => {
+                self.present = forest;
+                Ok(())
+            }
+            Err(error) => {
+                self.remove_entry(time, event);
+                Err(error)
+            }
+        }
+    }
+
+    /// 時刻 time に頂点 u と v を結ぶ
+    pub fn link_at(&mut self, time: u64, u: usize, v: usize) -> Result<(), LctError> {
+        self.insert(time, RetroEvent::Link(u, v))
+    }
+
+    /// 時刻 time に u と v を結ぶ辺を取り除く
+    pub fn cut_at(&mut self, time: u64, u: usize, v: usize) -> Result<(), LctError> {
+        self.insert(time, RetroEvent::Cut(u, v))
+    }
+
+    /// 時刻 time の event を一つ取り除く
+    fn remove_entry(&mut self, time: u64, event: RetroEvent) -> bool {
+        let Some(events) = self.events.get_mut(&time) else { return false };
+        let Some(i) = events.iter().rposition(|&e| e == event) else { return false };
+        events.remove(i);
+        if events.is_empty() {
+            self.events.remove(&time);
+        }
+        true
+    }
+
+    /// 時刻 time に加えた event を取り消し、取り消したかを返す
+    /// そのせいで後の操作が行えなくなるなら、何も変えずに Err を返す
+    pub fn remove(&mut self, time: u64, event: RetroEvent) -> Result<bool, LctError> {
+        if !self.remove_entry(time, event) {
+            return Ok(false);
+        }
+        match Self::build(&self.values, &self.events, u64::MAX) {
+            Ok(forest) => {
+                self.present = forest;
+                Ok(true)
+            }
+            Err(error) => {
+                self.events.entry(time).or_default().push(event);
+                Err(error)
+            }
+        }
+    }
+
+    /// 時刻の順に並べたすべての操作
+    pub fn events(&self) -> impl Iterator<Item = (u64, RetroEvent)> + '_ {
+        self.events.iter().flat_map(|(&time, events)| events.iter().map(move |&event| (time, event)))
+    }
+
+    /// 最新の時刻の森
+    pub fn present(&mut self) -> &mut Forest<M> {
+        &mut self.present
+    }
+
+    pub fn connected(&mut self, u: usize, v: usize) -> bool {
+        self.present.connected(u, v)
+    }
+
+    pub fn path_query(&mut self, u: usize, v: usize) -> Option<M::Value> {
+        self.present.path_query(u, v)
+    }
+
+    /// 時刻 time までの操作を行った森を作る（O(m log n)）
+    pub fn as_of(&self, time: u64) -> Forest<M> {
+        Self::build(&self.values, &self.events, time).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    #[test]
+    fn late_events() {
+        let mut forest = RetroactiveForest::<Sum<i64>>::new(vec![1, 2, 4, 8]);
+        forest.link_at(10, 0, 1).unwrap();
+        forest.link_at(20, 1, 2).unwrap();
+        forest.cut_at(30, 0, 1).unwrap();
+        assert!(!forest.connected(0, 2));
+        // 時刻 25 に届いた辺は 30 の cut の前に加わる
+        forest.link_at(25, 2, 3).unwrap();
+        assert_eq!(forest.path_query(1, 3), Some(14));
+        // 時刻 15 に 0 - 2 を結ぶと、20 の 1 - 2 で閉路ができる
+        assert_eq!(forest.link_at(15, 0, 2), Err(LctError::Connected(1, 2)));
+        assert_eq!(forest.events().count(), 4);
+        // 10 の link を取り消すと、30 の cut が行えない
+        assert_eq!(forest.remove(10, RetroEvent::Link(0, 1)), Err(LctError::NotAdjacent(0, 1)));
+        assert_eq!(forest.remove(30, RetroEvent::Cut(0, 1)), Ok(true));
+        assert_eq!(forest.remove(30, RetroEvent::Cut(0, 1)), Ok(false));
+        assert_eq!(forest.path_query(0, 3), Some(15));
+
+        let mut past = forest.as_of(22);
+        assert!(past.connected(0, 2));
+        assert!(!past.connected(0, 3));
+        assert_eq!(past.path_query(0, 2), Some(7));
+        assert!(!forest.as_of(5).connected(0, 1));
+        assert_eq!(forest.len(), 4);
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+
+use crate::monoid::Sum;
+use crate::Forest;
+
+/// 台本の一つの操作
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Add(i64),
+    Link(usize, usize),
+    Cut(usize),
+    Evert(usize),
+    Set(usize, i64),
+    Query(usize, usize),
+    Connected(usize, usize),
+}
+
+/// 問い合わせの答え
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Answer {
+    Path(Option<i64>),
+    Connected(bool),
+}
+
+/// 台本を読めなかったか、実行中に問題が見つかった
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptError {
+    /// line 行目（1 始まり）が読めない
+    Parse { line: usize, message: String },
+    /// step 番目（0 始まり）の操作が前提を満たさない（存在しない頂点、根でない頂点の link など）
+    Invalid { step: usize, op: Op },
+    /// step 番目の操作で森と素朴な実装の答えが食い違った
+    Diverged { step: usize, op: Op, expected: Answer, actual: Answer },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            ScriptError::Invalid { step, op } => write!(f, "step {}: {:?} is not applicable", step, op),
+            ScriptError::Diverged { step, op, expected, actual } => {
+                write!(f, "step {}: {:?} answered {:?}, expected {:?}", step, op, actual, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// 一行に一つの操作を書いた台本を読む（# から行末までは注釈）
+///
+/// ```text
+/// add 3          値 3 の頂点を追加する
+/// link 1 0       木の根 1 の親を 0 にする
+/// cut 1          1 を親から切り離す
+/// evert 2        2 を根にする
+/// set 2 5        2 の値を 5 にする
+/// query 0 2      0 から 2 へのパスの値の和
+/// connected 0 2  0 と 2 が同じ木に属するか
+/// ```
+pub fn parse(text: &str) -> Result<Vec<Op>, ScriptError> {
+    let mut ops = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: &str| ScriptError::Parse { line: i + 1, message: message.to_string() };
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap();
+        let args: Vec<i64> = words.map(|word| word.parse().map_err(|_| error("expected an integer"))).collect::<Result<_, _>>()?;
+        let vertex = |x: i64| usize::try_from(x).map_err(|_| error("expected a vertex"));
+        let op = match (name, args.as_slice()) {
+            ("add", &[x]) => Op::Add(x),
+            ("link", &[c, p]) => Op::Link(vertex(c)?, vertex(p)?),
+            ("cut", &[v]) => Op::Cut(vertex(v)?),
+            ("evert", &[v]) => Op::Evert(vertex(v)?),
+            ("set", &[v, x]) => Op::Set(vertex(v)?, x),
+            ("query", &[u, v]) => Op::Query(vertex(u)?, vertex(v)?),
+            ("connected", &[u, v]) => Op::Connected(vertex(u)?, vertex(v)?),
+            ("add" | "link" | "cut" | "evert" | "set" | "query" | "connected", _) => {
+                return Err(error("wrong number of arguments"))
+            }
+            _ => return Err(error("unknown operation")),
+        };
+        ops.push(op);
+    }
+    Ok(ops)
+}
+
+/// 親の配列で表した素朴な森
+struct Naive {
+    parent: Vec<Option<usize>>,
+    values: Vec<i64>,
+}
+
+impl Naive {
+    fn root(&self, mut v: usize) -> usize {
+        while let Some(p) = self.parent[v] {
+            v = p;
+        }
+        v
+    }
+
+    fn up(&self, mut v: usize) -> Vec<usize> {
+        let mut path = vec![v];
+        while let Some(p) = self.parent[v] {
+            path.push(p);
+            v = p;
+        }
+        path
+    }
+
+    fn evert(&mut self, v: usize) {
+        let mut prev = None;
+        let mut cur = Some(v);
+        while let Some(x) = cur {
+            cur = std::mem::replace(&mut self.parent[x], prev);
+            prev = Some(x);
+        }
+    }
+
+    fn query(&self, u: usize, v: usize) -> Option<i64> {
+        let (pu, pv) = (self.up(u), self.up(v));
+        if pu.last() != pv.last() {
+            return None;
+        }
+        let lca = *pu.iter().find(|x| pv.contains(x)).unwrap();
+        let sum = |path: &[usize]| path.iter().take_while(|&&x| x != lca).map(|&x| self.values[x]).sum::<i64>();
+        Some(sum(&pu) + sum(&pv) + self.values[lca])
+    }
+}
+
+/// 台本を森で実行し、問い合わせの答えを順に返す
+/// oracle が真なら、問い合わせのたびに素朴な実装の答えと比べ、最初に食い違った操作を返す
+pub fn run(ops: &[Op], oracle: bool) -> Result<Vec<Answer>, ScriptError> {
+    let mut forest = Forest::<Sum<i64>>::new();
+    let mut naive = Naive { parent: vec![], values: vec![] };
+    let mut answers = vec![];
+    for (step, &op) in ops.iter().enumerate() {
+        let invalid = ScriptError::Invalid { step, op };
+        let n = forest.len();
+        let answer = match op {
+            Op::Add(x) => {
+                forest.add_node(x);
+                naive.parent.push(None);
+                naive.values.push(x);
+                None
+            }
+            Op::Link(c, p) => {
+                if c >= n || p >= n || forest.root(c) != c || forest.root(p) == c {
+                    return Err(invalid);
+                }
+                forest.link(c, p);
+                naive.parent[c] = Some(p);
+                None
+            }
+            Op::Cut(v) => {
+                if v >= n || forest.root(v) == v {
+                    return Err(invalid);
+                }
+                forest.cut(v);
+                naive.parent[v] = None;
+                None
+            }
+            Op::Evert(v) => {
+                if v >= n {
+                    return Err(invalid);
+                }
+                forest.evert(v);
+                naive.evert(v);
+                None
+            }
+            Op::Set(v, x) => {
+                if v >= n {
+                    return Err(invalid);
+                }
+                forest.set_value(v, x);
+                naive.values[v] = x;
+                None
+            }
+            Op::Query(u, v) => {
+                if u >= n || v >= n {
+                    return Err(invalid);
+                }
+                Some((Answer::Path(forest.path_query(u, v)), Answer::Path(naive.query(u, v))))
+            }
+            Op::Connected(u, v) => {
+                if u >= n || v >= n {
+                    return Err(invalid);
+                }
+                Some((Answer::Connected(forest.connected(u, v)), Answer::Connected(naive.root(u) == naive.root(v))))
+            }
+        };
+        if let Some((actual, expected)) = answer {
+            if oracle && actual != expected {
+                return Err(ScriptError::Diverged { step, op, expected, actual });
+            }
+            answers.push(actual);
+        }
+    }
+    Ok(answers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_run() {
+        let script = "
+            # 0 - 1 - 2
+            add 1
+            add 2
+            add 4
+            link 1 0
+            link 2 1
+            query 0 2
+            cut 1
+            connected 0 2
+            evert 2   # 2 - 1
+            set 1 10
+            query 2 1
+        ";
+        let ops = parse(script).unwrap();
+        assert_eq!(ops.len(), 11);
+        assert_eq!(ops[3], Op::Link(1, 0));
+        let answers = run(&ops, true).unwrap();
+        assert_eq!(answers, vec![Answer::Path(Some(7)), Answer::Connected(false), Answer::Path(Some(14))]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("add 1\nlink 1").unwrap_err(), ScriptError::Parse { line: 2, message: "wrong number of arguments".into() });
+        assert!(matches!(parse("jump 1 2"), Err(ScriptError::Parse { line: 1, .. })));
+        assert!(matches!(parse("cut -1"), Err(ScriptError::Parse { line: 1, .. })));
+        let ops = parse("add 0\nadd 0\nlink 1 0\nlink 0 1").unwrap();
+        assert_eq!(run(&ops, true), Err(ScriptError::Invalid { step: 3, op: Op::Link(0, 1) }));
+    }
+}
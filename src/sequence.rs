@@ -0,0 +1,434 @@
+use std::marker::PhantomData;
+
+use crate::monoid::Monoid;
+use crate::splay::SplayTree;
+use crate::store::{NodeStore, Slab};
+use crate::Dir;
+
+/// splay 木で表した列の集まり（列の連結・分割・反転と、列の値の集約）
+/// 要素は add が返す番号で指し、列はその列に含まれるどの要素でも指せる
+/// 森の補助木と同じく、反転は子に遅延して伝える
+/// 節点は N に置く（既定では取り除いた番号を使い回す Slab）
+pub struct SplaySequences<M: Monoid = (), N: NodeStore<SequenceNode<M::Value>> = Slab<SequenceNode<<M as Monoid>::Value>>> {
+    nodes: N,
+    monoid: PhantomData<M>,
+}
+
+/// SplaySequences の節点
+pub struct SequenceNode<T> {
+    parent: Option<usize>,
+    children: [Option<usize>; 2],
+    len: usize,
+    rev: bool,
+    value: T,
+    sum: T,
+    rev_sum: T,
+}
+
+impl<M: Monoid, N: NodeStore<SequenceNode<M::Value>> + Default> Default for SplaySequences<M, N> {
+    fn default() -> Self {
+        Self::with_store(N::default())
+    }
+}
+
+impl<M: Monoid> SplaySequences<M> {
+    pub fn new() -> Self {
+        Self::with_store(Slab::new())
+    }
+}
+
+impl<M: Monoid, N: NodeStore<SequenceNode<M::Value>>> SplaySequences<M, N> {
+    /// 節点を store に置く（store は空であること）
+    pub fn with_store(store: N) -> Self {
+        assert!(store.is_empty(), "store must be empty");
+        Self { nodes: store, monoid: PhantomData }
+    }
+
+    /// 要素の数（すべての列の長さの和）
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 値 value の要素だけからなる列を作り、その要素の番号を返す
+    pub fn add(&mut self, value: M::Value) -> usize {
+        self.nodes.alloc(SequenceNode {
+            parent: None,
+            children: [None, None],
+            len: 1,
+            rev: false,
+            sum: value.clone(),
+            rev_sum: value.clone(),
+            value,
+        })
+    }
+
+    /// x をその列から取り除いて捨て、その値を返す（x の番号は後の add で再び使われうる）
+    pub fn remove(&mut self, x: usize) -> M::Value {
+        self.erase(x);
+        self.nodes.free(x).value
+    }
+
+    fn toggle(&mut self, x: usize) {
+        let node = &mut self.nodes[x];
+        node.children.swap(0, 1);
+        std::mem::swap(&mut node.sum, &mut node.rev_sum);
+        node.rev ^= true;
+    }
+
+    /// x をその列の splay 木の根にする
+    fn splay(&mut self, x: usize) {
+        SplayTree::splay(self, x);
+    }
+
+    /// 根 x の splay 木で k 番目（0 始まり）の要素を根にする
+    fn splay_kth(&mut self, mut x: usize, mut k: usize) -> usize {
+        loop {
+            self.push(x);
+            let left = self.child(x, Dir::Left).map_or(0, |left| self.nodes[left].len);
+            if k < left {
+                x = self.child(x, Dir::Left).unwrap();
+            } else if k == left {
+                break;
+            } else {
+                k -= left + 1;
+                x = self.child(x, Dir::Right).unwrap();
+            }
+        }
+        self.splay(x);
+        x
+    }
+
+    /// x を含む列の長さ
+    pub fn seq_len(&mut self, x: usize) -> usize {
+        self.splay(x);
+        self.nodes[x].len
+    }
+
+    /// x を含む列で k 番目（0 始まり）の要素（k が長さ以上なら None）
+    pub fn kth(&mut self, x: usize, k: usize) -> Option<usize> {
+        self.splay(x);
+        (k < self.nodes[x].len).then(|| self.splay_kth(x, k))
+    }
+
+    /// x がその列で何番目（0 始まり）か
+    pub fn position(&mut self, x: usize) -> usize {
+        self.splay(x);
+        self.child(x, Dir::Left).map_or(0, |left| self.nodes[left].len)
+    }
+
+    /// x と y が同じ列に含まれるか
+    pub fn same(&mut self, x: usize, y: usize) -> bool {
+        self.splay(x);
+        self.splay(y);
+        x == y || self.nodes[x].parent.is_some()
+    }
+
+    pub fn value(&mut self, x: usize) -> &M::Value {
+        self.splay(x);
+        &self.nodes[x].value
+    }
+
+    pub fn set_value(&mut self, x: usize, value: M::Value) {
+        self.splay(x);
+        self.nodes[x].value = value;
+        self.update(x);
+    }
+
+    /// x を含む列の値を先頭から集約したもの
+    pub fn fold(&mut self, x: usize) -> M::Value {
+        self.splay(x);
+        self.nodes[x].sum.clone()
+    }
+
+    /// x を含む列を反転する
+    pub fn reverse(&mut self, x: usize) {
+        self.splay(x);
+        self.toggle(x);
+    }
+
+    /// x を含む列のあとに y を含む列をつなげ、つないだ列の要素を一つ返す（同じ列なら None）
+    pub fn merge(&mut self, x: usize, y: usize) -> Option<usize> {
+        if self.same(x, y) {
+            return None;
+        }
+        self.splay(x);
+        let len = self.nodes[x].len;
+        let last = self.splay_kth(x, len - 1);
+        self.splay(y);
+        self.nodes[last].children[Dir::Right.index()] = Some(y);
+        self.nodes[y].parent = Some(last);
+        self.update(last);
+        Some(last)
+    }
+
+    /// x を含む列を先頭の k 個とそれ以外に分け、それぞれの要素を一つずつ返す（空なら None）
+    pub fn split(&mut self, x: usize, k: usize) -> (Option<usize>, Option<usize>) {
+        self.splay(x);
+        let len = self.nodes[x].len;
+        if k == 0 {
+            return (None, Some(x));
+        }
+        if k >= len {
+            return (Some(x), None);
+        }
+        let first = self.splay_kth(x, k);
+        let left = self.nodes[first].children[Dir::Left.index()].take().unwrap();
+        self.nodes[left].parent = None;
+        self.update(first);
+        (Some(left), Some(first))
+    }
+
+    /// y（一つだけの列）を x を含む列の i 番目に差し込む
+    pub fn insert(&mut self, x: usize, i: usize, y: usize) {
+        assert_eq!(self.seq_len(y), 1, "y must be a sequence of its own");
+        match self.split(x, i) {
+            (Some(left), Some(right)) => {
+                let joined = self.merge(left, y).unwrap();
+                self.merge(joined, right);
+            }
+            (Some(left), None) => {
+                self.merge(left, y);
+            }
+            (None, Some(right)) => {
+                self.merge(y, right);
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    /// x をその列から取り除き、一つだけの列にする（残りの列の要素を返す）
+    pub fn erase(&mut self, x: usize) -> Option<usize> {
+        let i = self.position(x);
+        let (left, rest) = self.split(x, i);
+        let (_, right) = self.split(rest.unwrap(), 1);
+        match (left, right) {
+            (Some(left), Some(right)) => self.merge(left, right),
+            (left, right) => left.or(right),
+        }
+    }
+
+    /// x を含む列の [l, r) 番目の値の集約
+    pub fn fold_range(&mut self, x: usize, l: usize, r: usize) -> M::Value {
+        self.with_range(x, l, r, |this, mid| mid.map_or_else(M::identity, |mid| this.nodes[mid].sum.clone()))
+    }
+
+    /// x を含む列の [l, r) 番目を反転する
+    pub fn reverse_range(&mut self, x: usize, l: usize, r: usize) {
+        self.with_range(x, l, r, |this, mid| {
+            if let Some(mid) = mid {
+                this.toggle(mid);
+            }
+        })
+    }
+
+    /// [l, r) 番目を切り出して f に渡し、つなぎ直す
+    fn with_range<R>(&mut self, x: usize, l: usize, r: usize, f: impl FnOnce(&mut Self, Option<usize>) -> R) -> R {
+        assert!(l <= r && r <= self.seq_len(x), "range out of bounds");
+        let (left, rest) = self.split(x, l);
+        let (mid, right) = match rest {
+            Some(rest) => self.split(rest, r - l),
+            None => (None, None),
+        };
+        if let Some(mid) = mid {
+            self.splay(mid);
+        }
+        let result = f(self, mid);
+        for (a, b) in [(left, mid), (left, right), (mid, right)] {
+            if let (Some(a), Some(b)) = (a, b) {
+                self.merge(a, b);
+            }
+        }
+        result
+    }
+}
+
+impl<M: Monoid, N: NodeStore<SequenceNode<M::Value>>> SplayTree for SplaySequences<M, N> {
+    fn parent_of(&self, x: usize) -> Option<usize> {
+        self.nodes[x].parent
+    }
+
+    fn set_parent_of(&mut self, x: usize, parent: Option<usize>) {
+        self.nodes[x].parent = parent;
+    }
+
+    fn child(&self, x: usize, dir: Dir) -> Option<usize> {
+        self.nodes[x].children[dir.index()]
+    }
+
+    fn set_child(&mut self, x: usize, dir: Dir, child: Option<usize>) {
+        self.nodes[x].children[dir.index()] = child;
+    }
+
+    fn push(&mut self, x: usize) {
+        if std::mem::take(&mut self.nodes[x].rev) {
+            for child in self.nodes[x].children.into_iter().flatten() {
+                self.toggle(child);
+            }
+        }
+    }
+
+    fn update(&mut self, x: usize) {
+        let [left, right] = self.nodes[x].children;
+        let mut len = 1;
+        let mut sum = self.nodes[x].value.clone();
+        let mut rev_sum = sum.clone();
+        if let Some(left) = left {
+            len += self.nodes[left].len;
+            sum = M::op(&self.nodes[left].sum, &sum);
+            rev_sum = M::op(&rev_sum, &self.nodes[left].rev_sum);
+        }
+        if let Some(right) = right {
+            len += self.nodes[right].len;
+            sum = M::op(&sum, &self.nodes[right].sum);
+            rev_sum = M::op(&self.nodes[right].rev_sum, &rev_sum);
+        }
+        let node = &mut self.nodes[x];
+        (node.len, node.sum, node.rev_sum) = (len, sum, rev_sum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SplaySequences;
+    use crate::monoid::Sum;
+    use crate::store::Slab;
+    use crate::test_util::xorshift;
+
+    #[test]
+    fn sequences_match_vectors() {
+        let mut rand = xorshift(88172645463325252);
+        let n = 40;
+        let mut seqs = SplaySequences::<Sum<i64>>::new();
+        // 列ごとの要素の番号と、要素の値
+        let mut naive: Vec<Vec<usize>> = vec![];
+        let mut values = vec![];
+        for x in 0 .. n {
+            values.push(rand(100) as i64);
+            assert_eq!(seqs.add(values[x]), x);
+            naive.push(vec![x]);
+        }
+        let find = |naive: &[Vec<usize>], x: usize| naive.iter().position(|s| s.contains(&x)).unwrap();
+        for _ in 0 .. 3000 {
+            let x = rand(n);
+            let s = find(&naive, x);
+            let len = naive[s].len();
+            match rand(8) {
+                0 => {
+                    let y = rand(n);
+                    let t = find(&naive, y);
+                    if s == t {
+                        assert_eq!(seqs.merge(x, y), None);
+                    } else {
+                        assert!(seqs.merge(x, y).is_some());
+                        let tail = std::mem::take(&mut naive[t]);
+                        naive[s].extend(tail);
+                        naive.retain(|s| !s.is_empty());
+                    }
+                }
+                1 => {
+                    let k = rand(len + 1);
+                    let (left, right) = seqs.split(x, k);
+                    let tail = naive[s].split_off(k);
+                    assert_eq!(left.is_some(), k > 0);
+                    assert_eq!(right.is_some(), k < len);
+                    if let Some(left) = left {
+                        assert!(naive[s].contains(&left));
+                    }
+                    if let Some(right) = right {
+                        assert!(tail.contains(&right));
+                    }
+                    naive.push(tail);
+                    naive.retain(|s| !s.is_empty());
+                }
+                2 => {
+                    let rest = seqs.erase(x);
+                    naive[s].retain(|&y| y != x);
+                    assert_eq!(rest.is_some(), !naive[s].is_empty());
+                    naive.push(vec![x]);
+                    naive.retain(|s| !s.is_empty());
+                    let y = rand(n);
+                    if y != x {
+                        let t = find(&naive, y);
+                        let i = rand(naive[t].len() + 1);
+                        seqs.insert(y, i, x);
+                        naive[t].insert(i, x);
+                        naive.retain(|s| s != &[x]);
+                    }
+                }
+                3 => {
+                    let (l, r) = (rand(len + 1), rand(len + 1));
+                    let (l, r) = (l.min(r), l.max(r));
+                    seqs.reverse_range(x, l, r);
+                    naive[s][l .. r].reverse();
+                }
+                4 => {
+                    seqs.reverse(x);
+                    naive[s].reverse();
+                }
+                5 => {
+                    values[x] = rand(100) as i64;
+                    seqs.set_value(x, values[x]);
+                }
+                6 => {
+                    let (l, r) = (rand(len + 1), rand(len + 1));
+                    let (l, r) = (l.min(r), l.max(r));
+                    let expected: i64 = naive[s][l .. r].iter().map(|&y| values[y]).sum();
+                    assert_eq!(seqs.fold_range(x, l, r), expected);
+                }
+                _ => {
+                    assert_eq!(seqs.seq_len(x), len);
+                    assert_eq!(seqs.position(x), naive[s].iter().position(|&y| y == x).unwrap());
+                    let k = rand(len + 1);
+                    assert_eq!(seqs.kth(x, k), naive[s].get(k).copied());
+                    assert_eq!(seqs.fold(x), naive[s].iter().map(|&y| values[y]).sum::<i64>());
+                    assert_eq!(*seqs.value(x), values[x]);
+                    let y = rand(n);
+                    assert_eq!(seqs.same(x, y), naive[s].contains(&y));
+                }
+            }
+        }
+        assert_eq!(seqs.len(), n);
+    }
+
+    #[test]
+    fn custom_store_and_remove() {
+        let mut seqs = SplaySequences::<Sum<i64>, Vec<_>>::default();
+        let xs: Vec<usize> = (0 .. 5).map(|x| seqs.add(x)).collect();
+        for &x in &xs[1 ..] {
+            seqs.merge(xs[0], x);
+        }
+        seqs.reverse_range(xs[0], 1, 4);
+        assert_eq!((0 .. 5).map(|k| seqs.kth(xs[0], k).unwrap()).collect::<Vec<_>>(), vec![0, 3, 2, 1, 4]);
+        assert_eq!(seqs.remove(4), 4);
+        assert_eq!(seqs.fold(0), 6);
+
+        let mut seqs = SplaySequences::<Sum<i64>>::new();
+        for x in 0 .. 4 {
+            seqs.add(x * 10);
+            if x > 0 {
+                seqs.merge(0, x as usize);
+            }
+        }
+        assert_eq!(seqs.remove(1), 10);
+        assert_eq!(seqs.len(), 3);
+        assert_eq!(seqs.fold(3), 50);
+        assert_eq!(seqs.add(7), 1);
+        seqs.insert(0, 1, 1);
+        assert_eq!(seqs.fold_range(2, 0, 2), 7);
+
+        let mut seqs = SplaySequences::<Sum<i64>, Slab<_>>::default();
+        for x in 0 .. 100 {
+            seqs.add(x);
+            if x > 0 {
+                seqs.merge(0, x as usize);
+            }
+        }
+        seqs.reverse_range(50, 10, 90);
+        assert_eq!(seqs.kth(0, 10), Some(89));
+        assert_eq!(seqs.fold_range(0, 5, 15), (5 .. 10).sum::<i64>() + (85 .. 90).sum::<i64>());
+    }
+}
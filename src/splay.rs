@@ -0,0 +1,169 @@
+use crate::Dir;
+
+/// 添字で節点を指す splay 木の組み替え（SplaySequences・FixedForest・Forest の補助木で共有する）
+/// 親と子の読み書き、遅延の伝播、集約の計算を実装すれば、回転と splay は既定のものが使える
+///
+/// Forest の補助木では splay 木の根の親に path-parent が入っている
+/// 森の節点をこの trait で直接組み替えると優先パスへの分解が壊れるので、森の外からは呼ばないこと
+pub trait SplayTree {
+    /// x の親（splay 木の根なら None か、その木の外の節点）
+    fn parent_of(&self, x: usize) -> Option<usize>;
+    fn set_parent_of(&mut self, x: usize, parent: Option<usize>);
+    fn child(&self, x: usize, dir: Dir) -> Option<usize>;
+    fn set_child(&mut self, x: usize, dir: Dir, child: Option<usize>);
+    /// x に溜めた遅延を子に伝える
+    fn push(&mut self, x: usize);
+    /// 子の集約から x の集約を求め直す
+    fn update(&mut self, x: usize);
+
+    /// rotate(x) で x を親 parent の位置に上げる直前に呼ぶ
+    fn on_rotate(&mut self, _x: usize, _parent: usize) {}
+
+    /// 親から見た向き（splay 木の根なら None）
+    fn dir(&self, x: usize) -> Option<Dir> {
+        let parent = self.parent_of(x)?;
+        [Dir::Left, Dir::Right].into_iter().find(|&dir| self.child(parent, dir) == Some(x))
+    }
+
+    /// x を親の位置に上げる（x は splay 木の根でないこと）
+    fn rotate(&mut self, x: usize) {
+        let dir = self.dir(x).unwrap();
+        let parent = self.parent_of(x).unwrap();
+        self.on_rotate(x, parent);
+        let parent_dir = self.dir(parent);
+        let ancestor = self.parent_of(parent);
+        let child = self.child(x, dir.flip());
+        self.set_child(parent, dir, child);
+        if let Some(child) = child {
+            self.set_parent_of(child, Some(parent));
+        }
+        self.set_child(x, dir.flip(), Some(parent));
+        self.set_parent_of(parent, Some(x));
+        self.set_parent_of(x, ancestor);
+        if let Some(parent_dir) = parent_dir {
+            self.set_child(ancestor.unwrap(), parent_dir, Some(x));
+        }
+        self.update(parent);
+        self.update(x);
+    }
+
+    /// splay 木の根から x まで遅延を伝え、x の深さを返す
+    fn push_path(&mut self, x: usize) -> usize {
+        let mut path = vec![x];
+        while self.dir(*path.last().unwrap()).is_some() {
+            path.push(self.parent_of(*path.last().unwrap()).unwrap());
+        }
+        for &y in path.iter().rev() {
+            self.push(y);
+        }
+        path.len() - 1
+    }
+
+    /// 遅延を伝えてから x をその splay 木の根にし、もとの x の深さを返す
+    fn splay(&mut self, x: usize) -> usize
+    where
+        Self: Sized,
+    {
+        let depth = self.push_path(x);
+        Splay::raise(self, x);
+        debug_assert!(self.dir(x).is_none(), "Balance::raise must bring the node to the root");
+        depth
+    }
+}
+
+/// splay 木で節点を根まで上げるときの回し方
+/// link-cut tree の expose は上げた節点が splay 木の根になっていることを前提にするので、
+/// 途中で止める semi-splay や、根を持ち上げない treap・WAVL のような木はここには入らない
+pub(crate) trait Balance {
+    /// x を splay 木の根まで上げる（x から根までの遅延は伝えてある）
+    fn raise<T: SplayTree>(tree: &mut T, x: usize);
+}
+
+/// zig-zig と zig-zag で二段ずつ上げる splay（各操作が償却 O(log n)）
+pub(crate) struct Splay;
+
+impl Balance for Splay {
+    fn raise<T: SplayTree>(tree: &mut T, x: usize) {
+        while let Some(dir) = tree.dir(x) {
+            let parent = tree.parent_of(x).unwrap();
+            if let Some(parent_dir) = tree.dir(parent) {
+                if dir == parent_dir {
+                    tree.rotate(parent);
+                } else {
+                    tree.rotate(x);
+                }
+            }
+            tree.rotate(x);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 部分木の大きさだけを持つ木
+    struct Plain {
+        parent: Vec<Option<usize>>,
+        children: Vec<[Option<usize>; 2]>,
+        len: Vec<usize>,
+    }
+
+    impl SplayTree for Plain {
+        fn parent_of(&self, x: usize) -> Option<usize> {
+            self.parent[x]
+        }
+
+        fn set_parent_of(&mut self, x: usize, parent: Option<usize>) {
+            self.parent[x] = parent;
+        }
+
+        fn child(&self, x: usize, dir: Dir) -> Option<usize> {
+            self.children[x][dir.index()]
+        }
+
+        fn set_child(&mut self, x: usize, dir: Dir, child: Option<usize>) {
+            self.children[x][dir.index()] = child;
+        }
+
+        fn push(&mut self, _x: usize) {}
+
+        fn update(&mut self, x: usize) {
+            self.len[x] = 1 + self.children[x].iter().flatten().map(|&c| self.len[c]).sum::<usize>();
+        }
+    }
+
+    impl Plain {
+        fn in_order(&self, x: Option<usize>, out: &mut Vec<usize>) {
+            if let Some(x) = x {
+                self.in_order(self.children[x][0], out);
+                out.push(x);
+                self.in_order(self.children[x][1], out);
+            }
+        }
+    }
+
+    #[test]
+    fn splay_keeps_order() {
+        // 0 を根とし、右の子に 1, 2, ... と連なる列
+        let n = 50;
+        let mut tree = Plain { parent: vec![None; n], children: vec![[None; 2]; n], len: vec![1; n] };
+        for x in (0 .. n - 1).rev() {
+            tree.set_child(x, Dir::Right, Some(x + 1));
+            tree.set_parent_of(x + 1, Some(x));
+            tree.update(x);
+        }
+        let mut root = 0;
+        for x in [n - 1, 7, 31, 0, 48, 25] {
+            let depth = tree.splay(x);
+            assert!(depth < n);
+            assert_eq!(tree.parent_of(x), None);
+            root = x;
+            assert_eq!(tree.len[root], n);
+            let mut order = vec![];
+            tree.in_order(Some(root), &mut order);
+            assert_eq!(order, (0 .. n).collect::<Vec<_>>());
+        }
+        assert_eq!(root, 25);
+    }
+}
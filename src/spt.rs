@@ -0,0 +1,76 @@
+use crate::monoid::Sum;
+use crate::{Forest, LctError};
+
+/// 始点からの最短路木を、辺の緩和で保つ
+/// 距離は根である始点からの辺の重みの和として求めるので、付け替えた部分木の距離も同時に変わる
+pub struct ShortestPathTree {
+    forest: Forest<(), Sum<i64>>,
+    source: usize,
+}
+
+impl ShortestPathTree {
+    /// n 頂点で、始点 source だけに到達している状態
+    pub fn new(n: usize, source: usize) -> Self {
+        assert!(source < n, "source must be a vertex");
+        let mut forest = Forest::new();
+        for _ in 0 .. n {
+            forest.add_node(());
+        }
+        Self { forest, source }
+    }
+
+    pub fn source(&self) -> usize {
+        self.source
+    }
+
+    /// 始点から v までの距離（到達していなければ None）
+    pub fn dist(&mut self, v: usize) -> Option<i64> {
+        self.forest.path_query_edges(self.source, v)
+    }
+
+    /// 最短路木での v の親
+    pub fn parent(&mut self, v: usize) -> Option<usize> {
+        self.forest.parent(v)
+    }
+
+    /// 長さ w の辺 (u, v) で v への距離が縮まるなら v の親を u にし、縮まったかを返す
+    /// u が v の子孫なら負の閉路があるので Err を返す
+    pub fn relax(&mut self, u: usize, v: usize, w: i64) -> Result<bool, LctError> {
+        let Some(du) = self.dist(u) else { return Ok(false) };
+        if let Some(dv) = self.dist(v) {
+            if du + w >= dv {
+                return Ok(false);
+            }
+            if self.forest.is_ancestor(v, u) {
+                return Err(LctError::Ancestor { ancestor: v, descendant: u });
+            }
+            self.forest.cut(v);
+        }
+        self.forest.link_weighted(v, u, w);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relax_updates_descendants() {
+        let mut spt = ShortestPathTree::new(5, 0);
+        assert_eq!(spt.relax(1, 2, 1), Ok(false));
+        assert_eq!(spt.relax(0, 1, 10), Ok(true));
+        assert_eq!(spt.relax(1, 2, 5), Ok(true));
+        assert_eq!(spt.relax(2, 3, 1), Ok(true));
+        assert_eq!(spt.dist(3), Some(16));
+        assert_eq!(spt.dist(4), None);
+        // 0 -> 4 -> 1 で 1 の部分木の距離がまとめて縮む
+        assert_eq!(spt.relax(0, 4, 2), Ok(true));
+        assert_eq!(spt.relax(4, 1, 3), Ok(true));
+        assert_eq!(spt.relax(0, 1, 7), Ok(false));
+        assert_eq!(spt.parent(1), Some(4));
+        assert_eq!(spt.dist(3), Some(11));
+        assert_eq!(spt.relax(3, 1, -7), Err(LctError::Ancestor { ancestor: 1, descendant: 3 }));
+        assert_eq!(spt.relax(3, 1, 0), Ok(false));
+    }
+}
@@ -0,0 +1,144 @@
+#[cfg(feature = "snapshot")]
+mod file;
+
+use std::ops::{Index, IndexMut};
+
+#[cfg(feature = "snapshot")]
+pub use file::FileStore;
+
+/// 節点を置く場所（添字で節点を指し、`store[i]` で読み書きする）
+/// 自前の確保の仕組みで節点を持ちたいときに実装する
+/// Forest に使うなら、free しない間は alloc が 0 から順に添字を返すこと（Forest は節点を取り除かない）
+pub trait NodeStore<T>: Index<usize, Output = T> + IndexMut<usize> {
+    /// node を置き、その添字を返す
+    fn alloc(&mut self, node: T) -> usize;
+    /// i 番目の節点を取り除いて返す（その添字は後の alloc で再び使われうる）
+    fn free(&mut self, i: usize) -> T;
+    /// 置かれている節点の数
+    fn len(&self) -> usize;
+    /// すべての節点を取り除く
+    fn clear(&mut self);
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// さらに additional 個の節点を置けるように領域を確保する（既定では何もしない）
+    fn reserve(&mut self, _additional: usize) {}
+}
+
+/// 末尾に積むだけの置き場所（最後に置いた節点しか取り除けない）
+impl<T> NodeStore<T> for Vec<T> {
+    fn alloc(&mut self, node: T) -> usize {
+        self.push(node);
+        self.len() - 1
+    }
+
+    fn free(&mut self, i: usize) -> T {
+        assert_eq!(i + 1, self.len(), "Vec can only free its last node");
+        self.pop().unwrap()
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn clear(&mut self) {
+        Vec::clear(self);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+}
+
+/// 取り除いた添字を使い回す置き場所
+#[derive(Clone, Debug)]
+pub struct Slab<T> {
+    slots: Vec<Option<T>>,
+    /// 空いている添字
+    vacant: Vec<usize>,
+}
+
+impl<T> Default for Slab<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Slab<T> {
+    pub fn new() -> Self {
+        Self { slots: vec![], vacant: vec![] }
+    }
+}
+
+impl<T> Index<usize> for Slab<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        self.slots[i].as_ref().expect("node must not be freed")
+    }
+}
+
+impl<T> IndexMut<usize> for Slab<T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        self.slots[i].as_mut().expect("node must not be freed")
+    }
+}
+
+impl<T> NodeStore<T> for Slab<T> {
+    fn alloc(&mut self, node: T) -> usize {
+        match self.vacant.pop() {
+            Some(i) => {
+                self.slots[i] = Some(node);
+                i
+            }
+            None => {
+                self.slots.push(Some(node));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    fn free(&mut self, i: usize) -> T {
+        let node = self.slots[i].take().expect("node must not be freed");
+        self.vacant.push(i);
+        node
+    }
+
+    fn len(&self) -> usize {
+        self.slots.len() - self.vacant.len()
+    }
+
+    fn clear(&mut self) {
+        self.slots.clear();
+        self.vacant.clear();
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.slots.reserve(additional);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slab_reuses_slots() {
+        let mut slab = Slab::new();
+        assert!(slab.is_empty());
+        let (a, b, c) = (slab.alloc('a'), slab.alloc('b'), slab.alloc('c'));
+        assert_eq!((a, b, c), (0, 1, 2));
+        assert_eq!(slab.free(b), 'b');
+        assert_eq!(slab.len(), 2);
+        assert_eq!(slab.alloc('d'), 1);
+        slab[c] = 'e';
+        assert_eq!([slab[0], slab[1], slab[2]], ['a', 'd', 'e']);
+        let mut vec = vec![];
+        assert_eq!(NodeStore::alloc(&mut vec, 1), 0);
+        assert_eq!(NodeStore::alloc(&mut vec, 2), 1);
+        assert_eq!(NodeStore::free(&mut vec, 1), 2);
+        assert_eq!(NodeStore::len(&vec), 1);
+    }
+}
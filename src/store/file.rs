@@ -0,0 +1,223 @@
+use std::cell::{Cell, OnceCell, RefCell};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::{Index, IndexMut};
+
+use super::NodeStore;
+use crate::SnapshotValue;
+
+/// 節点をファイルに置き、触った頁だけをメモリに読み込む置き場所
+/// 頁は page_len 個の節点をまとめたもので、メモリに置く頁が max_pages を超えると
+/// 書き換えで触ったときに、しばらく使っていない頁から書き戻して手放す（clock 法）
+///
+/// 森の操作は expose した道の節点しか触らないので、道に沿った頁だけがメモリに残る
+/// 読むだけの操作（&self）では頁を手放せないので、書き換えるまでは max_pages を超えて読み込むことがある
+/// Forest に使ってファイルに移るのは節点（値・重み・集約・splay 木の形）だけで、
+/// 辺の記録・隣接リスト・EdgeId の表・summaries・頂点ごとの HashMap はメモリに残る
+/// 残る分は頂点あたり 24 バイトほど、辺あたり 100 バイトほどなので、頂点や辺の数そのものがメモリに収まらない森は扱えない
+/// 節点のうちメモリに置く分は resident_pages × page_len 個で抑えられる
+/// 読み書きに失敗したときは panic する（確かめたいときは flush を呼ぶ）
+pub struct FileStore<T: SnapshotValue> {
+    file: RefCell<File>,
+    page_len: usize,
+    max_pages: usize,
+    len: usize,
+    pages: Vec<OnceCell<Vec<T>>>,
+    /// 最後に見てから触ったか
+    touched: Vec<Cell<bool>>,
+    dirty: Vec<bool>,
+    /// メモリに置いている頁の数
+    resident: Cell<usize>,
+    /// clock 法で次に見る頁
+    hand: usize,
+}
+
+impl<T: SnapshotValue> FileStore<T> {
+    /// file を空にして、節点を置く場所として使う
+    pub fn new(file: File, page_len: usize, max_pages: usize) -> io::Result<Self> {
+        assert!(page_len > 0 && max_pages > 0, "a page must hold a node and at least one page must fit in memory");
+        file.set_len(0)?;
+        Ok(Self {
+            file: RefCell::new(file),
+            page_len,
+            max_pages,
+            len: 0,
+            pages: vec![],
+            touched: vec![],
+            dirty: vec![],
+            resident: Cell::new(0),
+            hand: 0,
+        })
+    }
+
+    /// メモリに置いている頁の数
+    pub fn resident_pages(&self) -> usize {
+        self.resident.get()
+    }
+
+    /// 書き換えた頁をすべてファイルに書き戻す
+    pub fn flush(&mut self) -> io::Result<()> {
+        for page in 0 .. self.pages.len() {
+            self.write_back(page)?;
+        }
+        self.file.get_mut().flush()
+    }
+
+    fn load(&self, page: usize) -> Vec<T> {
+        let count = (self.len - page * self.page_len).min(self.page_len);
+        let mut bytes = vec![0; count * T::SIZE];
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start((page * self.page_len * T::SIZE) as u64)).and_then(|_| file.read_exact(&mut bytes)).expect("failed to read a page of nodes");
+        self.resident.set(self.resident.get() + 1);
+        let mut nodes = Vec::with_capacity(self.page_len);
+        nodes.extend((0 .. count).map(|i| T::read(&bytes[i * T::SIZE .. (i + 1) * T::SIZE])));
+        nodes
+    }
+
+    fn page(&self, page: usize) -> &Vec<T> {
+        self.touched[page].set(true);
+        self.pages[page].get_or_init(|| self.load(page))
+    }
+
+    fn page_mut(&mut self, page: usize) -> &mut Vec<T> {
+        self.evict(page);
+        self.page(page);
+        self.dirty[page] = true;
+        self.pages[page].get_mut().unwrap()
+    }
+
+    fn write_back(&mut self, page: usize) -> io::Result<()> {
+        if !self.dirty[page] {
+            return Ok(());
+        }
+        if let Some(nodes) = self.pages[page].get() {
+            let mut bytes = Vec::with_capacity(nodes.len() * T::SIZE);
+            for node in nodes {
+                node.write(&mut bytes);
+            }
+            let file = self.file.get_mut();
+            file.seek(SeekFrom::Start((page * self.page_len * T::SIZE) as u64))?;
+            file.write_all(&bytes)?;
+        }
+        self.dirty[page] = false;
+        Ok(())
+    }
+
+    /// keep 以外の頁を手放して、keep を読み込んでも置いている頁が max_pages 以下になるようにする
+    fn evict(&mut self, keep: usize) {
+        let incoming = self.pages[keep].get().is_none() as usize;
+        while self.resident.get() + incoming > self.max_pages {
+            let page = self.hand;
+            self.hand = (self.hand + 1) % self.pages.len();
+            if page == keep || self.pages[page].get().is_none() {
+                continue;
+            }
+            if self.touched[page].replace(false) {
+                continue;
+            }
+            self.write_back(page).expect("failed to write back a page of nodes");
+            self.pages[page].take();
+            self.resident.set(self.resident.get() - 1);
+        }
+    }
+}
+
+impl<T: SnapshotValue> Index<usize> for FileStore<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        assert!(i < self.len, "node {} is out of range", i);
+        &self.page(i / self.page_len)[i % self.page_len]
+    }
+}
+
+impl<T: SnapshotValue> IndexMut<usize> for FileStore<T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        assert!(i < self.len, "node {} is out of range", i);
+        let page_len = self.page_len;
+        &mut self.page_mut(i / page_len)[i % page_len]
+    }
+}
+
+/// Vec と同じく末尾に積むだけ（最後に置いた節点しか取り除けない）
+impl<T: SnapshotValue> NodeStore<T> for FileStore<T> {
+    fn alloc(&mut self, node: T) -> usize {
+        if self.len.is_multiple_of(self.page_len) {
+            self.pages.push(OnceCell::from(Vec::with_capacity(self.page_len)));
+            self.touched.push(Cell::new(true));
+            self.dirty.push(true);
+            self.resident.set(self.resident.get() + 1);
+        }
+        let page = self.len / self.page_len;
+        self.page_mut(page).push(node);
+        self.len += 1;
+        self.len - 1
+    }
+
+    fn free(&mut self, i: usize) -> T {
+        assert_eq!(i + 1, self.len, "FileStore can only free its last node");
+        let page = i / self.page_len;
+        let node = self.page_mut(page).pop().unwrap();
+        self.len -= 1;
+        if self.len.is_multiple_of(self.page_len) {
+            self.pages.pop();
+            self.touched.pop();
+            self.dirty.pop();
+            self.resident.set(self.resident.get() - 1);
+            self.hand = 0;
+        }
+        node
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.pages.clear();
+        self.touched.clear();
+        self.dirty.clear();
+        self.resident.set(0);
+        self.hand = 0;
+        self.len = 0;
+        self.file.get_mut().set_len(0).expect("failed to truncate the node file");
+    }
+}
+
+impl<T: SnapshotValue> Drop for FileStore<T> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> (std::path::PathBuf, File) {
+        let path = std::env::temp_dir().join(format!("link-cut-tree-{}-{}", name, std::process::id()));
+        let file = File::options().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+        (path, file)
+    }
+
+    #[test]
+    fn pages_out_and_back() {
+        let (path, file) = temp_file("file-store");
+        let mut store = FileStore::<u64>::new(file, 4, 2).unwrap();
+        for i in 0 .. 40 {
+            assert_eq!(store.alloc(i * 3), i as usize);
+        }
+        assert!(store.resident_pages() <= 2);
+        for i in (0 .. 40).rev() {
+            store[i] += 1;
+            assert!(store.resident_pages() <= 2);
+        }
+        assert!((0 .. 40).all(|i| store[i] == i as u64 * 3 + 1));
+        assert_eq!(store.free(39), 118);
+        assert_eq!(store.len(), 39);
+        store.flush().unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 40 * 8);
+        drop(store);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,765 @@
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::ops::{Add, BitXor, Sub};
+
+use crate::action::{Action, AssignAdd, AssignAddMap};
+use crate::monoid::{mul_mod, pow_mod, Bounded, First, ModSum, Modulus, Monoid, Sum};
+
+/// 部分木全体の集約
+///
+/// 各頂点（と辺）について、そこに垂れ下がる軽い子の部分木の集約 Virtual とまとめた Sum を作り、
+/// 根からの列の順に合成する。軽い子は expose のたびに出入りするので、Virtual からは取り除けること。
+pub trait Subtree<M: Monoid, E: Monoid = ()> {
+    /// 軽い子の部分木の集約をまとめたもの
+    type Virtual: Clone;
+    /// 根からの列の区間と、そこに垂れ下がる部分木の集約
+    type Sum: Clone;
+
+    fn identity() -> Self::Sum;
+    /// 列で a の後に b が続くときの集約
+    fn op(a: &Self::Sum, b: &Self::Sum) -> Self::Sum;
+    /// 値が value の頂点と、その下に垂れ下がる virt
+    fn vertex(value: &M::Value, virt: &Self::Virtual) -> Self::Sum;
+    /// 重みが weight の辺と、その先に垂れ下がる virt
+    fn edge(weight: &E::Value, virt: &Self::Virtual) -> Self::Sum;
+
+    /// 軽い子のない状態
+    fn empty() -> Self::Virtual;
+    fn add(virt: &mut Self::Virtual, sum: &Self::Sum);
+    fn remove(virt: &mut Self::Virtual, sum: &Self::Sum);
+}
+
+/// 部分木の頂点の値と辺の重みにまとめて作用させる写像（Forest::subtree_apply で使う）
+///
+/// 軽い子の部分木には、親にそれまで作用させた写像を合成して溜めておき、その子の splay 木に触れるときに
+/// 前に受け取ったところから後の分（since）だけを伝える。act は常に Some を返すこと。
+pub trait SubtreeAction<M: Monoid, E: Monoid, S: Subtree<M, E>>: Action<M, E> {
+    /// 軽い子の部分木（頂点は合わせて len 個）の集約 virt に f を作用させる
+    fn act_virtual(f: &Self::Map, virt: &S::Virtual, len: usize) -> S::Virtual;
+    /// 列の区間とそこに垂れ下がる部分木（頂点は合わせて len 個）の集約 sum に f を作用させる
+    fn act_subtree(f: &Self::Map, sum: &S::Sum, len: usize) -> S::Sum;
+    /// history が base の後に写像を続けて合成したものであるとき、続けた写像
+    fn since(history: &Self::Map, base: &Self::Map) -> Self::Map;
+}
+
+impl<E: Monoid> SubtreeAction<Sum<i64>, E, ()> for AssignAdd {
+    fn act_virtual(_: &AssignAddMap, _: &(), _: usize) {}
+    fn act_subtree(_: &AssignAddMap, _: &(), _: usize) {}
+    fn since(history: &AssignAddMap, base: &AssignAddMap) -> AssignAddMap { base.since(history) }
+}
+
+/// 集約しない
+impl<M: Monoid, E: Monoid> Subtree<M, E> for () {
+    type Virtual = ();
+    type Sum = ();
+
+    fn identity() {}
+    fn op(_: &(), _: &()) {}
+    fn vertex(_: &M::Value, _: &()) {}
+    fn edge(_: &E::Value, _: &()) {}
+
+    fn empty() {}
+    fn add(_: &mut (), _: &()) {}
+    fn remove(_: &mut (), _: &()) {}
+}
+
+/// 二つの集約を同時に持つ
+impl<M: Monoid, E: Monoid, S: Subtree<M, E>, T: Subtree<M, E>> Subtree<M, E> for (S, T) {
+    type Virtual = (S::Virtual, T::Virtual);
+    type Sum = (S::Sum, T::Sum);
+
+    fn identity() -> Self::Sum { (S::identity(), T::identity()) }
+    fn op(a: &Self::Sum, b: &Self::Sum) -> Self::Sum { (S::op(&a.0, &b.0), T::op(&a.1, &b.1)) }
+    fn vertex(value: &M::Value, virt: &Self::Virtual) -> Self::Sum { (S::vertex(value, &virt.0), T::vertex(value, &virt.1)) }
+    fn edge(weight: &E::Value, virt: &Self::Virtual) -> Self::Sum { (S::edge(weight, &virt.0), T::edge(weight, &virt.1)) }
+
+    fn empty() -> Self::Virtual { (S::empty(), T::empty()) }
+    fn add(virt: &mut Self::Virtual, sum: &Self::Sum) {
+        S::add(&mut virt.0, &sum.0);
+        T::add(&mut virt.1, &sum.1);
+    }
+    fn remove(virt: &mut Self::Virtual, sum: &Self::Sum) {
+        S::remove(&mut virt.0, &sum.0);
+        T::remove(&mut virt.1, &sum.1);
+    }
+}
+
+/// 根付き部分木の多項式ハッシュ
+/// 部分木の根 v に対して、各頂点 w の値に BASE^(w の深さ - v の深さ) を掛けて法 P で足したもの
+/// 深さごとの値の和で決まるので、根付き木の同型を完全に区別するものではない
+pub struct TreeHash<P, const BASE: u64>(PhantomData<P>);
+
+impl<P: Modulus, const BASE: u64, E: Monoid> Subtree<ModSum<P>, E> for TreeHash<P, BASE> {
+    /// 軽い子の部分木のハッシュの和
+    type Virtual = u64;
+    /// (ハッシュ, BASE^頂点数)
+    type Sum = (u64, u64);
+
+    fn identity() -> (u64, u64) { (0, 1 % P::modulus()) }
+    fn op(a: &(u64, u64), b: &(u64, u64)) -> (u64, u64) {
+        ((a.0 + mul_mod::<P>(a.1, b.0)) % P::modulus(), mul_mod::<P>(a.1, b.1))
+    }
+    fn vertex(value: &u64, virt: &u64) -> (u64, u64) {
+        let base = BASE % P::modulus();
+        ((value + mul_mod::<P>(base, *virt)) % P::modulus(), base)
+    }
+    fn edge(_: &E::Value, virt: &u64) -> (u64, u64) { (*virt, 1 % P::modulus()) }
+
+    fn empty() -> u64 { 0 }
+    fn add(virt: &mut u64, sum: &(u64, u64)) { *virt = (*virt + sum.0) % P::modulus() }
+    fn remove(virt: &mut u64, sum: &(u64, u64)) { *virt = (*virt + P::modulus() - sum.0) % P::modulus() }
+}
+
+/// 多重集合に x を一つ加える
+fn insert<T: Ord>(set: &mut BTreeMap<T, usize>, x: T) {
+    *set.entry(x).or_insert(0) += 1;
+}
+
+/// 多重集合から x を一つ取り除く
+fn erase<T: Ord>(set: &mut BTreeMap<T, usize>, x: T) {
+    let count = set.get_mut(&x).expect("removed a light child that was never added");
+    *count -= 1;
+    if *count == 0 {
+        set.remove(&x);
+    }
+}
+
+/// 現在の根に関する葉（子を持たない頂点）の数
+pub struct Leaves;
+
+/// 根からの列の区間とそこに垂れ下がる部分木の葉の数
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeavesSum {
+    pub leaves: usize,
+    /// 区間の末尾が子を持たない頂点で、区間の後に続く節点があれば葉でなくなる
+    open: bool,
+    empty: bool,
+}
+
+impl<M: Monoid, E: Monoid> Subtree<M, E> for Leaves {
+    /// (軽い子の部分木の数, その葉の数の和)
+    type Virtual = (usize, usize);
+    type Sum = LeavesSum;
+
+    fn identity() -> LeavesSum {
+        LeavesSum { leaves: 0, open: false, empty: true }
+    }
+
+    fn op(a: &LeavesSum, b: &LeavesSum) -> LeavesSum {
+        if a.empty || b.empty {
+            return if a.empty { *b } else { *a };
+        }
+        LeavesSum { leaves: a.leaves + b.leaves - a.open as usize, open: b.open, empty: false }
+    }
+
+    fn vertex(_: &M::Value, virt: &(usize, usize)) -> LeavesSum {
+        let leaf = virt.0 == 0;
+        LeavesSum { leaves: virt.1 + leaf as usize, open: leaf, empty: false }
+    }
+
+    fn edge(_: &E::Value, virt: &(usize, usize)) -> LeavesSum {
+        LeavesSum { leaves: virt.1, open: false, empty: false }
+    }
+
+    fn empty() -> (usize, usize) { (0, 0) }
+    fn add(virt: &mut (usize, usize), sum: &LeavesSum) {
+        virt.0 += 1;
+        virt.1 += sum.leaves;
+    }
+    fn remove(virt: &mut (usize, usize), sum: &LeavesSum) {
+        virt.0 -= 1;
+        virt.1 -= sum.leaves;
+    }
+}
+
+/// 部分木の頂点の値の和（軽い子は引いて取り除く）
+pub struct SubtreeSum<T>(PhantomData<T>);
+
+impl<T: Clone + Default + Add<Output = T> + Sub<Output = T>, M: Monoid<Value = T>, E: Monoid> Subtree<M, E> for SubtreeSum<T> {
+    /// 軽い子の部分木の和
+    type Virtual = T;
+    type Sum = T;
+
+    fn identity() -> T { T::default() }
+    fn op(a: &T, b: &T) -> T { a.clone() + b.clone() }
+    fn vertex(value: &T, virt: &T) -> T { value.clone() + virt.clone() }
+    fn edge(_: &E::Value, virt: &T) -> T { virt.clone() }
+
+    fn empty() -> T { T::default() }
+    fn add(virt: &mut T, sum: &T) { *virt = virt.clone() + sum.clone() }
+    fn remove(virt: &mut T, sum: &T) { *virt = virt.clone() - sum.clone() }
+}
+
+impl<E: Monoid> SubtreeAction<Sum<i64>, E, SubtreeSum<i64>> for AssignAdd {
+    fn act_virtual(f: &AssignAddMap, virt: &i64, len: usize) -> i64 { f.act_sum(*virt, len) }
+    fn act_subtree(f: &AssignAddMap, sum: &i64, len: usize) -> i64 { f.act_sum(*sum, len) }
+    fn since(history: &AssignAddMap, base: &AssignAddMap) -> AssignAddMap { base.since(history) }
+}
+
+/// 部分木の頂点の値の排他的論理和（群なので、軽い子は打ち消して取り除ける）
+pub struct SubtreeXor<T>(PhantomData<T>);
+
+impl<T: Clone + Default + BitXor<Output = T>, M: Monoid<Value = T>, E: Monoid> Subtree<M, E> for SubtreeXor<T> {
+    /// 軽い子の部分木の排他的論理和
+    type Virtual = T;
+    type Sum = T;
+
+    fn identity() -> T { T::default() }
+    fn op(a: &T, b: &T) -> T { a.clone() ^ b.clone() }
+    fn vertex(value: &T, virt: &T) -> T { value.clone() ^ virt.clone() }
+    fn edge(_: &E::Value, virt: &T) -> T { virt.clone() }
+
+    fn empty() -> T { T::default() }
+    fn add(virt: &mut T, sum: &T) { *virt = virt.clone() ^ sum.clone() }
+    fn remove(virt: &mut T, sum: &T) { *virt = virt.clone() ^ sum.clone() }
+}
+
+/// 部分木の頂点の値の最小値（軽い子の部分木の最小値を多重集合で持つ）
+pub struct SubtreeMin<T>(PhantomData<T>);
+
+impl<T: Clone + Ord + Bounded, M: Monoid<Value = T>, E: Monoid> Subtree<M, E> for SubtreeMin<T> {
+    type Virtual = BTreeMap<T, usize>;
+    type Sum = T;
+
+    fn identity() -> T { T::MAX }
+    fn op(a: &T, b: &T) -> T { a.min(b).clone() }
+    fn vertex(value: &T, virt: &BTreeMap<T, usize>) -> T {
+        virt.keys().next().map_or(value, |x| x.min(value)).clone()
+    }
+    fn edge(_: &E::Value, virt: &BTreeMap<T, usize>) -> T {
+        virt.keys().next().cloned().unwrap_or(T::MAX)
+    }
+
+    fn empty() -> BTreeMap<T, usize> { BTreeMap::new() }
+    fn add(virt: &mut BTreeMap<T, usize>, sum: &T) { insert(virt, sum.clone()) }
+    fn remove(virt: &mut BTreeMap<T, usize>, sum: &T) { erase(virt, sum.clone()) }
+}
+
+/// 部分木の頂点の値の最大値（軽い子の部分木の最大値を多重集合で持つ）
+pub struct SubtreeMax<T>(PhantomData<T>);
+
+impl<T: Clone + Ord + Bounded, M: Monoid<Value = T>, E: Monoid> Subtree<M, E> for SubtreeMax<T> {
+    type Virtual = BTreeMap<T, usize>;
+    type Sum = T;
+
+    fn identity() -> T { T::MIN }
+    fn op(a: &T, b: &T) -> T { a.max(b).clone() }
+    fn vertex(value: &T, virt: &BTreeMap<T, usize>) -> T {
+        virt.keys().next_back().map_or(value, |x| x.max(value)).clone()
+    }
+    fn edge(_: &E::Value, virt: &BTreeMap<T, usize>) -> T {
+        virt.keys().next_back().cloned().unwrap_or(T::MIN)
+    }
+
+    fn empty() -> BTreeMap<T, usize> { BTreeMap::new() }
+    fn add(virt: &mut BTreeMap<T, usize>, sum: &T) { insert(virt, sum.clone()) }
+    fn remove(virt: &mut BTreeMap<T, usize>, sum: &T) { erase(virt, sum.clone()) }
+}
+
+/// 頂点がないことを表す距離
+const NONE: i64 = i64::MIN / 4;
+
+/// 最も遠い頂点までの距離と直径（辺の重みは非負）
+pub struct Farthest;
+
+/// 根からの列の区間について、区間の両端から最も遠い頂点までの距離と直径
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FarthestSum {
+    /// 区間の端から端までの長さ
+    pub len: i64,
+    /// 区間の根に近い端から最も遠い頂点までの距離
+    pub top: i64,
+    /// 区間の根から遠い端から最も遠い頂点までの距離
+    pub bottom: i64,
+    /// 区間とそこに垂れ下がる部分木の直径
+    pub diameter: i64,
+}
+
+/// 軽い子の部分木の top と直径の多重集合
+#[derive(Clone, Debug, Default)]
+pub struct FarthestVirtual {
+    tops: BTreeMap<i64, usize>,
+    diameters: BTreeMap<i64, usize>,
+}
+
+impl FarthestVirtual {
+    /// 大きい方から二つの top（なければ NONE）
+    fn top2(&self) -> (i64, i64) {
+        let mut iter = self.tops.iter().rev().flat_map(|(&x, &count)| std::iter::repeat_n(x, count.min(2)));
+        (iter.next().unwrap_or(NONE), iter.next().unwrap_or(NONE))
+    }
+
+    fn diameter(&self) -> i64 {
+        self.diameters.keys().next_back().copied().unwrap_or(NONE)
+    }
+}
+
+impl<M: Monoid> Subtree<M, Sum<i64>> for Farthest {
+    type Virtual = FarthestVirtual;
+    type Sum = FarthestSum;
+
+    fn identity() -> FarthestSum {
+        FarthestSum { len: 0, top: NONE, bottom: NONE, diameter: NONE }
+    }
+
+    fn op(a: &FarthestSum, b: &FarthestSum) -> FarthestSum {
+        FarthestSum {
+            len: a.len + b.len,
+            top: a.top.max(a.len + b.top),
+            bottom: b.bottom.max(b.len + a.bottom),
+            diameter: a.diameter.max(b.diameter).max(a.bottom + b.top),
+        }
+    }
+
+    fn vertex(_: &M::Value, virt: &FarthestVirtual) -> FarthestSum {
+        let (first, second) = virt.top2();
+        let top = first.max(0);
+        FarthestSum { len: 0, top, bottom: top, diameter: virt.diameter().max(top + second.max(0)) }
+    }
+
+    fn edge(weight: &i64, virt: &FarthestVirtual) -> FarthestSum {
+        // 辺の先の部分木は辺の根から遠い端に垂れ下がる
+        let (first, _) = virt.top2();
+        FarthestSum { len: *weight, top: weight + first, bottom: first, diameter: virt.diameter() }
+    }
+
+    fn empty() -> FarthestVirtual {
+        FarthestVirtual::default()
+    }
+
+    fn add(virt: &mut FarthestVirtual, sum: &FarthestSum) {
+        insert(&mut virt.tops, sum.top);
+        insert(&mut virt.diameters, sum.diameter);
+    }
+
+    fn remove(virt: &mut FarthestVirtual, sum: &FarthestSum) {
+        erase(&mut virt.tops, sum.top);
+        erase(&mut virt.diameters, sum.diameter);
+    }
+}
+
+/// 印の付いた頂点がないことを表す (距離, 頂点)
+const UNMARKED: (i64, usize) = (i64::MAX / 4, usize::MAX);
+
+/// 印の付いた頂点 p までの距離を d だけ延ばす
+fn farther(p: (i64, usize), d: i64) -> (i64, usize) {
+    if p == UNMARKED { p } else { (p.0 + d, p.1) }
+}
+
+/// 最も近い印の付いた頂点（辺の重みは非負）
+/// 頂点の値は、印が付いていれば Some(頂点の番号)、なければ None とする
+pub struct NearestMarked;
+
+/// 根からの列の区間について、区間の両端から最も近い印の付いた頂点までの (距離, 頂点)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NearestMarkedSum {
+    /// 区間の端から端までの長さ
+    pub len: i64,
+    /// 区間の根に近い端から
+    pub top: (i64, usize),
+    /// 区間の根から遠い端から
+    pub bottom: (i64, usize),
+}
+
+impl<E> Subtree<First<usize>, E> for NearestMarked
+where
+    E: Monoid<Value = i64>,
+{
+    /// 軽い子の部分木の top の多重集合
+    type Virtual = BTreeMap<(i64, usize), usize>;
+    type Sum = NearestMarkedSum;
+
+    fn identity() -> NearestMarkedSum {
+        NearestMarkedSum { len: 0, top: UNMARKED, bottom: UNMARKED }
+    }
+
+    fn op(a: &NearestMarkedSum, b: &NearestMarkedSum) -> NearestMarkedSum {
+        NearestMarkedSum {
+            len: a.len + b.len,
+            top: a.top.min(farther(b.top, a.len)),
+            bottom: b.bottom.min(farther(a.bottom, b.len)),
+        }
+    }
+
+    fn vertex(value: &Option<usize>, virt: &Self::Virtual) -> NearestMarkedSum {
+        let mut nearest = virt.keys().next().copied().unwrap_or(UNMARKED);
+        if let Some(v) = *value {
+            nearest = nearest.min((0, v));
+        }
+        NearestMarkedSum { len: 0, top: nearest, bottom: nearest }
+    }
+
+    fn edge(weight: &i64, virt: &Self::Virtual) -> NearestMarkedSum {
+        // 辺の先の部分木は辺の根から遠い端に垂れ下がる
+        let nearest = virt.keys().next().copied().unwrap_or(UNMARKED);
+        NearestMarkedSum { len: *weight, top: farther(nearest, *weight), bottom: nearest }
+    }
+
+    fn empty() -> Self::Virtual {
+        BTreeMap::new()
+    }
+
+    fn add(virt: &mut Self::Virtual, sum: &NearestMarkedSum) {
+        insert(virt, sum.top);
+    }
+
+    fn remove(virt: &mut Self::Virtual, sum: &NearestMarkedSum) {
+        erase(virt, sum.top);
+    }
+}
+
+/// 頂点の組すべてについての距離の和（辺の重みで測る）
+pub struct Distances;
+
+/// 根からの列の区間とそこに垂れ下がる部分木について、頂点の数と距離の和
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DistancesSum {
+    /// 区間の端から端までの長さ
+    pub len: i64,
+    pub count: i64,
+    /// 区間の根に近い端から各頂点までの距離の和
+    pub top: i64,
+    /// 区間の根から遠い端から各頂点までの距離の和
+    pub bottom: i64,
+    /// 頂点の組すべてについての距離の和
+    pub pairs: i64,
+}
+
+/// 軽い子の部分木の集約の和
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DistancesVirtual {
+    count: i64,
+    top: i64,
+    pairs: i64,
+    /// 部分木ごとの top × count の和（異なる部分木の間の組を数えるのに使う）
+    weighted: i64,
+}
+
+impl DistancesVirtual {
+    /// 異なる部分木に属する頂点の組についての、付け根を通る距離の和
+    fn cross(&self) -> i64 {
+        self.top * self.count - self.weighted
+    }
+}
+
+impl<M: Monoid> Subtree<M, Sum<i64>> for Distances {
+    type Virtual = DistancesVirtual;
+    type Sum = DistancesSum;
+
+    fn identity() -> DistancesSum {
+        DistancesSum::default()
+    }
+
+    fn op(a: &DistancesSum, b: &DistancesSum) -> DistancesSum {
+        // a の根から遠い端と b の根に近い端は同じ点
+        DistancesSum {
+            len: a.len + b.len,
+            count: a.count + b.count,
+            top: a.top + b.top + a.len * b.count,
+            bottom: b.bottom + a.bottom + b.len * a.count,
+            pairs: a.pairs + b.pairs + a.bottom * b.count + b.top * a.count,
+        }
+    }
+
+    fn vertex(_: &M::Value, virt: &DistancesVirtual) -> DistancesSum {
+        DistancesSum { len: 0, count: virt.count + 1, top: virt.top, bottom: virt.top, pairs: virt.pairs + virt.cross() + virt.top }
+    }
+
+    fn edge(weight: &i64, virt: &DistancesVirtual) -> DistancesSum {
+        // 辺の先の部分木は辺の根から遠い端に垂れ下がる
+        DistancesSum { len: *weight, count: virt.count, top: virt.top + weight * virt.count, bottom: virt.top, pairs: virt.pairs + virt.cross() }
+    }
+
+    fn empty() -> DistancesVirtual {
+        DistancesVirtual::default()
+    }
+
+    fn add(virt: &mut DistancesVirtual, sum: &DistancesSum) {
+        virt.count += sum.count;
+        virt.top += sum.top;
+        virt.pairs += sum.pairs;
+        virt.weighted += sum.top * sum.count;
+    }
+
+    fn remove(virt: &mut DistancesVirtual, sum: &DistancesSum) {
+        virt.count -= sum.count;
+        virt.top -= sum.top;
+        virt.pairs -= sum.pairs;
+        virt.weighted -= sum.top * sum.count;
+    }
+}
+
+/// 現在の根からの深さ（辺の数）の最大値
+pub struct Height;
+
+/// 根からの列の区間について、区間の辺の数と、区間の根に近い端から最も深い頂点までの辺の数
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeightSum {
+    pub len: i64,
+    pub top: i64,
+}
+
+impl<M: Monoid, E: Monoid> Subtree<M, E> for Height {
+    /// 軽い子の部分木の top の多重集合
+    type Virtual = BTreeMap<i64, usize>;
+    type Sum = HeightSum;
+
+    fn identity() -> HeightSum {
+        HeightSum { len: 0, top: NONE }
+    }
+
+    fn op(a: &HeightSum, b: &HeightSum) -> HeightSum {
+        HeightSum { len: a.len + b.len, top: a.top.max(a.len + b.top) }
+    }
+
+    fn vertex(_: &M::Value, virt: &BTreeMap<i64, usize>) -> HeightSum {
+        HeightSum { len: 0, top: virt.keys().next_back().map_or(0, |&top| top.max(0)) }
+    }
+
+    fn edge(_: &E::Value, virt: &BTreeMap<i64, usize>) -> HeightSum {
+        HeightSum { len: 1, top: 1 + virt.keys().next_back().copied().unwrap_or(NONE) }
+    }
+
+    fn empty() -> BTreeMap<i64, usize> { BTreeMap::new() }
+    fn add(virt: &mut BTreeMap<i64, usize>, sum: &HeightSum) { insert(virt, sum.top) }
+    fn remove(virt: &mut BTreeMap<i64, usize>, sum: &HeightSum) { erase(virt, sum.top) }
+}
+
+/// 現在の根からの深さが偶数・奇数の頂点の数
+pub struct DepthParity;
+
+/// 根からの列の区間とそこに垂れ下がる部分木について、区間の両端からの辺の数が偶数・奇数の頂点の数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepthParitySum {
+    /// 区間の辺の数が奇数か
+    pub odd: bool,
+    /// 区間の根に近い端からの [偶数, 奇数]
+    pub top: [usize; 2],
+    /// 区間の根から遠い端からの [偶数, 奇数]
+    pub bottom: [usize; 2],
+}
+
+/// 偶奇を odd だけずらす
+fn shift(counts: [usize; 2], odd: bool) -> [usize; 2] {
+    if odd { [counts[1], counts[0]] } else { counts }
+}
+
+impl<M: Monoid, E: Monoid> Subtree<M, E> for DepthParity {
+    /// 軽い子の部分木の top の和
+    type Virtual = [usize; 2];
+    type Sum = DepthParitySum;
+
+    fn identity() -> DepthParitySum {
+        DepthParitySum::default()
+    }
+
+    fn op(a: &DepthParitySum, b: &DepthParitySum) -> DepthParitySum {
+        let (top, bottom) = (shift(b.top, a.odd), shift(a.bottom, b.odd));
+        DepthParitySum {
+            odd: a.odd ^ b.odd,
+            top: [a.top[0] + top[0], a.top[1] + top[1]],
+            bottom: [b.bottom[0] + bottom[0], b.bottom[1] + bottom[1]],
+        }
+    }
+
+    fn vertex(_: &M::Value, virt: &[usize; 2]) -> DepthParitySum {
+        let counts = [virt[0] + 1, virt[1]];
+        DepthParitySum { odd: false, top: counts, bottom: counts }
+    }
+
+    fn edge(_: &E::Value, virt: &[usize; 2]) -> DepthParitySum {
+        // 辺の先の部分木は辺の根から遠い端に垂れ下がる
+        DepthParitySum { odd: true, top: shift(*virt, true), bottom: *virt }
+    }
+
+    fn empty() -> [usize; 2] { [0, 0] }
+    fn add(virt: &mut [usize; 2], sum: &DepthParitySum) {
+        virt[0] += sum.top[0];
+        virt[1] += sum.top[1];
+    }
+    fn remove(virt: &mut [usize; 2], sum: &DepthParitySum) {
+        virt[0] -= sum.top[0];
+        virt[1] -= sum.top[1];
+    }
+}
+
+/// 利用者が与える木 DP（Dp と組み合わせて Subtree として使う）
+///
+/// 根からの列の区間を compress でまとめ、頂点に垂れ下がる軽い子の部分木を rake でまとめる。
+/// evert のため、列を逆にしても同じ意味になること。辺の重みは使わない。
+pub trait TreeDp<M: Monoid> {
+    /// 根からの列の区間の集約（区間の下に続く頂点の状態から区間の先頭の頂点の状態への関数など）
+    type Path: Clone;
+    /// 軽い子の部分木の集約をまとめたもの
+    type Point: Clone;
+
+    /// 空の区間
+    fn identity() -> Self::Path;
+    /// 区間 upper の下に lower が続く区間
+    fn compress(upper: &Self::Path, lower: &Self::Path) -> Self::Path;
+    /// 値が value の頂点に軽い子 light が垂れ下がった区間
+    fn vertex(value: &M::Value, light: &Self::Point) -> Self::Path;
+    /// 下に何も続かない区間を、上の頂点から見た部分木の集約にする
+    fn finish(path: &Self::Path) -> Self::Point;
+    /// 部分木の集約を、その部分木だけからなる区間として扱う（finish の逆）
+    fn lift(point: &Self::Point) -> Self::Path;
+
+    /// 軽い子のない状態
+    fn empty() -> Self::Point;
+    /// 軽い子の集約をまとめる（可換であること）
+    fn rake(a: &Self::Point, b: &Self::Point) -> Self::Point;
+    /// a から b を取り除く（rake の逆）
+    fn unrake(a: &Self::Point, b: &Self::Point) -> Self::Point;
+}
+
+/// TreeDp を部分木の集約として保つ
+pub struct Dp<T>(PhantomData<T>);
+
+impl<M: Monoid, E: Monoid, T: TreeDp<M>> Subtree<M, E> for Dp<T> {
+    /// 軽い子の数と、その集約
+    type Virtual = (usize, T::Point);
+    type Sum = T::Path;
+
+    fn identity() -> T::Path { T::identity() }
+    fn op(a: &T::Path, b: &T::Path) -> T::Path { T::compress(a, b) }
+    fn vertex(value: &M::Value, virt: &(usize, T::Point)) -> T::Path { T::vertex(value, &virt.1) }
+
+    fn edge(_: &E::Value, virt: &(usize, T::Point)) -> T::Path {
+        // 辺の先の頂点が軽い子なら、その部分木が区間の続き
+        if virt.0 == 0 {
+            T::identity()
+        } else {
+            T::lift(&virt.1)
+        }
+    }
+
+    fn empty() -> (usize, T::Point) { (0, T::empty()) }
+    fn add(virt: &mut (usize, T::Point), sum: &T::Path) { *virt = (virt.0 + 1, T::rake(&virt.1, &T::finish(sum))) }
+    fn remove(virt: &mut (usize, T::Point), sum: &T::Path) { *virt = (virt.0 - 1, T::unrake(&virt.1, &T::finish(sum))) }
+}
+
+/// 頂点の重みの和が最大の独立集合（TreeDp の例）
+/// 部分木の集約は [根を選ばないときの最大値, 根を選ぶときの最大値]
+pub struct MaxIndependentSet;
+
+impl MaxIndependentSet {
+    const NEG: i64 = i64::MIN / 4;
+
+    /// (max, +) での行列の積
+    fn mul(a: &[[i64; 2]; 2], b: &[[i64; 2]; 2]) -> [[i64; 2]; 2] {
+        let mut c = [[Self::NEG; 2]; 2];
+        for i in 0 .. 2 {
+            for j in 0 .. 2 {
+                c[i][j] = (0 .. 2).map(|k| (a[i][k] + b[k][j]).max(Self::NEG)).max().unwrap();
+            }
+        }
+        c
+    }
+}
+
+impl TreeDp<Sum<i64>> for MaxIndependentSet {
+    /// 区間の下に続く頂点を選ぶか（列）から、区間の先頭の頂点を選ぶか（行）への最大値
+    type Path = [[i64; 2]; 2];
+    /// 軽い子それぞれについての [選ばない・選ぶの大きい方, 選ばないとき] の和
+    type Point = [i64; 2];
+
+    fn identity() -> [[i64; 2]; 2] { [[0, Self::NEG], [Self::NEG, 0]] }
+    fn compress(upper: &[[i64; 2]; 2], lower: &[[i64; 2]; 2]) -> [[i64; 2]; 2] { Self::mul(upper, lower) }
+    fn vertex(&w: &i64, light: &[i64; 2]) -> [[i64; 2]; 2] { [[light[0], light[0]], [light[1] + w, Self::NEG]] }
+    fn finish(path: &[[i64; 2]; 2]) -> [i64; 2] { [path[0][0].max(path[1][0]), path[0][0]] }
+    fn lift(point: &[i64; 2]) -> [[i64; 2]; 2] { [[point[1], Self::NEG], [point[0], Self::NEG]] }
+
+    fn empty() -> [i64; 2] { [0, 0] }
+    fn rake(a: &[i64; 2], b: &[i64; 2]) -> [i64; 2] { [a[0] + b[0], a[1] + b[1]] }
+    fn unrake(a: &[i64; 2], b: &[i64; 2]) -> [i64; 2] { [a[0] - b[0], a[1] - b[1]] }
+}
+
+/// 式木の頂点の演算（値は子の値と operand をまとめたもの）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExprOp {
+    /// 子の値と operand の和（子がなければ operand）
+    Add(u64),
+    /// 子の値と operand の積（子がなければ operand）
+    Mul(u64),
+}
+
+/// 法 P で式木として評価した部分木の値（P は素数であること）
+/// 頂点の値が None なら Add(0) として扱う
+pub struct Expression<P>(PhantomData<P>);
+
+/// 根からの列の区間の評価
+/// 区間の下に続く頂点の値 x から区間の先頭の値への一次関数 (a, b) と、
+/// 区間の下に何も続かないときの区間の先頭の値 end（None なら区間の下の値をそのまま通す）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExprSum {
+    pub a: u64,
+    pub b: u64,
+    pub end: Option<u64>,
+}
+
+/// 軽い子の値の和と積（積は 0 を除いて持ち、0 の個数を数える）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExprVirtual {
+    count: usize,
+    sum: u64,
+    product: u64,
+    zeros: usize,
+}
+
+impl<P: Modulus, E: Monoid> Subtree<First<ExprOp>, E> for Expression<P> {
+    type Virtual = ExprVirtual;
+    type Sum = ExprSum;
+
+    fn identity() -> ExprSum {
+        ExprSum { a: 1 % P::modulus(), b: 0, end: None }
+    }
+
+    fn op(s: &ExprSum, t: &ExprSum) -> ExprSum {
+        let apply = |x: u64| (mul_mod::<P>(s.a, x) + s.b) % P::modulus();
+        ExprSum { a: mul_mod::<P>(s.a, t.a), b: apply(t.b), end: t.end.map(apply).or(s.end) }
+    }
+
+    fn vertex(value: &Option<ExprOp>, virt: &ExprVirtual) -> ExprSum {
+        match value.unwrap_or(ExprOp::Add(0)) {
+            ExprOp::Add(k) => {
+                let light = (k + virt.sum) % P::modulus();
+                ExprSum { a: 1 % P::modulus(), b: light, end: Some(light) }
+            }
+            ExprOp::Mul(k) => {
+                let light = if virt.zeros > 0 { 0 } else { mul_mod::<P>(k, virt.product) };
+                ExprSum { a: light, b: 0, end: Some(light) }
+            }
+        }
+    }
+
+    fn edge(_: &E::Value, virt: &ExprVirtual) -> ExprSum {
+        // 辺の先の頂点が軽い子なら、辺の値はその頂点の値
+        ExprSum { a: 1 % P::modulus(), b: 0, end: (virt.count > 0).then_some(virt.sum) }
+    }
+
+    fn empty() -> ExprVirtual {
+        ExprVirtual { count: 0, sum: 0, product: 1 % P::modulus(), zeros: 0 }
+    }
+
+    fn add(virt: &mut ExprVirtual, sum: &ExprSum) {
+        let x = sum.end.expect("a light subtree always ends with a value");
+        virt.count += 1;
+        virt.sum = (virt.sum + x) % P::modulus();
+        if x == 0 {
+            virt.zeros += 1;
+        } else {
+            virt.product = mul_mod::<P>(virt.product, x);
+        }
+    }
+
+    fn remove(virt: &mut ExprVirtual, sum: &ExprSum) {
+        let x = sum.end.expect("a light subtree always ends with a value");
+        virt.count -= 1;
+        virt.sum = (virt.sum + P::modulus() - x) % P::modulus();
+        if x == 0 {
+            virt.zeros -= 1;
+        } else {
+            virt.product = mul_mod::<P>(virt.product, pow_mod::<P>(x, P::modulus() - 2));
+        }
+    }
+}
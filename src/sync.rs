@@ -0,0 +1,134 @@
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::subtree::Subtree;
+use crate::{EdgeId, Forest};
+
+/// 複数のスレッドから共有できる森
+///
+/// パスの集約などの問い合わせも splay 木を組み替えるので、`&mut Forest` を取る操作は
+/// すべて排他的に行う（write）。`&Forest` で済む操作（隣接や次数、値の参照など）だけが
+/// 同時に行える（read）。
+pub struct ForestSync<M: Monoid = (), E: Monoid = (), A: Action<M, E> = (), S: Subtree<M, E> = ()> {
+    inner: RwLock<Forest<M, E, A, S>>,
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> Default for ForestSync<M, E, A, S> {
+    fn default() -> Self {
+        Self::new(Forest::new())
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> From<Forest<M, E, A, S>> for ForestSync<M, E, A, S> {
+    fn from(forest: Forest<M, E, A, S>) -> Self {
+        Self::new(forest)
+    }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> ForestSync<M, E, A, S> {
+    pub fn new(forest: Forest<M, E, A, S>) -> Self {
+        Self { inner: RwLock::new(forest) }
+    }
+
+    pub fn into_inner(self) -> Forest<M, E, A, S> {
+        self.inner.into_inner().expect("a thread panicked while modifying the forest")
+    }
+
+    fn read_guard(&self) -> RwLockReadGuard<'_, Forest<M, E, A, S>> {
+        self.inner.read().expect("a thread panicked while modifying the forest")
+    }
+
+    fn write_guard(&self) -> RwLockWriteGuard<'_, Forest<M, E, A, S>> {
+        self.inner.write().expect("a thread panicked while modifying the forest")
+    }
+
+    /// 共有ロックを取って f を呼ぶ（他の read と同時に行える）
+    pub fn read<R>(&self, f: impl FnOnce(&Forest<M, E, A, S>) -> R) -> R {
+        f(&self.read_guard())
+    }
+
+    /// 排他ロックを取って f を呼ぶ
+    pub fn write<R>(&self, f: impl FnOnce(&mut Forest<M, E, A, S>) -> R) -> R {
+        f(&mut self.write_guard())
+    }
+
+    pub fn len(&self) -> usize {
+        self.read(|forest| forest.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read(|forest| forest.is_empty())
+    }
+
+    pub fn add_node(&self, value: M::Value) -> usize {
+        self.write(|forest| forest.add_node(value))
+    }
+
+    pub fn link(&self, child: usize, parent: usize) {
+        self.write(|forest| forest.link(child, parent))
+    }
+
+    /// 重みのついた辺で一度につなぐ（つないでから重みを変える間に問い合わせが割り込まない）
+    pub fn link_weighted(&self, child: usize, parent: usize, weight: E::Value) {
+        self.write(|forest| forest.link_weighted(child, parent, weight))
+    }
+
+    pub fn path_query_edges(&self, u: usize, v: usize) -> Option<E::Value> {
+        self.write(|forest| forest.path_query_edges(u, v))
+    }
+
+    pub fn cut(&self, v: usize) -> EdgeId {
+        self.write(|forest| forest.cut(v))
+    }
+
+    pub fn connected(&self, u: usize, v: usize) -> bool {
+        self.write(|forest| forest.connected(u, v))
+    }
+
+    pub fn path_query(&self, u: usize, v: usize) -> Option<M::Value> {
+        self.write(|forest| forest.path_query(u, v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    #[test]
+    fn shared_between_threads() {
+        let forest = ForestSync::<Sum<i64>>::default();
+        for i in 0 .. 40 {
+            forest.add_node(i);
+        }
+        std::thread::scope(|scope| {
+            for t in 0 .. 4 {
+                let forest = &forest;
+                scope.spawn(move || {
+                    // スレッドごとに別の鎖を作る
+                    for i in 1 .. 10 {
+                        forest.link(10 * t + i, 10 * t + i - 1);
+                    }
+                    let expected = (10 * t .. 10 * t + 10).sum::<usize>() as i64;
+                    assert_eq!(forest.path_query(10 * t, 10 * t + 9), Some(expected));
+                    assert_eq!(forest.read(|forest| forest.degree(10 * t + 5)), 2);
+                });
+            }
+        });
+        assert!(!forest.connected(9, 10));
+        let mut forest = forest.into_inner();
+        assert_eq!(forest.root(39), 30);
+    }
+
+    #[test]
+    fn link_weighted() {
+        let forest = ForestSync::<(), Sum<i64>>::default();
+        for _ in 0 .. 3 {
+            forest.add_node(());
+        }
+        forest.link_weighted(1, 0, 4);
+        forest.link_weighted(2, 1, 5);
+        assert_eq!(forest.path_query_edges(0, 2), Some(9));
+    }
+}
@@ -0,0 +1,17 @@
+//! テストで使う乱数
+
+/// xorshift で u64 を次々に返す関数（seed は 0 でないこと）
+pub(crate) fn xorshift64(mut seed: u64) -> impl FnMut() -> u64 {
+    move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    }
+}
+
+/// xorshift で 0 以上 n 未満の整数を次々に返す関数
+pub(crate) fn xorshift(seed: u64) -> impl FnMut(usize) -> usize {
+    let mut next = xorshift64(seed);
+    move |n| (next() % n as u64) as usize
+}
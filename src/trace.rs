@@ -0,0 +1,109 @@
+//! 操作の記録（tracing 機能）
+//!
+//! 外部のクレートには依存せず、登録したフックに事象を渡す。
+//! tracing クレートなどに流すには、フックの中で記録すればよい。
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+/// 事象の対象の節点
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceNode {
+    Vertex(usize),
+    Edge(usize),
+}
+
+impl TraceNode {
+    pub(crate) fn from_index(x: usize) -> Self {
+        if x & 1 == 0 {
+            TraceNode::Vertex(x >> 1)
+        } else {
+            TraceNode::Edge(x >> 1)
+        }
+    }
+}
+
+/// 記録される事象
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// child を parent の子にした
+    Link { child: usize, parent: usize, elapsed: Duration },
+    /// v を親から切り離した
+    Cut { v: usize, elapsed: Duration },
+    /// 頂点を根からのパスにつなげた（splay した回数）
+    Expose { vertex: usize, splays: usize },
+    /// 節点を splay 木の根にした（回転した回数）
+    Splay { node: TraceNode, rotations: usize },
+    /// node を splay 木の親 parent の上に回転した
+    Rotate { node: TraceNode, parent: TraceNode },
+    /// expose の途中で node の優先する子（splay 木の右の子）を old から new に替えた
+    Switch { node: TraceNode, old: Option<TraceNode>, new: Option<TraceNode> },
+}
+
+type Hook = Box<dyn Fn(&TraceEvent)>;
+
+thread_local! {
+    static HOOK: RefCell<Option<Hook>> = const { RefCell::new(None) };
+}
+
+/// このスレッドで起きた事象を hook に渡すようにする
+pub fn set_trace_hook(hook: impl Fn(&TraceEvent) + 'static) {
+    HOOK.with(|cell| *cell.borrow_mut() = Some(Box::new(hook)));
+}
+
+/// このスレッドのフックを外す
+pub fn clear_trace_hook() {
+    HOOK.with(|cell| *cell.borrow_mut() = None);
+}
+
+pub(crate) fn emit(event: TraceEvent) {
+    HOOK.with(|cell| {
+        if let Some(hook) = cell.borrow().as_ref() {
+            hook(&event);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Forest;
+    use std::rc::Rc;
+
+    #[test]
+    fn hook_receives_events() {
+        let events = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&events);
+        set_trace_hook(move |event| sink.borrow_mut().push(event.clone()));
+        let mut forest = Forest::<()>::new();
+        forest.add_node(());
+        forest.add_node(());
+        forest.link(1, 0);
+        forest.cut(1);
+        clear_trace_hook();
+        forest.link(1, 0);
+        let events = events.borrow();
+        assert!(events.iter().any(|event| matches!(event, TraceEvent::Link { child: 1, parent: 0, .. })));
+        assert!(events.iter().any(|event| matches!(event, TraceEvent::Cut { v: 1, .. })));
+        assert!(events.iter().any(|event| matches!(event, TraceEvent::Expose { vertex: 1, .. })));
+        assert!(events.iter().any(|event| matches!(event, TraceEvent::Splay { node: TraceNode::Edge(0), .. })));
+        assert_eq!(events.iter().filter(|event| matches!(event, TraceEvent::Link { .. })).count(), 1);
+    }
+
+    #[test]
+    fn repeated_expose_skips_splaying() {
+        let events = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&events);
+        let mut forest = Forest::<crate::monoid::Sum<i64>>::new();
+        for x in 0 .. 3 {
+            forest.add_node(x);
+        }
+        forest.link(1, 0);
+        forest.link(2, 1);
+        forest.subtree_query(2);
+        set_trace_hook(move |event| sink.borrow_mut().push(event.clone()));
+        forest.subtree_query(2);
+        clear_trace_hook();
+        assert_eq!(*events.borrow(), vec![TraceEvent::Expose { vertex: 2, splays: 0 }]);
+    }
+}
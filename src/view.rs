@@ -0,0 +1,111 @@
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::subtree::Subtree;
+use crate::{EdgeId, Forest, ForestSync, Frozen};
+
+/// 森を書き換えずに答えられる問い合わせ
+///
+/// Forest の問い合わせは splay 木を組み替えるので `&mut self` を取る。
+/// `&self` で問い合わせたいときは、freeze した Frozen か ForestSync を通す。
+#[allow(clippy::len_without_is_empty)]
+pub trait ForestRead<M: Monoid, E: Monoid = ()> {
+    fn len(&self) -> usize;
+    /// v を含む木の根
+    fn root(&self, v: usize) -> usize;
+    fn parent(&self, v: usize) -> Option<usize>;
+    /// u と v の最小共通祖先（異なる木なら None）
+    fn lca(&self, u: usize, v: usize) -> Option<usize>;
+    /// u から v へのパスの頂点の値の集約（異なる木なら None）
+    fn path_query(&self, u: usize, v: usize) -> Option<M::Value>;
+    /// u から v へのパスの辺の重みの集約（異なる木なら None）
+    fn path_query_edges(&self, u: usize, v: usize) -> Option<E::Value>;
+
+    fn connected(&self, u: usize, v: usize) -> bool {
+        self.root(u) == self.root(v)
+    }
+}
+
+/// 森の形と値を書き換える操作
+pub trait ForestWrite<M: Monoid, E: Monoid = ()> {
+    fn add_node(&mut self, value: M::Value) -> usize;
+    /// 木の根 child の親を parent にする
+    fn link(&mut self, child: usize, parent: usize);
+    /// v を親から切り離し、切った辺の番号を返す
+    fn cut(&mut self, v: usize) -> EdgeId;
+    /// v を木の根にする
+    fn evert(&mut self, v: usize);
+    fn set_value(&mut self, v: usize, value: M::Value);
+}
+
+impl<M: Monoid, E: Monoid> ForestRead<M, E> for Frozen<M, E> {
+    fn len(&self) -> usize { Frozen::len(self) }
+    fn root(&self, v: usize) -> usize { Frozen::root(self, v) }
+    fn parent(&self, v: usize) -> Option<usize> { Frozen::parent(self, v) }
+    fn lca(&self, u: usize, v: usize) -> Option<usize> { Frozen::lca(self, u, v) }
+    fn path_query(&self, u: usize, v: usize) -> Option<M::Value> { Frozen::path_query(self, u, v) }
+    fn path_query_edges(&self, u: usize, v: usize) -> Option<E::Value> { Frozen::path_query_edges(self, u, v) }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> ForestRead<M, E> for ForestSync<M, E, A, S> {
+    fn len(&self) -> usize { ForestSync::len(self) }
+    fn root(&self, v: usize) -> usize { self.write(|forest| forest.root(v)) }
+    fn parent(&self, v: usize) -> Option<usize> { self.write(|forest| forest.parent(v)) }
+    fn lca(&self, u: usize, v: usize) -> Option<usize> { self.write(|forest| forest.lca(u, v)) }
+    fn path_query(&self, u: usize, v: usize) -> Option<M::Value> { ForestSync::path_query(self, u, v) }
+    fn path_query_edges(&self, u: usize, v: usize) -> Option<E::Value> { ForestSync::path_query_edges(self, u, v) }
+    fn connected(&self, u: usize, v: usize) -> bool { ForestSync::connected(self, u, v) }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> ForestWrite<M, E> for Forest<M, E, A, S> {
+    fn add_node(&mut self, value: M::Value) -> usize { Forest::add_node(self, value) }
+    fn link(&mut self, child: usize, parent: usize) { Forest::link(self, child, parent) }
+    fn cut(&mut self, v: usize) -> EdgeId { Forest::cut(self, v) }
+    fn evert(&mut self, v: usize) { Forest::evert(self, v) }
+    fn set_value(&mut self, v: usize, value: M::Value) { Forest::set_value(self, v, value) }
+}
+
+impl<M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> ForestWrite<M, E> for ForestSync<M, E, A, S> {
+    fn add_node(&mut self, value: M::Value) -> usize { ForestSync::add_node(self, value) }
+    fn link(&mut self, child: usize, parent: usize) { ForestSync::link(self, child, parent) }
+    fn cut(&mut self, v: usize) -> EdgeId { ForestSync::cut(self, v) }
+    fn evert(&mut self, v: usize) { self.write(|forest| forest.evert(v)) }
+    fn set_value(&mut self, v: usize, value: M::Value) { self.write(|forest| forest.set_value(v, value)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+
+    /// 0 - 1 - ... - n-1 の鎖を作る
+    fn build(forest: &mut impl ForestWrite<Sum<i64>>, n: usize) {
+        for i in 0 .. n {
+            forest.add_node(i as i64);
+            if i > 0 {
+                forest.link(i, i - 1);
+            }
+        }
+        forest.evert(n - 1);
+        forest.set_value(0, 10);
+    }
+
+    fn check(forest: &impl ForestRead<Sum<i64>>) {
+        assert_eq!(forest.len(), 4);
+        assert_eq!(forest.root(0), 3);
+        assert_eq!(forest.parent(1), Some(2));
+        assert_eq!(forest.lca(0, 1), Some(1));
+        assert_eq!(forest.path_query(0, 2), Some(13));
+        assert_eq!(forest.path_query_edges(0, 2), Some(()));
+        assert!(forest.connected(0, 3));
+    }
+
+    #[test]
+    fn read_and_write_halves() {
+        let mut forest = Forest::<Sum<i64>>::new();
+        build(&mut forest, 4);
+        check(&forest.freeze());
+        let mut sync = ForestSync::<Sum<i64>>::default();
+        build(&mut sync, 4);
+        check(&sync);
+    }
+}
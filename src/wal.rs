@@ -0,0 +1,367 @@
+//! 操作を記録しておき、落ちたあとに森を作り直す（wal 機能）
+//!
+//! 形と値を変える操作を一つずつ固定長の記録としてログに書き出す。
+//! checkpoint で今の森を記録の列として新しいログの先頭に書くので、古いログは捨ててよい。
+//! 書きかけで途切れた末尾の記録は recover で読み飛ばす。
+
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Deref;
+
+use crate::action::Action;
+use crate::monoid::Monoid;
+use crate::subtree::Subtree;
+use crate::{EdgeId, Fallible, Forest, Frozen, LctError, PolicyForest, SnapshotValue};
+
+const ADD: u8 = 0;
+const LINK: u8 = 1;
+const CUT: u8 = 2;
+const EVERT: u8 = 3;
+const SET: u8 = 4;
+const WEIGHT: u8 = 5;
+
+/// ログに書けなかったか、ログから作り直せなかった
+#[derive(Debug)]
+pub enum WalError {
+    Io(io::Error),
+    /// 操作が前提を満たさない（ログには書かない）
+    Forest(LctError),
+    /// offset バイト目の記録の種類がわからない
+    Corrupt { offset: usize },
+    /// offset バイト目の記録を作り直している森に施せない
+    Replay { offset: usize, error: LctError },
+}
+
+impl fmt::Display for WalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalError::Io(error) => write!(f, "{}", error),
+            WalError::Forest(error) => write!(f, "{}", error),
+            WalError::Corrupt { offset } => write!(f, "unknown record at byte {}", offset),
+            WalError::Replay { offset, error } => write!(f, "record at byte {}: {}", offset, error),
+        }
+    }
+}
+
+impl std::error::Error for WalError {}
+
+impl From<io::Error> for WalError {
+    fn from(error: io::Error) -> Self {
+        WalError::Io(error)
+    }
+}
+
+impl From<LctError> for WalError {
+    fn from(error: LctError) -> Self {
+        WalError::Forest(error)
+    }
+}
+
+/// 形と値を変える操作を log に書き出す森
+/// &self の問い合わせは Deref で Forest のものを使い、splay 木を組み替える問い合わせはここに写してある
+/// 記録しない操作で森を変えられないように、Forest への &mut は渡さない
+/// 操作を施してから記録を書くので、書き出しに失敗したら森とログが食い違う（checkpoint で書き直すこと）
+pub struct WalForest<W: Write, M: Monoid = (), E: Monoid = (), A: Action<M, E> = (), S: Subtree<M, E> = ()> {
+    forest: PolicyForest<Fallible, M, E, A, S>,
+    log: W,
+}
+
+impl<W: Write, M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> Deref for WalForest<W, M, E, A, S> {
+    type Target = Forest<M, E, A, S>;
+
+    fn deref(&self) -> &Forest<M, E, A, S> {
+        &self.forest
+    }
+}
+
+/// 形と値を変えない問い合わせ（splay 木の組み替えは記録しなくても作り直した森の答えを変えない）
+impl<W: Write, M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> WalForest<W, M, E, A, S> {
+    pub fn value(&mut self, v: usize) -> &M::Value {
+        self.forest.value(v)
+    }
+
+    pub fn edge_weight(&mut self, u: usize, v: usize) -> Option<&E::Value> {
+        self.forest.edge_weight(u, v)
+    }
+
+    pub fn root(&mut self, v: usize) -> usize {
+        self.forest.root(v)
+    }
+
+    pub fn roots(&mut self) -> Vec<usize> {
+        self.forest.roots()
+    }
+
+    pub fn parent(&mut self, v: usize) -> Option<usize> {
+        self.forest.parent(v)
+    }
+
+    pub fn children(&mut self, v: usize) -> Vec<usize> {
+        self.forest.children(v)
+    }
+
+    pub fn to_parents(&mut self) -> Vec<Option<usize>> {
+        self.forest.to_parents()
+    }
+
+    pub fn connected(&mut self, u: usize, v: usize) -> bool {
+        self.forest.connected(u, v)
+    }
+
+    pub fn lca(&mut self, u: usize, v: usize) -> Option<usize> {
+        self.forest.lca(u, v)
+    }
+
+    pub fn path_query(&mut self, u: usize, v: usize) -> Option<M::Value> {
+        self.forest.path_query(u, v)
+    }
+
+    pub fn path_query_edges(&mut self, u: usize, v: usize) -> Option<E::Value> {
+        self.forest.path_query_edges(u, v)
+    }
+
+    /// ここにない問い合わせは、今の森の読み取り専用の写しで答える
+    pub fn freeze(&mut self) -> Frozen<M, E> {
+        self.forest.freeze()
+    }
+}
+
+fn put(out: &mut Vec<u8>, x: usize) {
+    out.extend_from_slice(&(x as u64).to_le_bytes());
+}
+
+fn get(bytes: &[u8], offset: usize) -> usize {
+    u64::from_le_bytes(bytes[offset .. offset + 8].try_into().unwrap()) as usize
+}
+
+impl<W: Write, M: Monoid, E: Monoid, A: Action<M, E>, S: Subtree<M, E>> WalForest<W, M, E, A, S>
+where
+    M::Value: SnapshotValue,
+    E::Value: SnapshotValue,
+{
+    /// 空の森で始める
+    pub fn new(log: W) -> Self {
+        Self { forest: PolicyForest::default(), log }
+    }
+
+    /// forest の今の状態を log の先頭に書いて始める（recover で作り直した森を引き継ぐときなど）
+    pub fn from_forest(mut forest: Forest<M, E, A, S>, mut log: W) -> Result<Self, WalError> {
+        log.write_all(&Self::snapshot(&mut forest))?;
+        log.flush()?;
+        Ok(Self { forest: PolicyForest::new(forest), log })
+    }
+
+    /// 今の森を作り直す記録の列
+    fn snapshot(forest: &mut Forest<M, E, A, S>) -> Vec<u8> {
+        let mut out = vec![];
+        for v in 0 .. forest.len() {
+            out.push(ADD);
+            forest.value(v).write(&mut out);
+        }
+        for (v, parent) in forest.to_parents().into_iter().enumerate() {
+            let Some(p) = parent else { continue };
+            out.push(LINK);
+            put(&mut out, v);
+            put(&mut out, p);
+            forest.edge_weight(v, p).unwrap().write(&mut out);
+        }
+        out
+    }
+
+    fn append(&mut self, record: Vec<u8>) -> Result<(), WalError> {
+        self.log.write_all(&record)?;
+        Ok(())
+    }
+
+    /// 今の森を記録の列として log に書いて以後の記録をそこへ書き、それまでのログを返す
+    pub fn checkpoint(&mut self, mut log: W) -> Result<W, WalError> {
+        log.write_all(&Self::snapshot(&mut self.forest))?;
+        log.flush()?;
+        Ok(std::mem::replace(&mut self.log, log))
+    }
+
+    pub fn flush(&mut self) -> Result<(), WalError> {
+        self.log.flush()?;
+        Ok(())
+    }
+
+    pub fn log(&self) -> &W {
+        &self.log
+    }
+
+    pub fn into_inner(self) -> (Forest<M, E, A, S>, W) {
+        (self.forest.into_inner(), self.log)
+    }
+
+    /// 値 value を持つ孤立点を追加し、その番号を返す
+    pub fn add_node(&mut self, value: M::Value) -> Result<usize, WalError> {
+        let mut record = vec![ADD];
+        value.write(&mut record);
+        let v = self.forest.add_node(value);
+        self.append(record)?;
+        Ok(v)
+    }
+
+    /// 木の根 child の親を parent にする
+    pub fn link(&mut self, child: usize, parent: usize) -> Result<(), WalError> {
+        self.link_weighted(child, parent, E::identity())
+    }
+
+    /// 木の根 child の親を parent にし、その辺の重みを weight にする
+    pub fn link_weighted(&mut self, child: usize, parent: usize, weight: E::Value) -> Result<(), WalError> {
+        let mut record = vec![LINK];
+        put(&mut record, child);
+        put(&mut record, parent);
+        weight.write(&mut record);
+        self.forest.link_weighted(child, parent, weight)?;
+        self.append(record)
+    }
+
+    /// v を親から切り離し、切った辺の番号を返す
+    pub fn cut(&mut self, v: usize) -> Result<EdgeId, WalError> {
+        let id = self.forest.cut(v)?;
+        let mut record = vec![CUT];
+        put(&mut record, v);
+        self.append(record)?;
+        Ok(id)
+    }
+
+    /// v を根にする
+    pub fn evert(&mut self, v: usize) -> Result<(), WalError> {
+        self.forest.evert(v)?;
+        let mut record = vec![EVERT];
+        put(&mut record, v);
+        self.append(record)
+    }
+
+    pub fn set_value(&mut self, v: usize, value: M::Value) -> Result<(), WalError> {
+        let mut record = vec![SET];
+        put(&mut record, v);
+        value.write(&mut record);
+        self.forest.set_value(v, value)?;
+        self.append(record)
+    }
+
+    /// u と v を結ぶ辺の重みを weight にする
+    pub fn set_edge_weight(&mut self, u: usize, v: usize, weight: E::Value) -> Result<(), WalError> {
+        let mut record = vec![WEIGHT];
+        put(&mut record, u);
+        put(&mut record, v);
+        weight.write(&mut record);
+        self.forest.set_edge_weight(u, v, weight)?;
+        self.append(record)
+    }
+
+    /// ログ bytes を先頭から施して森を作り直す（途切れた末尾の記録は読み飛ばす）
+    pub fn recover(bytes: &[u8]) -> Result<Forest<M, E, A, S>, WalError> {
+        let mut forest = PolicyForest::<Fallible, M, E, A, S>::default();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let size = match bytes[offset] {
+                ADD => M::Value::SIZE,
+                LINK | WEIGHT => 16 + E::Value::SIZE,
+                CUT | EVERT => 8,
+                SET => 8 + M::Value::SIZE,
+                _ => return Err(WalError::Corrupt { offset }),
+            };
+            if offset + 1 + size > bytes.len() {
+                break;
+            }
+            let body = &bytes[offset + 1 .. offset + 1 + size];
+            let result = match bytes[offset] {
+                ADD => {
+                    forest.add_node(M::Value::read(body));
+                    Ok(())
+                }
+                LINK => forest.link_weighted(get(body, 0), get(body, 8), E::Value::read(&body[16 ..])),
+                CUT => forest.cut(get(body, 0)).map(|_| ()),
+                EVERT => forest.evert(get(body, 0)),
+                SET => forest.set_value(get(body, 0), M::Value::read(&body[8 ..])),
+                _ => forest.set_edge_weight(get(body, 0), get(body, 8), E::Value::read(&body[16 ..])),
+            };
+            result.map_err(|error| WalError::Replay { offset, error })?;
+            offset += 1 + size;
+        }
+        Ok(forest.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Sum;
+    use crate::test_util::xorshift;
+
+    type Wal = WalForest<Vec<u8>, Sum<i64>, Sum<i64>>;
+
+    /// 親・値・辺の重みが同じか
+    fn assert_same(a: &mut Forest<Sum<i64>, Sum<i64>>, b: &mut Forest<Sum<i64>, Sum<i64>>) {
+        assert_eq!(a.len(), b.len());
+        let parents = a.to_parents();
+        assert_eq!(parents, b.to_parents());
+        for (v, parent) in parents.into_iter().enumerate() {
+            assert_eq!(a.value(v), b.value(v));
+            if let Some(p) = parent {
+                assert_eq!(a.edge_weight(v, p), b.edge_weight(v, p));
+            }
+        }
+    }
+
+    #[test]
+    fn recover_replays_log() {
+        let mut rand = xorshift(88172645463325252);
+        let mut wal = Wal::new(vec![]);
+        for x in 0 .. 10 {
+            wal.add_node(x).unwrap();
+        }
+        let mut old = vec![];
+        for step in 0 .. 500 {
+            let n = wal.len();
+            let (u, v) = (rand(n), rand(n));
+            let result = match rand(6) {
+                0 => wal.link_weighted(u, v, rand(10) as i64),
+                1 => wal.cut(u).map(|_| ()),
+                2 => wal.evert(u),
+                3 => wal.set_value(u, rand(100) as i64),
+                4 => wal.set_edge_weight(u, v, rand(10) as i64),
+                _ => wal.add_node(rand(100) as i64).map(|_| ()),
+            };
+            assert!(matches!(result, Ok(()) | Err(WalError::Forest(_))));
+            if step == 250 {
+                old = wal.checkpoint(vec![]).unwrap();
+            }
+        }
+        assert!(!old.is_empty());
+        let mut log = wal.log().clone();
+        let mut recovered = Wal::recover(&log).unwrap();
+        assert_same(&mut recovered, &mut wal.forest);
+        // 途切れた末尾の記録は読み飛ばす
+        log.extend_from_slice(&[LINK, 1, 0, 0]);
+        let mut recovered = Wal::recover(&log).unwrap();
+        assert_same(&mut recovered, &mut wal.forest);
+        assert_eq!(wal.path_query(0, 0), Some(*wal.value(0)));
+        assert_eq!(wal.freeze().root(0), wal.root(0));
+        let mut corrupt = wal.log().clone();
+        corrupt.push(9);
+        assert!(matches!(Wal::recover(&corrupt), Err(WalError::Corrupt { .. })));
+    }
+
+    #[test]
+    fn from_forest_and_replay_errors() {
+        let mut forest = Forest::<Sum<i64>, Sum<i64>>::new();
+        for x in 0 .. 3 {
+            forest.add_node(x);
+        }
+        forest.link_weighted(1, 0, 5);
+        let mut wal = Wal::from_forest(forest, vec![]).unwrap();
+        assert!(matches!(wal.link(1, 2), Err(WalError::Forest(LctError::NotRoot(1)))));
+        wal.link(2, 1).unwrap();
+        let (mut forest, log) = wal.into_inner();
+        let mut recovered = Wal::recover(&log).unwrap();
+        assert_same(&mut recovered, &mut forest);
+        assert_eq!(recovered.path_query_edges(0, 2), Some(5));
+        let mut bad = vec![CUT];
+        put(&mut bad, 0);
+        assert!(matches!(Wal::recover(&bad), Err(WalError::Replay { offset: 0, error: LctError::OutOfRange(0) })));
+    }
+}